@@ -4,6 +4,7 @@ use ferrocene::{
     analysis::StandardAnalyzer,
     document::{Document, Field},
     index::IndexWriter,
+    store::FSDirectory,
 };
 
 fn main() {
@@ -12,14 +13,17 @@ fn main() {
     let data_path = workspace_path.join("examples").join("data");
 
     // Create a new index writer
-    let mut writer = IndexWriter::create(&index_path, StandardAnalyzer::new());
+    let directory = FSDirectory::new(index_path).unwrap();
+    let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
 
     // index all files in data_path
     for entry in std::fs::read_dir(data_path).unwrap() {
         let path = entry.unwrap().path();
         let document = file_document(&path);
-        writer.add_document(document);
+        writer.add_document(document).unwrap();
     }
+
+    writer.commit().unwrap();
 }
 
 fn file_document(path: &Path) -> Document {
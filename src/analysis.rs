@@ -2,6 +2,7 @@ pub trait Analyzer {
     fn analyze<'a>(&self, text: &'a str) -> impl Iterator<Item = &'a str>;
 }
 
+#[derive(Default)]
 pub struct StandardAnalyzer {}
 
 impl StandardAnalyzer {
@@ -16,6 +16,12 @@ impl SimpleTextCodec {
     }
 }
 
+impl Default for SimpleTextCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Codec for SimpleTextCodec {
     fn name(&self) -> &str {
         &self.name
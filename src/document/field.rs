@@ -1,3 +1,5 @@
+use crate::index::doc_values::DocValuesType;
+
 /// A field is a section of a Document. Each field has two parts - name and value.
 /// - Values are analyzed into terms.
 /// - Keywords are not analyzed.
@@ -21,6 +23,10 @@ pub struct Field {
 
     /// Boost factor when scoring.
     pub boost: f32,
+
+    /// Type of doc values, if the value is also stored per document.
+    /// Doc values can be updated in place and read column-wise.
+    pub doc_values: Option<DocValuesType>,
 }
 
 impl Field {
@@ -33,6 +39,7 @@ impl Field {
             is_indexed: true,
             is_analyzed: true,
             boost: 1.0,
+            doc_values: None,
         }
     }
 
@@ -45,6 +52,7 @@ impl Field {
             is_indexed: true,
             is_analyzed: false,
             boost: 1.0,
+            doc_values: None,
         }
     }
 
@@ -57,6 +65,7 @@ impl Field {
             is_indexed: true,
             is_analyzed: true,
             boost: 1.0,
+            doc_values: None,
         }
     }
 
@@ -70,6 +79,20 @@ impl Field {
             is_indexed: false,
             is_analyzed: false,
             boost: 1.0,
+            doc_values: None,
+        }
+    }
+
+    /// Create a numeric doc values field, which is neither stored nor indexed.
+    pub fn numeric_doc_values(name: String, value: i64) -> Field {
+        Field {
+            name,
+            value: value.to_string(),
+            is_stored: false,
+            is_indexed: false,
+            is_analyzed: false,
+            boost: 1.0,
+            doc_values: Some(DocValuesType::Numeric),
         }
     }
 }
@@ -93,6 +116,7 @@ impl fmt::Display for Field {
 
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -140,6 +164,16 @@ mod tests {
         assert_eq!(field.boost, 1.0);
     }
 
+    #[test]
+    fn test_numeric_doc_values() {
+        let field = Field::numeric_doc_values("price".to_string(), -42);
+        assert_eq!(field.name, "price");
+        assert_eq!(field.value, "-42");
+        assert!(!field.is_stored);
+        assert!(!field.is_indexed);
+        assert_eq!(field.doc_values, Some(DocValuesType::Numeric));
+    }
+
     #[test]
     fn test_display() {
         let keyword = Field::keyword("id".to_string(), "test123".to_string());
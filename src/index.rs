@@ -1,14 +1,48 @@
+pub mod buffered_deletes;
 pub mod codec_utils;
+pub mod directory_reader;
+pub mod doc_values;
 pub mod document_writer;
 pub mod field_info;
+pub mod fields_reader;
 pub mod fields_writer;
+pub mod index_commit;
+pub mod index_deletion_policy;
+pub mod index_error;
+pub mod index_file_deleter;
 pub mod index_writer;
+pub mod live_docs;
+pub mod log_byte_size_merge_policy;
+pub mod memory_terms;
+pub mod merge_policy;
+pub mod merge_scheduler;
+pub mod norms;
 pub mod posting;
+pub mod postings_enum;
+pub mod postings_writer;
 pub mod segment_commit_info;
 pub mod segment_info;
 pub mod segment_infos;
+pub mod segment_merger;
+pub mod segment_reader;
+pub mod segment_terms;
+pub mod snapshot_deletion_policy;
 pub mod term;
+pub mod term_info;
+pub mod terms;
+pub mod tiered_merge_policy;
 
+pub use directory_reader::DirectoryReader;
+pub use index_commit::IndexCommit;
+pub use index_deletion_policy::{IndexDeletionPolicy, KeepOnlyLastCommitDeletionPolicy};
+pub use index_error::IndexError;
 pub use index_writer::IndexWriter;
+pub use log_byte_size_merge_policy::LogByteSizeMergePolicy;
+pub use merge_policy::{MergePolicy, NoMergePolicy};
+pub use merge_scheduler::{ConcurrentMergeScheduler, MergeScheduler, SerialMergeScheduler};
 pub use posting::Posting;
+pub use postings_enum::{PostingsEnum, NO_MORE_DOCS};
+pub use snapshot_deletion_policy::SnapshotDeletionPolicy;
 pub use term::Term;
+pub use terms::{SeekStatus, Terms, TermsEnum};
+pub use tiered_merge_policy::TieredMergePolicy;
@@ -0,0 +1,57 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::search::Query;
+
+use super::Term;
+
+/// Update of a numeric doc values field, for all docs containing the term.
+#[derive(Debug, Clone)]
+pub struct DocValuesUpdate {
+    pub term: Term,
+    pub field: String,
+    pub value: i64,
+    /// Number of buffered docs when the update was added.
+    pub doc_id_upto: u32,
+}
+
+/// Deletes and doc values updates buffered by the `IndexWriter`,
+/// resolved when buffered docs are flushed.
+#[derive(Debug, Default)]
+pub struct BufferedDeletes {
+    /// Delete terms, each with the number of buffered docs when it was added.
+    /// A delete only applies to buffered docs added before it.
+    pub terms: HashMap<Term, u32>,
+    /// Delete queries, each with the number of buffered docs when it was added.
+    pub queries: Vec<(Arc<dyn Query>, u32)>,
+    /// Doc values updates in the order they were added, later ones win.
+    pub doc_values_updates: Vec<DocValuesUpdate>,
+}
+
+impl BufferedDeletes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_term(&mut self, term: Term, doc_id_upto: u32) {
+        // A later delete of the same term covers everything an earlier one did
+        self.terms.insert(term, doc_id_upto);
+    }
+
+    pub fn add_query(&mut self, query: Arc<dyn Query>, doc_id_upto: u32) {
+        self.queries.push((query, doc_id_upto));
+    }
+
+    pub fn add_doc_values_update(&mut self, update: DocValuesUpdate) {
+        self.doc_values_updates.push(update);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.queries.is_empty() && self.doc_values_updates.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.terms.clear();
+        self.queries.clear();
+        self.doc_values_updates.clear();
+    }
+}
@@ -1,13 +1,25 @@
 use std::fmt;
 
-use crate::store::InputStream;
+use crate::store::{DirectoryError, InputStream, OutputStream};
 
 pub const ID_LENGTH: u32 = 16;
 pub const CODEC_MAGIC: u32 = 0x3fd76c17;
+pub const FOOTER_MAGIC: u32 = !CODEC_MAGIC;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Id(Vec<u8>);
 
+impl Id {
+    /// Generates a new random id, used to identify segments and commits.
+    pub fn random() -> Self {
+        Id((0..ID_LENGTH).map(|_| fastrand::u8(..)).collect())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 impl fmt::Display for Id {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.0.is_empty() {
@@ -31,37 +43,69 @@ impl fmt::Debug for Id {
     }
 }
 
-
-pub fn read_suffix<I: InputStream>(input: &mut I) -> String {
+pub fn read_suffix<I: InputStream>(input: &mut I) -> Result<String, DirectoryError> {
     let suffix_length = input.read_byte();
-    println!("suffix_length - {}", suffix_length);
-
     let suffix_bytes = input.read_bytes(suffix_length as usize);
-    println!("suffix_bytes - {:?}", suffix_bytes);
 
-    return String::from_utf8(suffix_bytes).unwrap();
+    String::from_utf8(suffix_bytes)
+        .map_err(|_| DirectoryError::CorruptIndexError("Header suffix is not UTF-8".to_string()))
 }
 
 pub fn read_id<I: InputStream>(input: &mut I) -> Id {
     Id(input.read_bytes(ID_LENGTH as usize))
 }
 
-pub fn check_header<I: InputStream>(input: &mut I) {
+/// Reads the codec magic starting a Lucene index header, failing if it doesn't match.
+pub fn check_magic<I: InputStream>(input: &mut I) -> Result<(), DirectoryError> {
     let magic = input.read_u32();
-    assert!(magic == CODEC_MAGIC, "Magic not matching");
-    // Check header to get version
-    let codec = input.read_string();
-    println!("codec - {}", codec);
-
-    let version = input.read_int();
-    println!("version - {}", version);
-    // Check header ID
-    let segment_id = read_id(input);
-    println!("segment_id - {:?}", segment_id);
-
-    // Check header suffix
-    let suffix = read_suffix(input);
-    println!("suffix - {:?}", suffix);
+    if magic != CODEC_MAGIC {
+        return Err(DirectoryError::CorruptIndexError(format!(
+            "Codec header mismatch: expected {:#x}, got {:#x}",
+            CODEC_MAGIC, magic
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reads a Lucene index header, failing if it doesn't start with the codec magic.
+pub fn check_header<I: InputStream>(input: &mut I) -> Result<(), DirectoryError> {
+    check_magic(input)?;
+
+    let _codec = input.read_string();
+    let _version = input.read_int();
+    let _segment_id = read_id(input);
+    read_suffix(input)?;
+
+    Ok(())
 }
 
 pub fn check_footer() {}
+
+pub fn write_id<O: OutputStream>(output: &mut O, id: &Id) {
+    output.write_bytes(id.as_bytes());
+}
+
+/// Writes a Lucene index header: magic, codec name, version, id and suffix.
+pub fn write_index_header<O: OutputStream>(
+    output: &mut O,
+    codec: &str,
+    version: u32,
+    id: &Id,
+    suffix: &str,
+) {
+    output.write_u32(CODEC_MAGIC);
+    output.write_string(codec);
+    output.write_int(version);
+    write_id(output, id);
+    output.write_byte(suffix.len() as u8);
+    output.write_bytes(suffix.as_bytes());
+}
+
+/// Writes a Lucene footer: magic, checksum algorithm and CRC32 of everything before it.
+pub fn write_footer<O: OutputStream>(output: &mut O) {
+    output.write_u32(FOOTER_MAGIC);
+    output.write_int(0);
+    let checksum = output.checksum();
+    output.write_long(checksum);
+}
@@ -0,0 +1,519 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    analysis::Analyzer,
+    document::Document,
+    store::{Directory, InputStream, OutputStream},
+};
+
+use super::{
+    index_commit::IndexCommit,
+    index_writer::IndexWriter,
+    segment_commit_info::SegmentCommitInfo,
+    segment_infos::{self, SegmentInfos},
+    segment_reader::SegmentReader,
+    IndexError, Term,
+};
+
+/// Reads a commit of the index, with one `SegmentReader` per segment.
+///
+/// Docs are numbered across segments, a doc of a segment is at the
+/// doc base of the segment plus its number in the segment.
+/// The reader sees the commit as of opening, later changes need a new reader.
+pub struct DirectoryReader {
+    segment_infos: SegmentInfos,
+    readers: Vec<Arc<SegmentReader>>,
+    /// First doc of each segment.
+    doc_bases: Vec<u32>,
+    max_doc: u32,
+    num_docs: u32,
+}
+
+impl DirectoryReader {
+    /// Opens the latest commit in the directory.
+    pub fn open<I, O, D>(directory: &D) -> Result<Self, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        if segment_infos::find_last_commit_generation(directory)?.is_none() {
+            return Err(IndexError::IndexNotFound(
+                "No segments file found in directory".to_string(),
+            ));
+        }
+
+        Self::open_segment_infos(
+            directory,
+            segment_infos::read_latest_segment_infos(directory)?,
+        )
+    }
+
+    /// Opens an older commit, as listed by `IndexWriter::commits`.
+    pub fn open_commit<I, O, D>(directory: &D, commit: &IndexCommit) -> Result<Self, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        if !directory.file_exists(commit.segments_file_name()) {
+            return Err(IndexError::IndexNotFound(format!(
+                "Commit {} was deleted",
+                commit.segments_file_name()
+            )));
+        }
+
+        Self::open_segment_infos(directory, commit.segment_infos(directory)?)
+    }
+
+    /// Opens a near-real-time reader over the segments of a writer, including uncommitted ones.
+    ///
+    /// Buffered docs and deletes are flushed first, without a commit.
+    /// Segments are loaded in memory, so the writer can go on merging and deleting their files.
+    pub fn open_from_writer<A, I, O, D>(writer: &mut IndexWriter<A, D>) -> Result<Self, IndexError>
+    where
+        A: Analyzer,
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O> + Send + Sync + 'static,
+    {
+        writer.flush()?;
+
+        let segment_infos = writer.segment_infos().clone();
+        Self::open_segment_infos(writer.directory(), segment_infos)
+    }
+
+    /// Opens the latest commit if it differs from the one of this reader, `None` otherwise.
+    /// Readers of segments that didn't change are shared with this reader.
+    pub fn open_if_changed<I, O, D>(&self, directory: &D) -> Result<Option<Self>, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        let segment_infos = segment_infos::read_latest_segment_infos(directory)?;
+        if segment_infos.generation == self.segment_infos.generation
+            && self.is_current(&segment_infos)
+        {
+            return Ok(None);
+        }
+
+        self.reopen(directory, segment_infos).map(Some)
+    }
+
+    /// Opens a near-real-time reader like `open_from_writer` if the segments of the writer
+    /// differ from the ones of this reader, `None` otherwise.
+    /// Readers of segments that didn't change are shared with this reader.
+    pub fn open_if_changed_from_writer<A, I, O, D>(
+        &self,
+        writer: &mut IndexWriter<A, D>,
+    ) -> Result<Option<Self>, IndexError>
+    where
+        A: Analyzer,
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O> + Send + Sync + 'static,
+    {
+        writer.flush()?;
+
+        if self.is_current(writer.segment_infos()) {
+            return Ok(None);
+        }
+
+        let segment_infos = writer.segment_infos().clone();
+        self.reopen(writer.directory(), segment_infos).map(Some)
+    }
+
+    /// Whether the segment infos hold the same segments, with the same deletes and updates.
+    fn is_current(&self, segment_infos: &SegmentInfos) -> bool {
+        segment_infos.sis_version == self.segment_infos.sis_version
+            && segment_infos.segments.len() == self.readers.len()
+            && segment_infos
+                .segments
+                .iter()
+                .zip(self.readers.iter())
+                .all(|(segment, reader)| is_same_segment(segment, &reader.commit_info))
+    }
+
+    fn reopen<I, O, D>(
+        &self,
+        directory: &D,
+        segment_infos: SegmentInfos,
+    ) -> Result<Self, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        let current: HashMap<&str, &Arc<SegmentReader>> =
+            self.readers.iter().map(|r| (r.name(), r)).collect();
+
+        let readers = segment_infos
+            .segments
+            .iter()
+            .map(
+                |commit_info| match current.get(commit_info.info.name.as_str()) {
+                    Some(reader) if is_same_segment(commit_info, &reader.commit_info) => {
+                        Ok(Arc::clone(reader))
+                    }
+                    _ => SegmentReader::open(directory, commit_info).map(Arc::new),
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(segment_infos, readers))
+    }
+
+    pub(crate) fn open_segment_infos<I, O, D>(
+        directory: &D,
+        segment_infos: SegmentInfos,
+    ) -> Result<Self, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        let readers = segment_infos
+            .segments
+            .iter()
+            .map(|commit_info| SegmentReader::open(directory, commit_info).map(Arc::new))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(segment_infos, readers))
+    }
+
+    fn new(segment_infos: SegmentInfos, readers: Vec<Arc<SegmentReader>>) -> Self {
+        let mut doc_bases = Vec::with_capacity(readers.len());
+        let mut max_doc = 0;
+        let mut num_docs = 0;
+
+        for reader in readers.iter() {
+            doc_bases.push(max_doc);
+            max_doc += reader.max_doc();
+            num_docs += reader.num_docs();
+        }
+
+        Self {
+            segment_infos,
+            readers,
+            doc_bases,
+            max_doc,
+            num_docs,
+        }
+    }
+
+    /// Segment infos of the commit the reader was opened on.
+    pub fn segment_infos(&self) -> &SegmentInfos {
+        &self.segment_infos
+    }
+
+    /// Version of the segment infos, it changes with every change to the index.
+    pub fn version(&self) -> u64 {
+        self.segment_infos.sis_version
+    }
+
+    /// Readers of the segments, in doc order.
+    pub fn leaves(&self) -> &[Arc<SegmentReader>] {
+        &self.readers
+    }
+
+    /// First doc of each segment, in the order of `leaves`.
+    pub fn doc_bases(&self) -> &[u32] {
+        &self.doc_bases
+    }
+
+    /// Number of docs in the index, including deleted docs.
+    pub fn max_doc(&self) -> u32 {
+        self.max_doc
+    }
+
+    /// Number of live docs in the index.
+    pub fn num_docs(&self) -> u32 {
+        self.num_docs
+    }
+
+    pub fn has_deletions(&self) -> bool {
+        self.num_docs < self.max_doc
+    }
+
+    /// Index of the segment holding a doc, in the order of `leaves`.
+    pub fn reader_index(&self, doc: u32) -> usize {
+        assert!(doc < self.max_doc, "Doc {} out of bounds", doc);
+
+        match self.doc_bases.binary_search(&doc) {
+            // Empty segments share their doc base with the next segment
+            Ok(index) => {
+                let mut index = index;
+                while self.readers[index].max_doc() == 0 {
+                    index += 1;
+                }
+                index
+            }
+            Err(index) => index - 1,
+        }
+    }
+
+    /// Whether the doc is neither deleted nor soft-deleted.
+    pub fn is_live(&self, doc: u32) -> bool {
+        let index = self.reader_index(doc);
+        self.readers[index].is_live(doc - self.doc_bases[index])
+    }
+
+    /// Stored fields of a doc.
+    pub fn document(&self, doc: u32) -> Document {
+        let index = self.reader_index(doc);
+        self.readers[index].document(doc - self.doc_bases[index])
+    }
+
+    /// Number of docs containing the term in all segments, including deleted docs.
+    pub fn doc_freq(&self, term: &Term) -> u32 {
+        self.readers.iter().map(|r| r.doc_freq(term)).sum()
+    }
+}
+
+/// Whether a segment has the same deletes and doc values updates, so its reader can be shared.
+/// Segments are told apart by ID too, as a name may be written again after a rollback.
+fn is_same_segment(a: &SegmentCommitInfo, b: &SegmentCommitInfo) -> bool {
+    a.info.name == b.info.name
+        && a.info.id == b.info.id
+        && a.del_gen == b.del_gen
+        && a.dv_gen == b.dv_gen
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        index::codec_utils::Id,
+        store::{DirectoryError, FSDirectory, OutputStream},
+        test_util::{doc, writer},
+    };
+
+    use super::*;
+
+    fn stored_id(document: &Document) -> String {
+        document.get("id").unwrap().to_string()
+    }
+
+    #[test]
+    fn test_open_without_index() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let result = DirectoryReader::open(&directory);
+
+        assert!(matches!(result, Err(IndexError::IndexNotFound(_))));
+    }
+
+    #[test]
+    fn test_open() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.flush().unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let reader = DirectoryReader::open(&directory).unwrap();
+
+        assert_eq!(reader.leaves().len(), 2);
+        assert_eq!(reader.doc_bases(), &[0, 2]);
+        assert_eq!(reader.max_doc(), 3);
+        assert_eq!(reader.num_docs(), 3);
+        assert!(!reader.has_deletions());
+
+        assert_eq!(reader.reader_index(1), 0);
+        assert_eq!(reader.reader_index(2), 1);
+        assert_eq!(stored_id(&reader.document(2)), "3");
+
+        let tests = Term::new("body".to_string(), "tests".to_string());
+        assert_eq!(reader.doc_freq(&tests), 3);
+        assert!(reader.leaves()[1].norms("body").is_some());
+        assert!(reader.leaves()[1].norms("missing").is_none());
+
+        // Deletes are only seen by readers opened after the commit
+        writer
+            .delete_documents(Term::new("id".to_string(), "2".to_string()))
+            .unwrap();
+        writer.commit().unwrap();
+        let first = reader.segment_infos().clone();
+        assert_eq!(reader.num_docs(), 3);
+
+        let reader = DirectoryReader::open(&directory).unwrap();
+        assert_eq!(reader.num_docs(), 2);
+        assert!(reader.has_deletions());
+        assert!(reader.is_live(0));
+        assert!(!reader.is_live(1));
+        assert!(reader.version() > first.sis_version);
+    }
+
+    #[test]
+    fn test_open_corrupt() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        directory.delete_file("_0.si").unwrap();
+        let mut output = directory.create_file("_0.si").unwrap();
+        output.write_bytes(&[0; 64]);
+        output.flush();
+        drop(output);
+
+        assert!(matches!(
+            DirectoryReader::open(&directory),
+            Err(IndexError::DirectoryError(
+                DirectoryError::CorruptIndexError(_)
+            ))
+        ));
+
+        // Missing files of a segment are errors too
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.flush().unwrap();
+        directory.delete_file("_1.fnm").unwrap();
+        let segment = writer.segment_infos().segments[1].clone();
+        assert!(matches!(
+            SegmentReader::open(&directory, &segment),
+            Err(IndexError::DirectoryError(DirectoryError::IOError(_)))
+        ));
+    }
+
+    #[test]
+    fn test_open_commit() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.commit().unwrap();
+        let commit = writer.commits()[0].clone();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.commit().unwrap();
+
+        // The default policy deleted the first commit
+        let directory = FSDirectory::new(&path).unwrap();
+        assert!(matches!(
+            DirectoryReader::open_commit(&directory, &commit),
+            Err(IndexError::IndexNotFound(_))
+        ));
+
+        let latest = writer.commits()[0].clone();
+        let reader = DirectoryReader::open_commit(&directory, &latest).unwrap();
+        assert_eq!(reader.max_doc(), 2);
+        assert_eq!(reader.segment_infos().generation, latest.generation());
+    }
+
+    #[test]
+    fn test_open_from_writer() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+
+        // Visible without a commit
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        assert_eq!(reader.num_docs(), 2);
+        assert!(writer.commits().is_empty());
+        assert!(reader
+            .open_if_changed_from_writer(&mut writer)
+            .unwrap()
+            .is_none());
+
+        // Unchanged segments are shared
+        writer.add_document(doc("3", "more tests")).unwrap();
+        let second = reader
+            .open_if_changed_from_writer(&mut writer)
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.num_docs(), 3);
+        assert_eq!(second.leaves().len(), 2);
+        assert!(Arc::ptr_eq(&reader.leaves()[0], &second.leaves()[0]));
+        assert_eq!(reader.num_docs(), 2);
+
+        // Segments with new deletes are reopened
+        writer
+            .delete_documents(Term::new("id".to_string(), "1".to_string()))
+            .unwrap();
+        let third = second
+            .open_if_changed_from_writer(&mut writer)
+            .unwrap()
+            .unwrap();
+        assert_eq!(third.num_docs(), 2);
+        assert!(!third.is_live(0));
+        assert!(!Arc::ptr_eq(&second.leaves()[0], &third.leaves()[0]));
+        assert!(Arc::ptr_eq(&second.leaves()[1], &third.leaves()[1]));
+    }
+
+    #[test]
+    fn test_open_if_changed_after_rollback() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.commit().unwrap();
+
+        writer.add_document(doc("2", "rolled back")).unwrap();
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        assert_eq!(reader.num_docs(), 2);
+
+        writer.rollback().unwrap();
+        writer.add_document(doc("3", "after rollback")).unwrap();
+
+        let reopened = reader
+            .open_if_changed_from_writer(&mut writer)
+            .unwrap()
+            .unwrap();
+        let ids: Vec<String> = (0..reopened.max_doc())
+            .map(|doc| stored_id(&reopened.document(doc)))
+            .collect();
+        assert_eq!(ids, vec!["1", "3"]);
+        assert!(Arc::ptr_eq(&reader.leaves()[0], &reopened.leaves()[0]));
+        assert!(!Arc::ptr_eq(&reader.leaves()[1], &reopened.leaves()[1]));
+
+        // A segment written again under the same name is not the same segment
+        let segment = &reader.leaves()[1].commit_info;
+        let mut rewritten = segment.clone();
+        rewritten.info.id = Id::random();
+        assert!(is_same_segment(segment, segment));
+        assert!(!is_same_segment(segment, &rewritten));
+
+        writer.commit().unwrap();
+        let directory = FSDirectory::new(&path).unwrap();
+        let committed = reopened.open_if_changed(&directory).unwrap().unwrap();
+        assert_eq!(committed.num_docs(), 2);
+        assert_eq!(stored_id(&committed.document(1)), "3");
+    }
+
+    #[test]
+    fn test_open_if_changed() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let reader = DirectoryReader::open(&directory).unwrap();
+
+        // Uncommitted changes are not seen
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.flush().unwrap();
+        assert!(reader.open_if_changed(&directory).unwrap().is_none());
+
+        writer.commit().unwrap();
+        let reopened = reader.open_if_changed(&directory).unwrap().unwrap();
+        assert_eq!(reopened.num_docs(), 2);
+        assert!(Arc::ptr_eq(&reader.leaves()[0], &reopened.leaves()[0]));
+        assert!(reopened.open_if_changed(&directory).unwrap().is_none());
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::{BTreeMap, HashSet};
+
+use radix_fmt::radix_36;
+
+use crate::{
+    store::{Directory, DirectoryError, InputStream, OutputStream},
+    util::FixedBitSet,
+};
+
+use super::{field_info, segment_commit_info::SegmentCommitInfo};
+
+pub const DOC_VALUES_EXTENSION: &str = "dvd";
+
+/// Type of per-document values of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocValuesType {
+    Numeric,
+}
+
+impl DocValuesType {
+    fn code(&self) -> u8 {
+        match self {
+            DocValuesType::Numeric => 1,
+        }
+    }
+
+    fn from_code(code: u8) -> Result<Self, DirectoryError> {
+        match code {
+            1 => Ok(DocValuesType::Numeric),
+            _ => Err(DirectoryError::CorruptIndexError(format!(
+                "Unknown doc values type {}",
+                code
+            ))),
+        }
+    }
+}
+
+/// Values of a field for every doc of a segment, `None` when a doc has no value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocValues {
+    Numeric(Vec<Option<i64>>),
+}
+
+impl DocValues {
+    /// Empty values of the type for `max_doc` docs.
+    pub fn new(doc_values_type: DocValuesType, max_doc: u32) -> Self {
+        match doc_values_type {
+            DocValuesType::Numeric => DocValues::Numeric(vec![None; max_doc as usize]),
+        }
+    }
+
+    pub fn doc_values_type(&self) -> DocValuesType {
+        match self {
+            DocValues::Numeric(_) => DocValuesType::Numeric,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            DocValues::Numeric(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn has_value(&self, doc: u32) -> bool {
+        match self {
+            DocValues::Numeric(values) => values[doc as usize].is_some(),
+        }
+    }
+
+    pub fn numeric(&self, doc: u32) -> Option<i64> {
+        match self {
+            DocValues::Numeric(values) => values[doc as usize],
+        }
+    }
+
+    pub fn set_numeric(&mut self, doc: u32, value: i64) {
+        match self {
+            DocValues::Numeric(values) => values[doc as usize] = Some(value),
+        }
+    }
+
+    /// Copies the value of `from_doc` in `from`, which must have the same type, to `doc`.
+    pub fn copy_value(&mut self, doc: u32, from: &DocValues, from_doc: u32) {
+        match (self, from) {
+            (DocValues::Numeric(values), DocValues::Numeric(from)) => {
+                values[doc as usize] = from[from_doc as usize]
+            }
+        }
+    }
+
+    /// Grows or shrinks to `len` docs, docs added have no value.
+    pub fn resize(&mut self, len: usize) {
+        match self {
+            DocValues::Numeric(values) => values.resize(len, None),
+        }
+    }
+}
+
+/// Doc values of a field in a segment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocValuesField {
+    pub name: String,
+    /// Whether a value marks the doc as soft-deleted
+    pub soft_deletes: bool,
+    pub values: DocValues,
+}
+
+/// Doc values file of a segment, `_0.dvd` for generation -1 and `_0_1.dvd` for updates.
+pub fn file_name(segment: &str, dv_gen: i64) -> String {
+    if dv_gen == -1 {
+        format!("{}.{}", segment, DOC_VALUES_EXTENSION)
+    } else {
+        format!(
+            "{}_{}.{}",
+            segment,
+            radix_36(dv_gen as u64),
+            DOC_VALUES_EXTENSION
+        )
+    }
+}
+
+/// Writes doc values of fields.
+/// Fields are self-describing, so update generations can add fields unknown to the segment.
+pub fn write<'a, D: Directory>(
+    directory: &D,
+    name: &str,
+    fields: impl ExactSizeIterator<Item = &'a DocValuesField>,
+) -> Result<(), DirectoryError> {
+    let mut output = directory.create_file(name)?;
+
+    output.write_vint(fields.len() as u32);
+
+    for field in fields {
+        output.write_string(&field.name);
+        output.write_byte(field.values.doc_values_type().code());
+        output.write_bool(field.soft_deletes);
+
+        match &field.values {
+            DocValues::Numeric(values) => {
+                for value in values {
+                    match value {
+                        Some(value) => {
+                            output.write_bool(true);
+                            output.write_vlong(zig_zag_encode(*value));
+                        }
+                        None => output.write_bool(false),
+                    }
+                }
+            }
+        }
+    }
+
+    output.flush();
+
+    Ok(())
+}
+
+/// Reads doc values of fields written by `write`.
+pub fn read<D: Directory>(
+    directory: &D,
+    name: &str,
+    max_doc: u32,
+) -> Result<Vec<DocValuesField>, DirectoryError> {
+    let mut input = directory.open_file(name)?;
+
+    let count = input.read_vint();
+    let mut fields = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        let name = input.read_string();
+        let doc_values_type = DocValuesType::from_code(input.read_byte())?;
+        let soft_deletes = input.read_bool();
+
+        let values = match doc_values_type {
+            DocValuesType::Numeric => DocValues::Numeric(
+                (0..max_doc)
+                    .map(|_| match input.read_bool() {
+                        true => Some(zig_zag_decode(input.read_vlong())),
+                        false => None,
+                    })
+                    .collect(),
+            ),
+        };
+
+        fields.push(DocValuesField {
+            name,
+            soft_deletes,
+            values,
+        });
+    }
+
+    Ok(fields)
+}
+
+/// Reads doc values of a segment, with updated fields read from their latest generation.
+pub fn read_segment<D: Directory>(
+    directory: &D,
+    commit_info: &SegmentCommitInfo,
+) -> Result<BTreeMap<String, DocValuesField>, DirectoryError> {
+    let info = &commit_info.info;
+    let base_file = file_name(&info.name, -1);
+    let mut fields = BTreeMap::new();
+
+    if info.files.contains(&base_file) {
+        for field in read(directory, &base_file, info.doc_count)? {
+            fields.insert(field.name.clone(), field);
+        }
+    }
+
+    if commit_info.dv_files.is_empty() {
+        return Ok(fields);
+    }
+
+    let field_infos = field_info::read_segment(directory, commit_info)?;
+    for (&field_number, files) in commit_info.dv_files.iter() {
+        let field_name = field_infos.get_field_name(field_number).ok_or_else(|| {
+            DirectoryError::CorruptIndexError(format!(
+                "Unknown doc values field {} of segment {}",
+                field_number, info.name
+            ))
+        })?;
+
+        for file in files {
+            for field in read(directory, file, info.doc_count)? {
+                if field.name == field_name {
+                    fields.insert(field.name.clone(), field);
+                }
+            }
+        }
+    }
+
+    Ok(fields)
+}
+
+/// Writes updated fields as a new doc values generation of the segment.
+/// Updates `dv_gen` and points `dv_files` of the fields to the new file.
+/// Fields the segment doesn't have are added to a new field infos generation.
+pub fn write_update<D: Directory>(
+    directory: &D,
+    commit_info: &mut SegmentCommitInfo,
+    fields: &BTreeMap<String, DocValuesField>,
+) -> Result<(), DirectoryError> {
+    let dv_gen = if commit_info.dv_gen == -1 {
+        1
+    } else {
+        commit_info.dv_gen + 1
+    };
+    let name = file_name(&commit_info.info.name, dv_gen);
+
+    let mut field_infos = field_info::read_segment(directory, commit_info)?;
+    let size = field_infos.size();
+    for field_name in fields.keys() {
+        field_infos.add(field_name.clone(), false);
+    }
+
+    write(directory, &name, fields.values())?;
+
+    if field_infos.size() > size {
+        let field_infos_file = field_info::file_name(&commit_info.info.name, dv_gen);
+        field_infos.write(directory, &field_infos_file);
+
+        commit_info.field_infos_gen = dv_gen;
+        commit_info.field_infos_files = HashSet::from([field_infos_file]);
+    }
+
+    commit_info.dv_gen = dv_gen;
+    for field_name in fields.keys() {
+        let field_number = field_infos.get_field_number(field_name).unwrap();
+        commit_info.dv_files.insert(field_number, HashSet::from([name.clone()]));
+    }
+    commit_info.size_in_bytes = None;
+
+    Ok(())
+}
+
+/// Docs having a value for the soft deletes field, if the segment has one.
+pub fn soft_deleted_docs(fields: &BTreeMap<String, DocValuesField>, max_doc: u32) -> FixedBitSet {
+    let mut soft_deleted = FixedBitSet::new(max_doc);
+
+    for field in fields.values().filter(|f| f.soft_deletes) {
+        for doc in 0..max_doc {
+            if field.values.has_value(doc) {
+                soft_deleted.set(doc);
+            }
+        }
+    }
+
+    soft_deleted
+}
+
+/// Number of soft-deleted docs, that are not also hard-deleted.
+pub fn soft_delete_count(
+    fields: &BTreeMap<String, DocValuesField>,
+    live_docs: &FixedBitSet,
+) -> u32 {
+    let soft_deleted = soft_deleted_docs(fields, live_docs.len());
+
+    (0..live_docs.len())
+        .filter(|&doc| live_docs.get(doc) && soft_deleted.get(doc))
+        .count() as u32
+}
+
+fn zig_zag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn zig_zag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::FSDirectory;
+
+    use super::*;
+
+    fn numeric_field(name: &str, soft_deletes: bool, values: Vec<Option<i64>>) -> DocValuesField {
+        DocValuesField {
+            name: name.to_string(),
+            soft_deletes,
+            values: DocValues::Numeric(values),
+        }
+    }
+
+    #[test]
+    fn test_zig_zag() {
+        for value in [0, 1, -1, 63, -64, i64::MAX, i64::MIN] {
+            assert_eq!(zig_zag_decode(zig_zag_encode(value)), value);
+        }
+        assert_eq!(zig_zag_encode(-1), 1);
+        assert_eq!(zig_zag_encode(1), 2);
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("_0", -1), "_0.dvd");
+        assert_eq!(file_name("_0", 1), "_0_1.dvd");
+    }
+
+    #[test]
+    fn test_doc_values_io() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let fields = [
+            numeric_field("price", false, vec![Some(10), None, Some(-3)]),
+            numeric_field("__soft_deletes", true, vec![None, Some(1), None]),
+        ];
+
+        write(&directory, "_0.dvd", fields.iter()).unwrap();
+
+        assert_eq!(read(&directory, "_0.dvd", 3).unwrap(), fields);
+    }
+
+    #[test]
+    fn test_read_unknown_type() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let mut output = directory.create_file("_0.dvd").unwrap();
+        output.write_vint(1);
+        output.write_string("price");
+        output.write_byte(9);
+        output.flush();
+        drop(output);
+
+        assert!(matches!(
+            read(&directory, "_0.dvd", 1),
+            Err(DirectoryError::CorruptIndexError(_))
+        ));
+    }
+
+    #[test]
+    fn test_soft_delete_count() {
+        let fields = BTreeMap::from([
+            (
+                "price".to_string(),
+                numeric_field("price", false, vec![Some(1), Some(2), Some(3)]),
+            ),
+            (
+                "__soft_deletes".to_string(),
+                numeric_field("__soft_deletes", true, vec![Some(1), Some(1), None]),
+            ),
+        ]);
+
+        let mut live_docs = FixedBitSet::all(3);
+        assert_eq!(soft_delete_count(&fields, &live_docs), 2);
+
+        // Hard deletes are not counted as soft deletes
+        live_docs.clear(0);
+        assert_eq!(soft_delete_count(&fields, &live_docs), 1);
+    }
+}
@@ -1,19 +1,37 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{
     analysis::Analyzer,
     document::Document,
-    store::{Directory, FSDirectory, InputStream, OutputStream},
+    store::{Directory, DirectoryError, FSDirectory, InputStream, OutputStream},
+    version,
 };
 
-use super::{field_info::FieldInfos, fields_writer::FieldsWriter, Posting, Term};
+use super::{
+    codec_utils::Id,
+    doc_values::{self, DocValues, DocValuesField, DocValuesType},
+    field_info::FieldInfos,
+    fields_writer::FieldsWriter,
+    norms::{self, NORMS_EXTENSION},
+    postings_writer::PostingsWriter,
+    segment_commit_info::SegmentCommitInfo,
+    segment_info::{self, SegmentInfo},
+    IndexError, Posting, Term,
+};
 
 pub const MAX_FIELD_LENGTH: usize = 1024;
 
+/// Codec name recorded for segments written by ferrocene.
+pub const CODEC_NAME: &str = "Ferrocene";
+
 pub type FSDocumentWriter<A> = DocumentWriter<A, FSDirectory>;
 
+#[derive(Default)]
 pub struct PostingsTable {
-    pub table: HashMap<Rc<Term>, Posting>,
+    pub table: HashMap<Arc<Term>, Posting>,
 }
 
 impl PostingsTable {
@@ -23,7 +41,7 @@ impl PostingsTable {
         }
     }
 
-    pub fn add(&mut self, term: Rc<Term>, position: usize) {
+    pub fn add(&mut self, term: Arc<Term>, position: usize) {
         let term_exists = self.table.contains_key(&term);
 
         if term_exists {
@@ -45,6 +63,14 @@ impl PostingsTable {
     }
 }
 
+/// Positions of a term within a buffered doc.
+pub struct BufferedPosting {
+    pub doc: u32,
+    pub positions: Vec<u32>,
+}
+
+/// Inverts documents and buffers them in memory as a single segment,
+/// until `flush` writes the segment files.
 pub struct DocumentWriter<A, D> {
     pub analyzer: A,
     pub directory: D,
@@ -53,6 +79,20 @@ pub struct DocumentWriter<A, D> {
     pub postings_table: PostingsTable,
     pub field_lengths: Vec<usize>,
     pub field_boosts: Vec<f32>,
+    /// Segment being buffered, set by the first doc added after a flush.
+    pub segment: Option<String>,
+    /// Number of docs buffered for the segment.
+    pub num_docs: u32,
+    /// Buffered docs, for writing stored fields.
+    pub docs: Vec<Document>,
+    /// Buffered postings of all docs, in term order.
+    pub postings: BTreeMap<Arc<Term>, Vec<BufferedPosting>>,
+    /// Buffered norms of indexed fields, by field number.
+    pub norms: BTreeMap<u32, Vec<u8>>,
+    /// Buffered doc values, by field name.
+    pub doc_values: BTreeMap<String, DocValuesField>,
+    /// Doc values field marking soft-deleted docs.
+    pub soft_deletes_field: Option<String>,
 }
 
 impl<A, I, O, D> DocumentWriter<A, D>
@@ -71,19 +111,37 @@ where
             postings_table: PostingsTable::new(),
             field_lengths: Vec::new(),
             field_boosts: Vec::new(),
+            segment: None,
+            num_docs: 0,
+            docs: Vec::new(),
+            postings: BTreeMap::new(),
+            norms: BTreeMap::new(),
+            doc_values: BTreeMap::new(),
+            soft_deletes_field: None,
         }
     }
 
-    pub fn add_doc(&mut self, segment_id: &str, doc: Document) {
-        // Write field names
-        self.field_infos.add_doc(&doc);
-        self.field_infos
-            .write(&self.directory, &format!("{}.fnm", segment_id));
+    /// Buffers a doc into the segment `segment_id`.
+    /// All docs of a segment are added before it is flushed.
+    pub fn add_doc(&mut self, segment_id: &str, doc: Document) -> Result<(), IndexError> {
+        // Checked first, so that nothing of a doc with invalid doc values is buffered
+        let doc_values = self.doc_values_of(&doc)?;
+
+        match &self.segment {
+            Some(segment) if segment != segment_id => {
+                return Err(IndexError::IllegalState(format!(
+                    "Segment {} must be flushed before adding docs to {}",
+                    segment, segment_id
+                )))
+            }
+            Some(_) => {}
+            None => self.segment = Some(segment_id.to_owned()),
+        }
 
-        // Write field values
-        let mut fields_writer = FieldsWriter::new(&self.directory, segment_id, &self.field_infos);
-        fields_writer.add_doc(&doc);
-        drop(fields_writer);
+        let doc_id = self.num_docs;
+
+        // Add field names
+        self.field_infos.add_doc(&doc);
 
         // Invert doc into postingTable
         self.postings_table.clear();
@@ -92,14 +150,223 @@ where
 
         self.invert_doc(&doc);
 
-        // Sort postingTable into an array for postings
-        let mut postings = self.postings_table.values();
-        postings.sort_by_key(|p| &p.term);
+        // Buffer postings, kept sorted by term
+        for posting in self.postings_table.values() {
+            self.postings
+                .entry(posting.term.clone())
+                .or_default()
+                .push(BufferedPosting {
+                    doc: doc_id,
+                    positions: posting.positions.clone(),
+                });
+        }
+
+        // Buffer norms of indexed fields
+        for field_info in self.field_infos.by_number.iter() {
+            if field_info.is_indexed {
+                let norms = self.norms.entry(field_info.number).or_default();
+                norms.resize(doc_id as usize, 0);
+
+                let length = self.field_lengths[field_info.number as usize];
+                norms.push(norms::int_to_byte4(length as u32));
+            }
+        }
+
+        // Buffer doc values
+        for (name, doc_values_type, value) in doc_values {
+            self.doc_values_field(name, doc_values_type, doc_id)
+                .values
+                .set_numeric(doc_id, value);
+        }
+
+        // Field values are written on flush
+        self.docs.push(doc);
+        self.num_docs += 1;
+
+        Ok(())
+    }
+
+    /// Writes all buffered docs as a new segment and clears the buffers.
+    /// Returns `None` when nothing was buffered.
+    pub fn flush(&mut self) -> Result<Option<SegmentCommitInfo>, DirectoryError> {
+        let segment = match self.segment.take() {
+            Some(segment) => segment,
+            None => return Ok(None),
+        };
+
+        let mut files = HashSet::new();
+
+        // Write field names
+        let field_infos_file = format!("{}.fnm", segment);
+        self.field_infos.write(&self.directory, &field_infos_file);
+        files.insert(field_infos_file);
+
+        // Write field values
+        let mut fields_writer = FieldsWriter::new(&self.directory, &segment, &self.field_infos);
+        for doc in self.docs.iter() {
+            fields_writer.add_doc(doc);
+        }
+        fields_writer.close();
+        files.insert(format!("{}.fdt", segment));
+        files.insert(format!("{}.fdx", segment));
 
         // Write postings
-        println!("{:?}", postings);
+        let mut postings_writer = PostingsWriter::new(&self.directory, &segment)?;
+        for (term, postings) in self.postings.iter() {
+            let field_number = self.field_infos.get_field_number(&term.field).unwrap();
+
+            postings_writer.start_term(field_number, &term.text);
+            for posting in postings {
+                postings_writer.add_doc(posting.doc, &posting.positions);
+            }
+            postings_writer.finish_term();
+        }
+        postings_writer.finish();
+        files.extend(PostingsWriter::<O>::files(&segment));
 
         // Write norms of indexed fields
+        for values in self.norms.values_mut() {
+            values.resize(self.num_docs as usize, 0);
+        }
+        norms::write(&self.directory, &segment, &self.norms)?;
+        files.insert(format!("{}.{}", segment, NORMS_EXTENSION));
+
+        // Write doc values, if any doc has them
+        if !self.doc_values.is_empty() {
+            for field in self.doc_values.values_mut() {
+                field.values.resize(self.num_docs as usize);
+            }
+
+            let doc_values_file = doc_values::file_name(&segment, -1);
+            doc_values::write(&self.directory, &doc_values_file, self.doc_values.values())?;
+            files.insert(doc_values_file);
+        }
+
+        let mut info = SegmentInfo {
+            name: segment,
+            id: Id::random(),
+            codec: CODEC_NAME.to_string(),
+            version: version::LATEST,
+            min_version: Some(version::LATEST),
+            doc_count: self.num_docs,
+            is_compound: false,
+            diagnostics: segment_info::diagnostics("flush"),
+            files,
+            attributes: HashMap::new(),
+            num_sort_fields: 0,
+        };
+        segment_info::write(&self.directory, &mut info)?;
+
+        self.reset();
+
+        let mut commit_info = SegmentCommitInfo::new(info);
+        commit_info.size_in_bytes(&self.directory)?;
+
+        Ok(Some(commit_info))
+    }
+
+    /// Buffered docs containing the term, among the first `doc_id_upto` docs.
+    pub fn docs_with_term(&self, term: &Term, doc_id_upto: u32) -> Vec<u32> {
+        self.postings
+            .get(term)
+            .map(|postings| {
+                postings
+                    .iter()
+                    .map(|p| p.doc)
+                    .take_while(|&doc| doc < doc_id_upto)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sets a numeric doc value of buffered docs containing the term,
+    /// among the first `doc_id_upto` docs.
+    pub fn update_doc_values(&mut self, term: &Term, doc_id_upto: u32, field: &str, value: i64) {
+        for doc in self.docs_with_term(term, doc_id_upto) {
+            self.doc_values_field(field, DocValuesType::Numeric, doc)
+                .values
+                .set_numeric(doc, value);
+        }
+    }
+
+    /// Buffered doc values of a field, with room for `doc`.
+    /// Doc values of the doc, by field name. Fails on values that are not numbers,
+    /// fields buffered with another type, and fields with more than one value in the doc.
+    fn doc_values_of<'d>(
+        &self,
+        doc: &'d Document,
+    ) -> Result<Vec<(&'d str, DocValuesType, i64)>, IndexError> {
+        let mut doc_values: Vec<(&str, DocValuesType, i64)> = Vec::new();
+
+        for field in doc.fields.iter() {
+            let doc_values_type = match field.doc_values {
+                Some(doc_values_type) => doc_values_type,
+                None => continue,
+            };
+
+            let value = field.value.parse().map_err(|_| {
+                IndexError::IllegalArgument(format!("Invalid numeric doc value {}", field))
+            })?;
+
+            if doc_values.iter().any(|(name, _, _)| *name == field.name) {
+                return Err(IndexError::IllegalArgument(format!(
+                    "Doc values field {} appears more than once in the doc",
+                    field.name
+                )));
+            }
+
+            if let Some(buffered) = self.doc_values.get(&field.name) {
+                if buffered.values.doc_values_type() != doc_values_type {
+                    return Err(IndexError::IllegalArgument(format!(
+                        "Doc values field {} has type {:?}, not {:?}",
+                        field.name,
+                        buffered.values.doc_values_type(),
+                        doc_values_type
+                    )));
+                }
+            }
+
+            doc_values.push((&field.name, doc_values_type, value));
+        }
+
+        Ok(doc_values)
+    }
+
+    fn doc_values_field(
+        &mut self,
+        name: &str,
+        doc_values_type: DocValuesType,
+        doc: u32,
+    ) -> &mut DocValuesField {
+        let soft_deletes = self.soft_deletes_field.as_deref() == Some(name);
+        let field = self
+            .doc_values
+            .entry(name.to_owned())
+            .or_insert_with(|| DocValuesField {
+                name: name.to_owned(),
+                soft_deletes,
+                values: DocValues::new(doc_values_type, 0),
+            });
+
+        if field.values.len() <= doc as usize {
+            field.values.resize(doc as usize + 1);
+        }
+
+        field
+    }
+
+    /// Drops all buffered docs, without writing anything.
+    pub fn reset(&mut self) {
+        self.field_infos = FieldInfos::new();
+        self.postings_table.clear();
+        self.field_lengths.clear();
+        self.field_boosts.clear();
+        self.segment = None;
+        self.num_docs = 0;
+        self.docs.clear();
+        self.postings.clear();
+        self.norms.clear();
+        self.doc_values.clear();
     }
 
     fn invert_doc(&mut self, doc: &Document) {
@@ -111,18 +378,18 @@ where
 
             let field_name = &field.name;
             let field_value = &field.value;
-            let field_number = self.field_infos.get_field_number(&field_name).unwrap();
+            let field_number = self.field_infos.get_field_number(field_name).unwrap();
             let mut position = self.field_lengths[field_number as usize];
 
             // No need to run analyzer for non-analyzed fields.
             // Whole field is stored as a single term.
             if !field.is_analyzed {
-                let term = Rc::new(Term::new(field_name.to_owned(), field_value.to_owned()));
+                let term = Arc::new(Term::new(field_name.to_owned(), field_value.to_owned()));
                 self.postings_table.add(term, position);
                 position += 1;
             } else {
-                for token in self.analyzer.analyze(&field_value) {
-                    let term = Rc::new(Term::new(field_name.to_owned(), token.to_owned()));
+                for token in self.analyzer.analyze(field_value) {
+                    let term = Arc::new(Term::new(field_name.to_owned(), token.to_owned()));
 
                     self.postings_table.add(term, position);
 
@@ -137,29 +404,6 @@ where
             self.field_boosts[field_number as usize] *= field.boost;
         }
     }
-
-    fn _write_postings(&mut self, segment_id: &str, postings: &Vec<&Posting>) {
-        let mut _freq = self
-            .directory
-            .create_file(&format!("{}.frq", segment_id))
-            .unwrap();
-        let mut _pos = self
-            .directory
-            .create_file(&format!("{}.prx", segment_id))
-            .unwrap();
-
-        // TermInfosWriter
-        // TermInfo
-
-        // Look through postings
-        for _posting in postings {
-            // Add an entry to the dictionary with pointers to prox and freq files.
-
-            // Add an entry to the freq file.
-
-            // Write positiongs using delta-encoding.
-        }
-    }
 }
 
 #[cfg(test)]
@@ -172,15 +416,15 @@ mod tests {
     fn test_postings_table() {
         let mut postings_table = PostingsTable::new();
 
-        let term1 = Rc::new(Term::new("title".to_owned(), "Tests".to_owned()));
-        let term2 = Rc::new(Term::new("description".to_owned(), "unit".to_owned()));
-        let term3 = Rc::new(Term::new("description".to_owned(), "tests".to_owned()));
-        let term4 = Rc::new(Term::new("description".to_owned(), "and".to_owned()));
-        let term5 = Rc::new(Term::new(
+        let term1 = Arc::new(Term::new("title".to_owned(), "Tests".to_owned()));
+        let term2 = Arc::new(Term::new("description".to_owned(), "unit".to_owned()));
+        let term3 = Arc::new(Term::new("description".to_owned(), "tests".to_owned()));
+        let term4 = Arc::new(Term::new("description".to_owned(), "and".to_owned()));
+        let term5 = Arc::new(Term::new(
             "description".to_owned(),
             "integration".to_owned(),
         ));
-        let term6 = Rc::new(Term::new("description".to_owned(), "tests".to_owned()));
+        let term6 = Arc::new(Term::new("description".to_owned(), "tests".to_owned()));
 
         postings_table.add(term1.clone(), 0);
         postings_table.add(term2.clone(), 0);
@@ -221,29 +465,133 @@ mod tests {
         ));
 
         // Execute
-        document_writer.add_doc("test-segment", doc);
+        document_writer.add_doc("test-segment", doc).unwrap();
 
         // Verify Field Infos
-        let field_infos = document_writer.field_infos;
+        let field_infos = &document_writer.field_infos;
         assert_eq!(field_infos.size(), 2);
         assert_eq!(field_infos.get_field_name(0).unwrap(), "title");
         assert_eq!(field_infos.get_field_name(1).unwrap(), "description");
 
         // Verify Postings Table
-        let postings_table = document_writer.postings_table;
+        let postings_table = &document_writer.postings_table;
         assert_eq!(postings_table.table.len(), 5);
 
-        let term1 = Rc::new(Term::new("title".to_owned(), "Tests".to_owned()));
+        let term1 = Arc::new(Term::new("title".to_owned(), "Tests".to_owned()));
         assert_eq!(postings_table.table.get(&term1).unwrap().freq, 1);
         assert_eq!(postings_table.table.get(&term1).unwrap().positions.len(), 1);
         assert_eq!(postings_table.table.get(&term1).unwrap().positions[0], 0);
 
-        let term2: Rc<Term> = Rc::new(Term::new("description".to_owned(), "tests".to_owned()));
+        let term2: Arc<Term> = Arc::new(Term::new("description".to_owned(), "tests".to_owned()));
         assert_eq!(postings_table.table.get(&term2).unwrap().freq, 2);
         assert_eq!(postings_table.table.get(&term2).unwrap().positions.len(), 2);
         assert_eq!(postings_table.table.get(&term2).unwrap().positions[0], 1);
         assert_eq!(postings_table.table.get(&term2).unwrap().positions[1], 4);
 
         // Verify files generated
+        let segment = document_writer.flush().unwrap().unwrap();
+        assert_eq!(segment.info.name, "test-segment");
+        assert_eq!(segment.info.doc_count, 1);
+
+        for ext in ["fnm", "fdt", "fdx", "tis", "frq", "prx", "nrm", "si"] {
+            let file = format!("test-segment.{}", ext);
+            assert!(segment.info.files.contains(&file));
+            assert!(document_writer.directory.file_exists(&file));
+        }
+
+        // Buffers are cleared
+        assert_eq!(document_writer.num_docs, 0);
+        assert!(document_writer.segment.is_none());
+        assert!(document_writer.postings.is_empty());
+    }
+
+    #[test]
+    fn test_buffered_postings() {
+        let root_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(root_dir.path().join("test-index")).unwrap();
+
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        let mut doc1 = Document::new();
+        doc1.add(Field::text("body".to_owned(), "unit tests".to_owned()));
+        let mut doc2 = Document::new();
+        doc2.add(Field::keyword("id".to_owned(), "2".to_owned()));
+        doc2.add(Field::text("body".to_owned(), "tests and tests".to_owned()));
+
+        document_writer.add_doc("_0", doc1).unwrap();
+        document_writer.add_doc("_0", doc2).unwrap();
+
+        assert_eq!(document_writer.num_docs, 2);
+
+        let tests = Arc::new(Term::new("body".to_owned(), "tests".to_owned()));
+        let postings = document_writer.postings.get(&tests).unwrap();
+        assert_eq!(postings.len(), 2);
+        assert_eq!(postings[0].doc, 0);
+        assert_eq!(postings[0].positions, vec![1]);
+        assert_eq!(postings[1].doc, 1);
+        assert_eq!(postings[1].positions, vec![0, 2]);
+
+        // Norms hold field lengths, zero when the field is missing
+        let body = document_writer.field_infos.get_field_number("body").unwrap();
+        let id = document_writer.field_infos.get_field_number("id").unwrap();
+        assert_eq!(document_writer.norms.get(&body).unwrap(), &vec![2, 3]);
+        assert_eq!(document_writer.norms.get(&id).unwrap(), &vec![0, 1]);
+
+        let segment = document_writer.flush().unwrap().unwrap();
+        assert_eq!(segment.info.doc_count, 2);
+
+        let norms = norms::read(&document_writer.directory, "_0", 2).unwrap();
+        assert_eq!(norms.get(&id).unwrap(), &vec![0, 1]);
+    }
+
+    #[test]
+    fn test_add_doc_with_invalid_doc_values() {
+        let root_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(root_dir.path().join("test-index")).unwrap();
+
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        let mut not_a_number = Field::numeric_doc_values("price".to_owned(), 0);
+        not_a_number.value = "cheap".to_owned();
+        let mut doc1 = Document::new();
+        doc1.add(Field::text("body".to_owned(), "unit tests".to_owned()));
+        doc1.add(not_a_number);
+
+        let mut doc2 = Document::new();
+        doc2.add(Field::numeric_doc_values("price".to_owned(), 1));
+        doc2.add(Field::numeric_doc_values("price".to_owned(), 2));
+
+        for doc in [doc1, doc2] {
+            assert!(matches!(
+                document_writer.add_doc("_0", doc),
+                Err(IndexError::IllegalArgument(_))
+            ));
+        }
+
+        // Nothing of the docs is buffered
+        assert_eq!(document_writer.num_docs, 0);
+        assert!(document_writer.segment.is_none());
+        assert_eq!(document_writer.field_infos.size(), 0);
+        assert!(document_writer.postings.is_empty());
+        assert!(document_writer.doc_values.is_empty());
+        assert!(document_writer.flush().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_add_doc_to_other_segment() {
+        let root_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(root_dir.path().join("test-index")).unwrap();
+
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        document_writer.add_doc("_0", Document::new()).unwrap();
+        assert!(matches!(
+            document_writer.add_doc("_1", Document::new()),
+            Err(IndexError::IllegalState(_))
+        ));
+        assert_eq!(document_writer.num_docs, 1);
     }
 }
@@ -1,10 +1,16 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, sync::Arc};
+
+use radix_fmt::radix_36;
 
 use crate::{
     document::{Document, Field},
-    store::{Directory, InputStream, OutputStream},
+    store::{Directory, DirectoryError, InputStream, OutputStream},
 };
 
+use super::segment_commit_info::SegmentCommitInfo;
+
+pub const FIELD_INFOS_EXTENSION: &str = "fnm";
+
 pub struct FieldInfo {
     pub name: String,
     pub is_indexed: bool,
@@ -36,9 +42,10 @@ pub struct FieldInfo {
 ///                           └──────────────────────────────────┘       
 ///                                        by_number    
 /// ```
+#[derive(Default)]
 pub struct FieldInfos {
-    pub by_number: Vec<Rc<FieldInfo>>,
-    pub by_name: HashMap<String, Rc<FieldInfo>>,
+    pub by_number: Vec<Arc<FieldInfo>>,
+    pub by_name: HashMap<String, Arc<FieldInfo>>,
 }
 
 impl FieldInfos {
@@ -71,7 +78,7 @@ impl FieldInfos {
             return;
         }
 
-        let field_info = Rc::new(FieldInfo {
+        let field_info = Arc::new(FieldInfo {
             name: name.clone(),
             is_indexed,
             number: self.by_number.len() as u32,
@@ -90,11 +97,11 @@ impl FieldInfos {
             .map(|fi| fi.name.clone())
     }
 
-    pub fn get_field_info_by_name(&self, name: &str) -> Option<Rc<FieldInfo>> {
+    pub fn get_field_info_by_name(&self, name: &str) -> Option<Arc<FieldInfo>> {
         self.by_name.get(name).cloned()
     }
 
-    pub fn get_field_info_by_number(&self, number: u32) -> Option<Rc<FieldInfo>> {
+    pub fn get_field_info_by_number(&self, number: u32) -> Option<Arc<FieldInfo>> {
         self.by_number.get(number as usize).cloned()
     }
 
@@ -102,13 +109,13 @@ impl FieldInfos {
         self.by_number.len()
     }
 
-    pub fn read<I, O, D>(&mut self, dir: &D, filename: &str)
+    pub fn read<I, O, D>(&mut self, dir: &D, filename: &str) -> Result<(), DirectoryError>
     where
         I: InputStream,
         O: OutputStream,
         D: Directory<Input = I, Output = O>,
     {
-        let mut input = dir.open_file(filename).unwrap();
+        let mut input = dir.open_file(filename)?;
 
         let size = input.read_vint() as usize;
 
@@ -118,6 +125,8 @@ impl FieldInfos {
 
             self.add(name, is_indexed);
         }
+
+        Ok(())
     }
 
     pub fn write<I, O, D>(&self, dir: &D, filename: &str)
@@ -137,7 +146,37 @@ impl FieldInfos {
     }
 }
 
+/// Field infos file of a segment, `_0.fnm` for generation -1 and `_0_1.fnm` once
+/// doc values updates added fields.
+pub fn file_name(segment: &str, field_infos_gen: i64) -> String {
+    if field_infos_gen == -1 {
+        format!("{}.{}", segment, FIELD_INFOS_EXTENSION)
+    } else {
+        format!(
+            "{}_{}.{}",
+            segment,
+            radix_36(field_infos_gen as u64),
+            FIELD_INFOS_EXTENSION
+        )
+    }
+}
+
+/// Reads field infos of a segment, from their latest generation.
+pub fn read_segment<D: Directory>(
+    directory: &D,
+    commit_info: &SegmentCommitInfo,
+) -> Result<FieldInfos, DirectoryError> {
+    let mut field_infos = FieldInfos::new();
+    field_infos.read(
+        directory,
+        &file_name(&commit_info.info.name, commit_info.field_infos_gen),
+    )?;
+
+    Ok(field_infos)
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use crate::store::FSDirectory;
 
@@ -201,7 +240,9 @@ mod tests {
 
         // Read field-infos from file
         let mut field_infos_read = FieldInfos::new();
-        field_infos_read.read(&directory, "field-infos.fnm");
+        field_infos_read
+            .read(&directory, "field-infos.fnm")
+            .unwrap();
 
         assert_eq!(field_infos.get_field_number("user_id").unwrap(), 0);
         assert_eq!(field_infos.get_field_number("user_name").unwrap(), 1);
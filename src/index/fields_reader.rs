@@ -0,0 +1,101 @@
+use bytes::{Buf, Bytes};
+
+use crate::{
+    document::{Document, Field},
+    store::{util, Directory, DirectoryError},
+};
+
+use super::field_info::FieldInfos;
+
+/// Reads stored fields written by `FieldsWriter`.
+/// Index file has a pointer per doc into the fields file.
+pub struct FieldsReader {
+    fields: Bytes,
+    index: Bytes,
+}
+
+impl FieldsReader {
+    pub fn open<D: Directory>(dir: &D, segment_id: &str) -> Result<Self, DirectoryError> {
+        Ok(Self {
+            fields: util::read_file(dir, &format!("{}.fdt", segment_id))?,
+            index: util::read_file(dir, &format!("{}.fdx", segment_id))?,
+        })
+    }
+
+    /// Number of docs in the fields file.
+    pub fn size(&self) -> u32 {
+        (self.index.len() / 8) as u32
+    }
+
+    /// Stored fields of a doc. Fields are rebuilt as keyword, text or un-indexed fields.
+    pub fn doc(&self, doc: u32, field_infos: &FieldInfos) -> Document {
+        let mut index = self.index.slice(doc as usize * 8..);
+        let pointer = index.get_u64();
+
+        let mut fields = self.fields.slice(pointer as usize..);
+        let stored_count = util::read_vint(&mut fields);
+
+        let mut document = Document::new();
+
+        for _ in 0..stored_count {
+            let field_number = util::read_vint(&mut fields);
+            let is_tokenized = fields.get_u8() == 1;
+            let value = util::read_string(&mut fields).unwrap();
+
+            let field_info = field_infos.get_field_info_by_number(field_number).unwrap();
+            let name = field_info.name.clone();
+
+            let field = match (field_info.is_indexed, is_tokenized) {
+                (true, true) => Field::text(name, value),
+                (true, false) => Field::keyword(name, value),
+                (false, _) => Field::unindexed(name, value),
+            };
+
+            document.add(field);
+        }
+
+        document
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{index::fields_writer::FieldsWriter, store::FSDirectory};
+
+    use super::*;
+
+    #[test]
+    fn test_fields_reader() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let mut first = Document::new();
+        first.add(Field::keyword("id".to_string(), "1".to_string()));
+        first.add(Field::text("body".to_string(), "unit tests".to_string()));
+        first.add(Field::new("hidden".to_string(), "not stored".to_string()));
+
+        let mut second = Document::new();
+        second.add(Field::unindexed("email".to_string(), "john@example.com".to_string()));
+
+        let mut field_infos = FieldInfos::new();
+        field_infos.add_doc(&first);
+        field_infos.add_doc(&second);
+
+        let mut fields_writer = FieldsWriter::new(&directory, "_0", &field_infos);
+        fields_writer.add_doc(&first);
+        fields_writer.add_doc(&second);
+        fields_writer.close();
+
+        let fields_reader = FieldsReader::open(&directory, "_0").unwrap();
+        assert_eq!(fields_reader.size(), 2);
+
+        assert_eq!(
+            fields_reader.doc(0, &field_infos).to_string(),
+            "Document<Keyword<id:1> Text<body:unit tests>>"
+        );
+        assert_eq!(
+            fields_reader.doc(1, &field_infos).to_string(),
+            "Document<UnIndexed<email:john@example.com>>"
+        );
+    }
+}
@@ -29,7 +29,7 @@ where
             field_infos,
             fields_stream,
             index_stream,
-            dir: &dir,
+            dir,
         }
     }
 
@@ -48,7 +48,7 @@ where
                 let field_number = self.field_infos.get_field_number(&f.name).unwrap();
                 let is_tokenized = f.is_analyzed as u8;
 
-                self.fields_stream.write_vint(field_number as u32);
+                self.fields_stream.write_vint(field_number);
                 self.fields_stream.write_byte(is_tokenized);
                 self.fields_stream.write_string(&f.value);
             }
@@ -56,6 +56,7 @@ where
     }
 
     pub fn close(&mut self) {
-        todo!()
+        self.fields_stream.flush();
+        self.index_stream.flush();
     }
 }
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::store::{self, Directory, DirectoryError};
+
+use super::segment_infos::{self, SegmentInfos, SEGMENTS};
+
+/// A commit point of the index, a `segments_N` file and the files it references.
+///
+/// Deletion policies decide which commits are kept, by deleting the others.
+/// Files of a deleted commit are removed once no kept commit references them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexCommit {
+    segments_file_name: String,
+    generation: u64,
+    /// All files of the commit, including the segments file.
+    files: HashSet<String>,
+    segment_count: usize,
+    user_data: HashMap<String, String>,
+    deleted: bool,
+}
+
+impl IndexCommit {
+    /// Commit point of segment infos, as written by a commit of their generation.
+    pub fn new(segment_infos: &SegmentInfos) -> Self {
+        let segments_file_name = segment_infos.segments_file_name();
+
+        let mut files: HashSet<String> = segment_infos
+            .segments
+            .iter()
+            .flat_map(|s| s.files())
+            .collect();
+        files.insert(segments_file_name.clone());
+
+        Self {
+            segments_file_name,
+            generation: segment_infos.generation,
+            files,
+            segment_count: segment_infos.segments.len(),
+            user_data: segment_infos.user_data.clone(),
+            deleted: false,
+        }
+    }
+
+    pub fn segments_file_name(&self) -> &str {
+        &self.segments_file_name
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// All files of the commit, including the segments file.
+    pub fn files(&self) -> &HashSet<String> {
+        &self.files
+    }
+
+    /// Files of the commit sorted by name, with the segments file last.
+    pub fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .files
+            .iter()
+            .filter(|f| **f != self.segments_file_name)
+            .cloned()
+            .collect();
+        names.sort();
+        names.push(self.segments_file_name.clone());
+        names
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segment_count
+    }
+
+    pub fn user_data(&self) -> &HashMap<String, String> {
+        &self.user_data
+    }
+
+    /// Marks the commit for deletion, called by deletion policies.
+    pub fn delete(&mut self) {
+        self.deleted = true;
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Keeps a commit a wrapped policy deleted, for policies that retain more commits.
+    pub(crate) fn undelete(&mut self) {
+        self.deleted = false;
+    }
+
+    /// Copies all files of the commit to another directory, for backups.
+    ///
+    /// The segments file is copied last, so the target holds a readable index
+    /// only once all files are there. The commit must not be deleted meanwhile,
+    /// which a `SnapshotDeletionPolicy` snapshot ensures while the index keeps changing.
+    pub fn copy_to<S, T>(&self, source: &S, target: &T) -> Result<(), DirectoryError>
+    where
+        S: Directory,
+        T: Directory,
+    {
+        store::copy_files(source, target, &self.file_names())
+    }
+
+    /// Reads the segment infos of the commit, to open its segments.
+    pub fn segment_infos<D: Directory>(
+        &self,
+        directory: &D,
+    ) -> Result<SegmentInfos, DirectoryError> {
+        segment_infos::read_segment_infos(directory, &self.segments_file_name)
+    }
+}
+
+/// All commits in the directory, oldest first.
+pub fn list_commits<D: Directory>(directory: &D) -> Result<Vec<IndexCommit>, DirectoryError> {
+    let mut generations: Vec<u64> = directory
+        .list()?
+        .iter()
+        .filter(|f| f.starts_with(SEGMENTS))
+        .map(segment_infos::get_generation_from_file_name)
+        .collect();
+    generations.sort();
+
+    generations
+        .into_iter()
+        .map(|generation| {
+            let file = segment_infos::file_name_from_generation(SEGMENTS, generation);
+            Ok(IndexCommit::new(&segment_infos::read_segment_infos(
+                directory, file,
+            )?))
+        })
+        .collect()
+}
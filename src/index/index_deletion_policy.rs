@@ -0,0 +1,82 @@
+use super::index_commit::IndexCommit;
+
+/// Decides which commits are kept in the directory.
+///
+/// The `IndexWriter` calls the policy with all commits it knows, oldest first,
+/// and the policy deletes the ones no longer needed with `IndexCommit::delete`.
+/// Files are only removed once no kept commit references them.
+pub trait IndexDeletionPolicy: Send {
+    /// Called once when the writer opens, with the commits in the directory.
+    /// Applies the same rule as `on_commit` by default.
+    fn on_init(&mut self, commits: &mut [IndexCommit]) {
+        self.on_commit(commits);
+    }
+
+    /// Called after every commit, the new commit is the last one.
+    /// Also called by `IndexWriter::delete_unused_files`.
+    fn on_commit(&mut self, commits: &mut [IndexCommit]);
+}
+
+/// Keeps only the latest commit, the default policy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepOnlyLastCommitDeletionPolicy;
+
+impl IndexDeletionPolicy for KeepOnlyLastCommitDeletionPolicy {
+    fn on_commit(&mut self, commits: &mut [IndexCommit]) {
+        if let Some((_, older)) = commits.split_last_mut() {
+            for commit in older {
+                commit.delete();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::index::segment_infos::SegmentInfos;
+
+    use super::*;
+
+    pub(crate) fn commits(count: u64) -> Vec<IndexCommit> {
+        (1..=count)
+            .map(|generation| {
+                let mut segment_infos = SegmentInfos::new();
+                segment_infos.generation = generation;
+                IndexCommit::new(&segment_infos)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_only_last_commit() {
+        let mut commits = commits(3);
+
+        KeepOnlyLastCommitDeletionPolicy.on_init(&mut commits);
+
+        let deleted: Vec<bool> = commits.iter().map(|c| c.is_deleted()).collect();
+        assert_eq!(deleted, vec![true, true, false]);
+
+        KeepOnlyLastCommitDeletionPolicy.on_commit(&mut []);
+    }
+
+    #[test]
+    fn test_keep_only_last_commit_after_commits() {
+        let all = commits(4);
+        let mut kept = vec![all[0].clone()];
+
+        KeepOnlyLastCommitDeletionPolicy.on_init(&mut kept);
+        assert!(!kept[0].is_deleted());
+
+        // Each commit deletes the one before, like the writer keeps only live commits
+        for commit in &all[1..] {
+            kept.push(commit.clone());
+            KeepOnlyLastCommitDeletionPolicy.on_commit(&mut kept);
+
+            let deleted: Vec<bool> = kept.iter().map(|c| c.is_deleted()).collect();
+            assert_eq!(deleted, vec![true, false]);
+            kept.retain(|c| !c.is_deleted());
+        }
+
+        assert_eq!(kept, vec![all[3].clone()]);
+    }
+}
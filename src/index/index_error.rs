@@ -0,0 +1,20 @@
+use thiserror::Error;
+
+use crate::store::DirectoryError;
+
+/// Error type for index reading and writing.
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error("Directory Error")]
+    DirectoryError(#[from] DirectoryError),
+    #[error("Index Not Found")]
+    IndexNotFound(String),
+    #[error("Illegal State")]
+    IllegalState(String),
+    #[error("Illegal Argument")]
+    IllegalArgument(String),
+    #[error("Merge Failed")]
+    MergeFailed(String),
+    #[error("Too Many Clauses")]
+    TooManyClauses(String),
+}
@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::store::Directory;
+
+use super::{
+    index_commit::{self, IndexCommit},
+    index_deletion_policy::IndexDeletionPolicy,
+    segment_infos::{SegmentInfos, PENDING_SEGMENTS, SEGMENTS},
+    IndexError,
+};
+
+/// Deletes files of the index once nothing references them anymore.
+///
+/// Files are reference counted. Commits kept by the deletion policy hold a
+/// reference to each of their files, and so do the segments of the writer
+/// as of the last `checkpoint`. The writer holds extra references while it
+/// needs files that are in neither, like those of a prepared commit.
+pub struct IndexFileDeleter<D> {
+    directory: D,
+    policy: Box<dyn IndexDeletionPolicy>,
+    ref_counts: HashMap<String, usize>,
+    /// Commits kept by the policy, oldest first.
+    commits: Vec<IndexCommit>,
+    /// Files of the segments of the last checkpoint.
+    last_files: HashSet<String>,
+}
+
+impl<D: Directory> IndexFileDeleter<D> {
+    /// Reads the commits in the directory and lets the policy delete the ones it doesn't keep.
+    /// Index files that neither kept commits nor `segment_infos` reference are deleted,
+    /// like leftovers of a writer that didn't commit or roll back.
+    pub fn new(
+        directory: D,
+        policy: Box<dyn IndexDeletionPolicy>,
+        segment_infos: &SegmentInfos,
+    ) -> Result<Self, IndexError> {
+        let commits = index_commit::list_commits(&directory)?;
+
+        let mut deleter = Self {
+            directory,
+            policy,
+            ref_counts: HashMap::new(),
+            commits,
+            last_files: HashSet::new(),
+        };
+
+        for commit in deleter.commits.iter() {
+            for file in commit.files() {
+                *deleter.ref_counts.entry(file.clone()).or_default() += 1;
+            }
+        }
+        deleter.checkpoint(segment_infos, false)?;
+
+        deleter.policy.on_init(&mut deleter.commits);
+        deleter.delete_commits()?;
+
+        for file in deleter.directory.list()? {
+            if is_index_file(&file) && !deleter.ref_counts.contains_key(&file) {
+                deleter.delete_file(&file)?;
+            }
+        }
+
+        Ok(deleter)
+    }
+
+    /// Commits kept by the deletion policy, oldest first.
+    pub fn commits(&self) -> &[IndexCommit] {
+        &self.commits
+    }
+
+    /// Records the current segments of the writer, and deletes files only
+    /// the previous checkpoint referenced.
+    ///
+    /// With `is_commit`, the segment infos were just committed instead. They are added
+    /// to the commits, the policy decides which commits to keep, and the segments
+    /// of the writer are left as they are.
+    pub fn checkpoint(
+        &mut self,
+        segment_infos: &SegmentInfos,
+        is_commit: bool,
+    ) -> Result<(), IndexError> {
+        if is_commit {
+            let commit = IndexCommit::new(segment_infos);
+            self.inc_ref(commit.files());
+            self.commits.push(commit);
+
+            self.policy.on_commit(&mut self.commits);
+            return self.delete_commits();
+        }
+
+        let files: HashSet<String> = segment_infos
+            .segments
+            .iter()
+            .flat_map(|s| s.files())
+            .collect();
+
+        // Referenced before released, so that files in both are kept
+        self.inc_ref(&files);
+        let last_files = std::mem::replace(&mut self.last_files, files);
+        self.dec_ref(&last_files)
+    }
+
+    /// Asks the policy again which commits to keep, after it changed its mind,
+    /// like when a snapshot was released.
+    pub fn revisit_policy(&mut self) -> Result<(), IndexError> {
+        self.policy.on_commit(&mut self.commits);
+        self.delete_commits()
+    }
+
+    pub fn inc_ref<'a>(&mut self, files: impl IntoIterator<Item = &'a String>) {
+        for file in files {
+            *self.ref_counts.entry(file.clone()).or_default() += 1;
+        }
+    }
+
+    /// Releases references to files, deleting files no longer referenced.
+    pub fn dec_ref<'a>(
+        &mut self,
+        files: impl IntoIterator<Item = &'a String>,
+    ) -> Result<(), IndexError> {
+        for file in files {
+            let count = self
+                .ref_counts
+                .get_mut(file)
+                .unwrap_or_else(|| panic!("File {} is not referenced", file));
+            *count -= 1;
+
+            if *count == 0 {
+                self.ref_counts.remove(file);
+                self.delete_file(file)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Number of references to the file, 0 for files the deleter doesn't know.
+    pub fn ref_count(&self, file: &str) -> usize {
+        self.ref_counts.get(file).copied().unwrap_or_default()
+    }
+
+    fn delete_commits(&mut self) -> Result<(), IndexError> {
+        let (deleted, kept): (Vec<IndexCommit>, Vec<IndexCommit>) =
+            std::mem::take(&mut self.commits)
+                .into_iter()
+                .partition(|c| c.is_deleted());
+        self.commits = kept;
+
+        for commit in deleted {
+            self.dec_ref(commit.files())?;
+        }
+
+        Ok(())
+    }
+
+    fn delete_file(&self, file: &str) -> Result<(), IndexError> {
+        if self.directory.file_exists(file) {
+            self.directory.delete_file(file)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the file is written by an index, files of segments start with `_`.
+fn is_index_file(file: &str) -> bool {
+    file.starts_with('_') || file.starts_with(SEGMENTS) || file.starts_with(PENDING_SEGMENTS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        index::{
+            index_deletion_policy::KeepOnlyLastCommitDeletionPolicy, segment_infos,
+            snapshot_deletion_policy::SnapshotDeletionPolicy,
+        },
+        store::{FSDirectory, OutputStream},
+    };
+
+    use super::*;
+
+    fn touch(directory: &FSDirectory, file: &str) {
+        let mut output = directory.create_file(file).unwrap();
+        output.write_byte(0);
+        output.flush();
+    }
+
+    #[test]
+    fn test_deletes_orphans_and_old_commits() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let directory = FSDirectory::new(&path).unwrap();
+
+        let mut sis = SegmentInfos::new();
+        segment_infos::commit(&directory, &mut sis).unwrap();
+        segment_infos::commit(&directory, &mut sis).unwrap();
+        touch(&directory, "_0.fnm");
+        touch(&directory, "pending_segments_3");
+        touch(&directory, "notes.txt");
+
+        let policy = Box::new(KeepOnlyLastCommitDeletionPolicy);
+        let deleter = IndexFileDeleter::new(FSDirectory::new(&path).unwrap(), policy, &sis).unwrap();
+
+        assert_eq!(deleter.commits().len(), 1);
+        assert_eq!(directory.list().unwrap(), vec!["notes.txt", "segments_2"]);
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let directory = FSDirectory::new(&path).unwrap();
+        let policy = SnapshotDeletionPolicy::new(KeepOnlyLastCommitDeletionPolicy);
+
+        let mut sis = SegmentInfos::new();
+        let mut deleter =
+            IndexFileDeleter::new(FSDirectory::new(&path).unwrap(), Box::new(policy.clone()), &sis)
+                .unwrap();
+
+        segment_infos::commit(&directory, &mut sis).unwrap();
+        deleter.checkpoint(&sis, true).unwrap();
+        let snapshot = policy.snapshot().unwrap();
+        assert_eq!(deleter.ref_count("segments_1"), 1);
+
+        // The snapshotted commit is kept, the one after it is not
+        segment_infos::commit(&directory, &mut sis).unwrap();
+        deleter.checkpoint(&sis, true).unwrap();
+        segment_infos::commit(&directory, &mut sis).unwrap();
+        deleter.checkpoint(&sis, true).unwrap();
+        assert_eq!(directory.list().unwrap(), vec!["segments_1", "segments_3"]);
+
+        policy.release(&snapshot).unwrap();
+        deleter.revisit_policy().unwrap();
+        assert_eq!(directory.list().unwrap(), vec!["segments_3"]);
+        assert_eq!(deleter.commits().len(), 1);
+    }
+}
@@ -1,37 +1,2170 @@
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, HashSet},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+};
 
-use crate::{analysis::Analyzer, document::{self, Document}};
+use radix_fmt::radix_36;
 
-pub struct IndexWriter<'p, A> {
-    path: &'p Path,
-    analyzer: A,
-    write_mode: WriteMode
+use crate::{
+    analysis::Analyzer,
+    document::{Document, Field},
+    search::{IndexSearcher, Query, ScoreMode, NO_MORE_DOCS},
+    store::{Directory, DirectoryError, InputStream, OutputStream, RateLimitedDirectory},
+    util::FixedBitSet,
+};
+
+use super::{
+    buffered_deletes::{BufferedDeletes, DocValuesUpdate},
+    directory_reader::DirectoryReader,
+    doc_values::{self, DocValues, DocValuesField, DocValuesType},
+    document_writer::DocumentWriter,
+    field_info,
+    index_commit::IndexCommit,
+    index_deletion_policy::{IndexDeletionPolicy, KeepOnlyLastCommitDeletionPolicy},
+    index_file_deleter::IndexFileDeleter,
+    live_docs,
+    merge_policy::{MergeContext, MergePolicy},
+    merge_scheduler::{ConcurrentMergeScheduler, MergeScheduler, MergeTask},
+    segment_commit_info::SegmentCommitInfo,
+    segment_infos::{self, SegmentInfos},
+    segment_merger::{SegmentMerger, SoftDeletesRetentionPolicy},
+    segment_reader::{SegmentReader, SegmentTermDocs},
+    tiered_merge_policy::TieredMergePolicy,
+    IndexError, Term,
+};
+
+pub const DEFAULT_MAX_BUFFERED_DOCS: usize = 1000;
+
+/// Adds documents to an index in a directory.
+///
+/// Documents are buffered in memory and flushed as new segments.
+/// Flushed segments become visible to readers only once committed,
+/// either in one step with `commit`, or in two with `prepare_commit` and `commit`.
+/// `rollback` discards everything since the last commit.
+///
+/// Deletes, by term or by query, are buffered too, and resolved against both buffered docs
+/// and flushed segments when buffered docs are flushed.
+///
+/// With a soft deletes field set, docs can instead be soft-deleted by giving them
+/// a value for that doc values field. Soft-deleted docs stay in the index,
+/// but readers hide them.
+///
+/// After each flush, the merge policy picks segments to merge into larger ones,
+/// dropping deleted docs. `force_merge` merges down to a number of segments.
+/// Files no longer needed are deleted as soon as neither the writer nor a commit
+/// kept by the deletion policy references them. By default only the last commit is kept.
+///
+/// Merges run on the merge scheduler, in the background by default. Finished merges
+/// replace their sources on the next flush, and deletes that hit the sources in
+/// the meantime are carried over. Failed merges are reported by the next call
+/// that checks for finished merges.
+pub struct IndexWriter<A, D> {
+    directory: Arc<D>,
+    document_writer: DocumentWriter<A, Arc<D>>,
+    buffered_deletes: BufferedDeletes,
+    write_mode: WriteMode,
+    max_buffered_docs: usize,
+    /// Doc values field marking soft-deleted docs.
+    soft_deletes_field: Option<String>,
+    merge_policy: Box<dyn MergePolicy>,
+    merge_scheduler: Box<dyn MergeScheduler>,
+    /// Segments of registered merges that have not been committed yet.
+    merging: HashSet<String>,
+    /// Merges send their results back, for the writer to commit them.
+    merge_sender: Sender<FinishedMerge>,
+    merge_receiver: Receiver<FinishedMerge>,
+    /// Soft-deleted docs kept by merges, all are dropped without it.
+    soft_deletes_retention_policy: Option<Arc<dyn SoftDeletesRetentionPolicy>>,
+    /// Segments of the index, including flushed segments not yet committed.
+    segment_infos: SegmentInfos,
+    /// Segments as of the last commit, restored on rollback.
+    last_commit: SegmentInfos,
+    /// Commit written by `prepare_commit`, published by `commit`.
+    pending_commit: Option<SegmentInfos>,
+    deleter: IndexFileDeleter<Arc<D>>,
+}
+
+/// Result of a merge, as sent back by the merge task.
+struct FinishedMerge {
+    segment: String,
+    /// Sources as they were when the merge was registered.
+    sources: Vec<SegmentCommitInfo>,
+    merged: Result<MergedSegment, IndexError>,
+}
+
+/// Segment written by a merge, with what is needed to carry over changes to its sources.
+struct MergedSegment {
+    info: SegmentCommitInfo,
+    /// Docs of the sources that were not hard-deleted when the merge was registered.
+    source_live_docs: Vec<FixedBitSet>,
+    doc_maps: Vec<Vec<Option<u32>>>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum WriteMode {
     CREATE,
-    OPEN
+    OPEN,
 }
 
-impl<'p, A: Analyzer> IndexWriter<'p, A> {
-    pub fn create(path: &'p Path, analyzer: A) -> Self {
-        Self { path, analyzer, write_mode: WriteMode::CREATE }
+impl<A, I, O, D> IndexWriter<A, D>
+where
+    A: Analyzer,
+    I: InputStream,
+    O: OutputStream,
+    D: Directory<Input = I, Output = O> + Send + Sync + 'static,
+{
+    /// Creates a new index, replacing any existing index in the directory on commit.
+    pub fn create(directory: D, analyzer: A) -> Result<Self, IndexError> {
+        Self::new(directory, analyzer, WriteMode::CREATE)
+    }
+
+    /// Opens the existing index in the directory, to add more documents.
+    pub fn open(directory: D, analyzer: A) -> Result<Self, IndexError> {
+        Self::new(directory, analyzer, WriteMode::OPEN)
+    }
+
+    pub fn new(directory: D, analyzer: A, write_mode: WriteMode) -> Result<Self, IndexError> {
+        Self::with_deletion_policy(directory, analyzer, write_mode, KeepOnlyLastCommitDeletionPolicy)
+    }
+
+    /// Opens a writer whose deletion policy decides which commits are kept.
+    /// The policy sees the commits already in the directory before anything is written,
+    /// which is why it can't be changed later.
+    pub fn with_deletion_policy<P>(
+        directory: D,
+        analyzer: A,
+        write_mode: WriteMode,
+        deletion_policy: P,
+    ) -> Result<Self, IndexError>
+    where
+        P: IndexDeletionPolicy + 'static,
+    {
+        let directory = Arc::new(directory);
+        let last_generation = segment_infos::find_last_commit_generation(directory.as_ref())?;
+
+        let last_commit = match (write_mode, last_generation) {
+            (WriteMode::OPEN, None) => {
+                return Err(IndexError::IndexNotFound(
+                    "No segments file found in directory".to_string(),
+                ))
+            }
+            (WriteMode::OPEN, Some(_)) => segment_infos::read_latest_segment_infos(directory.as_ref())?,
+            (WriteMode::CREATE, None) => SegmentInfos::new(),
+            (WriteMode::CREATE, Some(_)) => {
+                // Continue generations and segment names of the existing index,
+                // so that nothing it references is overwritten.
+                let existing = segment_infos::read_latest_segment_infos(directory.as_ref())?;
+                let mut segment_infos = SegmentInfos::new();
+                segment_infos.generation = existing.generation;
+                segment_infos.sis_version = existing.sis_version + 1;
+                segment_infos.sis_counter = existing.sis_counter;
+                segment_infos
+            }
+        };
+
+        let (merge_sender, merge_receiver) = mpsc::channel();
+        let deleter =
+            IndexFileDeleter::new(directory.clone(), Box::new(deletion_policy), &last_commit)?;
+
+        let mut segment_infos = last_commit.clone();
+        if write_mode == WriteMode::CREATE {
+            // Creating the index is a change to commit, even without docs
+            segment_infos.sis_version += 1;
+        }
+
+        Ok(Self {
+            document_writer: DocumentWriter::new(analyzer, directory.clone()),
+            buffered_deletes: BufferedDeletes::new(),
+            directory,
+            write_mode,
+            max_buffered_docs: DEFAULT_MAX_BUFFERED_DOCS,
+            soft_deletes_field: None,
+            merge_policy: Box::new(TieredMergePolicy::new()),
+            merge_scheduler: Box::new(ConcurrentMergeScheduler::new()),
+            merging: HashSet::new(),
+            merge_sender,
+            merge_receiver,
+            soft_deletes_retention_policy: None,
+            segment_infos,
+            last_commit,
+            pending_commit: None,
+            deleter,
+        })
+    }
+
+    pub fn write_mode(&self) -> WriteMode {
+        self.write_mode
+    }
+
+    /// Number of docs buffered in memory before they are flushed as a segment.
+    pub fn set_max_buffered_docs(&mut self, max_buffered_docs: usize) {
+        self.max_buffered_docs = max_buffered_docs;
+    }
+
+    /// Policy picking segments to merge, `TieredMergePolicy` by default.
+    pub fn set_merge_policy<P>(&mut self, merge_policy: P)
+    where
+        P: MergePolicy + 'static,
+    {
+        self.merge_policy = Box::new(merge_policy);
+    }
+
+    /// Scheduler running merges, `ConcurrentMergeScheduler` by default.
+    /// Waits for merges of the previous scheduler first.
+    pub fn set_merge_scheduler<S>(&mut self, merge_scheduler: S)
+    where
+        S: MergeScheduler + 'static,
+    {
+        self.merge_scheduler.wait();
+        self.merge_scheduler = Box::new(merge_scheduler);
+    }
+
+    /// Doc values field marking soft-deleted docs, used by `soft_update_document`.
+    pub fn set_soft_deletes_field(&mut self, field: &str) {
+        self.soft_deletes_field = Some(field.to_owned());
+        self.document_writer.soft_deletes_field = Some(field.to_owned());
+    }
+
+    pub fn soft_deletes_field(&self) -> Option<&str> {
+        self.soft_deletes_field.as_deref()
+    }
+
+    /// Soft-deleted docs retained by the policy survive merges, others are dropped.
+    pub fn set_soft_deletes_retention_policy<P>(&mut self, policy: P)
+    where
+        P: SoftDeletesRetentionPolicy + 'static,
+    {
+        self.soft_deletes_retention_policy = Some(Arc::new(policy));
+    }
+
+    /// Segments of the index, including flushed but uncommitted ones.
+    pub fn segment_infos(&self) -> &SegmentInfos {
+        &self.segment_infos
+    }
+
+    pub fn directory(&self) -> &D {
+        &self.directory
+    }
+
+    pub fn add_document(&mut self, document: Document) -> Result<(), IndexError> {
+        self.buffer_document(document)?;
+        self.flush_if_full()
+    }
+
+    /// Buffers the doc, without flushing. Nothing is buffered when the doc is invalid.
+    fn buffer_document(&mut self, document: Document) -> Result<(), IndexError> {
+        let segment = match &self.document_writer.segment {
+            Some(segment) => segment.clone(),
+            None => self.new_segment_name(),
+        };
+
+        self.document_writer.add_doc(&segment, document)
+    }
+
+    fn flush_if_full(&mut self) -> Result<(), IndexError> {
+        if self.document_writer.num_docs as usize >= self.max_buffered_docs {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes all docs containing the term.
+    /// Applies to docs added before this call, and is resolved on the next flush.
+    pub fn delete_documents(&mut self, term: Term) -> Result<(), IndexError> {
+        self.buffered_deletes
+            .add_term(term, self.document_writer.num_docs);
+
+        Ok(())
+    }
+
+    /// Deletes all docs matching the query.
+    /// Applies to docs added before this call, and is resolved on the next flush.
+    pub fn delete_documents_by_query(&mut self, query: Arc<dyn Query>) -> Result<(), IndexError> {
+        self.buffered_deletes
+            .add_query(query, self.document_writer.num_docs);
+
+        Ok(())
+    }
+
+    /// Atomically deletes all docs containing the term and adds the new doc.
+    /// The delete and the add are flushed together, so readers see either
+    /// the previous docs or the new one, never both or neither.
+    pub fn update_document(&mut self, term: Term, document: Document) -> Result<(), IndexError> {
+        // The delete is buffered only once the doc is, and doesn't apply to it
+        let doc_id_upto = self.document_writer.num_docs;
+        self.buffer_document(document)?;
+        self.buffered_deletes.add_term(term, doc_id_upto);
+
+        self.flush_if_full()
+    }
+
+    /// Sets numeric doc values of all docs containing the term.
+    /// Applies to docs added before this call, and is resolved on the next flush.
+    pub fn update_doc_values(&mut self, term: Term, fields: Vec<Field>) -> Result<(), IndexError> {
+        let updates = Self::doc_values_updates(term, fields, self.document_writer.num_docs)?;
+        for update in updates {
+            self.buffered_deletes.add_doc_values_update(update);
+        }
+
+        Ok(())
+    }
+
+    /// Updates of numeric doc values of docs before `doc_id_upto`, failing on any invalid field.
+    fn doc_values_updates(
+        term: Term,
+        fields: Vec<Field>,
+        doc_id_upto: u32,
+    ) -> Result<Vec<DocValuesUpdate>, IndexError> {
+        let mut updates = Vec::with_capacity(fields.len());
+
+        for field in fields {
+            if field.doc_values != Some(DocValuesType::Numeric) {
+                return Err(IndexError::IllegalArgument(format!(
+                    "{} is not a numeric doc values field",
+                    field
+                )));
+            }
+
+            let value = field.value.parse().map_err(|_| {
+                IndexError::IllegalArgument(format!("Invalid numeric doc value {}", field))
+            })?;
+
+            updates.push(DocValuesUpdate {
+                term: term.clone(),
+                field: field.name,
+                value,
+                doc_id_upto,
+            });
+        }
+
+        Ok(updates)
+    }
+
+    /// Atomically soft-deletes all docs containing the term and adds the new doc.
+    /// Matching docs get the values of `soft_deletes`, which must all be fields
+    /// of the soft deletes field, and are hidden from readers but kept in the index.
+    pub fn soft_update_document(
+        &mut self,
+        term: Term,
+        document: Document,
+        soft_deletes: Vec<Field>,
+    ) -> Result<(), IndexError> {
+        let soft_deletes_field = self.soft_deletes_field.as_deref().ok_or_else(|| {
+            IndexError::IllegalState("Soft deletes field is not set".to_string())
+        })?;
+
+        if let Some(field) = soft_deletes.iter().find(|f| f.name != soft_deletes_field) {
+            return Err(IndexError::IllegalArgument(format!(
+                "{} is not the soft deletes field {}",
+                field, soft_deletes_field
+            )));
+        }
+
+        // Like the delete of `update_document`, updates are buffered only once the doc is
+        let doc_id_upto = self.document_writer.num_docs;
+        let updates = Self::doc_values_updates(term, soft_deletes, doc_id_upto)?;
+        self.buffer_document(document)?;
+        for update in updates {
+            self.buffered_deletes.add_doc_values_update(update);
+        }
+
+        self.flush_if_full()
+    }
+
+    /// Writes buffered docs as a new segment and applies buffered deletes.
+    /// Neither is committed.
+    pub fn flush(&mut self) -> Result<(), IndexError> {
+        // Deletes of buffered docs only apply to docs added before them
+        let mut flushed_deletes = Vec::new();
+        for (term, doc_id_upto) in self.buffered_deletes.terms.iter() {
+            flushed_deletes.extend(self.document_writer.docs_with_term(term, *doc_id_upto));
+        }
+
+        for update in self.buffered_deletes.doc_values_updates.iter() {
+            self.document_writer.update_doc_values(
+                &update.term,
+                update.doc_id_upto,
+                &update.field,
+                update.value,
+            );
+        }
+
+        // Queries are resolved against buffered docs once they are flushed
+        let queries = self.buffered_deletes.queries.clone();
+
+        // Deletes of flushed segments apply to all their docs.
+        // They are resolved first, so that buffered docs and deletes are kept if they fail.
+        self.apply_deletes()?;
+
+        // The segment is tracked as soon as it is written, so that its docs and files are
+        // not lost if its deletes fail
+        if let Some(segment) = self.document_writer.flush()? {
+            self.segment_infos.segments.push(segment);
+            self.segment_infos.sis_version += 1;
+
+            let directory = self.directory.as_ref();
+            let segment = self.segment_infos.segments.last_mut().unwrap();
+            if !queries.is_empty() {
+                let segments = std::slice::from_ref(&*segment);
+                let mut query_docs = Self::query_docs(directory, segments, &queries, true)?;
+                flushed_deletes.append(&mut query_docs[0]);
+            }
+
+            live_docs::delete_docs(directory, segment, &flushed_deletes)?;
+            Self::update_soft_delete_count(directory, segment)?;
+        }
+
+        self.deleter.checkpoint(&self.segment_infos, false)?;
+
+        self.maybe_merge()
+    }
+
+    /// Commits finished merges, and schedules merges picked by the merge policy
+    /// until it finds no more. Fails if a merge failed since the last check.
+    pub fn maybe_merge(&mut self) -> Result<(), IndexError> {
+        self.apply_finished_merges()?;
+
+        loop {
+            self.update_segment_sizes()?;
+            let spec = self.merge_policy.find_merges(&self.segment_infos, &self.merge_context());
+
+            let spec = match spec {
+                Some(spec) => spec,
+                None => return Ok(()),
+            };
+
+            let mut registered = false;
+            for merge in spec.merges {
+                registered |= self.register_merge(&merge.segments)?;
+            }
+
+            // Merges that already finished, like those of a serial scheduler, can cascade
+            self.apply_finished_merges()?;
+
+            if !registered {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Blocks until all scheduled merges have finished, and commits them.
+    /// Fails if any of them failed.
+    pub fn wait_for_merges(&mut self) -> Result<(), IndexError> {
+        self.merge_scheduler.wait();
+        self.apply_finished_merges()
+    }
+
+    /// Resolves buffered deletes and doc values updates against flushed segments,
+    /// writing new live docs and doc values generations.
+    /// Only the term dictionaries and postings of segments having the fields are read.
+    fn apply_deletes(&mut self) -> Result<(), IndexError> {
+        if self.buffered_deletes.is_empty() {
+            return Ok(());
+        }
+
+        let directory = self.directory.as_ref();
+        let fields: HashSet<&str> = self
+            .buffered_deletes
+            .terms
+            .keys()
+            .chain(self.buffered_deletes.doc_values_updates.iter().map(|u| &u.term))
+            .map(|term| term.field.as_str())
+            .collect();
+
+        // Queries need whole segments, which are only opened when there are any
+        let mut query_docs = match self.buffered_deletes.queries.is_empty() {
+            true => vec![Vec::new(); self.segment_infos.segments.len()],
+            false => Self::query_docs(
+                directory,
+                &self.segment_infos.segments,
+                &self.buffered_deletes.queries,
+                false,
+            )?,
+        };
+
+        let segments = self.segment_infos.segments.iter_mut();
+        for (segment, query_docs) in segments.zip(query_docs.iter_mut()) {
+            let term_docs = SegmentTermDocs::open(directory, segment, &fields)?;
+            if term_docs.is_none() && query_docs.is_empty() {
+                continue;
+            }
+
+            let mut docs = std::mem::take(query_docs);
+            for term in self.buffered_deletes.terms.keys() {
+                docs.extend(term_docs.iter().flat_map(|t| t.term_docs(term)));
+            }
+
+            // Updated fields are rewritten in full, with values of docs not updated
+            let mut existing_fields = None;
+            let mut updated_fields: BTreeMap<String, DocValuesField> = BTreeMap::new();
+            for update in self.buffered_deletes.doc_values_updates.iter() {
+                let docs = match &term_docs {
+                    Some(term_docs) => term_docs.term_docs(&update.term),
+                    None => continue,
+                };
+                if docs.is_empty() {
+                    continue;
+                }
+
+                if existing_fields.is_none() {
+                    existing_fields = Some(doc_values::read_segment(directory, segment)?);
+                }
+                let existing_fields = existing_fields.as_ref().unwrap();
+
+                let field = updated_fields
+                    .entry(update.field.clone())
+                    .or_insert_with(|| match existing_fields.get(&update.field) {
+                        Some(field) => field.clone(),
+                        None => DocValuesField {
+                            name: update.field.clone(),
+                            soft_deletes: self.soft_deletes_field.as_ref() == Some(&update.field),
+                            values: DocValues::new(
+                                DocValuesType::Numeric,
+                                segment.info.doc_count,
+                            ),
+                        },
+                    });
+
+                for doc in docs {
+                    field.values.set_numeric(doc, update.value);
+                }
+            }
+
+            let mut changed = live_docs::delete_docs(directory, segment, &docs)? > 0;
+
+            if !updated_fields.is_empty() {
+                doc_values::write_update(directory, segment, &updated_fields)?;
+                changed = true;
+            }
+
+            if changed {
+                Self::update_soft_delete_count(directory, segment)?;
+                self.segment_infos.sis_version += 1;
+            }
+        }
+
+        self.buffered_deletes.clear();
+
+        Ok(())
+    }
+
+    /// Docs of each segment matching any of the delete queries, including deleted docs.
+    /// With `doc_id_upto`, a query only matches docs added before it.
+    fn query_docs(
+        directory: &D,
+        segments: &[SegmentCommitInfo],
+        queries: &[(Arc<dyn Query>, u32)],
+        doc_id_upto: bool,
+    ) -> Result<Vec<Vec<u32>>, IndexError> {
+        let mut segment_infos = SegmentInfos::new();
+        segment_infos.segments = segments.to_vec();
+        let reader = DirectoryReader::open_segment_infos(directory, segment_infos)?;
+        let searcher = IndexSearcher::new(&reader);
+
+        let mut docs = vec![Vec::new(); segments.len()];
+        for (query, upto) in queries {
+            let weight = searcher.create_weight(query.as_ref(), ScoreMode::CompleteNoScores)?;
+
+            for (leaf, docs) in reader.leaves().iter().zip(docs.iter_mut()) {
+                let mut scorer = match weight.scorer(leaf)? {
+                    Some(scorer) => scorer,
+                    None => continue,
+                };
+
+                loop {
+                    let doc = scorer.next_doc();
+                    if doc == NO_MORE_DOCS || (doc_id_upto && doc >= *upto) {
+                        break;
+                    }
+                    docs.push(doc);
+                }
+            }
+        }
+
+        Ok(docs)
     }
 
-    pub fn open(path: &'p Path, analyzer: A) -> Self {
-        Self { path, analyzer, write_mode: WriteMode::OPEN }
+    /// Counts docs of a segment that are soft-deleted but not hard-deleted.
+    fn update_soft_delete_count(
+        directory: &D,
+        segment: &mut SegmentCommitInfo,
+    ) -> Result<(), IndexError> {
+        let fields = doc_values::read_segment(directory, segment)?;
+        let live_docs = live_docs::read(directory, segment)?;
+        segment.soft_delete_count = doc_values::soft_delete_count(&fields, &live_docs);
+
+        Ok(())
+    }
+
+    /// Whether segments changed since the last commit, counting flushes, deletes and merges.
+    pub fn has_uncommitted_changes(&self) -> bool {
+        self.segment_infos.sis_version != self.last_commit.sis_version
+    }
+
+    /// First phase of a two-phase commit.
+    /// Flushes buffered docs, syncs all new files and writes `pending_segments_N`.
+    /// The commit is not visible to readers until `commit` is called,
+    /// and can be discarded with `rollback`. Does nothing without uncommitted changes.
+    pub fn prepare_commit(&mut self) -> Result<(), IndexError> {
+        if self.pending_commit.is_some() {
+            return Err(IndexError::IllegalState(
+                "prepare_commit was already called".to_string(),
+            ));
+        }
+
+        self.flush()?;
+
+        if !self.has_uncommitted_changes() {
+            return Ok(());
+        }
+
+        // Sync files of segments flushed since the last commit
+        let committed: HashSet<&str> = self
+            .last_commit
+            .segments
+            .iter()
+            .map(|s| s.info.name.as_str())
+            .collect();
+        let new_files: Vec<String> = self
+            .segment_infos
+            .segments
+            .iter()
+            .filter(|s| !committed.contains(s.info.name.as_str()))
+            .flat_map(|s| s.files())
+            .collect();
+        self.directory.sync(&new_files)?;
+
+        let mut to_commit = self.segment_infos.clone();
+        segment_infos::prepare_commit(self.directory.as_ref(), &mut to_commit)?;
+
+        // Merges may replace the segments before the commit is finished
+        self.deleter.inc_ref(&Self::files(&to_commit.segments));
+        self.pending_commit = Some(to_commit);
+
+        Ok(())
+    }
+
+    /// Commits all changes, publishing a new `segments_N`.
+    /// Completes a commit started with `prepare_commit`, or runs both phases.
+    /// Without changes since the last commit, no new commit is written.
+    pub fn commit(&mut self) -> Result<(), IndexError> {
+        if self.pending_commit.is_none() {
+            self.prepare_commit()?;
+        }
+
+        let pending_commit = match self.pending_commit.take() {
+            Some(pending_commit) => pending_commit,
+            None => return Ok(()),
+        };
+        segment_infos::finish_commit(self.directory.as_ref(), &pending_commit)?;
+
+        self.deleter.checkpoint(&pending_commit, true)?;
+        self.deleter.dec_ref(&Self::files(&pending_commit.segments))?;
+
+        self.segment_infos.generation = pending_commit.generation;
+        self.segment_infos.id = pending_commit.id.clone();
+        self.last_commit = pending_commit;
+
+        Ok(())
+    }
+
+    /// Discards all changes since the last commit.
+    /// Drops buffered docs, deletes files of uncommitted segments and any pending commit.
+    pub fn rollback(&mut self) -> Result<(), IndexError> {
+        if let Some(pending_commit) = self.pending_commit.take() {
+            segment_infos::rollback_commit(self.directory.as_ref(), &pending_commit)?;
+            self.deleter.dec_ref(&Self::files(&pending_commit.segments))?;
+        }
+
+        self.document_writer.reset();
+        self.buffered_deletes.clear();
+
+        // Merged segments were never part of the index
+        self.merge_scheduler.wait();
+        while let Ok(finished) = self.merge_receiver.try_recv() {
+            self.delete_segment_files(&finished.segment)?;
+            self.deleter.dec_ref(&Self::files(&finished.sources))?;
+        }
+        self.merging.clear();
+
+        // Segment names and versions handed out since the last commit are not reused,
+        // so that neither files left of discarded segments nor readers of them are
+        // mistaken for new ones
+        let sis_counter = self.segment_infos.sis_counter;
+        self.last_commit.sis_version = self.segment_infos.sis_version;
+
+        // Files of uncommitted segments are no longer referenced
+        self.segment_infos = self.last_commit.clone();
+        self.segment_infos.sis_counter = sis_counter;
+        self.deleter.checkpoint(&self.segment_infos, false)?;
+
+        Ok(())
+    }
+
+    /// Caches sizes of segments whose files changed since they were last read.
+    fn update_segment_sizes(&mut self) -> Result<(), IndexError> {
+        let directory = self.directory.as_ref();
+        for segment in self.segment_infos.segments.iter_mut() {
+            segment.size_in_bytes(directory)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sizes and deletes of segments for the merge policy, sizes must be cached.
+    /// Soft-deleted docs that merges would retain are not counted as deletes,
+    /// they are counted only for segments the policy asks about.
+    fn merge_context(&self) -> MergeContext<'_> {
+        let directory = self.directory.as_ref();
+        let mut context = MergeContext::new(&self.segment_infos);
+        context.set_merging(self.merging.iter().cloned());
+
+        if let Some(policy) = self.soft_deletes_retention_policy.as_deref() {
+            context.set_count_deletes_to_merge(move |segment| {
+                // A segment that can't be read counts all soft-deleted docs,
+                // merging it reports the error
+                let reader = match SegmentReader::open(directory, segment) {
+                    Ok(reader) => reader,
+                    Err(_) => return segment.del_count + segment.soft_delete_count,
+                };
+                let retained = (0..reader.max_doc())
+                    .filter(|&doc| {
+                        !reader.live_docs.get(doc)
+                            && reader.hard_live_docs.get(doc)
+                            && policy.retain(&reader, doc)
+                    })
+                    .count() as u32;
+
+                segment.del_count + segment.soft_delete_count - retained
+            });
+        }
+
+        context
+    }
+
+    /// Merges segments until at most `max_segments` are left, flushing buffered docs first.
+    /// The merge policy picks the segments, by default the newest ones are merged into one.
+    /// Merged segments replace their sources, and are published by the next commit.
+    /// Waits for running merges, and for the forced merges to finish.
+    pub fn force_merge(&mut self, max_segments: usize) -> Result<(), IndexError> {
+        if max_segments == 0 {
+            return Err(IndexError::IllegalArgument(
+                "max_segments must be at least 1".to_string(),
+            ));
+        }
+
+        self.flush()?;
+
+        loop {
+            self.wait_for_merges()?;
+
+            self.update_segment_sizes()?;
+            let spec = self.merge_policy.find_forced_merges(
+                &self.segment_infos,
+                max_segments,
+                &self.merge_context(),
+            );
+
+            match spec {
+                Some(spec) => {
+                    for merge in spec.merges {
+                        self.register_merge(&merge.segments)?;
+                    }
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Merges all segments into one, dropping deleted docs.
+    pub fn optimize(&mut self) -> Result<(), IndexError> {
+        self.force_merge(1)
+    }
+
+    /// Registers a merge of the named segments into a new segment, and schedules it.
+    /// Returns false if a segment is missing or already being merged.
+    ///
+    /// Sources are read by the merge task, as they are now. Their files are referenced
+    /// until the merge has finished, so that deletes and updates meanwhile don't remove them.
+    fn register_merge(&mut self, names: &[String]) -> Result<bool, IndexError> {
+        if names.iter().any(|name| self.merging.contains(name)) {
+            return Ok(false);
+        }
+
+        let sources: Vec<SegmentCommitInfo> = self
+            .segment_infos
+            .segments
+            .iter()
+            .filter(|s| names.contains(&s.info.name))
+            .cloned()
+            .collect();
+
+        if sources.is_empty() {
+            return Ok(false);
+        }
+
+        self.deleter.inc_ref(&Self::files(&sources));
+        self.merging
+            .extend(sources.iter().map(|s| s.info.name.clone()));
+
+        let name = self.new_segment_name();
+        let directory = self.directory.clone();
+        let retention_policy = self.soft_deletes_retention_policy.clone();
+        let sender = self.merge_sender.clone();
+        let segment = name.clone();
+
+        let task = MergeTask::new(&name, move |rate_limiter| {
+            let merge = || -> Result<MergedSegment, IndexError> {
+                let readers = sources
+                    .iter()
+                    .map(|s| SegmentReader::open(directory.as_ref(), s))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let source_live_docs = readers.iter().map(|r| r.hard_live_docs.clone()).collect();
+
+                let merger = SegmentMerger::new(&segment, readers, retention_policy.as_deref());
+                let info = match rate_limiter {
+                    Some(rate_limiter) => {
+                        merger.merge(&RateLimitedDirectory::new(&directory, rate_limiter))?
+                    }
+                    None => merger.merge(&directory)?,
+                };
+
+                Ok(MergedSegment {
+                    info,
+                    source_live_docs,
+                    doc_maps: merger.doc_maps().to_vec(),
+                })
+            };
+
+            let merged = panic::catch_unwind(AssertUnwindSafe(merge))
+                .unwrap_or_else(|_| Err(IndexError::MergeFailed(format!("Merge into {} panicked", segment))));
+
+            // The writer may be gone already
+            let _ = sender.send(FinishedMerge {
+                segment,
+                sources,
+                merged,
+            });
+        });
+
+        self.merge_scheduler.schedule(task);
+
+        Ok(true)
     }
 
-    pub fn add_document(&mut self, document: Document) {
-        println!("{:?}", document);
+    /// Commits merges that finished since the last call.
+    /// Files of failed merges are deleted, and the first failure is returned
+    /// once all finished merges are handled. Their sources can be merged again.
+    fn apply_finished_merges(&mut self) -> Result<(), IndexError> {
+        let mut failure = None;
+
+        while let Ok(finished) = self.merge_receiver.try_recv() {
+            for source in finished.sources.iter() {
+                self.merging.remove(&source.info.name);
+            }
+
+            match finished.merged {
+                Ok(merged) => self.commit_merge(
+                    merged.info,
+                    &finished.sources,
+                    &merged.source_live_docs,
+                    &merged.doc_maps,
+                )?,
+                Err(err) => {
+                    self.delete_segment_files(&finished.segment)?;
+
+                    if failure.is_none() {
+                        failure = Some(match err {
+                            IndexError::MergeFailed(_) => err,
+                            err => IndexError::MergeFailed(format!(
+                                "Merge into {} failed: {:?}",
+                                finished.segment, err
+                            )),
+                        });
+                    }
+                }
+            }
+
+            self.deleter.dec_ref(&Self::files(&finished.sources))?;
+        }
+
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the sources of a merge with the merged segment, at the place of the first source.
+    ///
+    /// Docs deleted and doc values updated in the sources while they were merged
+    /// are deleted and updated in the merged segment too. Files of sources are
+    /// deleted, unless a commit still references them.
+    fn commit_merge(
+        &mut self,
+        mut merged: SegmentCommitInfo,
+        sources: &[SegmentCommitInfo],
+        source_live_docs: &[FixedBitSet],
+        doc_maps: &[Vec<Option<u32>>],
+    ) -> Result<(), IndexError> {
+        let directory = self.directory.as_ref();
+        let current: Vec<SegmentCommitInfo> = sources
+            .iter()
+            .filter_map(|source| {
+                self.segment_infos
+                    .segments
+                    .iter()
+                    .find(|s| s.info.name == source.info.name)
+                    .cloned()
+            })
+            .collect();
+
+        if current.len() != sources.len() {
+            // Sources are gone, with a rollback or a new index
+            self.delete_segment_files(&merged.info.name)?;
+            return Ok(());
+        }
+
+        let mut merged_fields = if sources.iter().zip(current.iter()).any(|(s, c)| s.dv_gen != c.dv_gen) {
+            doc_values::read_segment(directory, &merged)?
+        } else {
+            BTreeMap::new()
+        };
+
+        let mut deleted = Vec::new();
+        let mut updated_fields: BTreeMap<String, DocValuesField> = BTreeMap::new();
+
+        for (((source, current), live_docs), doc_map) in sources
+            .iter()
+            .zip(current.iter())
+            .zip(source_live_docs.iter())
+            .zip(doc_maps.iter())
+        {
+            if current.del_gen != source.del_gen {
+                let current_live_docs = live_docs::read(directory, current)?;
+
+                for doc in 0..live_docs.len() {
+                    if live_docs.get(doc) && !current_live_docs.get(doc) {
+                        deleted.extend(doc_map[doc as usize]);
+                    }
+                }
+            }
+
+            if current.dv_gen != source.dv_gen {
+                let fields = doc_values::read_segment(directory, current)?;
+                let field_infos = field_info::read_segment(directory, current)?;
+
+                for (field_number, files) in current.dv_files.iter() {
+                    if source.dv_files.get(field_number) == Some(files) {
+                        continue;
+                    }
+
+                    let field_name = field_infos.get_field_name(*field_number).ok_or_else(|| {
+                        DirectoryError::CorruptIndexError(format!(
+                            "Unknown doc values field {} of segment {}",
+                            field_number, current.info.name
+                        ))
+                    })?;
+                    let field = &fields[&field_name];
+                    let merged_field = updated_fields
+                        .entry(field_name.clone())
+                        .or_insert_with(|| match merged_fields.remove(&field_name) {
+                            Some(merged_field) => merged_field,
+                            None => DocValuesField {
+                                name: field_name.clone(),
+                                soft_deletes: field.soft_deletes,
+                                values: DocValues::new(
+                                    field.values.doc_values_type(),
+                                    merged.info.doc_count,
+                                ),
+                            },
+                        });
+
+                    for (doc, new_doc) in doc_map.iter().enumerate() {
+                        if let Some(new_doc) = new_doc {
+                            merged_field
+                                .values
+                                .copy_value(*new_doc, &field.values, doc as u32);
+                        }
+                    }
+                }
+            }
+        }
+
+        live_docs::delete_docs(directory, &mut merged, &deleted)?;
+        if !updated_fields.is_empty() {
+            doc_values::write_update(directory, &mut merged, &updated_fields)?;
+        }
+        if !deleted.is_empty() || !updated_fields.is_empty() {
+            Self::update_soft_delete_count(directory, &mut merged)?;
+        }
+
+        let names: Vec<&str> = sources.iter().map(|s| s.info.name.as_str()).collect();
+        let segments = &mut self.segment_infos.segments;
+        let position = segments
+            .iter()
+            .position(|s| names.contains(&s.info.name.as_str()))
+            .unwrap();
+        segments.retain(|s| !names.contains(&s.info.name.as_str()));
+
+        // A segment without any docs left is dropped
+        if merged.info.doc_count > 0 {
+            segments.insert(position, merged);
+        } else {
+            for file in merged.files() {
+                directory.delete_file(&file)?;
+            }
+        }
+        self.segment_infos.sis_version += 1;
+
+        // Sources are deleted, unless a commit still references them
+        self.deleter.checkpoint(&self.segment_infos, false)
+    }
+
+    /// Deletes all files of a segment that is not part of the index, like a failed merge.
+    fn delete_segment_files(&self, segment: &str) -> Result<(), IndexError> {
+        let prefixes = [format!("{}.", segment), format!("{}_", segment)];
+
+        for file in self.directory.list()? {
+            let of_segment = prefixes.iter().any(|prefix| file.starts_with(prefix));
+
+            if of_segment && self.directory.file_exists(&file) {
+                self.directory.delete_file(&file)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// All files of the segments.
+    fn files(segments: &[SegmentCommitInfo]) -> HashSet<String> {
+        segments
+            .iter()
+            .flat_map(|s| s.files())
+            .collect()
     }
 
-    pub fn optimize(&mut self) {
-        todo!()
+    /// Commits kept by the deletion policy, oldest first.
+    pub fn commits(&self) -> &[IndexCommit] {
+        self.deleter.commits()
     }
 
-    pub fn close(&mut self) {
-        todo!()
+    /// Asks the deletion policy again which commits to keep, and deletes files
+    /// of the others. Needed after releasing a snapshot, to delete its files
+    /// before the next commit.
+    pub fn delete_unused_files(&mut self) -> Result<(), IndexError> {
+        self.deleter.revisit_policy()
+    }
+
+    /// Waits for merges, including merges they cascade into, and commits all changes.
+    pub fn close(&mut self) -> Result<(), IndexError> {
+        self.flush()?;
+
+        while !self.merging.is_empty() {
+            self.wait_for_merges()?;
+            self.maybe_merge()?;
+        }
+
+        self.commit()
+    }
+
+    fn new_segment_name(&mut self) -> String {
+        let name = format!("_{}", radix_36(self.segment_infos.sis_counter));
+        self.segment_infos.sis_counter += 1;
+        name
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::Field,
+        index::{
+            index_commit, LogByteSizeMergePolicy, SerialMergeScheduler, SnapshotDeletionPolicy,
+        },
+        search::{MatchAllDocsQuery, PrefixQuery, TermQuery},
+        store::{FSDirectory, RAMDirectory},
+        test_util::{doc, writer},
+    };
+
+    use super::*;
+
+    /// Writer that only merges on `force_merge` for few and small segments.
+    fn merging_writer(path: &std::path::Path) -> IndexWriter<StandardAnalyzer, FSDirectory> {
+        let mut writer = writer(path);
+        writer.set_merge_policy(LogByteSizeMergePolicy::new());
+        writer
+    }
+
+    #[test]
+    fn test_open_without_index() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let result = IndexWriter::open(directory, StandardAnalyzer::new());
+
+        assert!(matches!(result, Err(IndexError::IndexNotFound(_))));
+    }
+
+    #[test]
+    fn test_commit() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.generation, 1);
+        assert_eq!(sis.segments.len(), 1);
+        assert_eq!(sis.segments[0].info.name, "_0");
+        assert_eq!(sis.segments[0].info.doc_count, 2);
+
+        for file in sis.segments[0].files() {
+            assert!(directory.file_exists(&file), "Missing {}", file);
+        }
+
+        // Reopen and add to the existing index
+        let mut writer = IndexWriter::open(directory, StandardAnalyzer::new()).unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.generation, 2);
+        assert_eq!(sis.segments.len(), 2);
+        assert_eq!(sis.segments[1].info.name, "_1");
+    }
+
+    #[test]
+    fn test_commit_without_changes() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+
+        // Creating an empty index is committed
+        let mut writer = writer(&path);
+        assert!(writer.has_uncommitted_changes());
+        writer.commit().unwrap();
+        assert!(!writer.has_uncommitted_changes());
+
+        writer.commit().unwrap();
+        writer.prepare_commit().unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        assert_eq!(segment_infos::get_last_segments_file_name(&directory), "segments_1");
+        assert!(!directory.file_exists("pending_segments_2"));
+
+        // Opening and closing the index leaves it as it was
+        let mut writer = IndexWriter::open(directory, StandardAnalyzer::new()).unwrap();
+        assert!(!writer.has_uncommitted_changes());
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        assert_eq!(segment_infos::get_last_segments_file_name(&directory), "segments_1");
+
+        writer.delete_documents(Term::new("id".to_string(), "1".to_string())).unwrap();
+        writer.commit().unwrap();
+        assert_eq!(segment_infos::get_last_segments_file_name(&directory), "segments_1");
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.commit().unwrap();
+        assert_eq!(segment_infos::get_last_segments_file_name(&directory), "segments_2");
+    }
+
+    #[test]
+    fn test_two_phase_commit() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.prepare_commit().unwrap();
+
+        assert!(directory.file_exists("pending_segments_1"));
+        assert!(!directory.file_exists("segments_1"));
+        assert!(matches!(
+            writer.prepare_commit(),
+            Err(IndexError::IllegalState(_))
+        ));
+
+        writer.commit().unwrap();
+
+        assert!(!directory.file_exists("pending_segments_1"));
+        assert!(directory.file_exists("segments_1"));
+        assert_eq!(
+            segment_infos::read_latest_segment_infos(&directory).unwrap().segments.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_rollback() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.commit().unwrap();
+
+        // Flushed segment, buffered doc and prepared commit are all discarded
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.flush().unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.prepare_commit().unwrap();
+
+        assert!(directory.file_exists("_1.si"));
+        assert!(directory.file_exists("pending_segments_2"));
+
+        writer.rollback().unwrap();
+
+        assert!(!directory.file_exists("_1.si"));
+        assert!(!directory.file_exists("_1.frq"));
+        assert!(!directory.file_exists("pending_segments_2"));
+        assert!(directory.file_exists("_0.si"));
+        assert_eq!(writer.segment_infos().segments.len(), 1);
+        assert!(!writer.has_uncommitted_changes());
+
+        // Writer is still usable after rollback, without reusing discarded segment names
+        writer.add_document(doc("4", "after rollback")).unwrap();
+        writer.commit().unwrap();
+
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.generation, 2);
+        assert_eq!(sis.segments.len(), 2);
+        assert_eq!(sis.segments[1].info.doc_count, 1);
+        assert_eq!(sis.segments[1].info.name, "_3");
+    }
+
+    fn id(value: &str) -> Term {
+        Term::new("id".to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_delete_documents() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.commit().unwrap();
+
+        writer.delete_documents(id("2")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        let segment = &sis.segments[0];
+        assert_eq!(segment.del_gen, 1);
+        assert_eq!(segment.del_count, 1);
+        assert!(segment.files().contains("_0_1.liv"));
+
+        let reader = SegmentReader::open(&directory, segment).unwrap();
+        assert_eq!(reader.num_docs(), 2);
+        assert!(reader.live_docs.get(0));
+        assert!(!reader.live_docs.get(1));
+        assert!(reader.live_docs.get(2));
+
+        // A second delete writes a new generation
+        writer.delete_documents(Term::new("body".to_string(), "unit".to_string())).unwrap();
+        writer.commit().unwrap();
+
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.segments[0].del_gen, 2);
+        assert_eq!(sis.segments[0].del_count, 2);
+    }
+
+    #[test]
+    fn test_delete_buffered_documents() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        // Delete only applies to docs added before it
+        writer.add_document(doc("1", "first version")).unwrap();
+        writer.delete_documents(id("1")).unwrap();
+        writer.add_document(doc("1", "second version")).unwrap();
+        writer.delete_documents(id("missing")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.segments.len(), 1);
+        assert_eq!(sis.segments[0].del_count, 1);
+
+        let reader = SegmentReader::open(&directory, &sis.segments[0]).unwrap();
+        assert!(!reader.live_docs.get(0));
+        assert!(reader.live_docs.get(1));
+    }
+
+    #[test]
+    fn test_delete_across_segments() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = writer(&temp_dir.path().join("test-index"));
+
+        writer.add_document(doc("1", "tests")).unwrap();
+        writer.flush().unwrap();
+        writer.add_document(doc("2", "tests")).unwrap();
+        writer.delete_documents(Term::new("body".to_string(), "tests".to_string())).unwrap();
+        writer.add_document(doc("3", "tests")).unwrap();
+        writer.commit().unwrap();
+
+        let del_counts: Vec<u32> = writer
+            .segment_infos()
+            .segments
+            .iter()
+            .map(|s| s.del_count)
+            .collect();
+        assert_eq!(del_counts, vec![1, 1]);
+    }
+
+    /// Bodies of live docs with the id, across all committed segments.
+    fn live_bodies(path: &std::path::Path, id_value: &str) -> usize {
+        let directory = FSDirectory::new(path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+
+        sis.segments
+            .iter()
+            .map(|segment| {
+                let reader = SegmentReader::open(&directory, segment).unwrap();
+                reader
+                    .term_docs(&id(id_value))
+                    .into_iter()
+                    .filter(|&doc| reader.live_docs.get(doc))
+                    .count()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_failed_deletes_keep_docs() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.commit().unwrap();
+
+        // Deletes can't be resolved against the first segment while its terms are missing
+        let directory = FSDirectory::new(&path).unwrap();
+        directory.rename_file("_0.tis", "_0.tis.bak").unwrap();
+
+        writer.update_document(id("1"), doc("1", "integration tests")).unwrap();
+        writer.add_document(doc("2", "more tests")).unwrap();
+        assert!(writer.flush().is_err());
+        assert_eq!(writer.segment_infos().segments.len(), 1);
+
+        directory.rename_file("_0.tis.bak", "_0.tis").unwrap();
+        writer.commit().unwrap();
+
+        let reader = DirectoryReader::open(&directory).unwrap();
+        assert_eq!(reader.num_docs(), 2);
+        assert_eq!(reader.leaves()[0].num_docs(), 0);
+        assert_eq!(reader.leaves()[1].num_docs(), 2);
+        assert_eq!(reader.document(1).get("body"), Some("integration tests"));
+    }
+
+    #[test]
+    fn test_delete_documents_by_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.commit().unwrap();
+
+        // Only applies to buffered docs added before it
+        let integration = Term::new("body".to_string(), "integ".to_string());
+        writer.add_document(doc("3", "integration again")).unwrap();
+        writer
+            .delete_documents_by_query(Arc::new(PrefixQuery::new(integration)))
+            .unwrap();
+        writer.add_document(doc("4", "integration later")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let reader = DirectoryReader::open(&directory).unwrap();
+        assert_eq!(reader.num_docs(), 2);
+        assert!(reader.is_live(0));
+        assert!(!reader.is_live(1));
+        assert!(!reader.is_live(2));
+        assert!(reader.is_live(3));
+
+        // Queries without matches leave segments as they were
+        let missing = Term::new("body".to_string(), "missing".to_string());
+        writer
+            .delete_documents_by_query(Arc::new(TermQuery::new(missing)))
+            .unwrap();
+        writer.commit().unwrap();
+        assert_eq!(segment_infos::get_last_segments_file_name(&directory), "segments_2");
+
+        writer.rollback().unwrap();
+        writer
+            .delete_documents_by_query(Arc::new(MatchAllDocsQuery))
+            .unwrap();
+        writer.rollback().unwrap();
+        writer.commit().unwrap();
+        assert_eq!(DirectoryReader::open(&directory).unwrap().num_docs(), 2);
+    }
+
+    #[test]
+    fn test_update_document() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+
+        writer.add_document(doc("1", "first version")).unwrap();
+        writer.add_document(doc("2", "other doc")).unwrap();
+        writer.commit().unwrap();
+
+        // Update of a committed doc
+        writer.update_document(id("1"), doc("1", "second version")).unwrap();
+        // Updates of a buffered doc
+        writer.update_document(id("1"), doc("1", "third version")).unwrap();
+        // Update of a missing doc is an add
+        writer.update_document(id("3"), doc("3", "new doc")).unwrap();
+        writer.commit().unwrap();
+
+        assert_eq!(live_bodies(&path, "1"), 1);
+        assert_eq!(live_bodies(&path, "2"), 1);
+        assert_eq!(live_bodies(&path, "3"), 1);
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        let reader = SegmentReader::open(&directory, &sis.segments[1]).unwrap();
+        let third = Term::new("body".to_string(), "third".to_string());
+        assert_eq!(reader.term_docs(&third), vec![1]);
+        assert!(reader.live_docs.get(1));
+    }
+
+    #[test]
+    fn test_failed_update_keeps_docs() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        writer.set_soft_deletes_field("__soft_deletes");
+
+        writer.add_document(doc("1", "first version")).unwrap();
+        writer.commit().unwrap();
+
+        let invalid = || {
+            let mut invalid = doc("1", "second version");
+            let mut price = Field::numeric_doc_values("price".to_string(), 0);
+            price.value = "cheap".to_string();
+            invalid.add(price);
+            invalid
+        };
+
+        // Neither the doc nor the delete are buffered
+        let result = writer.update_document(id("1"), invalid());
+        assert!(matches!(result, Err(IndexError::IllegalArgument(_))));
+        let result = writer.soft_update_document(id("1"), invalid(), soft_delete());
+        assert!(matches!(result, Err(IndexError::IllegalArgument(_))));
+        writer.commit().unwrap();
+
+        assert_eq!(live_bodies(&path, "1"), 1);
+        assert_eq!(writer.segment_infos().segments.len(), 1);
+        assert_eq!(writer.segment_infos().segments[0].del_count, 0);
+        assert_eq!(writer.segment_infos().segments[0].dv_gen, -1);
+    }
+
+    #[test]
+    fn test_update_document_with_auto_flush() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        writer.set_max_buffered_docs(1);
+
+        // Each update flushes a segment, together with its delete
+        for version in ["first", "second", "third"] {
+            writer.update_document(id("1"), doc("1", version)).unwrap();
+            writer.commit().unwrap();
+
+            assert_eq!(live_bodies(&path, "1"), 1);
+        }
+
+        assert_eq!(writer.segment_infos().segments.len(), 3);
+    }
+
+    #[test]
+    fn test_rollback_deletes() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+
+        writer.add_document(doc("1", "tests")).unwrap();
+        writer.add_document(doc("2", "tests")).unwrap();
+        writer.commit().unwrap();
+
+        writer.delete_documents(id("1")).unwrap();
+        writer.flush().unwrap();
+        writer.delete_documents(id("2")).unwrap();
+        writer.flush().unwrap();
+
+        // Superseded generation is removed right away
+        assert!(!directory.file_exists("_0_1.liv"));
+        assert!(directory.file_exists("_0_2.liv"));
+
+        writer.rollback().unwrap();
+
+        assert!(!directory.file_exists("_0_2.liv"));
+        assert_eq!(writer.segment_infos().segments[0].del_gen, -1);
+        assert_eq!(writer.segment_infos().segments[0].del_count, 0);
+    }
+
+    #[test]
+    fn test_max_buffered_docs() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = writer(&temp_dir.path().join("test-index"));
+        writer.set_max_buffered_docs(2);
+
+        for i in 0..5 {
+            writer.add_document(doc(&i.to_string(), "tests")).unwrap();
+        }
+
+        assert_eq!(writer.segment_infos().segments.len(), 2);
+
+        writer.commit().unwrap();
+
+        let doc_counts: Vec<u32> = writer
+            .segment_infos()
+            .segments
+            .iter()
+            .map(|s| s.info.doc_count)
+            .collect();
+        assert_eq!(doc_counts, vec![2, 2, 1]);
+    }
+
+    fn soft_delete() -> Vec<Field> {
+        vec![Field::numeric_doc_values("__soft_deletes".to_string(), 1)]
+    }
+
+    #[test]
+    fn test_soft_update_document() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        writer.set_soft_deletes_field("__soft_deletes");
+
+        writer.add_document(doc("1", "first version")).unwrap();
+        writer.add_document(doc("2", "other doc")).unwrap();
+        writer.commit().unwrap();
+
+        // Soft update of a committed doc, then of the buffered doc
+        writer.soft_update_document(id("1"), doc("1", "second version"), soft_delete()).unwrap();
+        writer.soft_update_document(id("1"), doc("1", "third version"), soft_delete()).unwrap();
+        writer.commit().unwrap();
+
+        assert_eq!(live_bodies(&path, "1"), 1);
+        assert_eq!(live_bodies(&path, "2"), 1);
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        let soft_delete_counts: Vec<u32> =
+            sis.segments.iter().map(|s| s.soft_delete_count).collect();
+        assert_eq!(soft_delete_counts, vec![1, 1]);
+        assert_eq!(sis.segments[0].del_count, 0);
+        assert_eq!(sis.segments[0].dv_gen, 1);
+        assert!(sis.segments[0].files().contains("_0_1.dvd"));
+
+        // The segment had no soft deletes field, it is added to a new field infos generation
+        assert_eq!(sis.segments[0].field_infos_gen, 1);
+        assert!(sis.segments[0].files().contains("_0_1.fnm"));
+
+        // Soft-deleted docs are hidden, but kept for readers that want them
+        let reader = SegmentReader::open(&directory, &sis.segments[0]).unwrap();
+        let field_number = reader.field_infos.get_field_number("__soft_deletes").unwrap();
+        assert!(sis.segments[0].dv_files.contains_key(&field_number));
+        assert_eq!(reader.num_docs(), 1);
+        assert!(!reader.live_docs.get(0));
+        assert!(reader.hard_live_docs.get(0));
+        assert_eq!(reader.numeric_doc_value("__soft_deletes", 0), Some(1));
+        assert_eq!(reader.numeric_doc_value("__soft_deletes", 1), None);
+
+        // Hard delete of a soft-deleted doc is no longer counted as soft delete
+        writer.delete_documents(id("1")).unwrap();
+        writer.commit().unwrap();
+
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.segments[0].soft_delete_count, 0);
+        assert_eq!(sis.segments[0].del_count, 1);
+    }
+
+    #[test]
+    fn test_soft_update_document_without_field() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = writer(&temp_dir.path().join("test-index"));
+
+        let result = writer.soft_update_document(id("1"), doc("1", "tests"), soft_delete());
+        assert!(matches!(result, Err(IndexError::IllegalState(_))));
+
+        writer.set_soft_deletes_field("deleted");
+        let result = writer.soft_update_document(id("1"), doc("1", "tests"), soft_delete());
+        assert!(matches!(result, Err(IndexError::IllegalArgument(_))));
+    }
+
+    #[test]
+    fn test_update_doc_values() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+
+        let mut first = doc("1", "tests");
+        first.add(Field::numeric_doc_values("price".to_string(), 10));
+        writer.add_document(first).unwrap();
+        writer.add_document(doc("2", "tests")).unwrap();
+        writer.commit().unwrap();
+
+        let price = |value| vec![Field::numeric_doc_values("price".to_string(), value)];
+        writer.update_doc_values(id("2"), price(20)).unwrap();
+        writer.flush().unwrap();
+        writer.update_doc_values(id("1"), price(-5)).unwrap();
+        writer.commit().unwrap();
+
+        // Superseded generation is removed right away
+        assert!(directory.file_exists("_0.dvd"));
+        assert!(!directory.file_exists("_0_1.dvd"));
+        assert!(directory.file_exists("_0_2.dvd"));
+
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.segments[0].field_infos_gen, -1);
+        let reader = SegmentReader::open(&directory, &sis.segments[0]).unwrap();
+        assert_eq!(reader.numeric_doc_value("price", 0), Some(-5));
+        assert_eq!(reader.numeric_doc_value("price", 1), Some(20));
+        assert_eq!(reader.num_docs(), 2);
+
+        let keyword = Field::keyword("price".to_string(), "1".to_string());
+        let result = writer.update_doc_values(id("1"), vec![keyword]);
+        assert!(matches!(result, Err(IndexError::IllegalArgument(_))));
+    }
+
+    #[test]
+    fn test_optimize() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = merging_writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.commit().unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.flush().unwrap();
+        writer.add_document(doc("4", "even more tests")).unwrap();
+        writer.delete_documents(id("2")).unwrap();
+
+        writer.optimize().unwrap();
+
+        // Uncommitted sources are deleted right away, committed ones once no commit needs them
+        assert!(!directory.file_exists("_1.si"));
+        assert!(!directory.file_exists("_2.si"));
+        assert!(directory.file_exists("_0.si"));
+
+        writer.commit().unwrap();
+        assert!(!directory.file_exists("_0.si"));
+        assert!(!directory.file_exists("segments_1"));
+
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.segments.len(), 1);
+        assert_eq!(sis.segments[0].info.name, "_3");
+        assert_eq!(sis.segments[0].info.doc_count, 3);
+        assert_eq!(sis.segments[0].info.diagnostics["source"], "merge");
+        assert_eq!(sis.segments[0].del_count, 0);
+
+        assert_eq!(live_bodies(&path, "1"), 1);
+        assert_eq!(live_bodies(&path, "2"), 0);
+        assert_eq!(live_bodies(&path, "4"), 1);
+
+        let reader = SegmentReader::open(&directory, &sis.segments[0]).unwrap();
+        let ids: Vec<String> = (0..3)
+            .map(|doc| reader.document(doc).get("id").unwrap().to_string())
+            .collect();
+        assert_eq!(ids, vec!["1", "3", "4"]);
+
+        // A single segment without deletes is left alone
+        writer.optimize().unwrap();
+        assert_eq!(writer.segment_infos().segments[0].info.name, "_3");
+    }
+
+    #[test]
+    fn test_force_merge() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = merging_writer(&temp_dir.path().join("test-index"));
+        writer.set_max_buffered_docs(1);
+
+        for i in 0..4 {
+            writer.add_document(doc(&i.to_string(), "tests")).unwrap();
+        }
+
+        writer.force_merge(2).unwrap();
+
+        let doc_counts: Vec<u32> = writer
+            .segment_infos()
+            .segments
+            .iter()
+            .map(|s| s.info.doc_count)
+            .collect();
+        assert_eq!(doc_counts, vec![1, 3]);
+
+        assert!(matches!(
+            writer.force_merge(0),
+            Err(IndexError::IllegalArgument(_))
+        ));
+
+        // Merging away all docs drops the segment
+        writer
+            .delete_documents(Term::new("body".to_string(), "tests".to_string()))
+            .unwrap();
+        writer.optimize().unwrap();
+        assert!(writer.segment_infos().segments.is_empty());
+    }
+
+    #[test]
+    fn test_merge_soft_deletes_retention() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = merging_writer(&path);
+        writer.set_soft_deletes_field("__soft_deletes");
+        writer.set_soft_deletes_retention_policy(|reader: &SegmentReader, doc: u32| {
+            reader.document(doc).get("body") == Some("keep")
+        });
+
+        writer.add_document(doc("1", "keep")).unwrap();
+        writer.add_document(doc("2", "drop")).unwrap();
+        writer.commit().unwrap();
+
+        writer.soft_update_document(id("1"), doc("1", "new"), soft_delete()).unwrap();
+        writer.soft_update_document(id("2"), doc("2", "new"), soft_delete()).unwrap();
+        writer.optimize().unwrap();
+        writer.commit().unwrap();
+
+        let segment = &writer.segment_infos().segments[0];
+        assert_eq!(segment.info.doc_count, 3);
+        assert_eq!(segment.soft_delete_count, 1);
+
+        assert_eq!(live_bodies(&path, "1"), 1);
+        assert_eq!(live_bodies(&path, "2"), 1);
+    }
+
+    #[test]
+    fn test_merge_policy() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = writer(&temp_dir.path().join("test-index"));
+        writer.set_max_buffered_docs(1);
+
+        let mut policy = TieredMergePolicy::new();
+        policy.set_segments_per_tier(2.0);
+        policy.set_max_merge_at_once(2);
+        writer.set_merge_policy(policy);
+        writer.set_merge_scheduler(SerialMergeScheduler);
+
+        // Segments are merged as they are flushed
+        for i in 0..8 {
+            writer.add_document(doc(&i.to_string(), "tests")).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let segments = &writer.segment_infos().segments;
+        assert!(segments.len() <= 2, "{} segments", segments.len());
+        assert_eq!(segments.iter().map(|s| s.info.doc_count).sum::<u32>(), 8);
+        assert!(segments.iter().any(|s| s.info.diagnostics["source"] == "merge"));
+    }
+
+    #[test]
+    fn test_segment_sizes() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = merging_writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+        let file_lengths = |segment: &SegmentCommitInfo| {
+            let files = segment.files();
+            files.iter().map(|f| directory.file_length(f).unwrap()).sum::<u64>()
+        };
+
+        // Sizes are cached as segments are written
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.commit().unwrap();
+
+        let segment = &writer.segment_infos().segments[0];
+        assert_eq!(segment.size_in_bytes, Some(file_lengths(segment)));
+
+        // And read again once deletes change their files
+        writer.delete_documents(id("2")).unwrap();
+        writer.commit().unwrap();
+
+        let segment = &writer.segment_infos().segments[0];
+        assert!(segment.files().contains("_0_1.liv"));
+        assert_eq!(segment.size_in_bytes, Some(file_lengths(segment)));
+    }
+
+    #[test]
+    fn test_concurrent_merges() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        writer.set_max_buffered_docs(1);
+
+        let mut policy = TieredMergePolicy::new();
+        policy.set_segments_per_tier(2.0);
+        policy.set_max_merge_at_once(2);
+        writer.set_merge_policy(policy);
+
+        let mut scheduler = ConcurrentMergeScheduler::new();
+        scheduler.set_max_thread_count(2).unwrap();
+        scheduler.set_max_mb_per_sec(Some(100.0));
+        writer.set_merge_scheduler(scheduler);
+
+        for i in 0..20 {
+            writer.add_document(doc(&i.to_string(), "tests")).unwrap();
+        }
+        writer.delete_documents(id("3")).unwrap();
+        writer.close().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert!(sis.segments.len() <= 3, "{} segments", sis.segments.len());
+
+        let num_docs: u32 = sis
+            .segments
+            .iter()
+            .map(|s| SegmentReader::open(&directory, s).unwrap().num_docs())
+            .sum();
+        assert_eq!(num_docs, 19);
+    }
+
+    /// Runs merges only when waited for, so tests can change the index meanwhile.
+    #[derive(Default)]
+    struct DeferredMergeScheduler {
+        tasks: Vec<MergeTask>,
+    }
+
+    impl MergeScheduler for DeferredMergeScheduler {
+        fn schedule(&mut self, task: MergeTask) {
+            self.tasks.push(task);
+        }
+
+        fn wait(&mut self) {
+            for task in self.tasks.drain(..) {
+                task.run(None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deletes_during_merge() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        writer.set_soft_deletes_field("__soft_deletes");
+        writer.set_merge_scheduler(DeferredMergeScheduler::default());
+
+        let mut policy = LogByteSizeMergePolicy::new();
+        policy.set_merge_factor(2);
+        writer.set_merge_policy(policy);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "more unit tests")).unwrap();
+        writer.flush().unwrap();
+        writer.add_document(doc("3", "integration tests")).unwrap();
+        writer.add_document(doc("4", "more integration tests")).unwrap();
+        writer.flush().unwrap();
+
+        // The merge of both segments is registered, but doesn't run yet
+        assert_eq!(writer.segment_infos().segments.len(), 2);
+
+        writer.delete_documents(id("1")).unwrap();
+        writer
+            .soft_update_document(id("4"), doc("4", "updated tests"), soft_delete())
+            .unwrap();
+        writer.flush().unwrap();
+
+        writer.wait_for_merges().unwrap();
+
+        let segments = &writer.segment_infos().segments;
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].info.diagnostics["source"], "merge");
+        assert_eq!(segments[0].info.doc_count, 4);
+        assert_eq!(segments[0].del_count, 1);
+        assert_eq!(segments[0].soft_delete_count, 1);
+
+        writer.commit().unwrap();
+        assert_eq!(live_bodies(&path, "1"), 0);
+        assert_eq!(live_bodies(&path, "2"), 1);
+        assert_eq!(live_bodies(&path, "3"), 1);
+        assert_eq!(live_bodies(&path, "4"), 1);
+
+        // Sources are gone once the merge is committed
+        let directory = FSDirectory::new(&path).unwrap();
+        assert!(!directory.file_exists("_0.si"));
+        assert!(!directory.file_exists("_1.si"));
+    }
+
+    #[test]
+    fn test_merge_reads_sources_when_run() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let directory = FSDirectory::new(&path).unwrap();
+        let mut writer = writer(&path);
+        writer.set_merge_scheduler(DeferredMergeScheduler::default());
+
+        let mut policy = LogByteSizeMergePolicy::new();
+        policy.set_merge_factor(2);
+        writer.set_merge_policy(policy);
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "more unit tests")).unwrap();
+        writer.flush().unwrap();
+        writer.delete_documents(id("2")).unwrap();
+        writer.add_document(doc("3", "integration tests")).unwrap();
+        writer.flush().unwrap();
+
+        // The merge is registered with the first deletes of `_0`, which outlive newer deletes
+        writer.delete_documents(id("1")).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.segment_infos().segments[0].del_gen, 2);
+        assert!(directory.file_exists("_0_1.liv"));
+
+        writer.wait_for_merges().unwrap();
+        let segments = &writer.segment_infos().segments;
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].info.doc_count, 2);
+        assert_eq!(segments[0].del_count, 1);
+        assert!(!directory.file_exists("_0_1.liv"));
+        assert!(!directory.file_exists("_0_2.liv"));
+
+        writer.commit().unwrap();
+        assert_eq!(live_bodies(&path, "1"), 0);
+        assert_eq!(live_bodies(&path, "2"), 0);
+        assert_eq!(live_bodies(&path, "3"), 1);
+    }
+
+    #[test]
+    fn test_merge_failure() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = merging_writer(&path);
+        writer.set_merge_scheduler(ConcurrentMergeScheduler::new());
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.flush().unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.flush().unwrap();
+
+        // The merged segment can't be written
+        std::fs::create_dir(path.join("_2.fnm")).unwrap();
+
+        let result = writer.force_merge(1);
+        assert!(matches!(result, Err(IndexError::MergeFailed(_))));
+        assert_eq!(writer.segment_infos().segments.len(), 2);
+        assert!(!FSDirectory::new(&path).unwrap().file_exists("_2.si"));
+
+        // Sources of the failed merge can be merged again
+        std::fs::remove_dir(path.join("_2.fnm")).unwrap();
+        writer.force_merge(1).unwrap();
+
+        let segments = &writer.segment_infos().segments;
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].info.name, "_3");
+        assert_eq!(segments[0].info.doc_count, 2);
+    }
+
+    #[test]
+    fn test_keep_only_last_commit() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.commit().unwrap();
+        writer.delete_documents(id("1")).unwrap();
+        writer.commit().unwrap();
+        writer.delete_documents(id("2")).unwrap();
+        writer.commit().unwrap();
+
+        assert_eq!(writer.commits().len(), 1);
+        assert_eq!(writer.commits()[0].segments_file_name(), "segments_3");
+        assert!(!directory.file_exists("segments_1"));
+        assert!(!directory.file_exists("segments_2"));
+        assert!(!directory.file_exists("_0_1.liv"));
+        assert!(directory.file_exists("_0_2.liv"));
+
+        // Leftovers of a writer that never committed are deleted on open
+        let mut output = directory.create_file("_9.fdt").unwrap();
+        output.flush();
+        drop(output);
+
+        let writer =
+            IndexWriter::open(FSDirectory::new(&path).unwrap(), StandardAnalyzer::new()).unwrap();
+        assert!(!directory.file_exists("_9.fdt"));
+        assert_eq!(writer.commits().len(), 1);
+
+        let files: HashSet<String> = directory.list().unwrap().into_iter().collect();
+        assert_eq!(&files, writer.commits()[0].files());
+    }
+
+    #[test]
+    fn test_snapshot_deletion_policy() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let directory = FSDirectory::new(&path).unwrap();
+        let policy = SnapshotDeletionPolicy::new(KeepOnlyLastCommitDeletionPolicy);
+
+        let mut writer = IndexWriter::with_deletion_policy(
+            FSDirectory::new(&path).unwrap(),
+            StandardAnalyzer::new(),
+            WriteMode::CREATE,
+            policy.clone(),
+        )
+        .unwrap();
+        writer.set_merge_policy(LogByteSizeMergePolicy::new());
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.commit().unwrap();
+        let snapshot = policy.snapshot().unwrap();
+
+        writer.delete_documents(id("1")).unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.optimize().unwrap();
+        writer.commit().unwrap();
+
+        // The snapshot is kept, along with the latest commit
+        for file in snapshot.files() {
+            assert!(directory.file_exists(file), "Missing {}", file);
+        }
+        let commits = index_commit::list_commits(&directory).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0], snapshot);
+        assert_eq!(writer.commits().len(), 2);
+
+        // Segments of the older commit can still be read
+        let sis = snapshot.segment_infos(&directory).unwrap();
+        let num_docs: u32 = sis
+            .segments
+            .iter()
+            .map(|s| SegmentReader::open(&directory, s).unwrap().num_docs())
+            .sum();
+        assert_eq!(num_docs, 2);
+
+        policy.release(&snapshot).unwrap();
+        writer.delete_unused_files().unwrap();
+
+        assert!(!directory.file_exists(snapshot.segments_file_name()));
+        assert!(!directory.file_exists("_0.si"));
+        assert_eq!(index_commit::list_commits(&directory).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_during_prepared_commit() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = merging_writer(&path);
+        let directory = FSDirectory::new(&path).unwrap();
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.flush().unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.prepare_commit().unwrap();
+
+        // Sources of the merge are still needed by the prepared commit
+        writer.optimize().unwrap();
+        assert!(directory.file_exists("_0.si"));
+        assert!(directory.file_exists("_1.si"));
+
+        writer.commit().unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        assert_eq!(sis.segments.len(), 2);
+        for file in sis.segments.iter().flat_map(|s| s.files()) {
+            assert!(directory.file_exists(&file), "Missing {}", file);
+        }
+
+        // Once the merged segment is committed, they are gone
+        writer.commit().unwrap();
+        assert!(!directory.file_exists("_0.si"));
+        assert!(!directory.file_exists("_1.si"));
+        assert!(directory.file_exists("_2.si"));
+    }
+
+    #[test]
+    fn test_hot_backup() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let policy = SnapshotDeletionPolicy::new(KeepOnlyLastCommitDeletionPolicy);
+
+        let mut writer = IndexWriter::with_deletion_policy(
+            FSDirectory::new(&path).unwrap(),
+            StandardAnalyzer::new(),
+            WriteMode::CREATE,
+            policy.clone(),
+        )
+        .unwrap();
+        writer.set_merge_policy(LogByteSizeMergePolicy::new());
+
+        for i in 0..10 {
+            writer.add_document(doc(&i.to_string(), "unit tests")).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let snapshot = policy.snapshot().unwrap();
+        let backup = RAMDirectory::new();
+
+        // Copy on another thread, while indexing goes on
+        let copy = {
+            let (snapshot, backup, path) = (snapshot.clone(), backup.clone(), path.clone());
+            std::thread::spawn(move || {
+                let directory = FSDirectory::new(&path).unwrap();
+                snapshot.copy_to(&directory, &backup).unwrap();
+            })
+        };
+
+        writer.delete_documents(id("0")).unwrap();
+        for i in 10..20 {
+            writer.add_document(doc(&i.to_string(), "integration tests")).unwrap();
+        }
+        writer.optimize().unwrap();
+        writer.commit().unwrap();
+
+        copy.join().unwrap();
+
+        // The backup is the index as of the snapshot
+        let mut files = backup.list().unwrap();
+        files.sort();
+        let mut expected = snapshot.file_names();
+        expected.sort();
+        assert_eq!(files, expected);
+
+        let sis = segment_infos::read_latest_segment_infos(&backup).unwrap();
+        assert_eq!(sis.generation, snapshot.generation());
+        let num_docs: u32 = sis
+            .segments
+            .iter()
+            .map(|s| SegmentReader::open(&backup, s).unwrap().num_docs())
+            .sum();
+        assert_eq!(num_docs, 10);
+
+        policy.release(&snapshot).unwrap();
+        writer.delete_unused_files().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        assert!(!directory.file_exists(snapshot.segments_file_name()));
+    }
+}
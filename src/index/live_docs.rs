@@ -0,0 +1,197 @@
+use radix_fmt::radix_36;
+
+use crate::{
+    store::{Directory, DirectoryError, InputStream, OutputStream},
+    util::FixedBitSet,
+};
+
+use super::{codec_utils, segment_commit_info::SegmentCommitInfo};
+
+pub const LIVE_DOCS_EXTENSION: &str = "liv";
+pub const LIVE_DOCS_CODEC: &str = "Lucene50LiveDocs";
+pub const LIVE_DOCS_VERSION_CURRENT: u32 = 0;
+
+/// Live docs file for a deletion generation, eg: `_0_1.liv`.
+pub fn file_name(segment: &str, del_gen: i64) -> String {
+    format!(
+        "{}_{}.{}",
+        segment,
+        radix_36(del_gen as u64),
+        LIVE_DOCS_EXTENSION
+    )
+}
+
+/// Reads live docs of a segment, all docs are live when it has no deletes.
+pub fn read<D: Directory>(
+    directory: &D,
+    commit_info: &SegmentCommitInfo,
+) -> Result<FixedBitSet, DirectoryError> {
+    let max_doc = commit_info.info.doc_count;
+
+    if commit_info.del_gen == -1 {
+        return Ok(FixedBitSet::all(max_doc));
+    }
+
+    let name = file_name(&commit_info.info.name, commit_info.del_gen);
+    let mut input = directory.open_file(&name)?;
+
+    codec_utils::check_header(&mut input)?;
+
+    let words = (0..FixedBitSet::num_words(max_doc))
+        .map(|_| input.read_long())
+        .collect();
+
+    Ok(FixedBitSet::from_words(words, max_doc))
+}
+
+/// Writes live docs as a new deletion generation, returning the file name.
+pub fn write<D: Directory>(
+    directory: &D,
+    commit_info: &SegmentCommitInfo,
+    del_gen: i64,
+    live_docs: &FixedBitSet,
+) -> Result<String, DirectoryError> {
+    let info = &commit_info.info;
+    let name = file_name(&info.name, del_gen);
+    let mut output = directory.create_file(&name)?;
+
+    codec_utils::write_index_header(
+        &mut output,
+        LIVE_DOCS_CODEC,
+        LIVE_DOCS_VERSION_CURRENT,
+        &info.id,
+        &radix_36(del_gen as u64).to_string(),
+    );
+
+    for word in live_docs.words() {
+        output.write_long(*word);
+    }
+
+    codec_utils::write_footer(&mut output);
+    output.flush();
+
+    Ok(name)
+}
+
+/// Marks docs of a segment as deleted, writing a new deletion generation if any doc was live.
+/// Updates `del_gen` and `del_count`, and returns the number of newly deleted docs.
+pub fn delete_docs<D: Directory>(
+    directory: &D,
+    commit_info: &mut SegmentCommitInfo,
+    docs: &[u32],
+) -> Result<u32, DirectoryError> {
+    if docs.is_empty() {
+        return Ok(0);
+    }
+
+    let mut live_docs = read(directory, commit_info)?;
+    let mut deleted = 0;
+
+    for &doc in docs {
+        if live_docs.get(doc) {
+            live_docs.clear(doc);
+            deleted += 1;
+        }
+    }
+
+    if deleted > 0 {
+        let del_gen = next_del_gen(commit_info.del_gen);
+        write(directory, commit_info, del_gen, &live_docs)?;
+
+        commit_info.del_gen = del_gen;
+        commit_info.del_count += deleted;
+        commit_info.size_in_bytes = None;
+    }
+
+    Ok(deleted)
+}
+
+fn next_del_gen(del_gen: i64) -> i64 {
+    if del_gen == -1 {
+        1
+    } else {
+        del_gen + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::{
+        index::{codec_utils::Id, segment_info::SegmentInfo},
+        store::FSDirectory,
+        version,
+    };
+
+    use super::*;
+
+    fn commit_info(doc_count: u32) -> SegmentCommitInfo {
+        SegmentCommitInfo::new(SegmentInfo {
+            name: "_0".to_string(),
+            id: Id::random(),
+            codec: "Ferrocene".to_string(),
+            version: version::LATEST,
+            min_version: None,
+            doc_count,
+            is_compound: false,
+            diagnostics: HashMap::new(),
+            files: HashSet::new(),
+            attributes: HashMap::new(),
+            num_sort_fields: 0,
+        })
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("_0", 1), "_0_1.liv");
+        assert_eq!(file_name("_a", 36), "_a_10.liv");
+    }
+
+    #[test]
+    fn test_delete_docs() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut commit_info = commit_info(70);
+
+        assert_eq!(read(&directory, &commit_info).unwrap().cardinality(), 70);
+
+        assert_eq!(delete_docs(&directory, &mut commit_info, &[1, 65]).unwrap(), 2);
+        assert_eq!(commit_info.del_gen, 1);
+        assert_eq!(commit_info.del_count, 2);
+        assert!(directory.file_exists("_0_1.liv"));
+
+        // Deleting already deleted docs is a no-op
+        assert_eq!(delete_docs(&directory, &mut commit_info, &[1]).unwrap(), 0);
+        assert_eq!(commit_info.del_gen, 1);
+
+        assert_eq!(delete_docs(&directory, &mut commit_info, &[1, 2]).unwrap(), 1);
+        assert_eq!(commit_info.del_gen, 2);
+        assert_eq!(commit_info.del_count, 3);
+
+        let live_docs = read(&directory, &commit_info).unwrap();
+        assert_eq!(live_docs.cardinality(), 67);
+        assert!(live_docs.get(0));
+        assert!(!live_docs.get(1));
+        assert!(!live_docs.get(2));
+        assert!(!live_docs.get(65));
+    }
+
+    #[test]
+    fn test_read_corrupt() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut commit_info = commit_info(70);
+        commit_info.del_gen = 1;
+
+        let mut output = directory.create_file("_0_1.liv").unwrap();
+        output.write_bytes(&[0; 64]);
+        output.flush();
+        drop(output);
+
+        assert!(matches!(
+            read(&directory, &commit_info),
+            Err(DirectoryError::CorruptIndexError(_))
+        ));
+    }
+}
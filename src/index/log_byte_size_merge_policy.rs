@@ -0,0 +1,170 @@
+use super::{
+    merge_policy::{MergeContext, MergePolicy, MergeSpecification, OneMerge},
+    segment_commit_info::SegmentCommitInfo,
+    segment_infos::SegmentInfos,
+};
+
+pub const DEFAULT_MERGE_FACTOR: usize = 10;
+pub const DEFAULT_MIN_MERGE_BYTES: u64 = 1_677_722; // 1.6 MB
+pub const DEFAULT_MAX_MERGE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Segments whose level is within this span of the largest are on the same level.
+const LEVEL_LOG_SPAN: f64 = 0.75;
+
+/// Merges adjacent segments of the same size level, based on Lucene's `LogByteSizeMergePolicy`.
+///
+/// The level of a segment is the logarithm of its byte size, in base `merge_factor`.
+/// Whenever `merge_factor` adjacent segments are on the same level, they are merged
+/// into one segment of the next level. Merges keep segments in index order,
+/// so runs with a segment that is already being merged are skipped.
+#[derive(Debug, Clone)]
+pub struct LogByteSizeMergePolicy {
+    merge_factor: usize,
+    min_merge_bytes: u64,
+    max_merge_bytes: u64,
+}
+
+impl Default for LogByteSizeMergePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogByteSizeMergePolicy {
+    pub fn new() -> Self {
+        Self {
+            merge_factor: DEFAULT_MERGE_FACTOR,
+            min_merge_bytes: DEFAULT_MIN_MERGE_BYTES,
+            max_merge_bytes: DEFAULT_MAX_MERGE_BYTES,
+        }
+    }
+
+    /// Number of segments of a level that are merged together.
+    pub fn set_merge_factor(&mut self, merge_factor: usize) {
+        assert!(merge_factor >= 2, "merge_factor must be at least 2");
+        self.merge_factor = merge_factor;
+    }
+
+    /// Smaller segments are all on the lowest level.
+    pub fn set_min_merge_bytes(&mut self, min_merge_bytes: u64) {
+        self.min_merge_bytes = min_merge_bytes.max(1);
+    }
+
+    /// Segments at least this large are never merged.
+    pub fn set_max_merge_bytes(&mut self, max_merge_bytes: u64) {
+        self.max_merge_bytes = max_merge_bytes;
+    }
+
+    fn level(&self, size: u64) -> f64 {
+        (size.max(self.min_merge_bytes) as f64).ln() / (self.merge_factor as f64).ln()
+    }
+}
+
+impl MergePolicy for LogByteSizeMergePolicy {
+    fn find_merges(
+        &self,
+        segment_infos: &SegmentInfos,
+        context: &MergeContext,
+    ) -> Option<MergeSpecification> {
+        let segments: Vec<&SegmentCommitInfo> = segment_infos.segments.iter().collect();
+        let levels: Vec<f64> = segments.iter().map(|s| self.level(context.size(s))).collect();
+        let level_floor = self.level(self.min_merge_bytes);
+
+        let mut spec = MergeSpecification::new();
+        let mut start = 0;
+
+        while start < segments.len() {
+            // Largest level of the remaining segments, and segments down to its bottom
+            let max_level = levels[start..].iter().cloned().fold(f64::MIN, f64::max);
+            let level_bottom = if max_level <= level_floor {
+                -1.0
+            } else {
+                (max_level - LEVEL_LOG_SPAN).max(level_floor)
+            };
+
+            let upto = (start..segments.len())
+                .rev()
+                .find(|&i| levels[i] >= level_bottom)
+                .unwrap();
+
+            // Merge every full run of merge_factor segments on the level
+            let mut end = start + self.merge_factor;
+            while end <= upto + 1 {
+                let run = &segments[start..end];
+                let too_large = run
+                    .iter()
+                    .any(|s| context.size(s) >= self.max_merge_bytes);
+                let merging = run.iter().any(|s| context.is_merging(s));
+
+                if !too_large && !merging {
+                    spec.add(OneMerge::new(run));
+                }
+
+                start = end;
+                end = start + self.merge_factor;
+            }
+
+            start = upto + 1;
+        }
+
+        spec.into_option()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::merge_policy::tests::{names, segments};
+
+    use super::*;
+
+    fn policy() -> LogByteSizeMergePolicy {
+        let mut policy = LogByteSizeMergePolicy::new();
+        policy.set_merge_factor(3);
+        policy.set_min_merge_bytes(10);
+        policy
+    }
+
+    #[test]
+    fn test_merges_runs_of_a_level() {
+        let (segment_infos, context) = segments(&[
+            (1000, 0, 10_000),
+            (10, 0, 100),
+            (10, 0, 120),
+            (10, 0, 90),
+            (10, 0, 110),
+        ]);
+
+        let spec = policy().find_merges(&segment_infos, &context).unwrap();
+
+        // The large segment is on its own level, the last small one waits for more
+        assert_eq!(spec.merges.len(), 1);
+        assert_eq!(names(&spec.merges[0]), vec!["_1", "_2", "_3"]);
+    }
+
+    #[test]
+    fn test_not_enough_segments() {
+        let (segment_infos, context) = segments(&[(10, 0, 100), (10, 0, 100)]);
+
+        assert!(policy().find_merges(&segment_infos, &context).is_none());
+    }
+
+    #[test]
+    fn test_max_merge_bytes() {
+        let mut policy = policy();
+        policy.set_max_merge_bytes(150);
+
+        let (segment_infos, context) = segments(&[(10, 0, 100), (10, 0, 200), (10, 0, 100)]);
+
+        assert!(policy.find_merges(&segment_infos, &context).is_none());
+    }
+
+    #[test]
+    fn test_skips_merging_segments() {
+        let (segment_infos, mut context) = segments(&[(10, 0, 100); 6]);
+        context.set_merging(["_1".to_string()]);
+
+        let spec = policy().find_merges(&segment_infos, &context).unwrap();
+        assert_eq!(spec.merges.len(), 1);
+        assert_eq!(names(&spec.merges[0]), vec!["_3", "_4", "_5"]);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::search::DocIdSetIterator;
+
+use super::{
+    document_writer::PostingsTable,
+    postings_enum::{PostingsEnum, NO_MORE_DOCS},
+    terms::{self, SeekStatus, Terms, TermsEnum},
+    Posting,
+};
+
+/// Terms of a field in a `PostingsTable`, the inverted form of a single doc.
+///
+/// The doc is numbered 0, so the table can be searched like a one-doc segment.
+pub struct MemoryTerms<'a> {
+    /// Postings of the field, sorted by term text.
+    postings: Vec<&'a Posting>,
+}
+
+impl PostingsTable {
+    /// Terms of a field, `None` when the field has no terms in the table.
+    pub fn terms(&self, field: &str) -> Option<MemoryTerms<'_>> {
+        let mut postings: Vec<&Posting> = self
+            .table
+            .values()
+            .filter(|p| p.term.field == field)
+            .collect();
+
+        if postings.is_empty() {
+            return None;
+        }
+
+        postings.sort_by(|a, b| a.term.text.cmp(&b.term.text));
+        Some(MemoryTerms { postings })
+    }
+}
+
+impl Terms for MemoryTerms<'_> {
+    fn iterator(&self) -> Box<dyn TermsEnum + '_> {
+        Box::new(MemoryTermsEnum {
+            postings: &self.postings,
+            index: None,
+        })
+    }
+
+    fn size(&self) -> u64 {
+        self.postings.len() as u64
+    }
+
+    fn doc_count(&self) -> u32 {
+        1
+    }
+
+    fn sum_doc_freq(&self) -> u64 {
+        self.postings.len() as u64
+    }
+
+    fn sum_total_term_freq(&self) -> u64 {
+        self.postings.iter().map(|p| p.freq as u64).sum()
+    }
+}
+
+pub struct MemoryTermsEnum<'a> {
+    postings: &'a [&'a Posting],
+    /// Index of the current term, `len` once exhausted.
+    index: Option<usize>,
+}
+
+impl MemoryTermsEnum<'_> {
+    fn current(&self) -> &Posting {
+        let index = self.index.expect("Terms enum is not positioned");
+        self.postings[index]
+    }
+}
+
+impl TermsEnum for MemoryTermsEnum<'_> {
+    fn next(&mut self) -> Option<&str> {
+        let len = self.postings.len();
+        let index = self.index.map_or(0, |i| (i + 1).min(len));
+        self.index = Some(index);
+
+        self.postings.get(index).map(|p| p.term.text.as_str())
+    }
+
+    fn seek_ceil(&mut self, text: &str) -> SeekStatus {
+        let (index, status) = terms::seek_ceil(self.postings, text, |p| &p.term.text);
+        self.index = Some(index);
+        status
+    }
+
+    fn term(&self) -> &str {
+        &self.current().term.text
+    }
+
+    fn doc_freq(&self) -> u32 {
+        1
+    }
+
+    fn total_term_freq(&self) -> u64 {
+        self.current().freq as u64
+    }
+
+    fn postings(&self) -> Box<dyn PostingsEnum> {
+        Box::new(MemoryPostingsEnum {
+            positions: self.current().positions.clone(),
+            doc: None,
+            next_position: 0,
+        })
+    }
+}
+
+/// Postings of a term in the single doc of a `PostingsTable`.
+pub struct MemoryPostingsEnum {
+    positions: Vec<u32>,
+    doc: Option<u32>,
+    next_position: usize,
+}
+
+impl DocIdSetIterator for MemoryPostingsEnum {
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(0)
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        let doc = match self.doc {
+            None => 0,
+            Some(_) => NO_MORE_DOCS,
+        };
+        self.doc = Some(doc);
+        doc
+    }
+
+    fn cost(&self) -> u64 {
+        1
+    }
+}
+
+impl PostingsEnum for MemoryPostingsEnum {
+    fn freq(&self) -> u32 {
+        self.positions.len() as u32
+    }
+
+    fn next_position(&mut self) -> u32 {
+        let position = self.positions[self.next_position];
+        self.next_position += 1;
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::index::Term;
+
+    use super::*;
+
+    #[test]
+    fn test_memory_terms() {
+        let mut table = PostingsTable::new();
+        for (position, text) in ["unit", "tests", "and", "more", "tests"].iter().enumerate() {
+            table.add(
+                Arc::new(Term::new("body".to_string(), text.to_string())),
+                position,
+            );
+        }
+        table.add(Arc::new(Term::new("id".to_string(), "1".to_string())), 0);
+
+        assert!(table.terms("title").is_none());
+        let terms = table.terms("body").unwrap();
+        assert_eq!(terms.size(), 4);
+        assert_eq!(terms.doc_count(), 1);
+        assert_eq!(terms.sum_total_term_freq(), 5);
+
+        let mut terms_enum = terms.iterator();
+        assert_eq!(terms_enum.next(), Some("and"));
+        assert_eq!(terms_enum.seek_ceil("t"), SeekStatus::NotFound);
+        assert_eq!(terms_enum.term(), "tests");
+        assert_eq!(terms_enum.total_term_freq(), 2);
+
+        let mut postings = terms_enum.postings();
+        assert_eq!(postings.next_doc(), 0);
+        assert_eq!(postings.freq(), 2);
+        assert_eq!(postings.next_position(), 1);
+        assert_eq!(postings.next_position(), 4);
+        assert_eq!(postings.next_doc(), NO_MORE_DOCS);
+        assert_eq!(postings.doc_id(), NO_MORE_DOCS);
+
+        assert_eq!(terms_enum.next(), Some("unit"));
+        assert_eq!(terms_enum.next(), None);
+        assert_eq!(terms_enum.seek_ceil("zz"), SeekStatus::End);
+    }
+
+    #[test]
+    fn test_memory_terms_exhausted() {
+        let mut table = PostingsTable::new();
+        for (position, text) in ["unit", "tests"].iter().enumerate() {
+            table.add(
+                Arc::new(Term::new("body".to_string(), text.to_string())),
+                position,
+            );
+        }
+
+        let terms = table.terms("body").unwrap();
+        let mut terms_enum = terms.iterator();
+        assert_eq!(terms_enum.seek_ceil(""), SeekStatus::NotFound);
+        assert_eq!(terms_enum.term(), "tests");
+        assert_eq!(terms_enum.seek_ceil("units"), SeekStatus::End);
+        assert_eq!(terms_enum.next(), None);
+        assert!(!terms_enum.seek_exact("v"));
+        assert_eq!(terms_enum.next(), None);
+
+        terms_enum.seek_exact("unit");
+        let mut postings = terms_enum.postings();
+        assert_eq!(postings.advance(1), NO_MORE_DOCS);
+        assert_eq!(postings.doc_id(), NO_MORE_DOCS);
+
+        let mut postings = terms_enum.postings();
+        assert_eq!(postings.next_doc(), 0);
+        assert_eq!(postings.next_position(), 0);
+        assert_eq!(postings.start_offset(), None);
+        assert_eq!(postings.end_offset(), None);
+        assert_eq!(postings.payload(), None);
+        assert_eq!(postings.advance(1), NO_MORE_DOCS);
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::{segment_commit_info::SegmentCommitInfo, segment_infos::SegmentInfos};
+
+/// A single merge, of segments into one new segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OneMerge {
+    /// Names of the segments to merge.
+    pub segments: Vec<String>,
+}
+
+impl OneMerge {
+    pub fn new(segments: &[&SegmentCommitInfo]) -> Self {
+        Self {
+            segments: segments.iter().map(|s| s.info.name.clone()).collect(),
+        }
+    }
+}
+
+/// Merges picked by a `MergePolicy`, which can run independently of each other.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeSpecification {
+    pub merges: Vec<OneMerge>,
+}
+
+impl MergeSpecification {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, merge: OneMerge) {
+        self.merges.push(merge);
+    }
+
+    /// `None` if there is nothing to merge, for returning from a `MergePolicy`.
+    pub fn into_option(self) -> Option<Self> {
+        if self.merges.is_empty() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+/// Counts deleted docs a merge of a segment would drop.
+type CountDeletesToMerge<'a> = Box<dyn Fn(&SegmentCommitInfo) -> u32 + 'a>;
+
+/// What a merge policy knows about segments, besides their `SegmentInfos`.
+#[derive(Default)]
+pub struct MergeContext<'a> {
+    /// Bytes of all files of each segment, by segment name.
+    sizes: HashMap<String, u64>,
+    /// Deleted docs a merge would drop, when not all soft-deleted docs would be.
+    deletes_to_merge: RefCell<HashMap<String, u32>>,
+    /// Counts deletes to merge of segments with soft-deleted docs, when first asked for.
+    count_deletes_to_merge: Option<CountDeletesToMerge<'a>>,
+    /// Segments of merges still running, which can't be merged again.
+    merging: HashSet<String>,
+}
+
+impl fmt::Debug for MergeContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergeContext")
+            .field("sizes", &self.sizes)
+            .field("deletes_to_merge", &self.deletes_to_merge)
+            .field("merging", &self.merging)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> MergeContext<'a> {
+    /// Context with the sizes cached in the segment infos, see
+    /// `SegmentCommitInfo::size_in_bytes`. Segments without a cached size count as empty.
+    pub fn new(segment_infos: &SegmentInfos) -> Self {
+        let sizes = segment_infos
+            .segments
+            .iter()
+            .filter_map(|s| Some((s.info.name.clone(), s.size_in_bytes?)))
+            .collect();
+
+        Self {
+            sizes,
+            ..Self::default()
+        }
+    }
+
+    /// Context with the given sizes, by segment name.
+    pub fn with_sizes(sizes: HashMap<String, u64>) -> Self {
+        Self {
+            sizes,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the number of deleted docs a merge of the segment would drop,
+    /// for segments with soft-deleted docs that merges retain.
+    pub fn set_deletes_to_merge(&mut self, segment: &str, deletes: u32) {
+        self.deletes_to_merge.get_mut().insert(segment.to_owned(), deletes);
+    }
+
+    /// Counts deletes to merge of segments with soft-deleted docs with `count`,
+    /// once for each segment a policy asks about.
+    pub fn set_count_deletes_to_merge<F>(&mut self, count: F)
+    where
+        F: Fn(&SegmentCommitInfo) -> u32 + 'a,
+    {
+        self.count_deletes_to_merge = Some(Box::new(count));
+    }
+
+    /// Marks segments as being merged, policies must not pick them.
+    pub fn set_merging(&mut self, segments: impl IntoIterator<Item = String>) {
+        self.merging.extend(segments);
+    }
+
+    pub fn is_merging(&self, segment: &SegmentCommitInfo) -> bool {
+        self.merging.contains(&segment.info.name)
+    }
+
+    /// Number of deleted docs a merge of the segment would drop.
+    /// Hard and soft-deleted docs, unless set otherwise.
+    pub fn deletes_to_merge(&self, segment: &SegmentCommitInfo) -> u32 {
+        if let Some(deletes) = self.deletes_to_merge.borrow().get(&segment.info.name) {
+            return *deletes;
+        }
+
+        match self.count_deletes_to_merge.as_ref() {
+            Some(count) if segment.soft_delete_count > 0 => {
+                let deletes = count(segment);
+                self.deletes_to_merge.borrow_mut().insert(segment.info.name.clone(), deletes);
+                deletes
+            }
+            _ => segment.del_count + segment.soft_delete_count,
+        }
+    }
+
+    /// Ratio of docs of the segment a merge would drop.
+    pub fn deletes_ratio(&self, segment: &SegmentCommitInfo) -> f64 {
+        let max_doc = segment.info.doc_count;
+
+        if max_doc == 0 {
+            0.0
+        } else {
+            self.deletes_to_merge(segment) as f64 / max_doc as f64
+        }
+    }
+
+    /// Bytes of all files of the segment.
+    pub fn size_in_bytes(&self, segment: &SegmentCommitInfo) -> u64 {
+        self.sizes.get(&segment.info.name).copied().unwrap_or_default()
+    }
+
+    /// Bytes of the segment, pro-rated by the ratio of its docs that are not deleted.
+    pub fn size(&self, segment: &SegmentCommitInfo) -> u64 {
+        let size = self.size_in_bytes(segment) as f64;
+
+        (size * (1.0 - self.deletes_ratio(segment))) as u64
+    }
+}
+
+/// Decides which segments are merged, and when.
+///
+/// The `IndexWriter` asks for merges after flushing segments, and registers them
+/// until the policy has nothing more to merge. Merges run on the `MergeScheduler`,
+/// and segments they merge are marked in the `MergeContext` until they finish.
+pub trait MergePolicy: Send + Sync {
+    /// Merges to run after segments were flushed or merged, `None` for none.
+    /// Segments that are being merged must not be picked.
+    fn find_merges(
+        &self,
+        segment_infos: &SegmentInfos,
+        context: &MergeContext,
+    ) -> Option<MergeSpecification>;
+
+    /// Merges for `IndexWriter::force_merge`, towards at most `max_segment_count` segments.
+    /// Asked again after the merges ran, until it returns `None`.
+    ///
+    /// Merges the newest segments into one by default. With `max_segment_count`
+    /// of 1, a single segment with deletes is rewritten without them.
+    fn find_forced_merges(
+        &self,
+        segment_infos: &SegmentInfos,
+        max_segment_count: usize,
+        context: &MergeContext,
+    ) -> Option<MergeSpecification> {
+        let segments: Vec<&SegmentCommitInfo> = segment_infos
+            .segments
+            .iter()
+            .filter(|s| !context.is_merging(s))
+            .collect();
+
+        let merge_count = if segments.len() > max_segment_count {
+            segments.len() - max_segment_count + 1
+        } else if max_segment_count == 1
+            && segments.len() == 1
+            && context.deletes_to_merge(segments[0]) > 0
+        {
+            1
+        } else {
+            0
+        };
+
+        let mut spec = MergeSpecification::new();
+        if merge_count > 0 {
+            spec.add(OneMerge::new(&segments[segments.len() - merge_count..]));
+        }
+
+        spec.into_option()
+    }
+}
+
+/// Never merges, not even for `force_merge`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoMergePolicy;
+
+impl MergePolicy for NoMergePolicy {
+    fn find_merges(&self, _: &SegmentInfos, _: &MergeContext) -> Option<MergeSpecification> {
+        None
+    }
+
+    fn find_forced_merges(
+        &self,
+        _: &SegmentInfos,
+        _: usize,
+        _: &MergeContext,
+    ) -> Option<MergeSpecification> {
+        None
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        index::{codec_utils::Id, segment_info::SegmentInfo},
+        version,
+    };
+
+    use super::*;
+
+    /// Segment infos and a context, for segments given as (doc count, deleted docs, bytes).
+    pub(crate) fn segments<'a>(specs: &[(u32, u32, u64)]) -> (SegmentInfos, MergeContext<'a>) {
+        let mut segment_infos = SegmentInfos::new();
+        let mut sizes = HashMap::new();
+
+        for (i, &(doc_count, del_count, size)) in specs.iter().enumerate() {
+            let name = format!("_{}", i);
+            let mut segment = SegmentCommitInfo::new(SegmentInfo {
+                name: name.clone(),
+                id: Id::random(),
+                codec: "Ferrocene".to_string(),
+                version: version::LATEST,
+                min_version: None,
+                doc_count,
+                is_compound: false,
+                diagnostics: HashMap::new(),
+                files: HashSet::new(),
+                attributes: HashMap::new(),
+                num_sort_fields: 0,
+            });
+            segment.del_count = del_count;
+
+            segment_infos.segments.push(segment);
+            sizes.insert(name, size);
+        }
+
+        (segment_infos, MergeContext::with_sizes(sizes))
+    }
+
+    pub(crate) fn names(merge: &OneMerge) -> Vec<&str> {
+        merge.segments.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_size() {
+        let (segment_infos, mut context) = segments(&[(100, 25, 1000), (0, 0, 10)]);
+
+        assert_eq!(context.size_in_bytes(&segment_infos.segments[0]), 1000);
+        assert_eq!(context.size(&segment_infos.segments[0]), 750);
+        assert_eq!(context.size(&segment_infos.segments[1]), 10);
+
+        // Deletes that merges would keep don't count
+        context.set_deletes_to_merge("_0", 0);
+        assert_eq!(context.size(&segment_infos.segments[0]), 1000);
+    }
+
+    #[test]
+    fn test_count_deletes_to_merge() {
+        let counted = RefCell::new(Vec::new());
+        let (mut segment_infos, mut context) = segments(&[(100, 0, 1000), (100, 0, 1000)]);
+        segment_infos.segments[1].soft_delete_count = 50;
+
+        context.set_count_deletes_to_merge(|segment| {
+            counted.borrow_mut().push(segment.info.name.clone());
+            10
+        });
+
+        // Only segments with soft-deleted docs are counted, once each
+        assert_eq!(context.deletes_to_merge(&segment_infos.segments[0]), 0);
+        assert_eq!(context.deletes_to_merge(&segment_infos.segments[1]), 10);
+        assert_eq!(context.size(&segment_infos.segments[1]), 900);
+        drop(context);
+        assert_eq!(counted.into_inner(), vec!["_1".to_string()]);
+    }
+
+    #[test]
+    fn test_default_forced_merges() {
+        let (segment_infos, context) = segments(&[(10, 0, 100), (10, 0, 100), (10, 0, 100)]);
+
+        let spec = NoMergePolicy.find_forced_merges(&segment_infos, 1, &context);
+        assert!(spec.is_none());
+
+        struct OnlyForced;
+        impl MergePolicy for OnlyForced {
+            fn find_merges(&self, _: &SegmentInfos, _: &MergeContext) -> Option<MergeSpecification> {
+                None
+            }
+        }
+
+        let spec = OnlyForced.find_forced_merges(&segment_infos, 2, &context).unwrap();
+        assert_eq!(spec.merges.len(), 1);
+        assert_eq!(names(&spec.merges[0]), vec!["_1", "_2"]);
+
+        assert!(OnlyForced.find_forced_merges(&segment_infos, 3, &context).is_none());
+
+        let (segment_infos, context) = segments(&[(10, 1, 100)]);
+        let spec = OnlyForced.find_forced_merges(&segment_infos, 1, &context).unwrap();
+        assert_eq!(names(&spec.merges[0]), vec!["_0"]);
+
+        // Segments being merged are left out
+        let (segment_infos, mut context) = segments(&[(10, 0, 100), (10, 0, 100), (10, 0, 100)]);
+        context.set_merging(["_2".to_string()]);
+        let spec = OnlyForced.find_forced_merges(&segment_infos, 1, &context).unwrap();
+        assert_eq!(names(&spec.merges[0]), vec!["_0", "_1"]);
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+use crate::store::RateLimiter;
+
+use super::IndexError;
+
+/// Work of a merge, given the rate limiter to write through, if any.
+type MergeRun = Box<dyn FnOnce(Option<&RateLimiter>) + Send>;
+
+/// A merge ready to run, independent of the `IndexWriter` that registered it.
+///
+/// Running it writes the merged segment, through the rate limiter if one is given,
+/// and hands the result back to the writer, which commits it into the index
+/// the next time it checks for finished merges.
+pub struct MergeTask {
+    segment: String,
+    run: MergeRun,
+}
+
+impl MergeTask {
+    pub fn new<F>(segment: &str, run: F) -> Self
+    where
+        F: FnOnce(Option<&RateLimiter>) + Send + 'static,
+    {
+        Self {
+            segment: segment.to_owned(),
+            run: Box::new(run),
+        }
+    }
+
+    /// Name of the merged segment.
+    pub fn segment(&self) -> &str {
+        &self.segment
+    }
+
+    pub fn run(self, rate_limiter: Option<&RateLimiter>) {
+        (self.run)(rate_limiter)
+    }
+}
+
+/// Decides where and when merges picked by the merge policy run.
+pub trait MergeScheduler: Send {
+    /// Runs the merge, now or later.
+    fn schedule(&mut self, task: MergeTask);
+
+    /// Blocks until all scheduled merges have run.
+    fn wait(&mut self);
+}
+
+/// Runs merges one at a time, on the thread of the writer, as soon as they are scheduled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerialMergeScheduler;
+
+impl MergeScheduler for SerialMergeScheduler {
+    fn schedule(&mut self, task: MergeTask) {
+        task.run(None);
+    }
+
+    fn wait(&mut self) {}
+}
+
+#[derive(Default)]
+struct PoolState {
+    /// Merges waiting for a thread, with the rate limiter they write through.
+    queue: VecDeque<(MergeTask, Option<Arc<RateLimiter>>)>,
+    /// Threads running merges, they exit once the queue is empty.
+    threads: usize,
+}
+
+#[derive(Default)]
+struct Pool {
+    state: Mutex<PoolState>,
+    idle: Condvar,
+}
+
+/// Runs merges on background threads, so that adding documents doesn't wait for them.
+///
+/// At most `max_thread_count` merges run at once, further merges are queued
+/// and run in the order they were scheduled. All merges share the IO budget of
+/// `max_mb_per_sec`, so they don't starve searches and indexing of disk bandwidth.
+pub struct ConcurrentMergeScheduler {
+    max_thread_count: usize,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    pool: Arc<Pool>,
+}
+
+impl Default for ConcurrentMergeScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConcurrentMergeScheduler {
+    /// Scheduler with up to half the available cores, between 1 and 4 threads, not throttled.
+    pub fn new() -> Self {
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+
+        Self {
+            max_thread_count: (cores / 2).clamp(1, 4),
+            rate_limiter: None,
+            pool: Arc::new(Pool::default()),
+        }
+    }
+
+    /// Maximum number of merges running at once.
+    pub fn set_max_thread_count(&mut self, max_thread_count: usize) -> Result<(), IndexError> {
+        if max_thread_count == 0 {
+            return Err(IndexError::IllegalArgument(
+                "max_thread_count must be at least 1".to_string(),
+            ));
+        }
+
+        self.max_thread_count = max_thread_count;
+        Ok(())
+    }
+
+    pub fn max_thread_count(&self) -> usize {
+        self.max_thread_count
+    }
+
+    /// Limits bytes written by all merges together, `None` for no limit.
+    /// Applies to merges scheduled from now on.
+    pub fn set_max_mb_per_sec(&mut self, max_mb_per_sec: Option<f64>) {
+        self.rate_limiter = max_mb_per_sec.map(|mb| Arc::new(RateLimiter::new(mb)));
+    }
+
+    pub fn max_mb_per_sec(&self) -> Option<f64> {
+        self.rate_limiter.as_ref().map(|r| r.mb_per_sec())
+    }
+
+    /// Runs queued merges until there are none left.
+    fn work(pool: Arc<Pool>) {
+        loop {
+            let (task, rate_limiter) = {
+                let mut state = pool.state.lock().unwrap();
+                match state.queue.pop_front() {
+                    Some(task) => task,
+                    None => {
+                        state.threads -= 1;
+                        pool.idle.notify_all();
+                        return;
+                    }
+                }
+            };
+
+            // A panicking merge must not take the thread count down with it,
+            // the writer learns about it from the task itself.
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| task.run(rate_limiter.as_deref())));
+        }
+    }
+}
+
+impl MergeScheduler for ConcurrentMergeScheduler {
+    fn schedule(&mut self, task: MergeTask) {
+        let mut state = self.pool.state.lock().unwrap();
+        state.queue.push_back((task, self.rate_limiter.clone()));
+
+        if state.threads < self.max_thread_count {
+            state.threads += 1;
+            let pool = self.pool.clone();
+            thread::spawn(move || Self::work(pool));
+        }
+    }
+
+    fn wait(&mut self) {
+        let mut state = self.pool.state.lock().unwrap();
+        while state.threads > 0 {
+            state = self.pool.idle.wait(state).unwrap();
+        }
+    }
+}
+
+impl Drop for ConcurrentMergeScheduler {
+    /// Merges write to the directory of the writer, so they must not outlive it.
+    fn drop(&mut self) {
+        self.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_serial_merge_scheduler() {
+        let ran = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = SerialMergeScheduler;
+
+        let counter = ran.clone();
+        scheduler.schedule(MergeTask::new("_1", move |rate_limiter| {
+            assert!(rate_limiter.is_none());
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_concurrent_merge_scheduler() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let ran = Arc::new(AtomicUsize::new(0));
+
+        // Merges block until the gate opens, or give up and count as blocked
+        let gate = Arc::new((Mutex::new(false), Condvar::new()));
+        let blocked = Arc::new(AtomicUsize::new(0));
+
+        let mut scheduler = ConcurrentMergeScheduler::new();
+        scheduler.set_max_thread_count(2).unwrap();
+        scheduler.set_max_mb_per_sec(Some(10.0));
+
+        for i in 0..6 {
+            let (running, max_running, ran) = (running.clone(), max_running.clone(), ran.clone());
+            let (gate, blocked) = (gate.clone(), blocked.clone());
+
+            scheduler.schedule(MergeTask::new(&format!("_{}", i), move |rate_limiter| {
+                assert_eq!(rate_limiter.map(|r| r.mb_per_sec()), Some(10.0));
+
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+
+                let (open, opened) = &*gate;
+                let timeout = Duration::from_secs(10);
+                let timed_out = opened
+                    .wait_timeout_while(open.lock().unwrap(), timeout, |open| !*open)
+                    .unwrap()
+                    .1
+                    .timed_out();
+                if timed_out {
+                    blocked.fetch_add(1, Ordering::SeqCst);
+                }
+
+                running.fetch_sub(1, Ordering::SeqCst);
+                ran.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+
+        // Scheduling doesn't wait for merges to run
+        assert_eq!(ran.load(Ordering::SeqCst), 0);
+        let (open, opened) = &*gate;
+        *open.lock().unwrap() = true;
+        opened.notify_all();
+
+        scheduler.wait();
+        assert_eq!(ran.load(Ordering::SeqCst), 6);
+        assert_eq!(blocked.load(Ordering::SeqCst), 0);
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+
+        assert!(matches!(
+            scheduler.set_max_thread_count(0),
+            Err(IndexError::IllegalArgument(_))
+        ));
+        assert_eq!(scheduler.max_thread_count(), 2);
+    }
+
+    #[test]
+    fn test_panicking_merge() {
+        let ran = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = ConcurrentMergeScheduler::new();
+        scheduler.set_max_thread_count(1).unwrap();
+
+        scheduler.schedule(MergeTask::new("_1", |_| panic!("merge failed")));
+        let counter = ran.clone();
+        scheduler.schedule(MergeTask::new("_2", move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        scheduler.wait();
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+    }
+}
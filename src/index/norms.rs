@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use crate::store::{Directory, DirectoryError, InputStream, OutputStream};
+
+pub const NORMS_EXTENSION: &str = "nrm";
+
+/// Largest length that `long_to_int4` encodes within 8 bits once the free values are used up.
+const MAX_INT4: u32 = 231;
+/// Small lengths are stored as-is, larger ones are encoded with 4 significant bits.
+const NUM_FREE_VALUES: u32 = 255 - MAX_INT4;
+
+/// Encodes a field length into a single byte, lossy for large lengths.
+/// Same encoding as Lucene's `SmallFloat.intToByte4`.
+pub fn int_to_byte4(i: u32) -> u8 {
+    if i < NUM_FREE_VALUES {
+        i as u8
+    } else {
+        (NUM_FREE_VALUES + long_to_int4((i - NUM_FREE_VALUES) as u64)) as u8
+    }
+}
+
+/// Decodes a field length encoded with `int_to_byte4`.
+pub fn byte4_to_int(b: u8) -> u32 {
+    let i = b as u32;
+
+    if i < NUM_FREE_VALUES {
+        i
+    } else {
+        NUM_FREE_VALUES + int4_to_long(i - NUM_FREE_VALUES) as u32
+    }
+}
+
+fn long_to_int4(i: u64) -> u32 {
+    let num_bits = 64 - i.leading_zeros();
+
+    if num_bits < 4 {
+        // subnormal value
+        i as u32
+    } else {
+        // Keep the 4 most significant bits, the leading one is implicit
+        let shift = num_bits - 4;
+        let encoded = ((i >> shift) as u32) & 0x07;
+
+        // Encode the shift, adding 1 because 0 is reserved for subnormal values
+        encoded | ((shift + 1) << 3)
+    }
+}
+
+fn int4_to_long(i: u32) -> u64 {
+    let bits = (i & 0x07) as u64;
+    let shift = i >> 3;
+
+    if shift == 0 {
+        bits
+    } else {
+        (bits | 0x08) << (shift - 1)
+    }
+}
+
+/// Writes norms of indexed fields, one byte per doc for each field.
+pub fn write<D: Directory>(
+    directory: &D,
+    segment: &str,
+    norms: &BTreeMap<u32, Vec<u8>>,
+) -> Result<(), DirectoryError> {
+    let mut output = directory.create_file(&format!("{}.{}", segment, NORMS_EXTENSION))?;
+
+    output.write_vint(norms.len() as u32);
+
+    for (field_number, values) in norms {
+        output.write_vint(*field_number);
+        output.write_bytes(values);
+    }
+
+    output.flush();
+
+    Ok(())
+}
+
+/// Reads norms of indexed fields, written by `write`.
+pub fn read<D: Directory>(
+    directory: &D,
+    segment: &str,
+    max_doc: u32,
+) -> Result<BTreeMap<u32, Vec<u8>>, DirectoryError> {
+    let mut input = directory.open_file(&format!("{}.{}", segment, NORMS_EXTENSION))?;
+
+    let count = input.read_vint();
+    let mut norms = BTreeMap::new();
+
+    for _ in 0..count {
+        let field_number = input.read_vint();
+        let values = input.read_bytes(max_doc as usize);
+        norms.insert(field_number, values);
+    }
+
+    Ok(norms)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::FSDirectory;
+
+    use super::*;
+
+    #[test]
+    fn test_small_lengths_are_exact() {
+        for length in 0..NUM_FREE_VALUES + 8 {
+            assert_eq!(byte4_to_int(int_to_byte4(length)), length);
+        }
+    }
+
+    #[test]
+    fn test_large_lengths_are_lossy_and_ordered() {
+        let mut last = 0;
+
+        for length in [100, 1_000, 10_000, 100_000, u32::MAX / 2] {
+            let decoded = byte4_to_int(int_to_byte4(length));
+
+            assert!(decoded <= length);
+            assert!(decoded >= length - length / 8);
+            assert!(decoded > last);
+            last = decoded;
+        }
+
+        assert_eq!(int_to_byte4(i32::MAX as u32), 255);
+    }
+
+    #[test]
+    fn test_norms_io() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let norms = BTreeMap::from([(0, vec![1, 0, 3]), (2, vec![5, 6, 7])]);
+        write(&directory, "_0", &norms).unwrap();
+
+        assert_eq!(read(&directory, "_0", 3).unwrap(), norms);
+    }
+}
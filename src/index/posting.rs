@@ -1,17 +1,17 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::Term;
 
 /// Information about a term in a doc
 #[derive(Debug)]
 pub struct Posting {
-    pub term: Rc<Term>,
+    pub term: Arc<Term>,
     pub freq: u32,
     pub positions: Vec<u32>,
 }
 
 impl Posting {
-    pub fn new(term: Rc<Term>, position: u32) -> Self {
+    pub fn new(term: Arc<Term>, position: u32) -> Self {
         Self {
             term,
             freq: 1,
@@ -21,16 +21,17 @@ impl Posting {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec, clippy::get_first)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_posting_sorting() {
         let mut postings = vec![
-            Posting::new(Rc::new(Term::new("b".to_string(), "a".to_string())), 3),
-            Posting::new(Rc::new(Term::new("a".to_string(), "b".to_string())), 1),
-            Posting::new(Rc::new(Term::new("a".to_string(), "a".to_string())), 2),
-            Posting::new(Rc::new(Term::new("a".to_string(), "c".to_string())), 4),
+            Posting::new(Arc::new(Term::new("b".to_string(), "a".to_string())), 3),
+            Posting::new(Arc::new(Term::new("a".to_string(), "b".to_string())), 1),
+            Posting::new(Arc::new(Term::new("a".to_string(), "a".to_string())), 2),
+            Posting::new(Arc::new(Term::new("a".to_string(), "c".to_string())), 4),
         ];
 
         postings.sort_by_key(|p| p.term.clone());
@@ -0,0 +1,28 @@
+pub use crate::search::doc_id_set_iterator::NO_MORE_DOCS;
+
+use crate::search::DocIdSetIterator;
+
+/// Iterates the docs of a term in increasing order, and its positions within each doc.
+pub trait PostingsEnum: DocIdSetIterator {
+    /// Occurrences of the term in the current doc.
+    fn freq(&self) -> u32;
+
+    /// Next position of the term in the current doc,
+    /// must be called at most `freq` times per doc.
+    fn next_position(&mut self) -> u32;
+
+    /// Start offset of the current position, `None` when offsets aren't indexed.
+    fn start_offset(&self) -> Option<u32> {
+        None
+    }
+
+    /// End offset of the current position, `None` when offsets aren't indexed.
+    fn end_offset(&self) -> Option<u32> {
+        None
+    }
+
+    /// Payload of the current position, `None` when it has none.
+    fn payload(&self) -> Option<&[u8]> {
+        None
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::store::{Directory, DirectoryError, OutputStream};
+
+use super::term_info::{self, FieldStats, TermInfo, TERM_INFOS_EXTENSION};
+
+pub const FREQ_EXTENSION: &str = "frq";
+pub const PROX_EXTENSION: &str = "prx";
+
+/// Writes postings of a segment, one term at a time in `Term` order.
+///
+/// - `.frq` has doc deltas and freqs of each doc, for each term.
+/// - `.prx` has delta-encoded positions of each doc, for each term.
+/// - `.tis` has field stats and term infos pointing into `.frq` and `.prx`.
+///   It is buffered and written on `finish`.
+pub struct PostingsWriter<O> {
+    freq_stream: O,
+    prox_stream: O,
+    term_infos_stream: O,
+    term_infos: Vec<TermInfo>,
+    field_stats: BTreeMap<u32, FieldStats>,
+    field_docs: BTreeMap<u32, HashSet<u32>>,
+    last_doc: u32,
+}
+
+impl<O: OutputStream> PostingsWriter<O> {
+    pub fn new<D>(directory: &D, segment: &str) -> Result<Self, DirectoryError>
+    where
+        D: Directory<Output = O>,
+    {
+        Ok(Self {
+            freq_stream: directory.create_file(&format!("{}.{}", segment, FREQ_EXTENSION))?,
+            prox_stream: directory.create_file(&format!("{}.{}", segment, PROX_EXTENSION))?,
+            term_infos_stream: directory
+                .create_file(&format!("{}.{}", segment, TERM_INFOS_EXTENSION))?,
+            term_infos: Vec::new(),
+            field_stats: BTreeMap::new(),
+            field_docs: BTreeMap::new(),
+            last_doc: 0,
+        })
+    }
+
+    /// Names of the files written for a segment.
+    pub fn files(segment: &str) -> Vec<String> {
+        [FREQ_EXTENSION, PROX_EXTENSION, TERM_INFOS_EXTENSION]
+            .iter()
+            .map(|ext| format!("{}.{}", segment, ext))
+            .collect()
+    }
+
+    /// Starts postings of a new term, terms must arrive in sorted order.
+    pub fn start_term(&mut self, field_number: u32, text: &str) {
+        self.term_infos.push(TermInfo {
+            field_number,
+            text: text.to_owned(),
+            doc_freq: 0,
+            total_term_freq: 0,
+            freq_pointer: self.freq_stream.stream_position(),
+            prox_pointer: self.prox_stream.stream_position(),
+        });
+        self.last_doc = 0;
+    }
+
+    /// Adds a doc of the current term, docs must arrive in increasing order.
+    pub fn add_doc(&mut self, doc: u32, positions: &[u32]) {
+        let term_info = self.term_infos.last_mut().expect("start_term not called");
+
+        self.freq_stream.write_vint(doc - self.last_doc);
+        self.freq_stream.write_vint(positions.len() as u32);
+        self.last_doc = doc;
+
+        // Write positions using delta-encoding.
+        let mut last_position = 0;
+        for &position in positions {
+            self.prox_stream.write_vint(position - last_position);
+            last_position = position;
+        }
+
+        term_info.doc_freq += 1;
+        term_info.total_term_freq += positions.len() as u64;
+
+        self.field_docs
+            .entry(term_info.field_number)
+            .or_default()
+            .insert(doc);
+    }
+
+    /// Completes the current term. Terms without any docs are dropped.
+    pub fn finish_term(&mut self) {
+        let term_info = self.term_infos.last().expect("start_term not called");
+
+        if term_info.doc_freq == 0 {
+            self.term_infos.pop();
+            return;
+        }
+
+        let stats = self.field_stats.entry(term_info.field_number).or_default();
+        stats.sum_total_term_freq += term_info.total_term_freq;
+        stats.sum_doc_freq += term_info.doc_freq as u64;
+    }
+
+    /// Writes the term dictionary and flushes all files.
+    pub fn finish(mut self) {
+        for (field_number, docs) in &self.field_docs {
+            if let Some(stats) = self.field_stats.get_mut(field_number) {
+                stats.doc_count = docs.len() as u32;
+            }
+        }
+
+        term_info::write(
+            &mut self.term_infos_stream,
+            &self.field_stats,
+            &self.term_infos,
+        );
+
+        self.freq_stream.flush();
+        self.prox_stream.flush();
+        self.term_infos_stream.flush();
+    }
+}
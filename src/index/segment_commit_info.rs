@@ -1,14 +1,14 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::store::{Directory, InputStream};
+use crate::store::{Directory, DirectoryError, InputStream, OutputStream};
 
 use super::{
-    codec_utils,
+    codec_utils, live_docs,
     segment_info::{self, SegmentInfo},
 };
 
 /// Represents metadata about a specific segment commit
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SegmentCommitInfo {
     /// The segment info containing core segment metadata
     pub info: SegmentInfo,
@@ -24,20 +24,70 @@ pub struct SegmentCommitInfo {
     pub soft_delete_count: u32,
     /// Set of files containing field information
     pub field_infos_files: HashSet<String>,
-    /// Map of field names to their doc values files
-    pub dv_files: HashMap<String, String>,
+    /// Map of numbers of fields with updated doc values to their doc values files
+    pub dv_files: HashMap<u32, HashSet<String>>,
+    /// Bytes of all files, cached once known and cleared when files change
+    pub(crate) size_in_bytes: Option<u64>,
 }
 
-pub fn read<I, D>(input: &mut I, directory: &D) -> SegmentCommitInfo
+impl SegmentCommitInfo {
+    /// Commit info for a newly written segment, without any deletes or updates.
+    pub fn new(info: SegmentInfo) -> Self {
+        Self {
+            info,
+            del_gen: -1,
+            del_count: 0,
+            field_infos_gen: -1,
+            dv_gen: -1,
+            soft_delete_count: 0,
+            field_infos_files: HashSet::new(),
+            dv_files: HashMap::new(),
+            size_in_bytes: None,
+        }
+    }
+
+    /// Number of docs in the segment that are not deleted.
+    pub fn num_docs(&self) -> u32 {
+        self.info.doc_count - self.del_count
+    }
+
+    /// All files referenced by this segment commit.
+    pub fn files(&self) -> HashSet<String> {
+        let mut files = self.info.files.clone();
+        if self.del_gen != -1 {
+            files.insert(live_docs::file_name(&self.info.name, self.del_gen));
+        }
+        files.extend(self.field_infos_files.iter().cloned());
+        files.extend(self.dv_files.values().flatten().cloned());
+        files
+    }
+
+    /// Bytes of all files of the segment, read from the directory unless cached.
+    pub fn size_in_bytes<D: Directory>(&mut self, directory: &D) -> Result<u64, DirectoryError> {
+        if let Some(size) = self.size_in_bytes {
+            return Ok(size);
+        }
+
+        let mut size = 0;
+        for file in self.files() {
+            size += directory.file_length(&file)?;
+        }
+        self.size_in_bytes = Some(size);
+
+        Ok(size)
+    }
+}
+
+pub fn read<I, D>(input: &mut I, directory: &D) -> Result<SegmentCommitInfo, DirectoryError>
 where
     I: InputStream,
     D: Directory,
 {
     let segment_name = input.read_string();
     let segment_id = codec_utils::read_id(input);
-    let _codec = input.read_string();
+    let codec = input.read_string();
 
-    let segment_info = segment_info::read(directory, &segment_name, &segment_id);
+    let segment_info = segment_info::read(directory, &segment_name, &segment_id, codec)?;
 
     let del_gen = input.read_long() as i64;
     let del_count = input.read_int();
@@ -49,14 +99,14 @@ where
     let num_dv_fields = input.read_int();
     let mut dv_files = HashMap::new();
 
-    // Read docvalues field names and their files
+    // Read docvalues field numbers and their files
     for _ in 0..num_dv_fields {
-        let field_name = input.read_string();
-        let file_name = input.read_string();
-        dv_files.insert(field_name, file_name);
+        let field_number = input.read_int();
+        let files = input.read_set();
+        dv_files.insert(field_number, files);
     }
 
-    SegmentCommitInfo {
+    Ok(SegmentCommitInfo {
         info: segment_info,
         del_gen,
         del_count,
@@ -65,5 +115,33 @@ where
         soft_delete_count,
         field_infos_files,
         dv_files,
+        size_in_bytes: None,
+    })
+}
+
+/// Writes the per-segment entry of a segments file, mirrors `read`.
+/// The segment's own `.si` file is written separately by `segment_info::write`.
+pub fn write<O: OutputStream>(output: &mut O, commit_info: &SegmentCommitInfo) {
+    let info = &commit_info.info;
+
+    output.write_string(&info.name);
+    codec_utils::write_id(output, &info.id);
+    output.write_string(&info.codec);
+
+    output.write_long(commit_info.del_gen as u64);
+    output.write_int(commit_info.del_count);
+    output.write_long(commit_info.field_infos_gen as u64);
+    output.write_long(commit_info.dv_gen as u64);
+    output.write_int(commit_info.soft_delete_count);
+    output.write_set(&commit_info.field_infos_files);
+
+    // Write docvalues field numbers and their files, ordered by field number
+    let mut dv_files: Vec<_> = commit_info.dv_files.iter().collect();
+    dv_files.sort_by_key(|(field_number, _)| **field_number);
+
+    output.write_int(dv_files.len() as u32);
+    for (field_number, files) in dv_files {
+        output.write_int(*field_number);
+        output.write_set(files);
     }
 }
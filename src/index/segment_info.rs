@@ -1,20 +1,25 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     index::codec_utils,
-    store::{Directory, InputStream},
+    store::{Directory, DirectoryError, InputStream, OutputStream},
     version::Version,
 };
 
-use super::codec_utils::Id;
+use super::{codec_utils::Id, segment_infos::SEG_INFO_CODEC};
 
 /// Represents metadata about a segment in the index
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SegmentInfo {
     /// Name of the segment
     pub name: String,
     /// Unique identifier for the segment
     pub id: Id,
+    /// Name of the codec used to write this segment
+    pub codec: String,
     /// Version of Lucene that created this segment
     pub version: Version,
     /// Minimum version of Lucene that can read this segment
@@ -34,14 +39,41 @@ pub struct SegmentInfo {
 }
 
 pub const SEG_INFO_EXTENSION: &str = "si";
+pub const SEG_INFO_VERSION_CURRENT: u32 = 0;
+
+/// Boolean markers as written by Lucene's `SegmentInfo.YES` and `SegmentInfo.NO`.
+const YES: u8 = 1;
+const NO: u8 = 0xFF;
+
+/// Diagnostics recorded for a new segment, `source` is either `flush` or `merge`.
+pub fn diagnostics(source: &str) -> HashMap<String, String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
 
-// SegmentInfo (si) reading, based on Lucene70 codec
+    HashMap::from([
+        ("source".to_string(), source.to_string()),
+        ("os".to_string(), std::env::consts::OS.to_string()),
+        ("os.arch".to_string(), std::env::consts::ARCH.to_string()),
+        ("ferrocene.version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        ("timestamp".to_string(), timestamp.to_string()),
+    ])
+}
+
+// SegmentInfo (si) reading, in the layout of the Lucene70 codec.
+// Only files written by ferrocene are tested, no Lucene-written fixtures.
 // TODO: Make it codec specific.
-pub fn read<D: Directory>(directory: &D, segment_name: &str, segment_id: &Id) -> SegmentInfo {
+pub fn read<D: Directory>(
+    directory: &D,
+    segment_name: &str,
+    segment_id: &Id,
+    codec: String,
+) -> Result<SegmentInfo, DirectoryError> {
     let si_file = format!("{}.{}", segment_name, SEG_INFO_EXTENSION);
-    let mut input = directory.open_file(&si_file).unwrap();
+    let mut input = directory.open_file(&si_file)?;
 
-    codec_utils::check_header(&mut input);
+    codec_utils::check_header(&mut input)?;
 
     // Read version information
     let version = Version {
@@ -63,7 +95,7 @@ pub fn read<D: Directory>(directory: &D, segment_name: &str, segment_id: &Id) ->
     };
 
     let doc_count = input.read_int();
-    let is_compound = input.read_byte() != 0;
+    let is_compound = input.read_byte() == YES;
 
     let diagnostics = input.read_map();
     let files = input.read_set();
@@ -72,9 +104,10 @@ pub fn read<D: Directory>(directory: &D, segment_name: &str, segment_id: &Id) ->
 
     // TODO: Read IndexSort
 
-    SegmentInfo {
+    Ok(SegmentInfo {
         name: segment_name.to_string(),
         id: segment_id.clone(),
+        codec,
         version,
         min_version,
         doc_count,
@@ -83,5 +116,97 @@ pub fn read<D: Directory>(directory: &D, segment_name: &str, segment_id: &Id) ->
         files,
         attributes,
         num_sort_fields,
+    })
+}
+
+// SegmentInfo (si) writing, mirrors `read`.
+// The si file itself is added to the segment's files before writing.
+pub fn write<D: Directory>(
+    directory: &D,
+    segment_info: &mut SegmentInfo,
+) -> Result<(), DirectoryError> {
+    let si_file = format!("{}.{}", segment_info.name, SEG_INFO_EXTENSION);
+    segment_info.files.insert(si_file.clone());
+
+    let mut output = directory.create_file(&si_file)?;
+
+    codec_utils::write_index_header(
+        &mut output,
+        SEG_INFO_CODEC,
+        SEG_INFO_VERSION_CURRENT,
+        &segment_info.id,
+        "",
+    );
+
+    let version = &segment_info.version;
+    output.write_int(version.major);
+    output.write_int(version.minor);
+    output.write_int(version.bugfix);
+
+    match &segment_info.min_version {
+        Some(min_version) => {
+            output.write_byte(1);
+            output.write_int(min_version.major);
+            output.write_int(min_version.minor);
+            output.write_int(min_version.bugfix);
+        }
+        None => output.write_byte(0),
+    }
+
+    output.write_int(segment_info.doc_count);
+    output.write_byte(if segment_info.is_compound { YES } else { NO });
+
+    output.write_map(&segment_info.diagnostics);
+    output.write_set(&segment_info.files);
+    output.write_map(&segment_info.attributes);
+    output.write_vint(segment_info.num_sort_fields);
+
+    codec_utils::write_footer(&mut output);
+    output.flush();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{index::document_writer::CODEC_NAME, store::FSDirectory, version};
+
+    use super::*;
+
+    #[test]
+    fn test_segment_info_io() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let mut segment_info = SegmentInfo {
+            name: "_0".to_string(),
+            id: Id::random(),
+            codec: CODEC_NAME.to_string(),
+            version: version::LATEST,
+            min_version: Some(version::LATEST),
+            doc_count: 3,
+            is_compound: false,
+            diagnostics: HashMap::from([("source".to_string(), "flush".to_string())]),
+            files: HashSet::from(["_0.fdt".to_string()]),
+            attributes: HashMap::new(),
+            num_sort_fields: 0,
+        };
+
+        write(&directory, &mut segment_info).unwrap();
+
+        let read_info = read(&directory, "_0", &segment_info.id, CODEC_NAME.to_string()).unwrap();
+
+        assert_eq!(read_info.name, "_0");
+        assert_eq!(read_info.id, segment_info.id);
+        assert_eq!(read_info.version, version::LATEST);
+        assert_eq!(read_info.min_version, Some(version::LATEST));
+        assert_eq!(read_info.doc_count, 3);
+        assert!(!read_info.is_compound);
+        assert_eq!(read_info.diagnostics.get("source").unwrap(), "flush");
+        assert_eq!(
+            read_info.files,
+            HashSet::from(["_0.fdt".to_string(), "_0.si".to_string()])
+        );
+        assert_eq!(read_info.num_sort_fields, 0);
     }
 }
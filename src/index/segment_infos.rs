@@ -2,15 +2,15 @@ use std::collections::HashMap;
 
 use crate::{
     index::{codec_utils, segment_commit_info::SegmentCommitInfo},
-    store::{Directory, InputStream},
-    version::Version,
+    store::{Directory, DirectoryError, InputStream, OutputStream},
+    version::{self, Version},
 };
 use radix_fmt::radix_36;
 
 use super::{codec_utils::Id, segment_commit_info};
 
 /// Represents metadata about all segments in the index
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SegmentInfos {
     /// Generation number of the segments file
     pub generation: u64,
@@ -32,11 +32,54 @@ pub struct SegmentInfos {
     pub min_segment_lucene_version: Option<Version>,
 }
 
+impl SegmentInfos {
+    /// Creates empty segment infos for a new index, not yet committed.
+    pub fn new() -> Self {
+        SegmentInfos {
+            generation: 0,
+            version: version::LATEST,
+            index_created_version_major: version::LATEST.major,
+            sis_version: 0,
+            sis_counter: 0,
+            segments: Vec::new(),
+            user_data: HashMap::new(),
+            id: Id::random(),
+            min_segment_lucene_version: None,
+        }
+    }
+
+    /// Name of the segments file for the current generation.
+    pub fn segments_file_name(&self) -> String {
+        file_name_from_generation(SEGMENTS, self.generation)
+    }
+}
+
+impl Default for SegmentInfos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub const SEGMENTS: &str = "segments";
+pub const PENDING_SEGMENTS: &str = "pending_segments";
 pub const MAX_RADIX: u32 = 36;
 
 pub const SEG_INFO_CODEC: &str = "Lucene70SegmentInfo";
 
+pub const SEGMENTS_CODEC: &str = "segments";
+/// Format written by Lucene 7.4 onwards, the first to track soft deletes.
+pub const VERSION_74: u32 = 9;
+pub const VERSION_CURRENT: u32 = VERSION_74;
+
+/// File name for a generation, eg: `segments_a` for generation 10.
+pub fn file_name_from_generation(base: &str, generation: u64) -> String {
+    if generation == 0 {
+        base.to_string()
+    } else {
+        format!("{}_{}", base, radix_36(generation))
+    }
+}
+
 pub fn get_last_segments_file_name<D: Directory>(directory: &D) -> String {
     let files = directory.list().unwrap();
     let gen = get_last_commit_generation(files);
@@ -48,16 +91,29 @@ pub fn get_last_commit_generation(files: Vec<String>) -> u64 {
     files
         .iter()
         .filter(|&f| f.starts_with(SEGMENTS))
-        .map(|f| get_generation_from_file_name(f))
+        .map(get_generation_from_file_name)
         .max()
         .unwrap()
 }
 
+/// Generation of the latest commit in the directory, `None` when there is no commit yet.
+pub fn find_last_commit_generation<D: Directory>(
+    directory: &D,
+) -> Result<Option<u64>, DirectoryError> {
+    let files = directory.list()?;
+
+    Ok(files
+        .iter()
+        .filter(|&f| f.starts_with(SEGMENTS))
+        .map(get_generation_from_file_name)
+        .max())
+}
+
 pub fn get_generation_from_file_name(file_name: impl AsRef<str>) -> u64 {
     let file_name = file_name.as_ref();
 
     if file_name == SEGMENTS {
-        return 0;
+        0
     } else {
         let segment_length = SEGMENTS.len();
         let sub_str = &file_name[1 + segment_length..];
@@ -69,18 +125,18 @@ pub fn get_generation_from_file_name(file_name: impl AsRef<str>) -> u64 {
 pub fn read_segment_infos<D: Directory>(
     directory: &D,
     segments_file: impl AsRef<str>,
-) -> SegmentInfos {
+) -> Result<SegmentInfos, DirectoryError> {
     let segments_file = segments_file.as_ref();
     let generation: u64 = get_generation_from_file_name(segments_file);
 
-    let mut input = directory.open_file(segments_file).unwrap();
+    let mut input = directory.open_file(segments_file)?;
 
-    let _magic = input.read_u32();
+    codec_utils::check_magic(&mut input)?;
     let _codec = input.read_string();
     let _format = input.read_int();
 
     let id = codec_utils::read_id(&mut input);
-    let _suffix = codec_utils::read_suffix(&mut input);
+    let _suffix = codec_utils::read_suffix(&mut input)?;
 
     // Read Lucene version
     let version = read_version(&mut input);
@@ -100,7 +156,7 @@ pub fn read_segment_infos<D: Directory>(
     // Read each segment-commit-info
     let mut segments = Vec::with_capacity(num_segments as usize);
     for _ in 0..num_segments {
-        segments.push(segment_commit_info::read(&mut input, directory));
+        segments.push(segment_commit_info::read(&mut input, directory)?);
     }
 
     // Read user data
@@ -110,7 +166,7 @@ pub fn read_segment_infos<D: Directory>(
     let _footer_magic = input.read_int();
     let _algorithm_id = input.read_int();
 
-    SegmentInfos {
+    Ok(SegmentInfos {
         generation,
         version,
         index_created_version_major,
@@ -120,14 +176,114 @@ pub fn read_segment_infos<D: Directory>(
         user_data,
         id,
         min_segment_lucene_version,
-    }
+    })
 }
 
-pub fn read_latest_segment_infos<D: Directory>(directory: &D) -> SegmentInfos {
+pub fn read_latest_segment_infos<D: Directory>(
+    directory: &D,
+) -> Result<SegmentInfos, DirectoryError> {
     let segments_file = get_last_segments_file_name(directory);
     read_segment_infos(directory, segments_file)
 }
 
+/// Writes segment infos in the same layout `read_segment_infos` consumes.
+pub fn write_segment_infos<O: OutputStream>(output: &mut O, segment_infos: &SegmentInfos) {
+    let suffix = radix_36(segment_infos.generation).to_string();
+    codec_utils::write_index_header(
+        output,
+        SEGMENTS_CODEC,
+        VERSION_CURRENT,
+        &segment_infos.id,
+        &suffix,
+    );
+
+    write_version(output, &segment_infos.version);
+
+    output.write_vint(segment_infos.index_created_version_major);
+    output.write_long(segment_infos.sis_version);
+    output.write_vlong(segment_infos.sis_counter);
+    output.write_int(segment_infos.segments.len() as u32);
+
+    // Write minimum segment Lucene version if there are segments
+    let min_segment_version = segment_infos.segments.iter().map(|s| &s.info.version).min();
+    if let Some(min_segment_version) = min_segment_version {
+        write_version(output, min_segment_version);
+    }
+
+    // Write each segment-commit-info
+    for segment in &segment_infos.segments {
+        segment_commit_info::write(output, segment);
+    }
+
+    output.write_map(&segment_infos.user_data);
+
+    codec_utils::write_footer(output);
+}
+
+/// First phase of a commit: writes and syncs `pending_segments_N` for the next generation.
+/// The commit is not visible to readers until `finish_commit`.
+pub fn prepare_commit<D: Directory>(
+    directory: &D,
+    segment_infos: &mut SegmentInfos,
+) -> Result<String, DirectoryError> {
+    segment_infos.generation += 1;
+    segment_infos.id = Id::random();
+
+    let pending_file = file_name_from_generation(PENDING_SEGMENTS, segment_infos.generation);
+    let mut output = directory.create_file(&pending_file)?;
+
+    write_segment_infos(&mut output, segment_infos);
+    output.flush();
+    drop(output);
+
+    directory.sync(std::slice::from_ref(&pending_file))?;
+
+    Ok(pending_file)
+}
+
+/// Second phase of a commit: publishes `pending_segments_N` by renaming it to `segments_N`.
+pub fn finish_commit<D: Directory>(
+    directory: &D,
+    segment_infos: &SegmentInfos,
+) -> Result<String, DirectoryError> {
+    let pending_file = file_name_from_generation(PENDING_SEGMENTS, segment_infos.generation);
+    let segments_file = segment_infos.segments_file_name();
+
+    directory.rename_file(&pending_file, &segments_file)?;
+    directory.sync_meta_data()?;
+
+    Ok(segments_file)
+}
+
+/// Discards a prepared but unfinished commit.
+pub fn rollback_commit<D: Directory>(
+    directory: &D,
+    segment_infos: &SegmentInfos,
+) -> Result<(), DirectoryError> {
+    let pending_file = file_name_from_generation(PENDING_SEGMENTS, segment_infos.generation);
+
+    if directory.file_exists(&pending_file) {
+        directory.delete_file(&pending_file)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a new `segments_N` in one step.
+pub fn commit<D: Directory>(
+    directory: &D,
+    segment_infos: &mut SegmentInfos,
+) -> Result<String, DirectoryError> {
+    prepare_commit(directory, segment_infos)?;
+    finish_commit(directory, segment_infos)
+}
+
+fn write_version<O: OutputStream>(output: &mut O, version: &Version) {
+    output.write_vint(version.major);
+    output.write_vint(version.minor);
+    output.write_vint(version.bugfix);
+}
+
 fn read_version<I: InputStream>(input: &mut I) -> Version {
     Version {
         major: input.read_vint(),
@@ -138,8 +294,196 @@ fn read_version<I: InputStream>(input: &mut I) -> Version {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
-    use crate::store::mock_directory::MockDirectory;
+    use crate::{
+        index::{
+            document_writer::CODEC_NAME,
+            segment_info::{self, SegmentInfo},
+        },
+        store::{mock_directory::MockDirectory, util, FSDirectory},
+    };
+
+    fn segment_commit_info<D: Directory>(
+        directory: &D,
+        name: &str,
+        doc_count: u32,
+    ) -> SegmentCommitInfo {
+        let mut info = SegmentInfo {
+            name: name.to_string(),
+            id: Id::random(),
+            codec: CODEC_NAME.to_string(),
+            version: version::LATEST,
+            min_version: Some(version::LATEST),
+            doc_count,
+            is_compound: false,
+            diagnostics: HashMap::from([("source".to_string(), "flush".to_string())]),
+            files: HashSet::new(),
+            attributes: HashMap::new(),
+            num_sort_fields: 0,
+        };
+        segment_info::write(directory, &mut info).unwrap();
+
+        SegmentCommitInfo::new(info)
+    }
+
+    #[test]
+    fn test_segment_infos_round_trip() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let mut segment_infos = SegmentInfos::new();
+        segment_infos.sis_version = 3;
+        segment_infos.sis_counter = 2;
+        segment_infos
+            .segments
+            .push(segment_commit_info(&directory, "_0", 2));
+        segment_infos
+            .segments
+            .push(segment_commit_info(&directory, "_1", 1));
+        segment_infos
+            .user_data
+            .insert("max_seq_no".to_string(), "2".to_string());
+
+        let segments_file = commit(&directory, &mut segment_infos).unwrap();
+        assert_eq!(segments_file, "segments_1");
+
+        let read_infos = read_latest_segment_infos(&directory).unwrap();
+
+        assert_eq!(read_infos.generation, 1);
+        assert_eq!(read_infos.id, segment_infos.id);
+        assert_eq!(read_infos.version, version::LATEST);
+        assert_eq!(read_infos.index_created_version_major, 7);
+        assert_eq!(read_infos.sis_version, 3);
+        assert_eq!(read_infos.sis_counter, 2);
+        assert_eq!(read_infos.min_segment_lucene_version, Some(version::LATEST));
+        assert_eq!(read_infos.user_data.get("max_seq_no").unwrap(), "2");
+
+        assert_eq!(read_infos.segments.len(), 2);
+        assert_eq!(read_infos.segments[0].info.name, "_0");
+        assert_eq!(read_infos.segments[0].info.doc_count, 2);
+        assert_eq!(read_infos.segments[0].info.codec, CODEC_NAME);
+        assert_eq!(read_infos.segments[0].del_gen, -1);
+        assert_eq!(read_infos.segments[1].info.name, "_1");
+        assert_eq!(
+            read_infos.segments[1].info.id,
+            segment_infos.segments[1].info.id
+        );
+    }
+
+    #[test]
+    fn test_dv_files_layout() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let mut commit_info = segment_commit_info(&directory, "_0", 3);
+        commit_info.dv_gen = 1;
+        commit_info.dv_files = HashMap::from([(4, HashSet::from(["_0_1.dvd".to_string()]))]);
+
+        let mut output = directory.create_file("entry").unwrap();
+        segment_commit_info::write(&mut output, &commit_info);
+        output.flush();
+        drop(output);
+
+        // Lucene's layout: int count, then per field its int number and a set of files
+        let bytes = util::read_file(&directory, "entry").unwrap();
+        let expected: &[u8] = &[
+            0, 0, 0, 1, // fields
+            0, 0, 0, 4, // field number
+            1, 8, b'_', b'0', b'_', b'1', b'.', b'd', b'v', b'd', // files
+        ];
+        assert!(bytes.ends_with(expected), "{:?}", bytes);
+
+        let mut input = directory.open_file("entry").unwrap();
+        let read_info = segment_commit_info::read(&mut input, &directory).unwrap();
+        assert_eq!(read_info.dv_files, commit_info.dv_files);
+    }
+
+    #[test]
+    fn test_two_phase_commit() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let mut segment_infos = SegmentInfos::new();
+        commit(&directory, &mut segment_infos).unwrap();
+
+        let pending_file = prepare_commit(&directory, &mut segment_infos).unwrap();
+        assert_eq!(pending_file, "pending_segments_2");
+        assert!(directory.file_exists("pending_segments_2"));
+        assert_eq!(get_last_segments_file_name(&directory), "segments_1");
+
+        finish_commit(&directory, &segment_infos).unwrap();
+        assert!(!directory.file_exists("pending_segments_2"));
+        assert_eq!(get_last_segments_file_name(&directory), "segments_2");
+
+        prepare_commit(&directory, &mut segment_infos).unwrap();
+        rollback_commit(&directory, &segment_infos).unwrap();
+        assert!(!directory.file_exists("pending_segments_3"));
+        assert_eq!(get_last_segments_file_name(&directory), "segments_2");
+    }
+
+    #[test]
+    fn test_finish_commit_syncs_rename() {
+        let mut sequence = mockall::Sequence::new();
+        let mut mock_directory = MockDirectory::new();
+
+        mock_directory
+            .expect_rename_file()
+            .withf(|from, to| from == "pending_segments_1" && to == "segments_1")
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_, _| Ok(()));
+        mock_directory
+            .expect_sync_meta_data()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|| Ok(()));
+
+        let mut segment_infos = SegmentInfos::new();
+        segment_infos.generation = 1;
+
+        assert_eq!(
+            finish_commit(&mock_directory, &segment_infos).unwrap(),
+            "segments_1"
+        );
+    }
+
+    #[test]
+    fn test_segments_file_footer_checksum() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let directory = FSDirectory::new(&path).unwrap();
+
+        let mut segment_infos = SegmentInfos::new();
+        segment_infos
+            .segments
+            .push(segment_commit_info(&directory, "_0", 1));
+        commit(&directory, &mut segment_infos).unwrap();
+
+        let bytes = std::fs::read(path.join("segments_1")).unwrap();
+        let (body, checksum) = bytes.split_at(bytes.len() - 8);
+
+        assert_eq!(
+            u64::from_be_bytes(checksum.try_into().unwrap()),
+            crc32fast::hash(body) as u64
+        );
+        assert_eq!(
+            &body[body.len() - 8..body.len() - 4],
+            &codec_utils::FOOTER_MAGIC.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn test_file_name_from_generation() {
+        assert_eq!(file_name_from_generation(SEGMENTS, 0), "segments");
+        assert_eq!(file_name_from_generation(SEGMENTS, 1), "segments_1");
+        assert_eq!(file_name_from_generation(SEGMENTS, 36), "segments_10");
+        assert_eq!(
+            file_name_from_generation(PENDING_SEGMENTS, 2),
+            "pending_segments_2"
+        );
+    }
 
     #[test]
     fn test_get_last_segments_file_name() {
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    store::{Directory, InputStream, OutputStream},
+    util::FixedBitSet,
+    version,
+};
+
+use super::{
+    codec_utils::Id,
+    doc_values::{self, DocValues, DocValuesField},
+    document_writer::{BufferedPosting, CODEC_NAME},
+    field_info::FieldInfos,
+    fields_writer::FieldsWriter,
+    norms::{self, NORMS_EXTENSION},
+    postings_writer::PostingsWriter,
+    segment_commit_info::SegmentCommitInfo,
+    segment_info::{self, SegmentInfo},
+    segment_reader::SegmentReader,
+    IndexError, Term,
+};
+
+/// Decides which soft-deleted docs survive a merge.
+/// Soft-deleted docs that are not retained are dropped, like hard-deleted docs.
+pub trait SoftDeletesRetentionPolicy: Send + Sync {
+    fn retain(&self, reader: &SegmentReader, doc: u32) -> bool;
+}
+
+impl<F> SoftDeletesRetentionPolicy for F
+where
+    F: Fn(&SegmentReader, u32) -> bool + Send + Sync,
+{
+    fn retain(&self, reader: &SegmentReader, doc: u32) -> bool {
+        self(reader, doc)
+    }
+}
+
+/// Merges segments into a single new segment.
+///
+/// Deleted docs are dropped and the remaining docs are renumbered,
+/// in the order of the source segments.
+/// Field infos, stored fields, postings, norms and doc values are all rewritten
+/// with the merged field numbers and the new doc IDs.
+pub struct SegmentMerger {
+    segment: String,
+    readers: Vec<SegmentReader>,
+    /// New doc ID of each doc of each reader, `None` for dropped docs.
+    doc_maps: Vec<Vec<Option<u32>>>,
+    max_doc: u32,
+}
+
+impl SegmentMerger {
+    /// Merger of the readers into `segment`.
+    /// Soft-deleted docs are only kept if the retention policy retains them.
+    ///
+    /// Readers hold the segments in memory, so the merger can write the merged
+    /// segment on another thread, while the source segments change.
+    pub fn new(
+        segment: &str,
+        readers: Vec<SegmentReader>,
+        retention_policy: Option<&dyn SoftDeletesRetentionPolicy>,
+    ) -> Self {
+        let mut doc_maps = Vec::with_capacity(readers.len());
+        let mut max_doc = 0;
+
+        for reader in readers.iter() {
+            let doc_map = (0..reader.max_doc())
+                .map(|doc| {
+                    let keep = reader.live_docs.get(doc)
+                        || (reader.hard_live_docs.get(doc)
+                            && retention_policy.is_some_and(|p| p.retain(reader, doc)));
+
+                    if keep {
+                        max_doc += 1;
+                        Some(max_doc - 1)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            doc_maps.push(doc_map);
+        }
+
+        Self {
+            segment: segment.to_owned(),
+            readers,
+            doc_maps,
+            max_doc,
+        }
+    }
+
+    /// Number of docs of the merged segment.
+    pub fn max_doc(&self) -> u32 {
+        self.max_doc
+    }
+
+    /// New doc IDs of docs of each source segment, `None` for dropped docs.
+    pub fn doc_maps(&self) -> &[Vec<Option<u32>>] {
+        &self.doc_maps
+    }
+
+    /// Writes the merged segment to the directory.
+    pub fn merge<I, O, D>(&self, directory: &D) -> Result<SegmentCommitInfo, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        let segment = &self.segment;
+        let mut files = HashSet::new();
+
+        // Merge field names
+        let mut field_infos = FieldInfos::new();
+        for reader in self.readers.iter() {
+            field_infos.add_other(&reader.field_infos);
+        }
+
+        let field_infos_file = format!("{}.fnm", segment);
+        field_infos.write(directory, &field_infos_file);
+        files.insert(field_infos_file);
+
+        self.merge_fields(directory, &field_infos);
+        files.insert(format!("{}.fdt", segment));
+        files.insert(format!("{}.fdx", segment));
+
+        self.merge_postings(directory, &field_infos)?;
+        files.extend(PostingsWriter::<O>::files(segment));
+
+        self.merge_norms(directory, &field_infos)?;
+        files.insert(format!("{}.{}", segment, NORMS_EXTENSION));
+
+        let doc_values = self.merge_doc_values();
+        if !doc_values.is_empty() {
+            let doc_values_file = doc_values::file_name(segment, -1);
+            doc_values::write(directory, &doc_values_file, doc_values.values())?;
+            files.insert(doc_values_file);
+        }
+
+        let mut info = SegmentInfo {
+            name: segment.clone(),
+            id: Id::random(),
+            codec: CODEC_NAME.to_string(),
+            version: version::LATEST,
+            min_version: Some(version::LATEST),
+            doc_count: self.max_doc,
+            is_compound: false,
+            diagnostics: segment_info::diagnostics("merge"),
+            files,
+            attributes: HashMap::new(),
+            num_sort_fields: 0,
+        };
+        segment_info::write(directory, &mut info)?;
+
+        // Retained soft-deleted docs are still soft-deleted in the merged segment
+        let mut commit_info = SegmentCommitInfo::new(info);
+        commit_info.soft_delete_count =
+            doc_values::soft_delete_count(&doc_values, &FixedBitSet::all(self.max_doc));
+        commit_info.size_in_bytes(directory)?;
+
+        Ok(commit_info)
+    }
+
+    fn merge_fields<D: Directory>(&self, directory: &D, field_infos: &FieldInfos) {
+        let mut fields_writer = FieldsWriter::new(directory, &self.segment, field_infos);
+
+        for (reader, doc_map) in self.readers.iter().zip(self.doc_maps.iter()) {
+            for (doc, new_doc) in doc_map.iter().enumerate() {
+                if new_doc.is_some() {
+                    fields_writer.add_doc(&reader.document(doc as u32));
+                }
+            }
+        }
+
+        fields_writer.close();
+    }
+
+    fn merge_postings<I, O, D>(&self, directory: &D, field_infos: &FieldInfos) -> Result<(), IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        // Postings of all segments, in term order. Readers are in doc order,
+        // so postings of a term stay sorted by new doc ID.
+        let mut postings: BTreeMap<Term, Vec<BufferedPosting>> = BTreeMap::new();
+
+        for (reader, doc_map) in self.readers.iter().zip(self.doc_maps.iter()) {
+            for term_info in reader.term_infos() {
+                let field = reader
+                    .field_infos
+                    .get_field_name(term_info.field_number)
+                    .unwrap();
+                let term_postings = postings
+                    .entry(Term::new(field, term_info.text.clone()))
+                    .or_default();
+
+                for posting in reader.postings(term_info) {
+                    if let Some(new_doc) = doc_map[posting.doc as usize] {
+                        term_postings.push(BufferedPosting {
+                            doc: new_doc,
+                            positions: posting.positions,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut postings_writer = PostingsWriter::new(directory, &self.segment)?;
+        for (term, postings) in postings.iter() {
+            let field_number = field_infos.get_field_number(&term.field).unwrap();
+
+            postings_writer.start_term(field_number, &term.text);
+            for posting in postings {
+                postings_writer.add_doc(posting.doc, &posting.positions);
+            }
+            postings_writer.finish_term();
+        }
+        postings_writer.finish();
+
+        Ok(())
+    }
+
+    fn merge_norms<D: Directory>(&self, directory: &D, field_infos: &FieldInfos) -> Result<(), IndexError> {
+        let mut merged: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+
+        for (reader, doc_map) in self.readers.iter().zip(self.doc_maps.iter()) {
+            for (field_number, values) in reader.norms.iter() {
+                let field = reader.field_infos.get_field_name(*field_number).unwrap();
+                let new_field_number = field_infos.get_field_number(&field).unwrap();
+
+                // Docs of segments without the field have no length
+                let new_values = merged
+                    .entry(new_field_number)
+                    .or_insert_with(|| vec![0; self.max_doc as usize]);
+
+                for (doc, new_doc) in doc_map.iter().enumerate() {
+                    if let Some(new_doc) = new_doc {
+                        new_values[*new_doc as usize] = values[doc];
+                    }
+                }
+            }
+        }
+
+        norms::write(directory, &self.segment, &merged)?;
+
+        Ok(())
+    }
+
+    fn merge_doc_values(&self) -> BTreeMap<String, DocValuesField> {
+        let mut merged: BTreeMap<String, DocValuesField> = BTreeMap::new();
+
+        for (reader, doc_map) in self.readers.iter().zip(self.doc_maps.iter()) {
+            for field in reader.doc_values.values() {
+                let new_field = merged
+                    .entry(field.name.clone())
+                    .or_insert_with(|| DocValuesField {
+                        name: field.name.clone(),
+                        soft_deletes: field.soft_deletes,
+                        values: DocValues::new(field.values.doc_values_type(), self.max_doc),
+                    });
+                new_field.soft_deletes |= field.soft_deletes;
+
+                for (doc, new_doc) in doc_map.iter().enumerate() {
+                    if let Some(new_doc) = new_doc {
+                        new_field
+                            .values
+                            .copy_value(*new_doc, &field.values, doc as u32);
+                    }
+                }
+            }
+        }
+
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::document_writer::{DocumentWriter, FSDocumentWriter},
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    fn flush_segment(
+        document_writer: &mut FSDocumentWriter<StandardAnalyzer>,
+        segment: &str,
+        docs: &[(&str, &str)],
+    ) -> SegmentCommitInfo {
+        for (id, body) in docs {
+            let mut doc = Document::new();
+            doc.add(Field::keyword("id".to_string(), id.to_string()));
+            doc.add(Field::text(format!("body{}", segment), body.to_string()));
+            doc.add(Field::numeric_doc_values("rank".to_string(), id.parse().unwrap()));
+            document_writer.add_doc(segment, doc).unwrap();
+        }
+
+        document_writer.flush().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        let first = flush_segment(
+            &mut document_writer,
+            "_0",
+            &[("1", "unit tests"), ("2", "more unit tests")],
+        );
+        let mut second = flush_segment(
+            &mut document_writer,
+            "_1",
+            &[("3", "tests"), ("4", "integration tests")],
+        );
+
+        let directory = &document_writer.directory;
+        crate::index::live_docs::delete_docs(directory, &mut second, &[0]).unwrap();
+
+        let readers = vec![
+            SegmentReader::open(directory, &first).unwrap(),
+            SegmentReader::open(directory, &second).unwrap(),
+        ];
+        let merger = SegmentMerger::new("_2", readers, None);
+        assert_eq!(merger.max_doc(), 3);
+        assert_eq!(
+            merger.doc_maps(),
+            &[vec![Some(0), Some(1)], vec![None, Some(2)]]
+        );
+
+        let merged = merger.merge(directory).unwrap();
+        assert_eq!(merged.info.doc_count, 3);
+        assert_eq!(merged.info.diagnostics["source"], "merge");
+
+        let reader = SegmentReader::open(directory, &merged).unwrap();
+        assert_eq!(reader.num_docs(), 3);
+        assert_eq!(reader.field_infos.size(), 4);
+
+        // Stored fields follow the new doc IDs
+        let ids: Vec<String> = (0..3)
+            .map(|doc| reader.document(doc).get("id").unwrap().to_string())
+            .collect();
+        assert_eq!(ids, vec!["1", "2", "4"]);
+
+        // Postings are remapped, deleted docs are dropped
+        let unit = Term::new("body_0".to_string(), "unit".to_string());
+        assert_eq!(reader.term_docs(&unit), vec![0, 1]);
+        let tests = Term::new("body_1".to_string(), "tests".to_string());
+        assert_eq!(reader.term_docs(&tests), vec![2]);
+        assert!(reader
+            .term_docs(&Term::new("id".to_string(), "3".to_string()))
+            .is_empty());
+
+        let postings = reader.postings(reader.term_info(&tests).unwrap());
+        assert_eq!(postings[0].positions, vec![1]);
+
+        // Norms of fields missing in a segment are zero
+        let body_0 = reader.field_infos.get_field_number("body_0").unwrap();
+        let body_1 = reader.field_infos.get_field_number("body_1").unwrap();
+        assert_eq!(reader.norms[&body_0], vec![2, 3, 0]);
+        assert_eq!(reader.norms[&body_1], vec![0, 0, 2]);
+
+        let ranks: Vec<Option<i64>> = (0..3)
+            .map(|doc| reader.numeric_doc_value("rank", doc))
+            .collect();
+        assert_eq!(ranks, vec![Some(1), Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn test_merge_soft_deletes() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+        document_writer.soft_deletes_field = Some("__soft_deletes".to_string());
+
+        for id in ["1", "2", "3"] {
+            let mut doc = Document::new();
+            doc.add(Field::keyword("id".to_string(), id.to_string()));
+            if id != "3" {
+                doc.add(Field::numeric_doc_values("__soft_deletes".to_string(), 1));
+            }
+            document_writer.add_doc("_0", doc).unwrap();
+        }
+        let segment = document_writer.flush().unwrap().unwrap();
+        let directory = &document_writer.directory;
+
+        // Soft-deleted docs are dropped by default
+        let readers = vec![SegmentReader::open(directory, &segment).unwrap()];
+        let merged = SegmentMerger::new("_1", readers, None)
+            .merge(directory)
+            .unwrap();
+        assert_eq!(merged.info.doc_count, 1);
+        assert_eq!(merged.soft_delete_count, 0);
+
+        // Retained soft-deleted docs are kept, and still soft-deleted
+        let retain_first =
+            |reader: &SegmentReader, doc: u32| reader.document(doc).get("id") == Some("1");
+        let readers = vec![SegmentReader::open(directory, &segment).unwrap()];
+        let merged = SegmentMerger::new("_2", readers, Some(&retain_first))
+            .merge(directory)
+            .unwrap();
+        assert_eq!(merged.info.doc_count, 2);
+        assert_eq!(merged.soft_delete_count, 1);
+
+        let reader = SegmentReader::open(directory, &merged).unwrap();
+        assert_eq!(reader.num_docs(), 1);
+        assert!(!reader.live_docs.get(0));
+        assert_eq!(reader.document(1).get("id"), Some("3"));
+    }
+}
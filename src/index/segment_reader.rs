@@ -0,0 +1,443 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use bytes::Bytes;
+
+use crate::{
+    document::Document,
+    store::{util, Directory, InputStream, OutputStream},
+    util::FixedBitSet,
+};
+
+use super::{
+    doc_values::{self, DocValuesField},
+    document_writer::BufferedPosting,
+    field_info::{self, FieldInfos},
+    fields_reader::FieldsReader,
+    live_docs, norms,
+    postings_writer::{FREQ_EXTENSION, PROX_EXTENSION},
+    segment_commit_info::SegmentCommitInfo,
+    segment_terms::{SegmentPostingsEnum, SegmentTerms},
+    term_info::{self, FieldStats, TermInfo, TERM_INFOS_EXTENSION},
+    IndexError, Term,
+};
+
+/// Reads a segment written by ferrocene.
+/// The term dictionary is loaded in memory, postings are read from in-memory file buffers.
+///
+/// Soft-deleted docs are hidden from `live_docs`,
+/// readers that need them too can use `hard_live_docs`.
+pub struct SegmentReader {
+    pub commit_info: SegmentCommitInfo,
+    pub field_infos: FieldInfos,
+    pub field_stats: BTreeMap<u32, FieldStats>,
+    /// Term infos of each field, sorted by term text.
+    terms: HashMap<u32, Vec<TermInfo>>,
+    freq: Bytes,
+    prox: Bytes,
+    fields_reader: FieldsReader,
+    /// Norms of indexed fields, by field number.
+    pub norms: BTreeMap<u32, Vec<u8>>,
+    /// Doc values of the segment, by field name.
+    pub doc_values: BTreeMap<String, DocValuesField>,
+    /// Docs that are neither hard-deleted nor soft-deleted.
+    pub live_docs: FixedBitSet,
+    /// Docs that are not hard-deleted, including soft-deleted docs.
+    pub hard_live_docs: FixedBitSet,
+}
+
+impl SegmentReader {
+    pub fn open<I, O, D>(directory: &D, commit_info: &SegmentCommitInfo) -> Result<Self, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        let segment = &commit_info.info.name;
+
+        let field_infos = field_info::read_segment(directory, commit_info)?;
+
+        let mut input = directory.open_file(&format!("{}.{}", segment, TERM_INFOS_EXTENSION))?;
+        let (field_stats, term_infos) = term_info::read(&mut input);
+
+        let mut terms: HashMap<u32, Vec<TermInfo>> = HashMap::new();
+        for term_info in term_infos {
+            terms
+                .entry(term_info.field_number)
+                .or_default()
+                .push(term_info);
+        }
+
+        let freq = util::read_file(directory, &format!("{}.{}", segment, FREQ_EXTENSION))?;
+        let prox = util::read_file(directory, &format!("{}.{}", segment, PROX_EXTENSION))?;
+        let fields_reader = FieldsReader::open(directory, segment)?;
+        let norms = norms::read(directory, segment, commit_info.info.doc_count)?;
+        let doc_values = doc_values::read_segment(directory, commit_info)?;
+
+        let hard_live_docs = live_docs::read(directory, commit_info)?;
+        let soft_deleted = doc_values::soft_deleted_docs(&doc_values, hard_live_docs.len());
+        let mut live_docs = hard_live_docs.clone();
+        for doc in 0..soft_deleted.len() {
+            if soft_deleted.get(doc) {
+                live_docs.clear(doc);
+            }
+        }
+
+        Ok(Self {
+            commit_info: commit_info.clone(),
+            field_infos,
+            field_stats,
+            terms,
+            freq,
+            prox,
+            fields_reader,
+            norms,
+            doc_values,
+            live_docs,
+            hard_live_docs,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.commit_info.info.name
+    }
+
+    /// Number of docs in the segment, including deleted docs.
+    pub fn max_doc(&self) -> u32 {
+        self.commit_info.info.doc_count
+    }
+
+    /// Number of live docs in the segment, excluding soft-deleted docs.
+    pub fn num_docs(&self) -> u32 {
+        self.commit_info.num_docs() - self.commit_info.soft_delete_count
+    }
+
+    /// Whether the doc is neither hard-deleted nor soft-deleted.
+    pub fn is_live(&self, doc: u32) -> bool {
+        self.live_docs.get(doc)
+    }
+
+    /// Encoded norms of a field, one byte per doc, if the field is indexed.
+    pub fn norms(&self, field: &str) -> Option<&[u8]> {
+        let field_number = self.field_infos.get_field_number(field)?;
+        self.norms.get(&field_number).map(|n| n.as_slice())
+    }
+
+    /// Number of docs containing the term, including deleted docs.
+    pub fn doc_freq(&self, term: &Term) -> u32 {
+        self.term_info(term).map_or(0, |ti| ti.doc_freq)
+    }
+
+    /// Numeric doc value of a doc, if the field has one for it.
+    pub fn numeric_doc_value(&self, field: &str, doc: u32) -> Option<i64> {
+        self.doc_values.get(field)?.values.numeric(doc)
+    }
+
+    pub fn term_info(&self, term: &Term) -> Option<&TermInfo> {
+        find_term_info(&self.field_infos, &self.terms, term)
+    }
+
+    /// Term infos of all fields, in field number and then term order.
+    pub fn term_infos(&self) -> impl Iterator<Item = &TermInfo> {
+        let mut field_numbers: Vec<&u32> = self.terms.keys().collect();
+        field_numbers.sort();
+
+        field_numbers
+            .into_iter()
+            .flat_map(move |field_number| self.terms[field_number].iter())
+    }
+
+    /// Terms of a field, `None` when the field has no terms in the segment.
+    pub fn terms(&self, field: &str) -> Option<SegmentTerms<'_>> {
+        let field_number = self.field_infos.get_field_number(field)?;
+        let term_infos = self.terms.get(&field_number)?;
+        let stats = self.field_stats.get(&field_number)?;
+
+        Some(SegmentTerms::new(term_infos, stats, &self.freq, &self.prox))
+    }
+
+    /// Docs and positions of a term, including deleted docs.
+    pub fn postings_enum(&self, term: &Term) -> Option<SegmentPostingsEnum> {
+        let term_info = self.term_info(term)?;
+        Some(SegmentPostingsEnum::new(term_info, &self.freq, &self.prox))
+    }
+
+    /// Docs and positions of a term decoded at once, including deleted docs.
+    pub fn postings(&self, term_info: &TermInfo) -> Vec<BufferedPosting> {
+        let mut freq = self.freq.slice(term_info.freq_pointer as usize..);
+        let mut prox = self.prox.slice(term_info.prox_pointer as usize..);
+        let mut postings = Vec::with_capacity(term_info.doc_freq as usize);
+        let mut doc = 0;
+
+        for _ in 0..term_info.doc_freq {
+            doc += util::read_vint(&mut freq);
+            let term_freq = util::read_vint(&mut freq);
+
+            let mut position = 0;
+            let positions = (0..term_freq)
+                .map(|_| {
+                    position += util::read_vint(&mut prox);
+                    position
+                })
+                .collect();
+
+            postings.push(BufferedPosting { doc, positions });
+        }
+
+        postings
+    }
+
+    /// Stored fields of a doc.
+    pub fn document(&self, doc: u32) -> Document {
+        self.fields_reader.doc(doc, &self.field_infos)
+    }
+
+    /// Docs containing the term, including deleted docs.
+    pub fn term_docs(&self, term: &Term) -> Vec<u32> {
+        match self.term_info(term) {
+            Some(term_info) => decode_docs(&self.freq, term_info),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Docs of terms of a segment, read from its term dictionary and `.frq` only.
+/// Cheaper to open than a `SegmentReader` when only docs of terms are needed, like for deletes.
+pub(crate) struct SegmentTermDocs {
+    field_infos: FieldInfos,
+    terms: HashMap<u32, Vec<TermInfo>>,
+    freq: Bytes,
+}
+
+impl SegmentTermDocs {
+    /// Opens the terms of a segment, `None` when none of the fields is indexed in it.
+    pub(crate) fn open<I, O, D>(
+        directory: &D,
+        commit_info: &SegmentCommitInfo,
+        fields: &HashSet<&str>,
+    ) -> Result<Option<Self>, IndexError>
+    where
+        I: InputStream,
+        O: OutputStream,
+        D: Directory<Input = I, Output = O>,
+    {
+        let segment = &commit_info.info.name;
+
+        let field_infos = field_info::read_segment(directory, commit_info)?;
+
+        let field_numbers: HashSet<u32> = fields
+            .iter()
+            .filter_map(|field| field_infos.get_field_info_by_name(field))
+            .filter(|field_info| field_info.is_indexed)
+            .map(|field_info| field_info.number)
+            .collect();
+        if field_numbers.is_empty() {
+            return Ok(None);
+        }
+
+        let mut input = directory.open_file(&format!("{}.{}", segment, TERM_INFOS_EXTENSION))?;
+        let (_, term_infos) = term_info::read(&mut input);
+
+        let mut terms: HashMap<u32, Vec<TermInfo>> = HashMap::new();
+        for term_info in term_infos {
+            if field_numbers.contains(&term_info.field_number) {
+                terms
+                    .entry(term_info.field_number)
+                    .or_default()
+                    .push(term_info);
+            }
+        }
+
+        let freq = util::read_file(directory, &format!("{}.{}", segment, FREQ_EXTENSION))?;
+
+        Ok(Some(Self {
+            field_infos,
+            terms,
+            freq,
+        }))
+    }
+
+    /// Docs containing the term, including deleted docs.
+    pub(crate) fn term_docs(&self, term: &Term) -> Vec<u32> {
+        match find_term_info(&self.field_infos, &self.terms, term) {
+            Some(term_info) => decode_docs(&self.freq, term_info),
+            None => Vec::new(),
+        }
+    }
+}
+
+fn find_term_info<'a>(
+    field_infos: &FieldInfos,
+    terms: &'a HashMap<u32, Vec<TermInfo>>,
+    term: &Term,
+) -> Option<&'a TermInfo> {
+    let field_number = field_infos.get_field_number(&term.field)?;
+    let term_infos = terms.get(&field_number)?;
+
+    term_infos
+        .binary_search_by(|ti| ti.text.as_str().cmp(&term.text))
+        .ok()
+        .map(|index| &term_infos[index])
+}
+
+/// Docs of the term decoded from `.frq`, skipping freqs.
+fn decode_docs(freq: &Bytes, term_info: &TermInfo) -> Vec<u32> {
+    let mut freq = freq.slice(term_info.freq_pointer as usize..);
+    let mut docs = Vec::with_capacity(term_info.doc_freq as usize);
+    let mut doc = 0;
+
+    for _ in 0..term_info.doc_freq {
+        doc += util::read_vint(&mut freq);
+        let _freq = util::read_vint(&mut freq);
+        docs.push(doc);
+    }
+
+    docs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::{
+            document_writer::{DocumentWriter, FSDocumentWriter},
+            segment_infos,
+        },
+        store::FSDirectory,
+        test_util::{doc, writer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_term_docs() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        for body in ["unit tests", "integration", "tests and more tests"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            document_writer.add_doc("_0", doc).unwrap();
+        }
+
+        let commit_info = document_writer.flush().unwrap().unwrap();
+        let reader = SegmentReader::open(&document_writer.directory, &commit_info).unwrap();
+
+        assert_eq!(reader.name(), "_0");
+        assert_eq!(reader.max_doc(), 3);
+        assert_eq!(reader.num_docs(), 3);
+
+        let tests = Term::new("body".to_string(), "tests".to_string());
+        assert_eq!(reader.term_docs(&tests), vec![0, 2]);
+
+        let term_info = reader.term_info(&tests).unwrap();
+        assert_eq!(term_info.doc_freq, 2);
+        assert_eq!(term_info.total_term_freq, 3);
+
+        let missing = Term::new("title".to_string(), "tests".to_string());
+        assert!(reader.term_docs(&missing).is_empty());
+
+        let stats = reader.field_stats.get(&0).unwrap();
+        assert_eq!(stats.doc_count, 3);
+        assert_eq!(stats.sum_total_term_freq, 7);
+    }
+
+    #[test]
+    fn test_segment_term_docs() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        for (id, body) in [("1", "unit tests"), ("2", "integration"), ("3", "more tests")] {
+            let mut doc = Document::new();
+            doc.add(Field::keyword("id".to_string(), id.to_string()));
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            doc.add(Field::unindexed("note".to_string(), "tests".to_string()));
+            document_writer.add_doc("_0", doc).unwrap();
+        }
+
+        let commit_info = document_writer.flush().unwrap().unwrap();
+        let directory = &document_writer.directory;
+
+        // Segments without the fields, or where they are not indexed, are skipped
+        let fields = HashSet::from(["title", "note"]);
+        assert!(SegmentTermDocs::open(directory, &commit_info, &fields)
+            .unwrap()
+            .is_none());
+
+        let fields = HashSet::from(["title", "body"]);
+        let term_docs = SegmentTermDocs::open(directory, &commit_info, &fields)
+            .unwrap()
+            .unwrap();
+        let reader = SegmentReader::open(directory, &commit_info).unwrap();
+
+        let tests = Term::new("body".to_string(), "tests".to_string());
+        assert_eq!(term_docs.term_docs(&tests), reader.term_docs(&tests));
+        assert_eq!(term_docs.term_docs(&tests), vec![0, 2]);
+
+        // Terms of fields that were not asked for are not loaded
+        assert!(term_docs
+            .term_docs(&Term::new("id".to_string(), "1".to_string()))
+            .is_empty());
+        assert!(term_docs
+            .term_docs(&Term::new("body".to_string(), "missing".to_string()))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_soft_and_hard_deletes() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("test-index");
+        let mut writer = writer(&path);
+        writer.set_soft_deletes_field("__soft_deletes");
+
+        writer.add_document(doc("1", "unit tests")).unwrap();
+        writer.add_document(doc("2", "integration tests")).unwrap();
+        writer.add_document(doc("3", "more tests")).unwrap();
+        writer.commit().unwrap();
+
+        let id = |value: &str| Term::new("id".to_string(), value.to_string());
+        let soft_delete = vec![Field::numeric_doc_values("__soft_deletes".to_string(), 1)];
+        writer
+            .soft_update_document(id("1"), doc("1", "new tests"), soft_delete)
+            .unwrap();
+        writer.delete_documents(id("2")).unwrap();
+        writer.commit().unwrap();
+
+        let directory = FSDirectory::new(&path).unwrap();
+        let sis = segment_infos::read_latest_segment_infos(&directory).unwrap();
+        let reader = SegmentReader::open(&directory, &sis.segments[0]).unwrap();
+
+        assert_eq!(reader.max_doc(), 3);
+        assert_eq!(reader.num_docs(), 1);
+        assert!(!reader.is_live(0));
+        assert!(!reader.is_live(1));
+        assert!(reader.is_live(2));
+        assert!(reader.hard_live_docs.get(0));
+        assert!(!reader.hard_live_docs.get(1));
+        assert!(reader.hard_live_docs.get(2));
+
+        // Deleted docs keep their postings and stored fields
+        let tests = Term::new("body".to_string(), "tests".to_string());
+        assert_eq!(reader.term_docs(&tests), vec![0, 1, 2]);
+        assert_eq!(reader.doc_freq(&tests), 3);
+        assert_eq!(reader.document(1).get("id"), Some("2"));
+
+        // Missing fields and terms read as absent rather than failing
+        assert_eq!(reader.numeric_doc_value("__soft_deletes", 2), None);
+        assert_eq!(reader.numeric_doc_value("price", 0), None);
+        assert!(reader.norms("title").is_none());
+        assert!(reader.terms("title").is_none());
+        assert!(reader.postings_enum(&id("9")).is_none());
+        assert_eq!(reader.doc_freq(&id("9")), 0);
+
+        // A segment without deletes has all docs live
+        let reader = SegmentReader::open(&directory, &sis.segments[1]).unwrap();
+        assert_eq!(reader.num_docs(), 1);
+        assert_eq!(reader.live_docs, reader.hard_live_docs);
+        assert!(reader.is_live(0));
+    }
+}
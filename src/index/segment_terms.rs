@@ -0,0 +1,303 @@
+use bytes::Bytes;
+
+use crate::{search::DocIdSetIterator, store::util};
+
+use super::{
+    postings_enum::{PostingsEnum, NO_MORE_DOCS},
+    term_info::{FieldStats, TermInfo},
+    terms::{self, SeekStatus, Terms, TermsEnum},
+};
+
+/// Terms of a field in a segment, read from the term dictionary of `SegmentReader`.
+pub struct SegmentTerms<'a> {
+    term_infos: &'a [TermInfo],
+    stats: &'a FieldStats,
+    freq: &'a Bytes,
+    prox: &'a Bytes,
+}
+
+impl<'a> SegmentTerms<'a> {
+    pub(crate) fn new(
+        term_infos: &'a [TermInfo],
+        stats: &'a FieldStats,
+        freq: &'a Bytes,
+        prox: &'a Bytes,
+    ) -> Self {
+        Self {
+            term_infos,
+            stats,
+            freq,
+            prox,
+        }
+    }
+}
+
+impl Terms for SegmentTerms<'_> {
+    fn iterator(&self) -> Box<dyn TermsEnum + '_> {
+        Box::new(SegmentTermsEnum {
+            terms: self,
+            index: None,
+        })
+    }
+
+    fn size(&self) -> u64 {
+        self.term_infos.len() as u64
+    }
+
+    fn doc_count(&self) -> u32 {
+        self.stats.doc_count
+    }
+
+    fn sum_doc_freq(&self) -> u64 {
+        self.stats.sum_doc_freq
+    }
+
+    fn sum_total_term_freq(&self) -> u64 {
+        self.stats.sum_total_term_freq
+    }
+}
+
+pub struct SegmentTermsEnum<'a> {
+    terms: &'a SegmentTerms<'a>,
+    /// Index of the current term, `len` once exhausted.
+    index: Option<usize>,
+}
+
+impl SegmentTermsEnum<'_> {
+    fn current(&self) -> &TermInfo {
+        let index = self.index.expect("Terms enum is not positioned");
+        &self.terms.term_infos[index]
+    }
+}
+
+impl TermsEnum for SegmentTermsEnum<'_> {
+    fn next(&mut self) -> Option<&str> {
+        let len = self.terms.term_infos.len();
+        let index = self.index.map_or(0, |i| (i + 1).min(len));
+        self.index = Some(index);
+
+        self.terms.term_infos.get(index).map(|ti| ti.text.as_str())
+    }
+
+    fn seek_ceil(&mut self, text: &str) -> SeekStatus {
+        let (index, status) = terms::seek_ceil(self.terms.term_infos, text, |ti| &ti.text);
+        self.index = Some(index);
+        status
+    }
+
+    fn term(&self) -> &str {
+        &self.current().text
+    }
+
+    fn doc_freq(&self) -> u32 {
+        self.current().doc_freq
+    }
+
+    fn total_term_freq(&self) -> u64 {
+        self.current().total_term_freq
+    }
+
+    fn postings(&self) -> Box<dyn PostingsEnum> {
+        Box::new(SegmentPostingsEnum::new(
+            self.current(),
+            self.terms.freq,
+            self.terms.prox,
+        ))
+    }
+}
+
+/// Decodes the docs and positions of a term from `.frq` and `.prx` as it goes.
+pub struct SegmentPostingsEnum {
+    freq_data: Bytes,
+    prox_data: Bytes,
+    doc_freq: u32,
+    /// Number of docs decoded so far.
+    read: u32,
+    doc: u32,
+    freq: u32,
+    /// Positions of the current doc not read yet.
+    pending_positions: u32,
+    position: u32,
+}
+
+impl SegmentPostingsEnum {
+    pub(crate) fn new(term_info: &TermInfo, freq: &Bytes, prox: &Bytes) -> Self {
+        Self {
+            freq_data: freq.slice(term_info.freq_pointer as usize..),
+            prox_data: prox.slice(term_info.prox_pointer as usize..),
+            doc_freq: term_info.doc_freq,
+            read: 0,
+            doc: 0,
+            freq: 0,
+            pending_positions: 0,
+            position: 0,
+        }
+    }
+}
+
+impl DocIdSetIterator for SegmentPostingsEnum {
+    fn doc_id(&self) -> u32 {
+        self.doc
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        if self.read == self.doc_freq {
+            self.doc = NO_MORE_DOCS;
+            return self.doc;
+        }
+
+        // Skip positions of the previous doc the caller didn't read
+        for _ in 0..self.pending_positions {
+            util::read_vint(&mut self.prox_data);
+        }
+
+        self.doc += util::read_vint(&mut self.freq_data);
+        self.freq = util::read_vint(&mut self.freq_data);
+        self.pending_positions = self.freq;
+        self.position = 0;
+        self.read += 1;
+
+        self.doc
+    }
+
+    fn cost(&self) -> u64 {
+        self.doc_freq as u64
+    }
+}
+
+impl PostingsEnum for SegmentPostingsEnum {
+    fn freq(&self) -> u32 {
+        self.freq
+    }
+
+    fn next_position(&mut self) -> u32 {
+        assert!(
+            self.pending_positions > 0,
+            "All positions of doc {} read",
+            self.doc
+        );
+
+        self.pending_positions -= 1;
+        self.position += util::read_vint(&mut self.prox_data);
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::{
+            document_writer::{DocumentWriter, FSDocumentWriter},
+            segment_reader::SegmentReader,
+        },
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    fn positions(postings: &mut dyn PostingsEnum) -> Vec<u32> {
+        (0..postings.freq())
+            .map(|_| postings.next_position())
+            .collect()
+    }
+
+    #[test]
+    fn test_segment_terms() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        for body in ["unit tests", "integration", "tests and more tests"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            document_writer.add_doc("_0", doc).unwrap();
+        }
+
+        let commit_info = document_writer.flush().unwrap().unwrap();
+        let reader = SegmentReader::open(&document_writer.directory, &commit_info).unwrap();
+        assert!(reader.terms("title").is_none());
+
+        let terms = reader.terms("body").unwrap();
+        assert_eq!(terms.size(), 5);
+        assert_eq!(terms.doc_count(), 3);
+        assert_eq!(terms.sum_total_term_freq(), 7);
+
+        let mut terms_enum = terms.iterator();
+        let mut texts = Vec::new();
+        while let Some(text) = terms_enum.next() {
+            texts.push(text.to_string());
+        }
+        assert_eq!(texts, vec!["and", "integration", "more", "tests", "unit"]);
+        assert_eq!(terms_enum.next(), None);
+
+        assert_eq!(terms_enum.seek_ceil("m"), SeekStatus::NotFound);
+        assert_eq!(terms_enum.term(), "more");
+        assert_eq!(terms_enum.seek_ceil("z"), SeekStatus::End);
+        assert!(!terms_enum.seek_exact("test"));
+        assert!(terms_enum.seek_exact("tests"));
+        assert_eq!(terms_enum.doc_freq(), 2);
+        assert_eq!(terms_enum.total_term_freq(), 3);
+        assert_eq!(terms_enum.next(), Some("unit"));
+
+        // Positions left unread are skipped
+        terms_enum.seek_exact("tests");
+        let mut postings = terms_enum.postings();
+        assert_eq!(postings.cost(), 2);
+        assert_eq!(postings.next_doc(), 0);
+        assert_eq!(postings.freq(), 1);
+        assert_eq!(postings.next_doc(), 2);
+        assert_eq!(positions(postings.as_mut()), vec![0, 3]);
+        assert_eq!(postings.start_offset(), None);
+        assert_eq!(postings.payload(), None);
+        assert_eq!(postings.next_doc(), NO_MORE_DOCS);
+
+        let mut postings = terms_enum.postings();
+        assert_eq!(postings.advance(1), 2);
+        assert_eq!(postings.advance(3), NO_MORE_DOCS);
+        assert_eq!(postings.doc_id(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_segment_terms_exhausted() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut document_writer: FSDocumentWriter<StandardAnalyzer> =
+            DocumentWriter::new(StandardAnalyzer::new(), directory);
+
+        for body in ["unit tests", "more tests"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            document_writer.add_doc("_0", doc).unwrap();
+        }
+
+        let commit_info = document_writer.flush().unwrap().unwrap();
+        let reader = SegmentReader::open(&document_writer.directory, &commit_info).unwrap();
+        let terms = reader.terms("body").unwrap();
+        assert!(!terms.has_offsets());
+        assert!(!terms.has_payloads());
+
+        let mut terms_enum = terms.iterator();
+        assert_eq!(terms_enum.seek_ceil(""), SeekStatus::NotFound);
+        assert_eq!(terms_enum.term(), "more");
+        assert_eq!(terms_enum.seek_ceil("units"), SeekStatus::End);
+        assert_eq!(terms_enum.next(), None);
+        assert!(!terms_enum.seek_exact("v"));
+        assert_eq!(terms_enum.next(), None);
+
+        terms_enum.seek_exact("tests");
+        let mut postings = terms_enum.postings();
+        assert_eq!(postings.advance(2), NO_MORE_DOCS);
+        assert_eq!(postings.doc_id(), NO_MORE_DOCS);
+
+        let mut postings = terms_enum.postings();
+        assert_eq!(postings.advance(1), 1);
+        assert_eq!(postings.next_position(), 1);
+        assert_eq!(postings.start_offset(), None);
+        assert_eq!(postings.end_offset(), None);
+        assert_eq!(postings.payload(), None);
+        assert_eq!(postings.next_doc(), NO_MORE_DOCS);
+    }
+}
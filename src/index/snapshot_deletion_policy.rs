@@ -0,0 +1,198 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use super::{index_commit::IndexCommit, index_deletion_policy::IndexDeletionPolicy, IndexError};
+
+struct Snapshots {
+    primary: Box<dyn IndexDeletionPolicy>,
+    /// Snapshotted commits and how often each was snapshotted, by generation.
+    snapshots: HashMap<u64, (IndexCommit, usize)>,
+    last_commit: Option<IndexCommit>,
+}
+
+/// Keeps snapshotted commits, on top of the commits kept by a primary policy.
+///
+/// A snapshot pins the latest commit, so its files can be copied while the writer
+/// keeps committing, like with `IndexCommit::copy_to` for hot backups.
+/// The policy is cloned before it is given to the writer, and the clone takes
+/// and releases snapshots. Files of released commits are deleted on the next
+/// commit, or with `IndexWriter::delete_unused_files`.
+#[derive(Clone)]
+pub struct SnapshotDeletionPolicy {
+    inner: Arc<Mutex<Snapshots>>,
+}
+
+impl SnapshotDeletionPolicy {
+    pub fn new<P>(primary: P) -> Self
+    where
+        P: IndexDeletionPolicy + 'static,
+    {
+        Self {
+            inner: Arc::new(Mutex::new(Snapshots {
+                primary: Box::new(primary),
+                snapshots: HashMap::new(),
+                last_commit: None,
+            })),
+        }
+    }
+
+    /// Pins the latest commit, until it is released as often as it was snapshotted.
+    pub fn snapshot(&self) -> Result<IndexCommit, IndexError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let commit = inner.last_commit.clone().ok_or_else(|| {
+            IndexError::IllegalState("No commit to snapshot yet".to_string())
+        })?;
+
+        inner
+            .snapshots
+            .entry(commit.generation())
+            .or_insert_with(|| (commit.clone(), 0))
+            .1 += 1;
+
+        Ok(commit)
+    }
+
+    /// Releases a snapshot taken with `snapshot`.
+    pub fn release(&self, commit: &IndexCommit) -> Result<(), IndexError> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let count = match inner.snapshots.get_mut(&commit.generation()) {
+            Some((_, count)) => count,
+            None => {
+                return Err(IndexError::IllegalArgument(format!(
+                    "Commit {} is not snapshotted",
+                    commit.segments_file_name()
+                )))
+            }
+        };
+
+        *count -= 1;
+        if *count == 0 {
+            inner.snapshots.remove(&commit.generation());
+        }
+
+        Ok(())
+    }
+
+    /// Snapshotted commits, oldest first.
+    pub fn snapshots(&self) -> Vec<IndexCommit> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut snapshots: Vec<IndexCommit> =
+            inner.snapshots.values().map(|(c, _)| c.clone()).collect();
+        snapshots.sort_by_key(|c| c.generation());
+        snapshots
+    }
+
+    /// Number of snapshots taken and not released, of all commits.
+    pub fn snapshot_count(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+
+        inner.snapshots.values().map(|(_, count)| count).sum()
+    }
+
+    fn keep_snapshots(inner: &mut Snapshots, commits: &mut [IndexCommit]) {
+        for commit in commits.iter_mut() {
+            if inner.snapshots.contains_key(&commit.generation()) {
+                commit.undelete();
+            }
+        }
+
+        inner.last_commit = commits.last().cloned();
+    }
+}
+
+impl IndexDeletionPolicy for SnapshotDeletionPolicy {
+    fn on_init(&mut self, commits: &mut [IndexCommit]) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.primary.on_init(commits);
+        Self::keep_snapshots(&mut inner, commits);
+    }
+
+    fn on_commit(&mut self, commits: &mut [IndexCommit]) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.primary.on_commit(commits);
+        Self::keep_snapshots(&mut inner, commits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::index_deletion_policy::{
+        tests::commits, KeepOnlyLastCommitDeletionPolicy,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let policy = SnapshotDeletionPolicy::new(KeepOnlyLastCommitDeletionPolicy);
+        let mut writer_policy = policy.clone();
+
+        assert!(matches!(policy.snapshot(), Err(IndexError::IllegalState(_))));
+
+        let mut all = commits(3);
+        writer_policy.on_init(&mut all[..1]);
+        let first = policy.snapshot().unwrap();
+        assert_eq!(first.generation(), 1);
+        assert_eq!(policy.snapshot().unwrap(), first);
+        assert_eq!(policy.snapshot_count(), 2);
+
+        // The snapshotted commit survives later commits
+        writer_policy.on_commit(&mut all);
+        let deleted: Vec<bool> = all.iter().map(|c| c.is_deleted()).collect();
+        assert_eq!(deleted, vec![false, true, false]);
+
+        // Until it was released as often as it was snapshotted
+        policy.release(&first).unwrap();
+        let mut all = commits(3);
+        writer_policy.on_commit(&mut all);
+        assert!(!all[0].is_deleted());
+
+        policy.release(&first).unwrap();
+        assert!(policy.snapshots().is_empty());
+        let mut all = commits(3);
+        writer_policy.on_commit(&mut all);
+        assert!(all[0].is_deleted());
+
+        assert!(matches!(
+            policy.release(&first),
+            Err(IndexError::IllegalArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_release_unknown_snapshot() {
+        let policy = SnapshotDeletionPolicy::new(KeepOnlyLastCommitDeletionPolicy);
+        let mut writer_policy = policy.clone();
+
+        let all = commits(2);
+        assert!(matches!(
+            policy.release(&all[0]),
+            Err(IndexError::IllegalArgument(_))
+        ));
+
+        let mut all = commits(2);
+        writer_policy.on_init(&mut all);
+        let last = policy.snapshot().unwrap();
+
+        // Releasing another commit leaves the snapshot alone
+        assert!(matches!(
+            policy.release(&all[0]),
+            Err(IndexError::IllegalArgument(_))
+        ));
+        assert_eq!(policy.snapshots(), vec![last.clone()]);
+
+        policy.release(&last).unwrap();
+        assert!(matches!(
+            policy.release(&last),
+            Err(IndexError::IllegalArgument(_))
+        ));
+        assert_eq!(policy.snapshot_count(), 0);
+    }
+}
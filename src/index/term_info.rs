@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use crate::store::{InputStream, OutputStream};
+
+pub const TERM_INFOS_EXTENSION: &str = "tis";
+
+/// Dictionary entry of a term, pointing to its postings in freq and prox files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermInfo {
+    pub field_number: u32,
+    pub text: String,
+    /// Number of docs containing the term
+    pub doc_freq: u32,
+    /// Number of occurrences of the term across all docs
+    pub total_term_freq: u64,
+    /// Start of the term's docs and freqs in `.frq`
+    pub freq_pointer: u64,
+    /// Start of the term's positions in `.prx`
+    pub prox_pointer: u64,
+}
+
+/// Statistics of an indexed field within a segment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldStats {
+    /// Sum of `total_term_freq` over all terms of the field
+    pub sum_total_term_freq: u64,
+    /// Sum of `doc_freq` over all terms of the field
+    pub sum_doc_freq: u64,
+    /// Number of docs having at least one term for the field
+    pub doc_count: u32,
+}
+
+/// Writes field stats followed by term infos, in term order.
+pub fn write<O: OutputStream>(
+    output: &mut O,
+    field_stats: &BTreeMap<u32, FieldStats>,
+    term_infos: &[TermInfo],
+) {
+    output.write_vint(field_stats.len() as u32);
+
+    for (field_number, stats) in field_stats {
+        output.write_vint(*field_number);
+        output.write_vlong(stats.sum_total_term_freq);
+        output.write_vlong(stats.sum_doc_freq);
+        output.write_vint(stats.doc_count);
+    }
+
+    output.write_vint(term_infos.len() as u32);
+
+    for term_info in term_infos {
+        output.write_vint(term_info.field_number);
+        output.write_string(&term_info.text);
+        output.write_vint(term_info.doc_freq);
+        output.write_vlong(term_info.total_term_freq);
+        output.write_vlong(term_info.freq_pointer);
+        output.write_vlong(term_info.prox_pointer);
+    }
+}
+
+/// Reads field stats and term infos written by `write`.
+pub fn read<I: InputStream>(input: &mut I) -> (BTreeMap<u32, FieldStats>, Vec<TermInfo>) {
+    let num_fields = input.read_vint();
+    let mut field_stats = BTreeMap::new();
+
+    for _ in 0..num_fields {
+        let field_number = input.read_vint();
+        let stats = FieldStats {
+            sum_total_term_freq: input.read_vlong(),
+            sum_doc_freq: input.read_vlong(),
+            doc_count: input.read_vint(),
+        };
+        field_stats.insert(field_number, stats);
+    }
+
+    let num_terms = input.read_vint() as usize;
+    let mut term_infos = Vec::with_capacity(num_terms);
+
+    for _ in 0..num_terms {
+        term_infos.push(TermInfo {
+            field_number: input.read_vint(),
+            text: input.read_string(),
+            doc_freq: input.read_vint(),
+            total_term_freq: input.read_vlong(),
+            freq_pointer: input.read_vlong(),
+            prox_pointer: input.read_vlong(),
+        });
+    }
+
+    (field_stats, term_infos)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{Directory, FSDirectory};
+
+    use super::*;
+
+    #[test]
+    fn test_term_infos_io() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+
+        let field_stats = BTreeMap::from([(
+            1,
+            FieldStats {
+                sum_total_term_freq: 5,
+                sum_doc_freq: 4,
+                doc_count: 2,
+            },
+        )]);
+        let term_infos = vec![
+            TermInfo {
+                field_number: 1,
+                text: "integration".to_string(),
+                doc_freq: 1,
+                total_term_freq: 1,
+                freq_pointer: 0,
+                prox_pointer: 0,
+            },
+            TermInfo {
+                field_number: 1,
+                text: "tests".to_string(),
+                doc_freq: 2,
+                total_term_freq: 3,
+                freq_pointer: 2,
+                prox_pointer: 1,
+            },
+        ];
+
+        let mut output = directory.create_file("_0.tis").unwrap();
+        write(&mut output, &field_stats, &term_infos);
+        output.flush();
+
+        let mut input = directory.open_file("_0.tis").unwrap();
+        let (read_stats, read_infos) = read(&mut input);
+
+        assert_eq!(read_stats, field_stats);
+        assert_eq!(read_infos, term_infos);
+    }
+}
@@ -0,0 +1,91 @@
+use super::postings_enum::PostingsEnum;
+
+/// Terms of a field, with the statistics scoring needs.
+pub trait Terms {
+    /// Enumerates the terms, in sorted order.
+    fn iterator(&self) -> Box<dyn TermsEnum + '_>;
+
+    /// Number of distinct terms.
+    fn size(&self) -> u64;
+
+    /// Number of docs having at least one term.
+    fn doc_count(&self) -> u32;
+
+    /// Sum of `doc_freq` over all terms.
+    fn sum_doc_freq(&self) -> u64;
+
+    /// Sum of `total_term_freq` over all terms.
+    fn sum_total_term_freq(&self) -> u64;
+
+    fn has_positions(&self) -> bool {
+        true
+    }
+
+    /// Ferrocene doesn't index offsets yet.
+    fn has_offsets(&self) -> bool {
+        false
+    }
+
+    /// Ferrocene doesn't index payloads yet.
+    fn has_payloads(&self) -> bool {
+        false
+    }
+}
+
+/// Outcome of `TermsEnum::seek_ceil`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekStatus {
+    /// The enum is at the term sought.
+    Found,
+    /// The enum is at the smallest term after the term sought.
+    NotFound,
+    /// All terms are before the term sought, the enum is exhausted.
+    End,
+}
+
+/// Iterates the terms of a field in sorted order.
+///
+/// The enum is unpositioned until `next` or a seek, methods reading
+/// the current term panic before.
+pub trait TermsEnum {
+    /// Moves to the next term and returns it, `None` once exhausted.
+    fn next(&mut self) -> Option<&str>;
+
+    /// Moves to the term, or to the smallest term after it.
+    fn seek_ceil(&mut self, text: &str) -> SeekStatus;
+
+    /// Moves to the term, returns whether it exists.
+    /// When it doesn't, the enum is left as by `seek_ceil`.
+    fn seek_exact(&mut self, text: &str) -> bool {
+        self.seek_ceil(text) == SeekStatus::Found
+    }
+
+    /// Current term.
+    fn term(&self) -> &str;
+
+    /// Number of docs containing the current term, including deleted docs.
+    fn doc_freq(&self) -> u32;
+
+    /// Number of occurrences of the current term, including in deleted docs.
+    fn total_term_freq(&self) -> u64;
+
+    /// Docs and positions of the current term, including deleted docs.
+    fn postings(&self) -> Box<dyn PostingsEnum>;
+}
+
+/// Seeks in terms sorted by text, returning the index of the ceiling term.
+pub(crate) fn seek_ceil<T>(
+    terms: &[T],
+    text: &str,
+    key: impl Fn(&T) -> &str,
+) -> (usize, SeekStatus) {
+    let index = terms.partition_point(|t| key(t) < text);
+
+    let status = match terms.get(index) {
+        None => SeekStatus::End,
+        Some(t) if key(t) == text => SeekStatus::Found,
+        Some(_) => SeekStatus::NotFound,
+    };
+
+    (index, status)
+}
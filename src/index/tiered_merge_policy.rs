@@ -0,0 +1,330 @@
+use super::{
+    merge_policy::{MergeContext, MergePolicy, MergeSpecification, OneMerge},
+    segment_commit_info::SegmentCommitInfo,
+    segment_infos::SegmentInfos,
+};
+
+pub const DEFAULT_MAX_MERGE_AT_ONCE: usize = 10;
+pub const DEFAULT_MAX_MERGED_SEGMENT_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+pub const DEFAULT_FLOOR_SEGMENT_BYTES: u64 = 2 * 1024 * 1024;
+pub const DEFAULT_SEGMENTS_PER_TIER: f64 = 10.0;
+pub const DEFAULT_DELETES_PCT_ALLOWED: f64 = 33.0;
+
+/// Merges segments of roughly equal size, based on Lucene's `TieredMergePolicy`.
+///
+/// The index is allowed a budget of segments per tier, where each tier holds
+/// segments `max_merge_at_once` times larger than the one below. When there are
+/// more segments than the budget, the merge with the lowest score is picked:
+/// merges of similarly sized segments, that are small and reclaim many deletes.
+///
+/// Segments close to `max_merged_segment_bytes` are left alone, unless more than
+/// `deletes_pct_allowed` percent of their docs are deleted.
+#[derive(Debug, Clone)]
+pub struct TieredMergePolicy {
+    max_merge_at_once: usize,
+    max_merged_segment_bytes: u64,
+    floor_segment_bytes: u64,
+    segments_per_tier: f64,
+    deletes_pct_allowed: f64,
+}
+
+impl Default for TieredMergePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TieredMergePolicy {
+    pub fn new() -> Self {
+        Self {
+            max_merge_at_once: DEFAULT_MAX_MERGE_AT_ONCE,
+            max_merged_segment_bytes: DEFAULT_MAX_MERGED_SEGMENT_BYTES,
+            floor_segment_bytes: DEFAULT_FLOOR_SEGMENT_BYTES,
+            segments_per_tier: DEFAULT_SEGMENTS_PER_TIER,
+            deletes_pct_allowed: DEFAULT_DELETES_PCT_ALLOWED,
+        }
+    }
+
+    /// Maximum number of segments merged at once.
+    pub fn set_max_merge_at_once(&mut self, max_merge_at_once: usize) {
+        assert!(max_merge_at_once >= 2, "max_merge_at_once must be at least 2");
+        self.max_merge_at_once = max_merge_at_once;
+    }
+
+    /// Maximum size of a merged segment, in bytes.
+    pub fn set_max_merged_segment_bytes(&mut self, max_merged_segment_bytes: u64) {
+        self.max_merged_segment_bytes = max_merged_segment_bytes;
+    }
+
+    /// Smaller segments are treated as this size, so tiny segments are merged eagerly.
+    pub fn set_floor_segment_bytes(&mut self, floor_segment_bytes: u64) {
+        self.floor_segment_bytes = floor_segment_bytes.max(1);
+    }
+
+    /// Number of segments allowed per tier, smaller values merge more often.
+    pub fn set_segments_per_tier(&mut self, segments_per_tier: f64) {
+        assert!(segments_per_tier >= 2.0, "segments_per_tier must be at least 2");
+        self.segments_per_tier = segments_per_tier;
+    }
+
+    /// Percentage of deleted docs allowed in a segment, before it is rewritten.
+    pub fn set_deletes_pct_allowed(&mut self, deletes_pct_allowed: f64) {
+        assert!(
+            (0.0..=100.0).contains(&deletes_pct_allowed),
+            "deletes_pct_allowed must be a percentage"
+        );
+        self.deletes_pct_allowed = deletes_pct_allowed;
+    }
+
+    fn floor_size(&self, size: u64) -> u64 {
+        size.max(self.floor_segment_bytes)
+    }
+
+    /// Number of segments the index is allowed, for the given segment sizes.
+    fn allowed_segment_count(&self, total_bytes: u64, min_segment_bytes: u64) -> usize {
+        let mut level_size = self.floor_size(min_segment_bytes) as f64;
+        let mut bytes_left = total_bytes as f64;
+        let mut allowed = 0.0;
+
+        loop {
+            let segment_count_level = bytes_left / level_size;
+
+            if segment_count_level < self.segments_per_tier
+                || level_size >= self.max_merged_segment_bytes as f64
+            {
+                allowed += segment_count_level.ceil();
+                break;
+            }
+
+            allowed += self.segments_per_tier;
+            bytes_left -= self.segments_per_tier * level_size;
+            level_size = (level_size * self.max_merge_at_once as f64)
+                .min(self.max_merged_segment_bytes as f64);
+        }
+
+        (allowed as usize).max(self.segments_per_tier as usize)
+    }
+
+    /// Score of a candidate merge, lower is better.
+    fn score(
+        &self,
+        candidate: &[(&SegmentCommitInfo, u64)],
+        hit_too_large: bool,
+        context: &MergeContext,
+    ) -> f64 {
+        let total_before_merge: u64 = candidate
+            .iter()
+            .map(|(s, _)| context.size_in_bytes(s))
+            .sum();
+        let total_after_merge: u64 = candidate.iter().map(|(_, size)| size).sum();
+        let total_floored: u64 = candidate.iter().map(|(_, size)| self.floor_size(*size)).sum();
+
+        // Skew is how unbalanced the merge is, 1 / max_merge_at_once at best
+        let skew = if hit_too_large {
+            1.0 / self.max_merge_at_once as f64
+        } else {
+            self.floor_size(candidate[0].1) as f64 / total_floored as f64
+        };
+
+        // Gently favor smaller merges, and strongly favor merges reclaiming deletes
+        let non_deleted_ratio = if total_before_merge == 0 {
+            1.0
+        } else {
+            total_after_merge as f64 / total_before_merge as f64
+        };
+
+        skew * (total_after_merge as f64).powf(0.05) * non_deleted_ratio.powi(2)
+    }
+}
+
+impl MergePolicy for TieredMergePolicy {
+    fn find_merges(
+        &self,
+        segment_infos: &SegmentInfos,
+        context: &MergeContext,
+    ) -> Option<MergeSpecification> {
+        let mut spec = MergeSpecification::new();
+
+        // Largest segments first
+        let mut sorted: Vec<(&SegmentCommitInfo, u64)> = segment_infos
+            .segments
+            .iter()
+            .filter(|s| !context.is_merging(s))
+            .map(|s| (s, context.size(s)))
+            .collect();
+        sorted.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+
+        // Segments too large to merge further don't count towards the budget.
+        // They are rewritten on their own once they have too many deletes.
+        let too_large = self.max_merged_segment_bytes / 2;
+        let mut eligible = Vec::new();
+        for (segment, size) in sorted {
+            if size <= too_large {
+                eligible.push((segment, size));
+            } else if context.deletes_ratio(segment) * 100.0 > self.deletes_pct_allowed {
+                spec.add(OneMerge::new(&[segment]));
+            }
+        }
+
+        if eligible.is_empty() {
+            return spec.into_option();
+        }
+
+        let total_bytes: u64 = eligible.iter().map(|(_, size)| size).sum();
+        let min_segment_bytes = eligible.last().map(|(_, size)| *size).unwrap_or_default();
+        let allowed = self.allowed_segment_count(total_bytes, min_segment_bytes);
+        let mut merged_count = 0;
+
+        while eligible.len() + merged_count > allowed && eligible.len() > 1 {
+            let mut best: Option<(f64, Vec<usize>)> = None;
+
+            for start in 0..eligible.len() {
+                let mut candidate = Vec::new();
+                let mut total = 0;
+                let mut hit_too_large = false;
+
+                for (i, (_, size)) in eligible.iter().enumerate().skip(start) {
+                    if candidate.len() >= self.max_merge_at_once {
+                        break;
+                    }
+                    // Keep packing smaller segments, up to the max merged size
+                    if total + size > self.max_merged_segment_bytes {
+                        hit_too_large = true;
+                        continue;
+                    }
+
+                    candidate.push(i);
+                    total += size;
+                }
+
+                if candidate.len() < 2 {
+                    continue;
+                }
+
+                let segments: Vec<(&SegmentCommitInfo, u64)> =
+                    candidate.iter().map(|&i| eligible[i]).collect();
+                let score = self.score(&segments, hit_too_large, context);
+
+                if best.as_ref().is_none_or(|(best_score, _)| score < *best_score) {
+                    best = Some((score, candidate));
+                }
+            }
+
+            let (_, candidate) = match best {
+                Some(best) => best,
+                None => break,
+            };
+
+            let segments: Vec<&SegmentCommitInfo> =
+                candidate.iter().map(|&i| eligible[i].0).collect();
+            spec.add(OneMerge::new(&segments));
+
+            for i in candidate.into_iter().rev() {
+                eligible.remove(i);
+            }
+            merged_count += 1;
+        }
+
+        // Within budget, reclaim deletes of segments with too many of them
+        if spec.merges.is_empty() {
+            for (segment, _) in eligible {
+                if context.deletes_ratio(segment) * 100.0 > self.deletes_pct_allowed {
+                    spec.add(OneMerge::new(&[segment]));
+                }
+            }
+        }
+
+        spec.into_option()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::merge_policy::tests::{names, segments};
+
+    use super::*;
+
+    #[test]
+    fn test_within_budget() {
+        let policy = TieredMergePolicy::new();
+        let (segment_infos, context) = segments(&[(10, 0, 100); 10]);
+
+        assert!(policy.find_merges(&segment_infos, &context).is_none());
+    }
+
+    #[test]
+    fn test_merges_equal_segments() {
+        let mut policy = TieredMergePolicy::new();
+        policy.set_segments_per_tier(2.0);
+        policy.set_max_merge_at_once(2);
+
+        // Tiny segments are all floored to the same size, smallest merges win
+        let (segment_infos, context) = segments(&[
+            (10, 0, 100),
+            (10, 0, 110),
+            (10, 0, 90),
+            (10, 0, 120),
+            (10, 0, 80),
+        ]);
+
+        let spec = policy.find_merges(&segment_infos, &context).unwrap();
+        assert_eq!(spec.merges.len(), 2);
+        assert_eq!(names(&spec.merges[0]), vec!["_2", "_4"]);
+        assert_eq!(names(&spec.merges[1]), vec!["_1", "_0"]);
+    }
+
+    #[test]
+    fn test_max_merged_segment_size() {
+        let mut policy = TieredMergePolicy::new();
+        policy.set_segments_per_tier(2.0);
+        policy.set_max_merged_segment_bytes(1000);
+        policy.set_floor_segment_bytes(1);
+
+        // The segment over half the max size is never merged
+        let (segment_infos, context) = segments(&[
+            (10, 0, 600),
+            (10, 0, 100),
+            (10, 0, 100),
+            (10, 0, 100),
+            (10, 0, 100),
+            (10, 0, 100),
+        ]);
+
+        let spec = policy.find_merges(&segment_infos, &context).unwrap();
+        for merge in spec.merges.iter() {
+            assert!(!merge.segments.contains(&"_0".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_deletes_pct_allowed() {
+        let mut policy = TieredMergePolicy::new();
+        policy.set_deletes_pct_allowed(20.0);
+
+        let (segment_infos, context) = segments(&[(100, 10, 1000), (100, 50, 1000)]);
+
+        let spec = policy.find_merges(&segment_infos, &context).unwrap();
+        assert_eq!(spec.merges.len(), 1);
+        assert_eq!(names(&spec.merges[0]), vec!["_1"]);
+    }
+
+    #[test]
+    fn test_skips_merging_segments() {
+        let mut policy = TieredMergePolicy::new();
+        policy.set_segments_per_tier(2.0);
+        policy.set_max_merge_at_once(2);
+
+        let (segment_infos, mut context) = segments(&[(10, 0, 100); 4]);
+        context.set_merging(["_0".to_string(), "_1".to_string()]);
+
+        let spec = policy.find_merges(&segment_infos, &context);
+        assert!(spec.is_none());
+
+        context.set_merging(["_2".to_string()]);
+        let (segment_infos, _) = segments(&[(10, 0, 100); 6]);
+        let spec = policy.find_merges(&segment_infos, &context).unwrap();
+        for merge in spec.merges.iter() {
+            assert!(names(merge).iter().all(|name| !["_0", "_1", "_2"].contains(name)));
+        }
+    }
+}
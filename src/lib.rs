@@ -2,5 +2,11 @@ pub mod analysis;
 pub mod codec;
 pub mod document;
 pub mod index;
+pub mod query_parser;
+pub mod search;
 pub mod store;
+pub mod util;
 pub mod version;
+
+#[cfg(test)]
+mod test_util;
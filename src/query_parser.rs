@@ -0,0 +1,5 @@
+pub mod classic;
+pub mod simple;
+
+pub use classic::{Operator, ParseError, QueryParser};
+pub use simple::{Operators, SimpleQueryParser};
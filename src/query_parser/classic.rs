@@ -0,0 +1,892 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::{
+    analysis::Analyzer,
+    index::Term,
+    search::{
+        BooleanQuery, BoostQuery, FuzzyQuery, MatchAllDocsQuery, Occur, PhraseQuery, PrefixQuery,
+        Query, RegexpQuery, TermQuery, TermRangeQuery, WildcardQuery,
+    },
+    util::{levenshtein_automata, AutomatonError},
+};
+
+/// Deepest nesting of parentheses the classic query parser accepts.
+pub const MAX_DEPTH: usize = 100;
+
+/// Error of the classic query parser, at the 1-based line and column of the query where it occurred.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} at line {line}, column {column}")]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Operator between clauses without `AND` or `OR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Operator {
+    #[default]
+    Or,
+    And,
+}
+
+/// Parses queries in Lucene's classic syntax.
+///
+/// - `tests` and `title:tests` search a term of the default field or of a field,
+///   after running it through the analyzer.
+/// - `"integration tests"~2` searches a phrase, with an optional slop.
+/// - `+`, `-`, `NOT`, `AND`, `OR`, `&&`, `||` and `!` combine clauses, grouped with parentheses.
+/// - `tests^2` boosts a clause.
+/// - `test*`, `te?t`, `/te[sx]t/` and `ferocene~1` search wildcards, regexps and fuzzy terms.
+/// - `[a TO m]` and `{a TO *}` search ranges, including or excluding the bounds.
+/// - `*:*` matches all docs, and `\` escapes special chars.
+///
+/// Groups can be nested up to `MAX_DEPTH` deep.
+pub struct QueryParser<A: Analyzer> {
+    default_field: String,
+    analyzer: A,
+    default_operator: Operator,
+    allow_leading_wildcard: bool,
+    fuzzy_prefix_length: usize,
+}
+
+impl<A: Analyzer> QueryParser<A> {
+    pub fn new(default_field: String, analyzer: A) -> Self {
+        Self {
+            default_field,
+            analyzer,
+            default_operator: Operator::Or,
+            allow_leading_wildcard: false,
+            fuzzy_prefix_length: 0,
+        }
+    }
+
+    pub fn default_field(&self) -> &str {
+        &self.default_field
+    }
+
+    pub fn set_default_operator(&mut self, default_operator: Operator) {
+        self.default_operator = default_operator;
+    }
+
+    pub fn default_operator(&self) -> Operator {
+        self.default_operator
+    }
+
+    /// Whether wildcard terms may start with `*` or `?`, which enumerates all terms of the field.
+    pub fn set_allow_leading_wildcard(&mut self, allow_leading_wildcard: bool) {
+        self.allow_leading_wildcard = allow_leading_wildcard;
+    }
+
+    pub fn allow_leading_wildcard(&self) -> bool {
+        self.allow_leading_wildcard
+    }
+
+    /// Number of leading chars fuzzy terms must match exactly.
+    pub fn set_fuzzy_prefix_length(&mut self, fuzzy_prefix_length: usize) {
+        self.fuzzy_prefix_length = fuzzy_prefix_length;
+    }
+
+    pub fn fuzzy_prefix_length(&self) -> usize {
+        self.fuzzy_prefix_length
+    }
+
+    pub fn parse(&self, query: &str) -> Result<Arc<dyn Query>, ParseError> {
+        let tokens = Lexer::new(query).tokenize()?;
+        let mut parser = Parser {
+            config: self,
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+
+        if parser.peek().kind == TokenKind::End {
+            return Err(parser.unexpected());
+        }
+        let query = parser.parse_query(&self.default_field)?;
+        if parser.peek().kind != TokenKind::End {
+            return Err(parser.unexpected());
+        }
+
+        Ok(query.unwrap_or_else(|| Arc::new(BooleanQuery::new())))
+    }
+
+    /// Analyzed tokens of a text, skipping empty ones.
+    fn analyze<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        self.analyzer
+            .analyze(text)
+            .filter(|token| !token.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    And,
+    Or,
+    Not,
+    Plus,
+    Minus,
+    LParen,
+    RParen,
+    Colon,
+    Boost(f32),
+    /// `~` with an optional number.
+    Fuzzy(Option<f32>),
+    /// A term as written, with its escapes.
+    Term(String),
+    Phrase(String),
+    Regexp(String),
+    Range {
+        lower: String,
+        upper: String,
+        include_lower: bool,
+        include_upper: bool,
+    },
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+/// Chars ending a term, unless escaped.
+fn is_term_end(c: char) -> bool {
+    c.is_whitespace() || "():^[]\"{}~/".contains(c)
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn new(query: &str) -> Self {
+        Self {
+            chars: query.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.chars.get(self.pos + 1).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, ParseError> {
+        let mut tokens = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            let (start, line, column) = (self.pos, self.line, self.column);
+            let kind = self.next_token()?;
+            let end = kind == TokenKind::End;
+
+            tokens.push(Token {
+                kind,
+                text: self.chars[start..self.pos].iter().collect(),
+                line,
+                column,
+            });
+            if end {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<TokenKind, ParseError> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Ok(TokenKind::End),
+        };
+
+        match (c, self.peek_second()) {
+            ('&', Some('&')) | ('|', Some('|')) => {
+                self.next();
+                self.next();
+                return Ok(if c == '&' {
+                    TokenKind::And
+                } else {
+                    TokenKind::Or
+                });
+            }
+            _ => {}
+        }
+
+        let single = match c {
+            '+' => Some(TokenKind::Plus),
+            '-' => Some(TokenKind::Minus),
+            '!' => Some(TokenKind::Not),
+            '(' => Some(TokenKind::LParen),
+            ')' => Some(TokenKind::RParen),
+            ':' => Some(TokenKind::Colon),
+            _ => None,
+        };
+        if let Some(kind) = single {
+            self.next();
+            return Ok(kind);
+        }
+
+        match c {
+            '^' => {
+                self.next();
+                match self.number() {
+                    Some(boost) => Ok(TokenKind::Boost(boost)),
+                    None => Err(self.error("Expected a number")),
+                }
+            }
+            '~' => {
+                self.next();
+                Ok(TokenKind::Fuzzy(self.number()))
+            }
+            '"' => self.quoted().map(TokenKind::Phrase),
+            '/' => self.regexp(),
+            '[' | '{' => self.range(),
+            ']' | '}' => Err(self.error(&format!("Unexpected '{}'", c))),
+            _ => {
+                let term = self.term()?;
+                Ok(match term.as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => TokenKind::Term(term),
+                })
+            }
+        }
+    }
+
+    /// A number like `2` or `1.5`, if any.
+    fn number(&mut self) -> Option<f32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.next();
+        }
+        if self.peek() == Some('.') && self.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+            self.next();
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.next();
+            }
+        }
+
+        let number: String = self.chars[start..self.pos].iter().collect();
+        number.parse().ok()
+    }
+
+    fn term(&mut self) -> Result<String, ParseError> {
+        let mut term = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\\' {
+                self.next();
+                match self.next() {
+                    Some(escaped) => {
+                        term.push('\\');
+                        term.push(escaped);
+                    }
+                    None => return Err(self.error("Nothing to escape")),
+                }
+            } else if is_term_end(c) {
+                break;
+            } else {
+                term.push(c);
+                self.next();
+            }
+        }
+        Ok(term)
+    }
+
+    /// Text between double quotes, unescaped.
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let start = self.error("Unterminated phrase");
+        self.next();
+
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => text.extend(self.next()),
+                Some(c) => text.push(c),
+                None => return Err(start),
+            }
+        }
+    }
+
+    /// Text between slashes, keeping escapes other than `\/` for the regexp parser.
+    fn regexp(&mut self) -> Result<TokenKind, ParseError> {
+        let start = self.error("Unterminated regexp");
+        self.next();
+
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('/') => return Ok(TokenKind::Regexp(text)),
+                Some('\\') if self.peek() == Some('/') => text.extend(self.next()),
+                Some(c) => text.push(c),
+                None => return Err(start),
+            }
+        }
+    }
+
+    fn range(&mut self) -> Result<TokenKind, ParseError> {
+        let include_lower = self.next() == Some('[');
+
+        let lower = self.range_bound()?;
+        self.skip_whitespace();
+        if self.range_bound()? != "TO" {
+            return Err(self.error("Expected 'TO'"));
+        }
+        let upper = self.range_bound()?;
+        self.skip_whitespace();
+
+        let include_upper = match self.next() {
+            Some(']') => true,
+            Some('}') => false,
+            _ => return Err(self.error("Expected ']' or '}'")),
+        };
+
+        Ok(TokenKind::Range {
+            lower,
+            upper,
+            include_lower,
+            include_upper,
+        })
+    }
+
+    fn range_bound(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespace();
+        if self.peek() == Some('"') {
+            return self.quoted();
+        }
+
+        let mut bound = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == ']' || c == '}' {
+                break;
+            }
+            self.next();
+            if c == '\\' {
+                bound.extend(self.next());
+            } else {
+                bound.push(c);
+            }
+        }
+
+        if bound.is_empty() {
+            return Err(self.error("Expected a range bound"));
+        }
+        Ok(bound)
+    }
+}
+
+struct Parser<'p, A: Analyzer> {
+    config: &'p QueryParser<A>,
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Number of groups the parser is in.
+    depth: usize,
+}
+
+impl<A: Analyzer> Parser<'_, A> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error_at(token: &Token, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            line: token.line,
+            column: token.column,
+        }
+    }
+
+    /// Error for the token just peeked.
+    fn unexpected(&self) -> ParseError {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::End => Self::error_at(token, "Unexpected end of query"),
+            _ => Self::error_at(token, &format!("Unexpected '{}'", token.text)),
+        }
+    }
+
+    /// Clauses up to the end of the query or of the group, `None` when all were analyzed away.
+    fn parse_query(&mut self, field: &str) -> Result<Option<Arc<dyn Query>>, ParseError> {
+        let mut clauses: Vec<(Arc<dyn Query>, Occur)> = Vec::new();
+        let mut first = true;
+
+        while !matches!(self.peek().kind, TokenKind::End | TokenKind::RParen) {
+            let conjunction = match self.peek().kind {
+                TokenKind::And | TokenKind::Or if first => return Err(self.unexpected()),
+                TokenKind::And | TokenKind::Or => Some(self.next().kind),
+                _ => None,
+            };
+            let modifier = match self.peek().kind {
+                TokenKind::Plus | TokenKind::Minus | TokenKind::Not => Some(self.next().kind),
+                _ => None,
+            };
+
+            let query = self.parse_clause(field)?;
+            if let Some(query) = query {
+                self.add_clause(&mut clauses, conjunction, modifier, query);
+            }
+            first = false;
+        }
+
+        Ok(match clauses.len() {
+            0 => None,
+            1 if clauses[0].1 == Occur::Should => Some(clauses.pop().unwrap().0),
+            _ => {
+                let mut query = BooleanQuery::new();
+                for (clause, occur) in clauses {
+                    query.add(clause, occur);
+                }
+                Some(Arc::new(query))
+            }
+        })
+    }
+
+    /// Adds a clause as Lucene's classic parser does, where `AND` makes the previous clause
+    /// required, and `OR` makes it optional with the `And` default operator.
+    fn add_clause(
+        &self,
+        clauses: &mut Vec<(Arc<dyn Query>, Occur)>,
+        conjunction: Option<TokenKind>,
+        modifier: Option<TokenKind>,
+        query: Arc<dyn Query>,
+    ) {
+        let and = conjunction == Some(TokenKind::And);
+        let or = conjunction == Some(TokenKind::Or);
+
+        if let Some((_, occur)) = clauses.last_mut() {
+            if and && *occur == Occur::Should {
+                *occur = Occur::Must;
+            }
+            if or && self.config.default_operator == Operator::And && *occur == Occur::Must {
+                *occur = Occur::Should;
+            }
+        }
+
+        let prohibited = matches!(modifier, Some(TokenKind::Minus | TokenKind::Not));
+        let required = match self.config.default_operator {
+            Operator::Or => modifier == Some(TokenKind::Plus) || (and && !prohibited),
+            Operator::And => !prohibited && !or,
+        };
+
+        let occur = if prohibited {
+            Occur::MustNot
+        } else if required {
+            Occur::Must
+        } else {
+            Occur::Should
+        };
+        clauses.push((query, occur));
+    }
+
+    fn parse_clause(&mut self, default_field: &str) -> Result<Option<Arc<dyn Query>>, ParseError> {
+        let mut field = default_field.to_string();
+        if let (TokenKind::Term(name), Some(TokenKind::Colon)) = (
+            &self.peek().kind,
+            self.tokens.get(self.pos + 1).map(|t| &t.kind),
+        ) {
+            field = unescape(name);
+            self.pos += 2;
+        }
+
+        if !matches!(
+            self.peek().kind,
+            TokenKind::LParen
+                | TokenKind::Term(_)
+                | TokenKind::Phrase(_)
+                | TokenKind::Regexp(_)
+                | TokenKind::Range { .. }
+        ) {
+            return Err(self.unexpected());
+        }
+
+        let token = self.next();
+        let query = match &token.kind {
+            TokenKind::LParen => {
+                if self.depth == MAX_DEPTH {
+                    return Err(Self::error_at(
+                        &token,
+                        &format!("Groups nested deeper than {}", MAX_DEPTH),
+                    ));
+                }
+
+                self.depth += 1;
+                let query = self.parse_query(&field)?;
+                self.depth -= 1;
+
+                if self.peek().kind != TokenKind::RParen {
+                    return Err(Self::error_at(self.peek(), "Expected ')'"));
+                }
+                self.next();
+                query
+            }
+            TokenKind::Term(text) if field == "*" && text == "*" => {
+                Some(Arc::new(MatchAllDocsQuery) as Arc<dyn Query>)
+            }
+            TokenKind::Term(text) => {
+                let edits = match self.peek().kind {
+                    TokenKind::Fuzzy(edits) => {
+                        self.next();
+                        Some(edits.map_or(levenshtein_automata::MAX_EDITS, |e| {
+                            (e as u32).min(levenshtein_automata::MAX_EDITS)
+                        }))
+                    }
+                    _ => None,
+                };
+                self.term_query(&token, &field, text, edits)?
+            }
+            TokenKind::Phrase(text) => {
+                let slop = match self.peek().kind {
+                    TokenKind::Fuzzy(slop) => {
+                        self.next();
+                        slop.unwrap_or(0.0) as u32
+                    }
+                    _ => 0,
+                };
+                self.phrase_query(&field, text, slop)
+            }
+            TokenKind::Regexp(text) => {
+                let term = Term::new(field.clone(), text.clone());
+                let query = RegexpQuery::new(term).map_err(|e| automaton_error(&token, e))?;
+                Some(Arc::new(query) as Arc<dyn Query>)
+            }
+            TokenKind::Range {
+                lower,
+                upper,
+                include_lower,
+                include_upper,
+            } => {
+                let bound = |b: &String| (b != "*").then(|| b.clone());
+                Some(Arc::new(TermRangeQuery::new(
+                    field.clone(),
+                    bound(lower),
+                    bound(upper),
+                    *include_lower,
+                    *include_upper,
+                )) as Arc<dyn Query>)
+            }
+            _ => unreachable!(),
+        };
+
+        if let TokenKind::Boost(boost) = self.peek().kind {
+            self.next();
+            return Ok(query.map(|q| Arc::new(BoostQuery::new(q, boost)) as Arc<dyn Query>));
+        }
+        Ok(query)
+    }
+
+    fn term_query(
+        &self,
+        token: &Token,
+        field: &str,
+        text: &str,
+        edits: Option<u32>,
+    ) -> Result<Option<Arc<dyn Query>>, ParseError> {
+        let term = |text: String| Term::new(field.to_string(), text);
+
+        if let Some(edits) = edits {
+            let query = FuzzyQuery::new(
+                term(unescape(text)),
+                edits,
+                self.config.fuzzy_prefix_length,
+                true,
+            );
+            return Ok(Some(Arc::new(query)));
+        }
+
+        let wildcards: Vec<usize> = unescaped_positions(text, |c| c == '*' || c == '?');
+        if wildcards.is_empty() {
+            return Ok(self.analyzed_query(field, &unescape(text)));
+        }
+
+        // A single trailing `*` is a prefix
+        if wildcards == [text.len() - 1] && text.ends_with('*') {
+            let prefix = unescape(&text[..text.len() - 1]);
+            return Ok(Some(Arc::new(PrefixQuery::new(term(prefix)))));
+        }
+
+        if wildcards[0] == 0 && !self.config.allow_leading_wildcard {
+            return Err(Self::error_at(token, "Leading wildcards are not allowed"));
+        }
+        let query =
+            WildcardQuery::new(term(text.to_string())).map_err(|e| automaton_error(token, e))?;
+        Ok(Some(Arc::new(query)))
+    }
+
+    /// Term query of the analyzed text, or a disjunction when it has several tokens.
+    fn analyzed_query(&self, field: &str, text: &str) -> Option<Arc<dyn Query>> {
+        let term_query = |token: &str| -> Arc<dyn Query> {
+            Arc::new(TermQuery::new(Term::new(
+                field.to_string(),
+                token.to_string(),
+            )))
+        };
+
+        let tokens = self.config.analyze(text);
+        match tokens.as_slice() {
+            [] => None,
+            [token] => Some(term_query(token)),
+            _ => {
+                let mut query = BooleanQuery::new();
+                for token in tokens {
+                    query.add(term_query(token), Occur::Should);
+                }
+                Some(Arc::new(query))
+            }
+        }
+    }
+
+    fn phrase_query(&self, field: &str, text: &str, slop: u32) -> Option<Arc<dyn Query>> {
+        let tokens = self.config.analyze(text);
+        if tokens.len() < 2 {
+            return self.analyzed_query(field, text);
+        }
+
+        let mut query = PhraseQuery::new();
+        for token in tokens {
+            query.add(Term::new(field.to_string(), token.to_string()));
+        }
+        query.set_slop(slop);
+        Some(Arc::new(query))
+    }
+}
+
+fn automaton_error(token: &Token, error: AutomatonError) -> ParseError {
+    let message = match error {
+        AutomatonError::InvalidSyntax(message) => format!("Invalid regexp: {}", message),
+        AutomatonError::TooComplexToDeterminize(message) => {
+            format!("Too complex term: {}", message)
+        }
+    };
+    ParseError {
+        message,
+        line: token.line,
+        column: token.column,
+    }
+}
+
+/// Byte positions of the unescaped chars matching the predicate.
+fn unescaped_positions(text: &str, predicate: impl Fn(char) -> bool) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if predicate(c) {
+            positions.push(i);
+        }
+    }
+    positions
+}
+
+/// Text without its escaping backslashes.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unescaped.extend(chars.next());
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::{DirectoryReader, IndexWriter},
+        search::IndexSearcher,
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    fn parser() -> QueryParser<StandardAnalyzer> {
+        QueryParser::new("body".to_string(), StandardAnalyzer::new())
+    }
+
+    fn parse(query: &str) -> String {
+        parser().parse(query).unwrap().to_string()
+    }
+
+    fn error(query: &str) -> String {
+        parser().parse(query).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        for (query, expected) in [
+            ("tests", "body:tests"),
+            ("title:tests", "title:tests"),
+            ("unit tests", "body:unit body:tests"),
+            ("+unit -tests", "+body:unit -body:tests"),
+            ("unit AND tests", "+body:unit +body:tests"),
+            (
+                "unit && tests || reader",
+                "+body:unit +body:tests body:reader",
+            ),
+            ("unit AND NOT tests", "+body:unit -body:tests"),
+            ("unit !tests", "body:unit -body:tests"),
+            (
+                "(unit OR integration) AND tests",
+                "+(body:unit body:integration) +body:tests",
+            ),
+            ("title:(unit tests)", "title:unit title:tests"),
+            ("\"integration tests\"~2", "body:\"integration tests\"~2"),
+            ("\"tests\"", "body:tests"),
+            ("tests^2", "body:tests^2.0"),
+            ("(unit integration)^1.5", "(body:unit body:integration)^1.5"),
+            ("test*", "body:test*"),
+            ("te?t", "body:te?t"),
+            ("te*t*", "body:te*t*"),
+            ("/te[sx]t/", "body:/te[sx]t/"),
+            ("ferocene~1", "body:ferocene~1"),
+            ("ferocene~", "body:ferocene~2"),
+            ("ferocene~5^2", "body:ferocene~2^2.0"),
+            ("[a TO m]", "body:[a TO m]"),
+            ("title:{a TO *]", "title:{a TO *]"),
+            ("[\"a b\" TO c]", "body:[a b TO c]"),
+            ("*:*", "*:*"),
+            ("C\\+\\+ te\\*t", "body:C++ body:te*t"),
+        ] {
+            assert_eq!(parse(query), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_default_operator() {
+        let mut parser = parser();
+        parser.set_default_operator(Operator::And);
+
+        let parse = |query: &str| parser.parse(query).unwrap().to_string();
+        assert_eq!(parse("unit tests"), "+body:unit +body:tests");
+        assert_eq!(parse("unit OR tests"), "body:unit body:tests");
+        assert_eq!(parse("unit -tests"), "+body:unit -body:tests");
+    }
+
+    #[test]
+    fn test_errors() {
+        for (query, expected) in [
+            ("", "Unexpected end of query at line 1, column 1"),
+            ("unit AND", "Unexpected end of query at line 1, column 9"),
+            ("AND unit", "Unexpected 'AND' at line 1, column 1"),
+            ("(unit", "Expected ')' at line 1, column 6"),
+            ("unit)", "Unexpected ')' at line 1, column 5"),
+            ("unit\n  AND )", "Unexpected ')' at line 2, column 7"),
+            ("\"unit", "Unterminated phrase at line 1, column 1"),
+            ("tests^", "Expected a number at line 1, column 7"),
+            ("[a TO]", "Expected a range bound at line 1, column 6"),
+            ("[a b]", "Expected 'TO' at line 1, column 5"),
+            (
+                "*test",
+                "Leading wildcards are not allowed at line 1, column 1",
+            ),
+            ("title:", "Unexpected end of query at line 1, column 7"),
+            (
+                "unit /te[sx/",
+                "Invalid regexp: Unterminated class at position 5 at line 1, column 6",
+            ),
+        ] {
+            assert_eq!(error(query), expected, "{}", query);
+        }
+
+        let mut parser = parser();
+        parser.set_allow_leading_wildcard(true);
+        assert_eq!(parser.parse("*test").unwrap().to_string(), "body:*test");
+    }
+
+    #[test]
+    fn test_nesting() {
+        let nested = |depth: usize| format!("{}tests{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(parse(&nested(MAX_DEPTH)), "body:tests");
+
+        assert_eq!(
+            error(&nested(MAX_DEPTH + 1)),
+            "Groups nested deeper than 100 at line 1, column 101"
+        );
+        assert_eq!(
+            error(&format!("unit\n{}", "(".repeat(5000))),
+            "Groups nested deeper than 100 at line 2, column 101"
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+        for body in ["unit tests", "integration tests", "the index writer"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            writer.add_document(doc).unwrap();
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        let count = |query: &str| {
+            searcher
+                .count(parser().parse(query).unwrap().as_ref())
+                .unwrap()
+        };
+        assert_eq!(count("tests"), 2);
+        assert_eq!(count("tests -unit"), 1);
+        assert_eq!(count("\"integration tests\""), 1);
+        assert_eq!(count("writ* OR unit"), 2);
+        assert_eq!(count("*:* -tests"), 1);
+        assert_eq!(count("tsets~1"), 2);
+    }
+}
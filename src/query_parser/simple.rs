@@ -0,0 +1,679 @@
+use std::{fmt, ops::BitOr, sync::Arc};
+
+use crate::{
+    analysis::Analyzer,
+    index::Term,
+    search::{
+        BooleanQuery, BoostQuery, FuzzyQuery, MatchAllDocsQuery, Occur, PhraseQuery, PrefixQuery,
+        Query, TermQuery,
+    },
+    util::levenshtein_automata,
+};
+
+use super::Operator;
+
+/// Deepest nesting of groups, parentheses nested deeper are ignored.
+pub const MAX_DEPTH: usize = 100;
+
+/// Set of operators enabled in the simple query syntax, combined with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operators(u32);
+
+impl Operators {
+    /// `+` requires both sides.
+    pub const AND: Self = Self(1 << 0);
+    /// `|` matches either side.
+    pub const OR: Self = Self(1 << 1);
+    /// `-` excludes the next term, phrase or group.
+    pub const NOT: Self = Self(1 << 2);
+    /// `"..."` searches a phrase.
+    pub const PHRASE: Self = Self(1 << 3);
+    /// A trailing `*` searches a prefix.
+    pub const PREFIX: Self = Self(1 << 4);
+    /// `(...)` groups clauses.
+    pub const PRECEDENCE: Self = Self(1 << 5);
+    /// `\` escapes the next char.
+    pub const ESCAPE: Self = Self(1 << 6);
+    /// Whitespace separates terms, otherwise splitting is left to the analyzers.
+    pub const WHITESPACE: Self = Self(1 << 7);
+    /// `~N` after a term searches terms within N edits.
+    pub const FUZZY: Self = Self(1 << 8);
+    /// `~N` after a phrase sets its slop.
+    pub const NEAR: Self = Self(1 << 9);
+
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self((1 << 10) - 1);
+
+    pub fn contains(self, operators: Operators) -> bool {
+        self.0 & operators.0 == operators.0
+    }
+}
+
+impl Default for Operators {
+    fn default() -> Self {
+        Operators::ALL
+    }
+}
+
+impl BitOr for Operators {
+    type Output = Operators;
+
+    fn bitor(self, rhs: Operators) -> Operators {
+        Operators(self.0 | rhs.0)
+    }
+}
+
+/// Analyzed tokens of a text, skipping empty ones.
+type AnalyzeFn = Box<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
+struct WeightedField {
+    name: String,
+    weight: f32,
+    analyze: AnalyzeFn,
+}
+
+/// Parses end-user queries in a forgiving syntax, never failing.
+///
+/// - `unit tests` searches terms with the default operator, `unit + tests` requires
+///   both and `unit | tests` either.
+/// - `-tests` excludes docs, like a required `*:* -tests`.
+/// - `"integration tests"~2` searches a phrase, with an optional slop.
+/// - `test*` searches a prefix and `tsets~1` a fuzzy term.
+/// - `(...)` groups clauses and `\` escapes special chars.
+///
+/// Clauses are combined left to right, an operator change nesting the clauses before it.
+/// Unbalanced quotes and parentheses and dangling operators are ignored,
+/// as are parentheses nested deeper than `MAX_DEPTH`.
+/// Each term is searched in all fields, boosted by their weight.
+#[derive(Default)]
+pub struct SimpleQueryParser {
+    fields: Vec<WeightedField>,
+    default_operator: Operator,
+    operators: Operators,
+}
+
+impl SimpleQueryParser {
+    pub fn new() -> Self {
+        SimpleQueryParser::default()
+    }
+
+    /// Searches a field with its analyzer, boosting its matches by the weight.
+    pub fn add_field<A: Analyzer + Send + Sync + 'static>(
+        &mut self,
+        name: String,
+        weight: f32,
+        analyzer: A,
+    ) {
+        assert!(
+            weight.is_finite() && weight > 0.0,
+            "weight must be positive and finite"
+        );
+        assert!(
+            self.fields.iter().all(|f| f.name != name),
+            "field {} was already added",
+            name
+        );
+
+        self.fields.push(WeightedField {
+            name,
+            weight,
+            analyze: Box::new(move |text| {
+                analyzer
+                    .analyze(text)
+                    .filter(|token| !token.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }),
+        });
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.fields.iter().map(|f| (f.name.as_str(), f.weight))
+    }
+
+    pub fn set_default_operator(&mut self, default_operator: Operator) {
+        self.default_operator = default_operator;
+    }
+
+    pub fn default_operator(&self) -> Operator {
+        self.default_operator
+    }
+
+    /// Operators to parse, the chars of disabled ones being part of terms.
+    pub fn set_operators(&mut self, operators: Operators) {
+        self.operators = operators;
+    }
+
+    pub fn operators(&self) -> Operators {
+        self.operators
+    }
+
+    pub fn parse(&self, query: &str) -> Arc<dyn Query> {
+        let mut parser = Parser {
+            config: self,
+            chars: query.chars().collect(),
+            pos: 0,
+            ignored_groups: 0,
+        };
+
+        parser
+            .parse_query(0)
+            .unwrap_or_else(|| Arc::new(BooleanQuery::new()))
+    }
+
+    fn default_occur(&self) -> Occur {
+        match self.default_operator {
+            Operator::Or => Occur::Should,
+            Operator::And => Occur::Must,
+        }
+    }
+
+    /// Disjunction of the query built for each field, boosted by its weight.
+    fn fields_query(
+        &self,
+        query: impl Fn(&WeightedField) -> Option<Arc<dyn Query>>,
+    ) -> Option<Arc<dyn Query>> {
+        let mut queries: Vec<Arc<dyn Query>> = self
+            .fields
+            .iter()
+            .filter_map(|field| match query(field) {
+                Some(q) if field.weight != 1.0 => {
+                    Some(Arc::new(BoostQuery::new(q, field.weight)) as Arc<dyn Query>)
+                }
+                q => q,
+            })
+            .collect();
+
+        match queries.len() {
+            0 => None,
+            1 => queries.pop(),
+            _ => {
+                let mut query = BooleanQuery::new();
+                for q in queries {
+                    query.add(q, Occur::Should);
+                }
+                Some(Arc::new(query))
+            }
+        }
+    }
+
+    fn term_query(&self, text: &str) -> Option<Arc<dyn Query>> {
+        self.fields_query(|field| self.analyzed_query(field, text))
+    }
+
+    /// Term query of the analyzed text, combined with the default operator when it has several tokens.
+    fn analyzed_query(&self, field: &WeightedField, text: &str) -> Option<Arc<dyn Query>> {
+        let term_query = |token: String| -> Arc<dyn Query> {
+            Arc::new(TermQuery::new(Term::new(field.name.clone(), token)))
+        };
+
+        let mut tokens = (field.analyze)(text);
+        match tokens.len() {
+            0 => None,
+            1 => Some(term_query(tokens.pop().unwrap())),
+            _ => {
+                let mut query = BooleanQuery::new();
+                for token in tokens {
+                    query.add(term_query(token), self.default_occur());
+                }
+                Some(Arc::new(query))
+            }
+        }
+    }
+
+    fn phrase_query(&self, text: &str, slop: u32) -> Option<Arc<dyn Query>> {
+        self.fields_query(|field| {
+            let tokens = (field.analyze)(text);
+            if tokens.len() < 2 {
+                return self.analyzed_query(field, text);
+            }
+
+            let mut query = PhraseQuery::new();
+            for token in tokens {
+                query.add(Term::new(field.name.clone(), token));
+            }
+            query.set_slop(slop);
+            Some(Arc::new(query))
+        })
+    }
+
+    fn prefix_query(&self, prefix: &str) -> Option<Arc<dyn Query>> {
+        self.fields_query(|field| {
+            let term = Term::new(field.name.clone(), prefix.to_string());
+            Some(Arc::new(PrefixQuery::new(term)))
+        })
+    }
+
+    fn fuzzy_query(&self, text: &str, edits: u32) -> Option<Arc<dyn Query>> {
+        let edits = edits.min(levenshtein_automata::MAX_EDITS);
+        self.fields_query(|field| {
+            let term = Term::new(field.name.clone(), text.to_string());
+            Some(Arc::new(FuzzyQuery::new(term, edits, 0, true)))
+        })
+    }
+}
+
+impl fmt::Debug for SimpleQueryParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimpleQueryParser")
+            .field("fields", &self.fields().collect::<Vec<_>>())
+            .field("default_operator", &self.default_operator)
+            .field("operators", &self.operators)
+            .finish()
+    }
+}
+
+/// Clauses of a query or group, nested each time the operator changes.
+/// Past `max_nesting`, clauses are added with their operator to the innermost query instead.
+#[derive(Default)]
+struct Clauses {
+    /// The first clause, until a second one is added.
+    first: Option<Arc<dyn Query>>,
+    query: Option<BooleanQuery>,
+    /// Operator before the next clause.
+    operator: Option<Occur>,
+    previous_operator: Option<Occur>,
+    negations: usize,
+    nesting: usize,
+    max_nesting: usize,
+}
+
+impl Clauses {
+    fn add(&mut self, clause: Option<Arc<dyn Query>>, default_occur: Occur) {
+        let negated = self.negations % 2 == 1;
+        self.negations = 0;
+        let Some(mut clause) = clause else {
+            return;
+        };
+
+        if negated {
+            let mut query = BooleanQuery::new();
+            query.add(clause, Occur::MustNot);
+            query.add(Arc::new(MatchAllDocsQuery), Occur::Should);
+            clause = Arc::new(query);
+        }
+
+        let operator = self.operator.take().unwrap_or(default_occur);
+        if self.first.is_none() && self.query.is_none() {
+            self.first = Some(clause);
+            return;
+        }
+
+        if self.previous_operator != Some(operator)
+            && (self.query.is_none() || self.nesting < self.max_nesting)
+        {
+            let mut query = BooleanQuery::new();
+            query.add(self.finish().unwrap(), operator);
+            self.query = Some(query);
+            self.nesting += 1;
+        }
+        self.query.as_mut().unwrap().add(clause, operator);
+        self.previous_operator = Some(operator);
+    }
+
+    fn finish(&mut self) -> Option<Arc<dyn Query>> {
+        match self.query.take() {
+            Some(query) => Some(Arc::new(query)),
+            None => self.first.take(),
+        }
+    }
+}
+
+struct Parser<'p> {
+    config: &'p SimpleQueryParser,
+    chars: Vec<char>,
+    pos: usize,
+    /// Groups opened beyond `MAX_DEPTH`, whose clauses belong to the enclosing group.
+    ignored_groups: usize,
+}
+
+impl Parser<'_> {
+    fn enabled(&self, operators: Operators) -> bool {
+        self.config.operators.contains(operators)
+    }
+
+    /// Clauses up to the end of the query, or of the group at a `depth` above 0.
+    fn parse_query(&mut self, depth: usize) -> Option<Arc<dyn Query>> {
+        let default_occur = self.config.default_occur();
+        let mut clauses = Clauses {
+            max_nesting: MAX_DEPTH - depth,
+            ..Clauses::default()
+        };
+
+        while let Some(&c) = self.chars.get(self.pos) {
+            match c {
+                '(' if self.enabled(Operators::PRECEDENCE) => {
+                    self.pos += 1;
+                    if depth == MAX_DEPTH {
+                        self.ignored_groups += 1;
+                        continue;
+                    }
+                    let group = self.parse_query(depth + 1);
+                    clauses.add(group, default_occur);
+                }
+                ')' if self.enabled(Operators::PRECEDENCE) => {
+                    self.pos += 1;
+                    if self.ignored_groups > 0 {
+                        self.ignored_groups -= 1;
+                        continue;
+                    }
+                    // A stray `)` is skipped
+                    if depth > 0 {
+                        break;
+                    }
+                }
+                '"' if self.enabled(Operators::PHRASE) => {
+                    if let Some(phrase) = self.phrase() {
+                        clauses.add(phrase, default_occur);
+                    }
+                }
+                '+' if self.enabled(Operators::AND) => {
+                    self.pos += 1;
+                    clauses.operator = Some(Occur::Must);
+                }
+                '|' if self.enabled(Operators::OR) => {
+                    self.pos += 1;
+                    clauses.operator = Some(Occur::Should);
+                }
+                '-' if self.enabled(Operators::NOT) => {
+                    self.pos += 1;
+                    clauses.negations += 1;
+                }
+                c if c.is_whitespace() && self.enabled(Operators::WHITESPACE) => {
+                    self.pos += 1;
+                }
+                _ => {
+                    let term = self.term();
+                    clauses.add(term, default_occur);
+                }
+            }
+        }
+
+        clauses.finish()
+    }
+
+    fn is_term_end(&self, c: char) -> bool {
+        match c {
+            '|' => self.enabled(Operators::OR),
+            '+' => self.enabled(Operators::AND),
+            '(' | ')' => self.enabled(Operators::PRECEDENCE),
+            '"' => self.enabled(Operators::PHRASE),
+            '~' => self.enabled(Operators::FUZZY),
+            c => c.is_whitespace() && self.enabled(Operators::WHITESPACE),
+        }
+    }
+
+    /// Phrase at the current `"`, or `None` without a closing quote, then skipping the quote.
+    fn phrase(&mut self) -> Option<Option<Arc<dyn Query>>> {
+        let escape = self.enabled(Operators::ESCAPE);
+        let mut text = String::new();
+        let mut end = self.pos + 1;
+        loop {
+            match self.chars.get(end) {
+                None => {
+                    self.pos += 1;
+                    return None;
+                }
+                Some('"') => break,
+                Some('\\') if escape => {
+                    end += 1;
+                    text.extend(self.chars.get(end));
+                }
+                Some(&c) => text.push(c),
+            }
+            end += 1;
+        }
+        self.pos = end + 1;
+
+        let slop = match self.enabled(Operators::NEAR) {
+            true => self.distance().unwrap_or(0),
+            false => 0,
+        };
+        Some(self.config.phrase_query(&text, slop))
+    }
+
+    fn term(&mut self) -> Option<Arc<dyn Query>> {
+        let escape = self.enabled(Operators::ESCAPE);
+        let mut text = String::new();
+        let mut prefix = false;
+        while let Some(&c) = self.chars.get(self.pos) {
+            if self.is_term_end(c) {
+                break;
+            }
+            self.pos += 1;
+            prefix = c == '*';
+            match c {
+                '\\' if escape => {
+                    prefix = false;
+                    text.extend(self.chars.get(self.pos));
+                    self.pos += 1;
+                }
+                c => text.push(c),
+            }
+        }
+
+        let edits = match self.enabled(Operators::FUZZY) {
+            true => self.distance(),
+            false => None,
+        };
+
+        if prefix && self.enabled(Operators::PREFIX) {
+            text.pop();
+            return self.config.prefix_query(&text);
+        }
+        match edits {
+            Some(edits) if edits > 0 && !text.is_empty() => self.config.fuzzy_query(&text, edits),
+            _ => self.config.term_query(&text),
+        }
+    }
+
+    /// Number after a `~`, or 0 when it is not one, skipping up to the end of the term.
+    fn distance(&mut self) -> Option<u32> {
+        if self.chars.get(self.pos) != Some(&'~') {
+            return None;
+        }
+        self.pos += 1;
+
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|&c| !self.is_term_end(c))
+        {
+            self.pos += 1;
+        }
+        let number: String = self.chars[start..self.pos].iter().collect();
+        Some(number.parse().unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::{DirectoryReader, IndexWriter},
+        search::IndexSearcher,
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    fn parser() -> SimpleQueryParser {
+        let mut parser = SimpleQueryParser::new();
+        parser.add_field("body".to_string(), 1.0, StandardAnalyzer::new());
+        parser
+    }
+
+    fn parse(query: &str) -> String {
+        parser().parse(query).to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("tests"), "body:tests");
+        assert_eq!(parse("unit tests"), "body:unit body:tests");
+        assert_eq!(parse("unit + tests"), "+body:unit +body:tests");
+        assert_eq!(parse("unit | tests"), "body:unit body:tests");
+        assert_eq!(
+            parse("unit + tests | reader"),
+            "(+body:unit +body:tests) body:reader"
+        );
+        assert_eq!(
+            parse("unit + (tests | reader)"),
+            "+body:unit +(body:tests body:reader)"
+        );
+        assert_eq!(parse("-unit"), "-body:unit *:*");
+        assert_eq!(parse("tests + -unit"), "+body:tests +(-body:unit *:*)");
+        assert_eq!(parse("--unit"), "body:unit");
+        assert_eq!(parse("well-known"), "body:well-known");
+        assert_eq!(parse("\"integration tests\""), "body:\"integration tests\"");
+        assert_eq!(
+            parse("\"integration tests\"~2"),
+            "body:\"integration tests\"~2"
+        );
+        assert_eq!(parse("\"tests\""), "body:tests");
+        assert_eq!(parse("test*"), "body:test*");
+        assert_eq!(parse("test\\*"), "body:test*");
+        assert_eq!(parse("tsets~1"), "body:tsets~1");
+        assert_eq!(parse("tsets~5"), "body:tsets~2");
+        assert_eq!(parse("tsets~0"), "body:tsets");
+        assert_eq!(parse("a\\+b"), "body:a+b");
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert_eq!(parse("\"integration tests"), "body:integration body:tests");
+        assert_eq!(parse("(unit | tests"), "body:unit body:tests");
+        assert_eq!(parse("unit) tests"), "body:unit body:tests");
+        assert_eq!(parse("unit +"), "body:unit");
+        assert_eq!(parse("| unit"), "body:unit");
+        assert_eq!(parse("-"), "");
+        assert_eq!(parse("~2"), "");
+        assert_eq!(parse("()"), "");
+        assert_eq!(parse(""), "");
+        assert_eq!(parse("AND OR"), "body:AND body:OR");
+        assert_eq!(parse("tests\\"), "body:tests");
+
+        for query in [
+            "\"", "((", "))", "+|-", "\\", "*", "~", "\"~\"~", "a~b", "(\"a)\"",
+        ] {
+            parser().parse(query);
+        }
+    }
+
+    #[test]
+    fn test_nesting() {
+        let nested = |depth: usize, query: &str| {
+            format!("{}{}{}", "(".repeat(depth), query, ")".repeat(depth))
+        };
+        assert_eq!(
+            parse(&nested(MAX_DEPTH, "unit | tests")),
+            "body:unit body:tests"
+        );
+
+        // Deeper groups are flattened into the deepest one
+        let query = format!("{} + reader", nested(MAX_DEPTH + 5, "unit | (tests)"));
+        assert_eq!(parse(&query), "+(body:unit body:tests) +body:reader");
+
+        assert_eq!(parse(&"(".repeat(100_000)), "");
+        assert_eq!(
+            parse(&format!("{}tests", "(".repeat(100_000))),
+            "body:tests"
+        );
+
+        // Operator changes nest clauses too, up to the same depth
+        assert_eq!(
+            parse(&nested(MAX_DEPTH, "unit + tests | reader")),
+            "+body:unit +body:tests body:reader"
+        );
+        parse(&"unit + tests | ".repeat(50_000));
+    }
+
+    #[test]
+    fn test_operators() {
+        let mut parser = parser();
+        parser.set_default_operator(Operator::And);
+        assert_eq!(
+            parser.parse("unit tests").to_string(),
+            "+body:unit +body:tests"
+        );
+        assert_eq!(
+            parser.parse("unit tests | reader").to_string(),
+            "(+body:unit +body:tests) body:reader"
+        );
+
+        parser.set_default_operator(Operator::Or);
+        parser.set_operators(Operators::PHRASE | Operators::WHITESPACE);
+        assert_eq!(parser.parse("-unit").to_string(), "body:-unit");
+        assert_eq!(parser.parse("a+b test*").to_string(), "body:a+b body:test*");
+        assert_eq!(
+            parser.parse("\"a b\"~2").to_string(),
+            "body:\"a b\" body:~2"
+        );
+
+        parser.set_operators(Operators::NONE);
+        assert_eq!(
+            parser.parse("unit tests").to_string(),
+            "body:unit body:tests"
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut parser = parser();
+        parser.add_field("title".to_string(), 2.0, StandardAnalyzer::new());
+        assert_eq!(
+            parser.fields().collect::<Vec<_>>(),
+            [("body", 1.0), ("title", 2.0)]
+        );
+        assert_eq!(
+            parser.parse("tests").to_string(),
+            "body:tests title:tests^2.0"
+        );
+        assert_eq!(
+            parser.parse("unit + test*").to_string(),
+            "+(body:unit title:unit^2.0) +(body:test* title:test*^2.0)"
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+        for (title, body) in [
+            ("unit", "unit tests"),
+            ("integration", "integration tests"),
+            ("writer", "the index writer"),
+        ] {
+            let mut doc = Document::new();
+            doc.add(Field::text("title".to_string(), title.to_string()));
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            writer.add_document(doc).unwrap();
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        let mut parser = parser();
+        parser.add_field("title".to_string(), 2.0, StandardAnalyzer::new());
+        let count = |query: &str| searcher.count(parser.parse(query).as_ref()).unwrap();
+        assert_eq!(count("tests"), 2);
+        assert_eq!(count("tests + -unit"), 1);
+        assert_eq!(count("-tests"), 1);
+        assert_eq!(count("\"integration tests"), 2);
+        assert_eq!(count("\"integration tests\""), 1);
+        assert_eq!(count("writ* | unit"), 2);
+        assert_eq!(count("tsets~1"), 2);
+        assert_eq!(count("(unit"), 1);
+        assert_eq!(count(""), 0);
+
+        // The title weight ranks the doc with the term in both fields first
+        let top_docs = searcher.search(parser.parse("unit").as_ref(), 10).unwrap();
+        assert_eq!(top_docs.total_hits, 1);
+        let top_docs = searcher
+            .search(parser.parse("writer | tests").as_ref(), 10)
+            .unwrap();
+        assert_eq!(top_docs.score_docs[0].doc, 2);
+    }
+}
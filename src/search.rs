@@ -0,0 +1,55 @@
+pub mod bm25_similarity;
+pub mod boolean_query;
+pub mod boost_query;
+pub mod classic_similarity;
+pub mod collector;
+pub mod conjunction_scorer;
+pub mod constant_score_query;
+pub mod cursor;
+pub mod disjunction_scorer;
+pub mod doc_id_set_iterator;
+pub mod explanation;
+pub mod fuzzy_query;
+pub mod index_searcher;
+pub mod match_all_docs_query;
+pub mod multi_term_query;
+pub mod phrase_query;
+pub mod prefix_query;
+pub mod query;
+pub mod regexp_query;
+pub mod req_excl_scorer;
+pub mod req_opt_sum_scorer;
+pub mod scorer;
+pub mod similarity;
+pub mod sort;
+pub mod term_query;
+pub mod term_range_query;
+pub mod top_docs;
+pub mod weight;
+pub mod wildcard_query;
+
+pub use bm25_similarity::BM25Similarity;
+pub use boolean_query::{BooleanClause, BooleanQuery, Occur};
+pub use boost_query::BoostQuery;
+pub use classic_similarity::ClassicSimilarity;
+pub use collector::{Collector, TopFieldCollector, TopScoreDocCollector, TotalHitCountCollector};
+pub use constant_score_query::ConstantScoreQuery;
+pub use cursor::CursorError;
+pub use doc_id_set_iterator::{DocIdSetIterator, NO_MORE_DOCS};
+pub use explanation::Explanation;
+pub use fuzzy_query::FuzzyQuery;
+pub use index_searcher::IndexSearcher;
+pub use match_all_docs_query::{MatchAllDocsQuery, MatchNoDocsQuery};
+pub use multi_term_query::{AutomatonQuery, RewriteMethod};
+pub use phrase_query::PhraseQuery;
+pub use prefix_query::PrefixQuery;
+pub use query::{Query, ScoreMode};
+pub use regexp_query::RegexpQuery;
+pub use scorer::Scorer;
+pub use similarity::{CollectionStatistics, SimScorer, Similarity, TermStatistics};
+pub use sort::{Missing, Sort, SortField, SortFieldType};
+pub use term_query::TermQuery;
+pub use term_range_query::TermRangeQuery;
+pub use top_docs::{FieldDoc, ScoreDoc, SortValue, TopDocs, TopFieldDocs};
+pub use weight::Weight;
+pub use wildcard_query::WildcardQuery;
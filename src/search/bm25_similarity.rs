@@ -0,0 +1,248 @@
+use crate::index::norms;
+
+use super::{
+    similarity::{CollectionStatistics, SimScorer, Similarity, TermStatistics},
+    Explanation,
+};
+
+/// Okapi BM25, the default similarity.
+///
+/// Term freq saturates with `k1`, and `b` controls how much longer fields are penalized.
+#[derive(Debug, Clone, Copy)]
+pub struct BM25Similarity {
+    k1: f32,
+    b: f32,
+}
+
+impl BM25Similarity {
+    pub fn new(k1: f32, b: f32) -> Self {
+        assert!(
+            k1.is_finite() && k1 >= 0.0,
+            "k1 must be a non-negative number"
+        );
+        assert!((0.0..=1.0).contains(&b), "b must be within 0 and 1");
+
+        Self { k1, b }
+    }
+
+    pub fn k1(&self) -> f32 {
+        self.k1
+    }
+
+    pub fn b(&self) -> f32 {
+        self.b
+    }
+
+    /// Rare terms weigh more, `ln(1 + (doc_count - doc_freq + 0.5) / (doc_freq + 0.5))`.
+    pub fn idf(doc_freq: u64, doc_count: u64) -> f32 {
+        let doc_freq = doc_freq as f64;
+        let doc_count = doc_count as f64;
+
+        (1.0 + (doc_count - doc_freq + 0.5) / (doc_freq + 0.5)).ln() as f32
+    }
+
+    /// Average field length.
+    fn avg_field_length(collection: &CollectionStatistics) -> f32 {
+        collection.sum_total_term_freq as f32 / collection.doc_count.max(1) as f32
+    }
+}
+
+impl Default for BM25Similarity {
+    fn default() -> Self {
+        Self::new(1.2, 0.75)
+    }
+}
+
+impl Similarity for BM25Similarity {
+    fn scorer(
+        &self,
+        boost: f32,
+        collection: &CollectionStatistics,
+        terms: &[TermStatistics],
+    ) -> Box<dyn SimScorer> {
+        let idf = idf_explanation(collection, terms);
+        let avgdl = Self::avg_field_length(collection);
+
+        // Length normalization of each norm value
+        let mut cache = [0.0; 256];
+        for (norm, value) in cache.iter_mut().enumerate() {
+            let length = norms::byte4_to_int(norm as u8) as f32;
+            *value = self.k1 * (1.0 - self.b + self.b * length / avgdl);
+        }
+
+        Box::new(BM25Scorer {
+            similarity: *self,
+            boost,
+            weight: boost * idf.value(),
+            idf,
+            avgdl,
+            cache,
+        })
+    }
+}
+
+/// Idf of a term, or the sum of the idfs of the terms of a phrase.
+fn idf_explanation(collection: &CollectionStatistics, terms: &[TermStatistics]) -> Explanation {
+    let mut idfs: Vec<Explanation> = terms
+        .iter()
+        .map(|t| {
+            Explanation::matched(
+                BM25Similarity::idf(t.doc_freq, collection.doc_count),
+                "idf, computed as log(1 + (N - n + 0.5) / (n + 0.5)) from:".to_string(),
+                vec![
+                    Explanation::matched(
+                        t.doc_freq as f32,
+                        "n, number of documents containing term".to_string(),
+                        vec![],
+                    ),
+                    Explanation::matched(
+                        collection.doc_count as f32,
+                        "N, total number of documents with field".to_string(),
+                        vec![],
+                    ),
+                ],
+            )
+        })
+        .collect();
+
+    if idfs.len() == 1 {
+        return idfs.pop().unwrap();
+    }
+    let idf = idfs.iter().map(|e| e.value()).sum();
+    Explanation::matched(idf, "idf, sum of:".to_string(), idfs)
+}
+
+struct BM25Scorer {
+    similarity: BM25Similarity,
+    boost: f32,
+    idf: Explanation,
+    avgdl: f32,
+    weight: f32,
+    cache: [f32; 256],
+}
+
+impl SimScorer for BM25Scorer {
+    fn score(&self, freq: f32, norm: u8) -> f32 {
+        self.weight * freq / (freq + self.cache[norm as usize])
+    }
+
+    fn explain(&self, freq: Explanation, norm: u8) -> Explanation {
+        let value = freq.value();
+        let param = |value: f32, description: &str| {
+            Explanation::matched(value, description.to_string(), vec![])
+        };
+
+        let tf = Explanation::matched(
+            value / (value + self.cache[norm as usize]),
+            "tf, computed as freq / (freq + k1 * (1 - b + b * dl / avgdl)) from:".to_string(),
+            vec![
+                freq,
+                param(self.similarity.k1, "k1, term saturation parameter"),
+                param(self.similarity.b, "b, length normalization parameter"),
+                param(
+                    norms::byte4_to_int(norm) as f32,
+                    "dl, length of field (approximate)",
+                ),
+                param(self.avgdl, "avgdl, average length of field"),
+            ],
+        );
+
+        Explanation::matched(
+            self.score(value, norm),
+            format!(
+                "score(freq={:?}), computed as boost * idf * tf from:",
+                value
+            ),
+            vec![param(self.boost, "boost"), self.idf.clone(), tf],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::Term;
+
+    use super::*;
+
+    fn collection() -> CollectionStatistics {
+        CollectionStatistics {
+            field: "body".to_string(),
+            max_doc: 10,
+            doc_count: 10,
+            sum_total_term_freq: 40,
+            sum_doc_freq: 30,
+        }
+    }
+
+    fn term(doc_freq: u64) -> TermStatistics {
+        TermStatistics {
+            term: Term::new("body".to_string(), "tests".to_string()),
+            doc_freq,
+            total_term_freq: doc_freq,
+        }
+    }
+
+    #[test]
+    fn test_score() {
+        let scorer = BM25Similarity::default().scorer(2.0, &collection(), &[term(2)]);
+
+        let idf = (1.0f32 + 8.5 / 2.5).ln();
+        // avgdl is 4, a field of 8 terms
+        let expected = 2.0 * idf * 3.0 / (3.0 + 1.2 * (0.25 + 0.75 * 2.0));
+        assert!((scorer.score(3.0, norms::int_to_byte4(8)) - expected).abs() < 1e-6);
+
+        // Shorter fields and rarer terms score higher, freq saturates
+        assert!(scorer.score(1.0, 2) > scorer.score(1.0, 8));
+        let rare = BM25Similarity::default().scorer(2.0, &collection(), &[term(1)]);
+        assert!(rare.score(1.0, 4) > scorer.score(1.0, 4));
+        assert!(scorer.score(1000.0, 4) < 2.0 * idf);
+    }
+
+    #[test]
+    fn test_explain() {
+        let scorer = BM25Similarity::default().scorer(2.0, &collection(), &[term(2)]);
+        let freq = Explanation::matched(3.0, "freq".to_string(), vec![]);
+        let norm = norms::int_to_byte4(8);
+
+        let explanation = scorer.explain(freq, norm);
+        assert_eq!(explanation.value(), scorer.score(3.0, norm));
+        assert_eq!(
+            explanation.description(),
+            "score(freq=3.0), computed as boost * idf * tf from:"
+        );
+        let values: Vec<f32> = explanation.details().iter().map(|e| e.value()).collect();
+        assert_eq!(values[0], 2.0);
+        assert!((values[1] - (1.0f32 + 8.5 / 2.5).ln()).abs() < 1e-6);
+        assert!((values.iter().product::<f32>() - explanation.value()).abs() < 1e-6);
+
+        let tf = &explanation.details()[2];
+        let dl = &tf.details()[3];
+        assert_eq!(dl.description(), "dl, length of field (approximate)");
+        assert_eq!(dl.value(), 8.0);
+
+        // The idfs of the terms of a phrase add up
+        let scorer = BM25Similarity::default().scorer(1.0, &collection(), &[term(2), term(4)]);
+        let freq = Explanation::matched(1.0, "phraseFreq".to_string(), vec![]);
+        let explanation = scorer.explain(freq, norm);
+        let idf = &explanation.details()[1];
+        assert_eq!(idf.description(), "idf, sum of:");
+        assert_eq!(idf.details().len(), 2);
+    }
+
+    #[test]
+    fn test_parameters() {
+        // Without length normalization, the norm doesn't matter
+        let scorer = BM25Similarity::new(1.2, 0.0).scorer(1.0, &collection(), &[term(2)]);
+        assert_eq!(scorer.score(2.0, 1), scorer.score(2.0, 100));
+
+        // Without term freq saturation, only idf matters
+        let scorer = BM25Similarity::new(0.0, 0.75).scorer(1.0, &collection(), &[term(2)]);
+        assert_eq!(scorer.score(1.0, 4), scorer.score(5.0, 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "b must be within 0 and 1")]
+    fn test_invalid_b() {
+        BM25Similarity::new(1.2, 1.5);
+    }
+}
@@ -0,0 +1,467 @@
+use std::{fmt, sync::Arc};
+
+use crate::index::{segment_reader::SegmentReader, DirectoryReader, IndexError};
+
+use super::{
+    conjunction_scorer::ConjunctionScorer, disjunction_scorer::DisjunctionScorer, query::ScoreMode,
+    req_excl_scorer::ReqExclScorer, req_opt_sum_scorer::ReqOptSumScorer,
+    scorer::ConstantScoreScorer, Explanation, IndexSearcher, MatchNoDocsQuery, Query, Scorer,
+    Weight,
+};
+
+/// Default maximum number of clauses a query may be rewritten to.
+pub const DEFAULT_MAX_CLAUSE_COUNT: usize = 1024;
+
+/// How a clause of a `BooleanQuery` takes part in matching and scoring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occur {
+    /// Docs must match the clause, which adds to the score.
+    Must,
+    /// Docs should match the clause, which adds to the score when it does.
+    Should,
+    /// Docs must not match the clause.
+    MustNot,
+    /// Docs must match the clause, which doesn't add to the score.
+    Filter,
+}
+
+impl Occur {
+    fn is_scoring(&self) -> bool {
+        matches!(self, Occur::Must | Occur::Should)
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Occur::Must => "+",
+            Occur::Should => "",
+            Occur::MustNot => "-",
+            Occur::Filter => "#",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BooleanClause {
+    pub query: Arc<dyn Query>,
+    pub occur: Occur,
+}
+
+/// Combines sub-queries, scoring matching docs with the sum of the scores of
+/// their matching `Must` and `Should` clauses.
+///
+/// Without any required clause, docs must match at least one `Should` clause.
+/// A query of only `MustNot` clauses matches nothing.
+#[derive(Debug, Clone, Default)]
+pub struct BooleanQuery {
+    clauses: Vec<BooleanClause>,
+    minimum_should_match: usize,
+}
+
+impl BooleanQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, query: Arc<dyn Query>, occur: Occur) {
+        self.clauses.push(BooleanClause { query, occur });
+    }
+
+    pub fn clauses(&self) -> &[BooleanClause] {
+        &self.clauses
+    }
+
+    /// Minimum number of `Should` clauses docs must match, 0 by default.
+    pub fn set_minimum_should_match(&mut self, minimum_should_match: usize) {
+        self.minimum_should_match = minimum_should_match;
+    }
+
+    pub fn minimum_should_match(&self) -> usize {
+        self.minimum_should_match
+    }
+}
+
+impl Query for BooleanQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        if self.clauses.is_empty() {
+            return Ok(Some(Arc::new(MatchNoDocsQuery)));
+        }
+
+        // A single scoring clause is run on its own
+        if let [clause] = self.clauses.as_slice() {
+            if self.minimum_should_match == 0 && clause.occur.is_scoring() {
+                return Ok(Some(clause.query.clone()));
+            }
+        }
+
+        let mut rewritten = BooleanQuery::new();
+        rewritten.set_minimum_should_match(self.minimum_should_match);
+        let mut changed = false;
+        for clause in &self.clauses {
+            let query = match clause.query.rewrite(reader)? {
+                Some(query) => {
+                    changed = true;
+                    query
+                }
+                None => clause.query.clone(),
+            };
+            rewritten.add(query, clause.occur);
+        }
+
+        Ok(changed.then(|| Arc::new(rewritten) as Arc<dyn Query>))
+    }
+
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        let mut weights = Vec::with_capacity(self.clauses.len());
+        for clause in &self.clauses {
+            let weight = if clause.occur.is_scoring() {
+                clause.query.create_weight(searcher, score_mode, boost)?
+            } else {
+                clause
+                    .query
+                    .create_weight(searcher, ScoreMode::CompleteNoScores, 1.0)?
+            };
+            weights.push((weight, clause.occur));
+        }
+
+        Ok(Box::new(BooleanWeight {
+            weights,
+            minimum_should_match: self.minimum_should_match,
+            score_mode,
+        }))
+    }
+}
+
+impl fmt::Display for BooleanQuery {
+    /// Nested boolean queries are displayed in parentheses.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nested = f.alternate() || self.minimum_should_match > 0;
+        if nested {
+            write!(f, "(")?;
+        }
+
+        for (i, clause) in self.clauses.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}{:#}", clause.occur.prefix(), clause.query)?;
+        }
+
+        if nested {
+            write!(f, ")")?;
+        }
+        if self.minimum_should_match > 0 {
+            write!(f, "~{}", self.minimum_should_match)?;
+        }
+        Ok(())
+    }
+}
+
+struct BooleanWeight {
+    weights: Vec<(Box<dyn Weight>, Occur)>,
+    minimum_should_match: usize,
+    score_mode: ScoreMode,
+}
+
+impl Weight for BooleanWeight {
+    fn scorer<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+        let mut required: Vec<Box<dyn Scorer + 'a>> = Vec::new();
+        let mut optional = Vec::new();
+        let mut prohibited = Vec::new();
+
+        for (weight, occur) in &self.weights {
+            let scorer = weight.scorer(reader)?;
+            match (occur, scorer) {
+                (Occur::Must, Some(scorer)) => required.push(scorer),
+                (Occur::Filter, Some(scorer)) => {
+                    required.push(Box::new(ConstantScoreScorer::new(scorer, 0.0)))
+                }
+                (Occur::Must | Occur::Filter, None) => return Ok(None),
+                (Occur::Should, Some(scorer)) => optional.push(scorer),
+                (Occur::MustNot, Some(scorer)) => prohibited.push(scorer),
+                (Occur::Should | Occur::MustNot, None) => {}
+            }
+        }
+
+        if optional.len() < self.minimum_should_match {
+            return Ok(None);
+        }
+
+        // Enough optional clauses must match, so they become one more required clause
+        if self.minimum_should_match > 0 && !required.is_empty() {
+            let disjunction = DisjunctionScorer::new(optional, self.minimum_should_match);
+            required.push(Box::new(disjunction));
+            optional = Vec::new();
+        }
+
+        let mut scorer: Box<dyn Scorer + 'a> = if required.is_empty() {
+            if optional.is_empty() {
+                return Ok(None);
+            }
+            Box::new(DisjunctionScorer::new(optional, self.minimum_should_match))
+        } else {
+            let req = if required.len() == 1 {
+                required.pop().unwrap()
+            } else {
+                Box::new(ConjunctionScorer::new(required))
+            };
+
+            // Optional clauses only add to the score of required matches
+            if optional.is_empty() || !self.score_mode.needs_scores() {
+                req
+            } else {
+                let opt = Box::new(DisjunctionScorer::new(optional, 1));
+                Box::new(ReqOptSumScorer::new(req, opt))
+            }
+        };
+
+        if !prohibited.is_empty() {
+            let excl = Box::new(DisjunctionScorer::new(prohibited, 1));
+            scorer = Box::new(ReqExclScorer::new(scorer, excl));
+        }
+
+        Ok(Some(scorer))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: u32) -> Result<Explanation, IndexError> {
+        let mut details = Vec::new();
+        let mut sum = 0.0;
+        let mut failed = false;
+        let mut required = false;
+        let mut optional_matches = 0;
+
+        for (weight, occur) in &self.weights {
+            let explanation = weight.explain(reader, doc)?;
+            match (occur, explanation.is_match()) {
+                (Occur::Must, true) => {
+                    required = true;
+                    sum += explanation.value();
+                    details.push(explanation);
+                }
+                (Occur::Filter, true) => {
+                    required = true;
+                    details.push(Explanation::matched(
+                        0.0,
+                        "match on required clause, product of:".to_string(),
+                        vec![
+                            Explanation::matched(0.0, "Filter clause".to_string(), vec![]),
+                            explanation,
+                        ],
+                    ));
+                }
+                (Occur::Should, true) => {
+                    optional_matches += 1;
+                    sum += explanation.value();
+                    details.push(explanation);
+                }
+                (Occur::MustNot, true) => {
+                    failed = true;
+                    details.push(Explanation::no_match(
+                        "match on prohibited clause".to_string(),
+                        vec![explanation],
+                    ));
+                }
+                (Occur::Must | Occur::Filter, false) => {
+                    failed = true;
+                    details.push(Explanation::no_match(
+                        "no match on required clause".to_string(),
+                        vec![explanation],
+                    ));
+                }
+                (Occur::Should | Occur::MustNot, false) => {}
+            }
+        }
+
+        if failed {
+            return Ok(Explanation::no_match(
+                "Failure to meet condition(s) of required/prohibited clause(s)".to_string(),
+                details,
+            ));
+        }
+        if optional_matches < self.minimum_should_match {
+            return Ok(Explanation::no_match(
+                format!(
+                    "Failure to match minimum number of optional clauses: {}",
+                    self.minimum_should_match
+                ),
+                details,
+            ));
+        }
+        if !required && optional_matches == 0 {
+            return Ok(Explanation::no_match(
+                "No matching clauses".to_string(),
+                details,
+            ));
+        }
+        Ok(Explanation::matched(sum, "sum of:".to_string(), details))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        index::Term,
+        search::TermQuery,
+        test_util::reader,
+    };
+
+    use super::*;
+
+    const SEGMENTS: &[&[&str]] = &[
+        &["red apple", "green apple pie", "red cherry pie"],
+        &["green pear", "red apple pie", "cherry"],
+    ];
+
+    fn body(text: &str) -> Arc<dyn Query> {
+        Arc::new(TermQuery::new(Term::new(
+            "body".to_string(),
+            text.to_string(),
+        )))
+    }
+
+    fn query(clauses: &[(&str, Occur)]) -> BooleanQuery {
+        let mut query = BooleanQuery::new();
+        for (text, occur) in clauses {
+            query.add(body(text), *occur);
+        }
+        query
+    }
+
+    fn ids(searcher: &IndexSearcher, query: &dyn Query) -> Vec<String> {
+        let mut ids: Vec<String> = searcher
+            .search(query, 10)
+            .unwrap()
+            .score_docs
+            .iter()
+            .map(|sd| searcher.doc(sd.doc).get("id").unwrap().to_string())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn score(searcher: &IndexSearcher, query: &dyn Query, id: &str) -> f32 {
+        let top_docs = searcher.search(query, 10).unwrap();
+        top_docs
+            .score_docs
+            .iter()
+            .find(|sd| searcher.doc(sd.doc).get("id").unwrap() == id)
+            .unwrap()
+            .score
+    }
+
+    #[test]
+    fn test_occur() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        use Occur::*;
+        let q = query(&[("red", Must), ("apple", Must)]);
+        assert_eq!(ids(&searcher, &q), vec!["1", "5"]);
+
+        let q = query(&[("pear", Should), ("cherry", Should)]);
+        assert_eq!(ids(&searcher, &q), vec!["3", "4", "6"]);
+
+        let q = query(&[("red", Must), ("pie", Should)]);
+        assert_eq!(ids(&searcher, &q), vec!["1", "3", "5"]);
+
+        let q = query(&[("pie", Must), ("apple", MustNot)]);
+        assert_eq!(ids(&searcher, &q), vec!["3"]);
+
+        let q = query(&[("pie", Filter), ("red", Should), ("green", Should)]);
+        assert_eq!(ids(&searcher, &q), vec!["2", "3", "5"]);
+
+        // A missing required term matches nothing, a missing optional or prohibited one is ignored
+        let q = query(&[("red", Must), ("missing", Must)]);
+        assert!(ids(&searcher, &q).is_empty());
+        let q = query(&[("red", Must), ("missing", Should), ("missing", MustNot)]);
+        assert_eq!(ids(&searcher, &q), vec!["1", "3", "5"]);
+
+        // Pure negative and empty queries match nothing
+        let q = query(&[("red", MustNot)]);
+        assert!(ids(&searcher, &q).is_empty());
+        assert!(ids(&searcher, &BooleanQuery::new()).is_empty());
+
+        // Nested queries
+        let mut q = query(&[("pie", Must)]);
+        q.add(
+            Arc::new(query(&[("green", Should), ("cherry", Should)])),
+            Must,
+        );
+        assert_eq!(ids(&searcher, &q), vec!["2", "3"]);
+        assert_eq!(searcher.count(&q).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_minimum_should_match() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        use Occur::*;
+        let mut q = query(&[("red", Should), ("apple", Should), ("pie", Should)]);
+        q.set_minimum_should_match(2);
+        assert_eq!(ids(&searcher, &q), vec!["1", "2", "3", "5"]);
+        q.set_minimum_should_match(3);
+        assert_eq!(ids(&searcher, &q), vec!["5"]);
+        q.set_minimum_should_match(4);
+        assert!(ids(&searcher, &q).is_empty());
+
+        let mut q = query(&[("pie", Must), ("red", Should), ("cherry", Should)]);
+        q.set_minimum_should_match(2);
+        assert_eq!(ids(&searcher, &q), vec!["3"]);
+    }
+
+    #[test]
+    fn test_scores() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        use Occur::*;
+        let red = score(&searcher, body("red").as_ref(), "5");
+        let pie = score(&searcher, body("pie").as_ref(), "5");
+
+        // Scores of matching scoring clauses are summed
+        let q = query(&[("red", Must), ("pie", Should)]);
+        assert!((score(&searcher, &q, "5") - (red + pie)).abs() < 1e-6);
+        assert_eq!(
+            score(&searcher, &q, "1"),
+            score(&searcher, body("red").as_ref(), "1")
+        );
+
+        // Filter and prohibited clauses don't score
+        let q = query(&[("red", Must), ("pie", Filter), ("green", MustNot)]);
+        assert_eq!(score(&searcher, &q, "5"), red);
+        let q = query(&[("pie", Filter)]);
+        assert_eq!(score(&searcher, &q, "5"), 0.0);
+    }
+
+    #[test]
+    fn test_display() {
+        use Occur::*;
+        let mut q = query(&[
+            ("red", Must),
+            ("pie", Should),
+            ("green", MustNot),
+            ("apple", Filter),
+        ]);
+        assert_eq!(q.to_string(), "+body:red body:pie -body:green #body:apple");
+
+        let mut nested = query(&[("a", Should), ("b", Should)]);
+        nested.set_minimum_should_match(1);
+        q.add(Arc::new(nested), Must);
+        q.add(Arc::new(query(&[("c", Should), ("d", Should)])), MustNot);
+        assert_eq!(
+            q.to_string(),
+            "+body:red body:pie -body:green #body:apple +(body:a body:b)~1 -(body:c body:d)"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::{fmt, sync::Arc};
+
+use crate::index::{DirectoryReader, IndexError};
+
+use super::{query::ScoreMode, IndexSearcher, Query, Weight};
+
+/// Multiplies the scores of a query by a boost, like `tests^2` to weigh a clause more.
+#[derive(Debug, Clone)]
+pub struct BoostQuery {
+    query: Arc<dyn Query>,
+    boost: f32,
+}
+
+impl BoostQuery {
+    pub fn new(query: Arc<dyn Query>, boost: f32) -> Self {
+        assert!(
+            boost.is_finite() && boost >= 0.0,
+            "boost must be a non-negative number"
+        );
+
+        Self { query, boost }
+    }
+
+    pub fn query(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
+}
+
+impl Query for BoostQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        if self.boost == 1.0 {
+            return Ok(Some(self.query.clone()));
+        }
+
+        Ok(self
+            .query
+            .rewrite(reader)?
+            .map(|query| Arc::new(BoostQuery::new(query, self.boost)) as Arc<dyn Query>))
+    }
+
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        self.query
+            .create_weight(searcher, score_mode, boost * self.boost)
+    }
+}
+
+impl fmt::Display for BoostQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}^{:?}", self.query, self.boost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::{IndexWriter, Term},
+        search::{BooleanQuery, Occur, TermQuery},
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_boost_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+        for body in ["unit tests", "integration tests"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            writer.add_document(doc).unwrap();
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        let body = |text: &str| -> Arc<dyn Query> {
+            Arc::new(TermQuery::new(Term::new(
+                "body".to_string(),
+                text.to_string(),
+            )))
+        };
+        let score = |query: &dyn Query| searcher.search(query, 1).unwrap().score_docs[0].score;
+
+        let boosted = BoostQuery::new(body("unit"), 2.5);
+        assert!((score(&boosted) - 2.5 * score(body("unit").as_ref())).abs() < 1e-6);
+        assert_eq!(boosted.to_string(), "body:unit^2.5");
+
+        let mut query = BooleanQuery::new();
+        query.add(body("unit"), Occur::Should);
+        query.add(body("integration"), Occur::Should);
+        let boosted = BoostQuery::new(Arc::new(query), 2.0);
+        assert_eq!(boosted.to_string(), "(body:unit body:integration)^2.0");
+        assert_eq!(searcher.count(&boosted).unwrap(), 2);
+
+        let unboosted = BoostQuery::new(body("unit"), 1.0);
+        let rewritten = searcher.rewrite(&unboosted).unwrap().unwrap();
+        assert_eq!(rewritten.to_string(), "body:unit");
+    }
+}
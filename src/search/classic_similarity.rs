@@ -0,0 +1,165 @@
+use crate::index::norms;
+
+use super::{
+    similarity::{CollectionStatistics, SimScorer, Similarity, TermStatistics},
+    Explanation,
+};
+
+/// Lucene's classic TF-IDF similarity, to compare against `BM25Similarity`.
+///
+/// Scores `sqrt(freq) * idf^2 * boost / sqrt(field length)`, so term freq doesn't saturate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClassicSimilarity;
+
+impl ClassicSimilarity {
+    /// `1 + ln((doc_count + 1) / (doc_freq + 1))`
+    pub fn idf(doc_freq: u64, doc_count: u64) -> f32 {
+        (1.0 + ((doc_count as f64 + 1.0) / (doc_freq as f64 + 1.0)).ln()) as f32
+    }
+
+    pub fn tf(freq: f32) -> f32 {
+        freq.sqrt()
+    }
+
+    pub fn length_norm(length: u32) -> f32 {
+        if length == 0 {
+            return 0.0;
+        }
+        1.0 / (length as f32).sqrt()
+    }
+}
+
+impl Similarity for ClassicSimilarity {
+    fn scorer(
+        &self,
+        boost: f32,
+        collection: &CollectionStatistics,
+        terms: &[TermStatistics],
+    ) -> Box<dyn SimScorer> {
+        let mut idfs: Vec<Explanation> = terms
+            .iter()
+            .map(|t| {
+                Explanation::matched(
+                    Self::idf(t.doc_freq, collection.doc_count),
+                    "idf, computed as 1 + log((N + 1) / (n + 1)) from:".to_string(),
+                    vec![
+                        Explanation::matched(
+                            t.doc_freq as f32,
+                            "n, number of documents containing term".to_string(),
+                            vec![],
+                        ),
+                        Explanation::matched(
+                            collection.doc_count as f32,
+                            "N, total number of documents with field".to_string(),
+                            vec![],
+                        ),
+                    ],
+                )
+            })
+            .collect();
+        let idf = match idfs.len() {
+            1 => idfs.pop().unwrap(),
+            _ => {
+                let idf = idfs.iter().map(|e| e.value()).sum();
+                Explanation::matched(idf, "idf, sum of:".to_string(), idfs)
+            }
+        };
+
+        let mut cache = [0.0; 256];
+        for (norm, value) in cache.iter_mut().enumerate() {
+            *value = Self::length_norm(norms::byte4_to_int(norm as u8));
+        }
+
+        Box::new(ClassicScorer {
+            boost,
+            weight: boost * idf.value() * idf.value(),
+            idf,
+            cache,
+        })
+    }
+}
+
+struct ClassicScorer {
+    boost: f32,
+    idf: Explanation,
+    weight: f32,
+    cache: [f32; 256],
+}
+
+impl SimScorer for ClassicScorer {
+    fn score(&self, freq: f32, norm: u8) -> f32 {
+        ClassicSimilarity::tf(freq) * self.weight * self.cache[norm as usize]
+    }
+
+    fn explain(&self, freq: Explanation, norm: u8) -> Explanation {
+        let value = freq.value();
+        let length = norms::byte4_to_int(norm);
+
+        let tf = Explanation::matched(
+            ClassicSimilarity::tf(value),
+            "tf, computed as sqrt(freq) from:".to_string(),
+            vec![freq],
+        );
+        let length_norm = Explanation::matched(
+            self.cache[norm as usize],
+            "norm, computed as 1 / sqrt(dl) from:".to_string(),
+            vec![Explanation::matched(
+                length as f32,
+                "dl, length of field (approximate)".to_string(),
+                vec![],
+            )],
+        );
+
+        Explanation::matched(
+            self.score(value, norm),
+            format!(
+                "score(freq={:?}), computed as boost * idf^2 * tf * norm from:",
+                value
+            ),
+            vec![
+                Explanation::matched(self.boost, "boost".to_string(), vec![]),
+                self.idf.clone(),
+                tf,
+                length_norm,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::Term;
+
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        let collection = CollectionStatistics {
+            field: "body".to_string(),
+            max_doc: 10,
+            doc_count: 9,
+            sum_total_term_freq: 40,
+            sum_doc_freq: 30,
+        };
+        let term = TermStatistics {
+            term: Term::new("body".to_string(), "tests".to_string()),
+            doc_freq: 4,
+            total_term_freq: 6,
+        };
+
+        let scorer = ClassicSimilarity.scorer(1.0, &collection, &[term]);
+
+        let idf = 1.0 + 2.0f32.ln();
+        assert!((scorer.score(4.0, 4) - 2.0 * idf * idf * 0.5).abs() < 1e-6);
+
+        let freq = Explanation::matched(4.0, "freq".to_string(), vec![]);
+        let explanation = scorer.explain(freq, 4);
+        assert_eq!(explanation.value(), scorer.score(4.0, 4));
+        assert!((explanation.details()[1].value() - idf).abs() < 1e-6);
+        assert_eq!(explanation.details()[2].value(), 2.0);
+        assert_eq!(explanation.details()[3].value(), 0.5);
+
+        // Term freq doesn't saturate
+        assert!(scorer.score(100.0, 4) > 4.0 * scorer.score(4.0, 4));
+    }
+}
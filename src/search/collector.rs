@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use crate::{
+    index::{segment_reader::SegmentReader, IndexError},
+    util::PriorityQueue,
+};
+
+use super::{FieldDoc, ScoreDoc, Sort, TopDocs, TopFieldDocs};
+
+/// Receives the live docs matching a query, one segment at a time.
+pub trait Collector {
+    /// Whether `collect` needs scores, scoring is skipped otherwise.
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    /// Called before the docs of each segment, with the first doc of the segment.
+    fn set_next_reader(&mut self, _reader: &Arc<SegmentReader>, _doc_base: u32) {}
+
+    /// Called for each matching doc of the segment, in increasing order.
+    /// `doc` is the doc of the segment, the score is 0 when scores aren't needed.
+    fn collect(&mut self, doc: u32, score: f32);
+}
+
+/// Counts matching docs.
+#[derive(Debug, Default)]
+pub struct TotalHitCountCollector {
+    total_hits: u64,
+}
+
+impl TotalHitCountCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn total_hits(&self) -> u64 {
+        self.total_hits
+    }
+}
+
+impl Collector for TotalHitCountCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect(&mut self, _doc: u32, _score: f32) {
+        self.total_hits += 1;
+    }
+}
+
+type ScoreDocQueue = PriorityQueue<ScoreDoc, fn(&ScoreDoc, &ScoreDoc) -> bool>;
+
+/// Collects the `k` best scoring docs, ties go to the smaller doc.
+pub struct TopScoreDocCollector {
+    queue: ScoreDocQueue,
+    total_hits: u64,
+    doc_base: u32,
+    after: Option<ScoreDoc>,
+}
+
+impl TopScoreDocCollector {
+    pub fn new(k: usize) -> Self {
+        Self {
+            queue: PriorityQueue::new(k, Self::less_than),
+            total_hits: 0,
+            doc_base: 0,
+            after: None,
+        }
+    }
+
+    /// Collects the `k` best docs ranked after a hit of a previous page.
+    /// All matching docs still count in the total hits.
+    pub fn with_after(k: usize, after: ScoreDoc) -> Self {
+        Self {
+            after: Some(after),
+            ..Self::new(k)
+        }
+    }
+
+    fn less_than(a: &ScoreDoc, b: &ScoreDoc) -> bool {
+        a.score < b.score || (a.score == b.score && a.doc > b.doc)
+    }
+
+    pub fn top_docs(self) -> TopDocs {
+        TopDocs::new(self.total_hits, self.queue.into_sorted_vec())
+    }
+}
+
+impl Collector for TopScoreDocCollector {
+    fn set_next_reader(&mut self, _reader: &Arc<SegmentReader>, doc_base: u32) {
+        self.doc_base = doc_base;
+    }
+
+    fn collect(&mut self, doc: u32, score: f32) {
+        self.total_hits += 1;
+
+        let hit = ScoreDoc::new(self.doc_base + doc, score);
+        if self
+            .after
+            .is_some_and(|after| !Self::less_than(&hit, &after))
+        {
+            return;
+        }
+        self.queue.insert_with_overflow(hit);
+    }
+}
+
+type FieldDocQueue = PriorityQueue<FieldDoc, Box<dyn Fn(&FieldDoc, &FieldDoc) -> bool>>;
+
+/// Collects the `k` first docs in the order of a sort.
+pub struct TopFieldCollector {
+    sort: Sort,
+    queue: FieldDocQueue,
+    total_hits: u64,
+    reader: Option<Arc<SegmentReader>>,
+    doc_base: u32,
+    after: Option<FieldDoc>,
+}
+
+impl TopFieldCollector {
+    pub fn new(sort: Sort, k: usize) -> Self {
+        let order = sort.clone();
+        Self {
+            sort,
+            queue: PriorityQueue::new(k, Box::new(move |a, b| order.compare(a, b).is_gt())),
+            total_hits: 0,
+            reader: None,
+            doc_base: 0,
+            after: None,
+        }
+    }
+
+    /// Collects the `k` first docs sorted after a hit of a previous page with the same sort.
+    /// All matching docs still count in the total hits.
+    /// Fails if the hit doesn't have the values of the sort, like hits of another sort.
+    pub fn with_after(sort: Sort, k: usize, after: FieldDoc) -> Result<Self, IndexError> {
+        if !sort.accepts(&after.fields) {
+            return Err(IndexError::IllegalArgument(format!(
+                "Hit after doesn't have the values of sort {}",
+                sort
+            )));
+        }
+
+        Ok(Self {
+            after: Some(after),
+            ..Self::new(sort, k)
+        })
+    }
+
+    pub fn top_docs(self) -> TopFieldDocs {
+        TopFieldDocs::new(self.total_hits, self.queue.into_sorted_vec())
+    }
+}
+
+impl Collector for TopFieldCollector {
+    fn needs_scores(&self) -> bool {
+        self.sort.needs_scores()
+    }
+
+    fn set_next_reader(&mut self, reader: &Arc<SegmentReader>, doc_base: u32) {
+        self.reader = Some(reader.clone());
+        self.doc_base = doc_base;
+    }
+
+    fn collect(&mut self, doc: u32, score: f32) {
+        self.total_hits += 1;
+
+        let reader = self.reader.as_ref().expect("No segment to collect from");
+        let fields = self
+            .sort
+            .fields()
+            .iter()
+            .map(|field| field.value(reader, doc, self.doc_base, score))
+            .collect();
+
+        let hit = FieldDoc::new(self.doc_base + doc, score, fields);
+        if let Some(after) = &self.after {
+            if !self.sort.compare(&hit, after).is_gt() {
+                return;
+            }
+        }
+        self.queue.insert_with_overflow(hit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::SortValue;
+
+    use super::*;
+
+    #[test]
+    fn test_top_score_doc_collector() {
+        let mut collector = TopScoreDocCollector::new(3);
+
+        for (doc, score) in [(0, 1.0), (1, 3.0), (2, 2.0), (3, 3.0)] {
+            collector.collect(doc, score);
+        }
+        collector.doc_base = 10;
+        collector.collect(0, 2.0);
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits, 5);
+        assert_eq!(
+            top_docs.score_docs,
+            vec![
+                ScoreDoc::new(1, 3.0),
+                ScoreDoc::new(3, 3.0),
+                ScoreDoc::new(2, 2.0)
+            ]
+        );
+        assert_eq!(top_docs.max_score(), Some(3.0));
+    }
+
+    #[test]
+    fn test_after() {
+        let mut collector = TopScoreDocCollector::with_after(2, ScoreDoc::new(1, 3.0));
+
+        for (doc, score) in [(0, 1.0), (1, 3.0), (2, 2.0), (3, 3.0), (4, 4.0)] {
+            collector.collect(doc, score);
+        }
+
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits, 5);
+        assert_eq!(
+            top_docs.score_docs,
+            vec![ScoreDoc::new(3, 3.0), ScoreDoc::new(2, 2.0)]
+        );
+    }
+
+    #[test]
+    fn test_field_after() {
+        let after = FieldDoc::new(1, 0.0, vec![SortValue::Doc(1)]);
+        assert!(TopFieldCollector::with_after(Sort::index_order(), 2, after).is_ok());
+
+        // Hits of another sort are rejected
+        let after = FieldDoc::new(1, 0.0, vec![SortValue::Score(1.0)]);
+        assert!(matches!(
+            TopFieldCollector::with_after(Sort::index_order(), 2, after),
+            Err(IndexError::IllegalArgument(_))
+        ));
+    }
+}
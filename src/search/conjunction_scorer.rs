@@ -0,0 +1,85 @@
+use super::{DocIdSetIterator, Scorer, NO_MORE_DOCS};
+
+/// Matches docs all sub-scorers match, scored with the sum of their scores.
+///
+/// The cheapest sub-scorer leads, the others are advanced to its docs.
+pub struct ConjunctionScorer<'a> {
+    /// Sub-scorers by increasing cost.
+    scorers: Vec<Box<dyn Scorer + 'a>>,
+    doc: u32,
+    started: bool,
+}
+
+impl<'a> ConjunctionScorer<'a> {
+    pub fn new(mut scorers: Vec<Box<dyn Scorer + 'a>>) -> Self {
+        assert!(
+            !scorers.is_empty(),
+            "A conjunction needs at least one scorer"
+        );
+        scorers.sort_by_key(|s| s.cost());
+
+        Self {
+            scorers,
+            doc: 0,
+            started: false,
+        }
+    }
+
+    /// Positions the others on the first doc at or after the lead's, until they agree.
+    fn do_next(&mut self, mut doc: u32) -> u32 {
+        'lead: while doc != NO_MORE_DOCS {
+            for other in self.scorers[1..].iter_mut() {
+                let mut other_doc = other.doc_id();
+                if other_doc < doc {
+                    other_doc = other.advance(doc);
+                }
+
+                if other_doc > doc {
+                    doc = self.scorers[0].advance(other_doc);
+                    continue 'lead;
+                }
+            }
+            break;
+        }
+
+        self.doc = doc;
+        doc
+    }
+}
+
+impl DocIdSetIterator for ConjunctionScorer<'_> {
+    fn doc_id(&self) -> u32 {
+        self.doc
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        if !self.started {
+            return self.advance(0);
+        }
+
+        let doc = self.scorers[0].next_doc();
+        self.do_next(doc)
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        if !self.started {
+            self.started = true;
+            for other in self.scorers[1..].iter_mut() {
+                other.advance(target);
+            }
+        }
+
+        let doc = self.scorers[0].advance(target);
+        self.do_next(doc)
+    }
+
+    fn cost(&self) -> u64 {
+        self.scorers[0].cost()
+    }
+}
+
+impl Scorer for ConjunctionScorer<'_> {
+    fn score(&mut self) -> f32 {
+        self.scorers.iter_mut().map(|s| s.score()).sum()
+    }
+}
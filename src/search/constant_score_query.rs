@@ -0,0 +1,82 @@
+use std::{fmt, sync::Arc};
+
+use crate::index::{segment_reader::SegmentReader, DirectoryReader, IndexError};
+
+use super::{
+    query::ScoreMode, scorer::ConstantScoreScorer, Explanation, IndexSearcher, Query, Scorer,
+    Weight,
+};
+
+/// Matches the docs of a query, all with a score equal to the boost.
+/// The wrapped query is run without scores.
+#[derive(Debug)]
+pub struct ConstantScoreQuery {
+    query: Arc<dyn Query>,
+}
+
+impl ConstantScoreQuery {
+    pub fn new(query: Arc<dyn Query>) -> Self {
+        Self { query }
+    }
+
+    pub fn query(&self) -> &dyn Query {
+        self.query.as_ref()
+    }
+}
+
+impl Query for ConstantScoreQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        Ok(self
+            .query
+            .rewrite(reader)?
+            .map(|query| Arc::new(ConstantScoreQuery::new(query)) as Arc<dyn Query>))
+    }
+
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        let weight = self
+            .query
+            .create_weight(searcher, ScoreMode::CompleteNoScores, 1.0)?;
+
+        Ok(Box::new(ConstantScoreWeight {
+            description: self.to_string(),
+            weight,
+            boost,
+        }))
+    }
+}
+
+impl fmt::Display for ConstantScoreQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ConstantScore({})", self.query)
+    }
+}
+
+struct ConstantScoreWeight {
+    description: String,
+    weight: Box<dyn Weight>,
+    boost: f32,
+}
+
+impl Weight for ConstantScoreWeight {
+    fn scorer<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+        Ok(self.weight.scorer(reader)?.map(|scorer| {
+            Box::new(ConstantScoreScorer::new(scorer, self.boost)) as Box<dyn Scorer + 'a>
+        }))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: u32) -> Result<Explanation, IndexError> {
+        let explanation = self.weight.explain(reader, doc)?;
+        Ok(match explanation.is_match() {
+            true => Explanation::matched(self.boost, self.description.clone(), vec![]),
+            false => Explanation::no_match(self.description.clone(), vec![explanation]),
+        })
+    }
+}
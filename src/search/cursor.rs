@@ -0,0 +1,170 @@
+use thiserror::Error;
+
+/// Error decoding a cursor, which was not encoded by `to_cursor`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid cursor: {0}")]
+pub struct CursorError(pub String);
+
+/// Version of the cursor encoding, first byte of every cursor.
+const VERSION: u8 = 1;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Writes the bytes of a cursor, encoded as URL safe base 64 without padding.
+pub(crate) struct CursorWriter {
+    bytes: Vec<u8>,
+}
+
+impl CursorWriter {
+    pub(crate) fn new(kind: u8) -> Self {
+        Self {
+            bytes: vec![VERSION, kind],
+        }
+    }
+
+    pub(crate) fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub(crate) fn write_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn finish(self) -> String {
+        let mut cursor = String::with_capacity(self.bytes.len().div_ceil(3) * 4);
+        for chunk in self.bytes.chunks(3) {
+            let mut group = [0u8; 3];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+
+            for i in 0..=chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 0x3f;
+                cursor.push(ALPHABET[index as usize] as char);
+            }
+        }
+        cursor
+    }
+}
+
+/// Reads the bytes of a cursor written by `CursorWriter`.
+pub(crate) struct CursorReader {
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+impl CursorReader {
+    /// Decodes a cursor, checking its version and kind.
+    pub(crate) fn new(cursor: &str, kind: u8) -> Result<Self, CursorError> {
+        if cursor.len() % 4 == 1 {
+            return Err(CursorError("Truncated".to_string()));
+        }
+
+        let mut bytes = Vec::with_capacity(cursor.len() / 4 * 3 + 2);
+        for chunk in cursor.as_bytes().chunks(4) {
+            let mut bits = 0u32;
+            for (i, c) in chunk.iter().enumerate() {
+                let index = ALPHABET
+                    .iter()
+                    .position(|a| a == c)
+                    .ok_or_else(|| CursorError(format!("Unexpected char {:?}", *c as char)))?;
+                bits |= (index as u32) << (18 - 6 * i);
+            }
+            bytes.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+        }
+
+        let mut reader = Self { bytes, pos: 0 };
+        if reader.read_u8()? != VERSION {
+            return Err(CursorError("Unknown version".to_string()));
+        }
+        if reader.read_u8()? != kind {
+            return Err(CursorError("Cursor of another kind of hit".to_string()));
+        }
+        Ok(reader)
+    }
+
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], CursorError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| CursorError("Truncated".to_string()))?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, CursorError> {
+        Ok(self.read::<1>()?[0])
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, CursorError> {
+        Ok(u32::from_le_bytes(self.read()?))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, CursorError> {
+        Ok(u64::from_le_bytes(self.read()?))
+    }
+
+    /// Checks that the whole cursor was read.
+    pub(crate) fn finish(self) -> Result<(), CursorError> {
+        match self.pos == self.bytes.len() {
+            true => Ok(()),
+            false => Err(CursorError("Trailing bytes".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for len in 0..8u8 {
+            let mut writer = CursorWriter::new(7);
+            for i in 0..len {
+                writer.write_u8(i * 37);
+            }
+            let cursor = writer.finish();
+            assert!(cursor
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+
+            let mut reader = CursorReader::new(&cursor, 7).unwrap();
+            for i in 0..len {
+                assert_eq!(reader.read_u8().unwrap(), i * 37);
+            }
+            reader.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut writer = CursorWriter::new(7);
+        writer.write_u32(42);
+        let cursor = writer.finish();
+
+        assert_eq!(
+            CursorReader::new(&cursor, 8).err(),
+            Some(CursorError("Cursor of another kind of hit".to_string()))
+        );
+        assert_eq!(
+            CursorReader::new("AQ*", 7).err(),
+            Some(CursorError("Unexpected char '*'".to_string()))
+        );
+        assert_eq!(
+            CursorReader::new("", 7).err(),
+            Some(CursorError("Truncated".to_string()))
+        );
+        assert_eq!(
+            CursorReader::new(&cursor, 7).unwrap().read_u64().err(),
+            Some(CursorError("Truncated".to_string()))
+        );
+        assert_eq!(
+            CursorReader::new(&cursor, 7).unwrap().finish().err(),
+            Some(CursorError("Trailing bytes".to_string()))
+        );
+    }
+}
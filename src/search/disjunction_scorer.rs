@@ -0,0 +1,90 @@
+use super::{DocIdSetIterator, Scorer, NO_MORE_DOCS};
+
+/// Matches docs at least `min_should_match` sub-scorers match,
+/// scored with the sum of the scores of the matching sub-scorers.
+pub struct DisjunctionScorer<'a> {
+    scorers: Vec<Box<dyn Scorer + 'a>>,
+    min_should_match: usize,
+    doc: u32,
+    started: bool,
+}
+
+impl<'a> DisjunctionScorer<'a> {
+    pub fn new(scorers: Vec<Box<dyn Scorer + 'a>>, min_should_match: usize) -> Self {
+        assert!(
+            !scorers.is_empty(),
+            "A disjunction needs at least one scorer"
+        );
+
+        Self {
+            scorers,
+            min_should_match: min_should_match.max(1),
+            doc: 0,
+            started: false,
+        }
+    }
+
+    /// Moves to the first doc, from the current docs of the sub-scorers,
+    /// that enough of them match.
+    fn do_next(&mut self) -> u32 {
+        loop {
+            let doc = self.scorers.iter().map(|s| s.doc_id()).min().unwrap();
+
+            let matching = self.scorers.iter().filter(|s| s.doc_id() == doc).count();
+            if doc == NO_MORE_DOCS || matching >= self.min_should_match {
+                self.doc = doc;
+                return doc;
+            }
+
+            for scorer in self.scorers.iter_mut().filter(|s| s.doc_id() == doc) {
+                scorer.next_doc();
+            }
+        }
+    }
+}
+
+impl DocIdSetIterator for DisjunctionScorer<'_> {
+    fn doc_id(&self) -> u32 {
+        self.doc
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        if !self.started {
+            return self.advance(0);
+        }
+
+        let doc = self.doc;
+        for scorer in self.scorers.iter_mut().filter(|s| s.doc_id() == doc) {
+            scorer.next_doc();
+        }
+
+        self.do_next()
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        let started = std::mem::replace(&mut self.started, true);
+
+        for scorer in self.scorers.iter_mut() {
+            if !started || scorer.doc_id() < target {
+                scorer.advance(target);
+            }
+        }
+
+        self.do_next()
+    }
+
+    fn cost(&self) -> u64 {
+        self.scorers.iter().map(|s| s.cost()).sum()
+    }
+}
+
+impl Scorer for DisjunctionScorer<'_> {
+    fn score(&mut self) -> f32 {
+        let doc = self.doc;
+        self.scorers
+            .iter_mut()
+            .filter(|s| s.doc_id() == doc)
+            .map(|s| s.score())
+            .sum()
+    }
+}
@@ -0,0 +1,153 @@
+use crate::util::FixedBitSet;
+
+/// Doc ID returned once an iterator is exhausted.
+pub const NO_MORE_DOCS: u32 = u32::MAX;
+
+/// Iterates docs of a segment in increasing order.
+///
+/// The iterator is unpositioned until `next_doc` or `advance`.
+pub trait DocIdSetIterator {
+    /// Current doc, `NO_MORE_DOCS` once exhausted.
+    fn doc_id(&self) -> u32;
+
+    /// Moves to the next doc and returns it, `NO_MORE_DOCS` once exhausted.
+    fn next_doc(&mut self) -> u32;
+
+    /// Moves to the first doc at or after `target` and returns it.
+    /// Always moves forward, `target` must be after the current doc.
+    fn advance(&mut self, target: u32) -> u32 {
+        loop {
+            let doc = self.next_doc();
+            if doc >= target {
+                return doc;
+            }
+        }
+    }
+
+    /// Upper bound of the number of docs, to lead conjunctions with the cheapest iterator.
+    fn cost(&self) -> u64;
+}
+
+/// Iterates all docs of a range.
+pub struct DocIdRange {
+    min: u32,
+    max: u32,
+    doc: Option<u32>,
+}
+
+impl DocIdRange {
+    /// Docs from `min` up to `max`, excluded.
+    pub fn new(min: u32, max: u32) -> Self {
+        Self {
+            min,
+            max,
+            doc: None,
+        }
+    }
+
+    /// All docs of a segment.
+    pub fn all(max_doc: u32) -> Self {
+        Self::new(0, max_doc)
+    }
+}
+
+impl DocIdSetIterator for DocIdRange {
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(self.min)
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        let target = match self.doc {
+            None => self.min,
+            Some(doc) => doc.saturating_add(1),
+        };
+        self.advance(target)
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        let doc = if target < self.max {
+            target.max(self.min)
+        } else {
+            NO_MORE_DOCS
+        };
+        self.doc = Some(doc);
+        doc
+    }
+
+    fn cost(&self) -> u64 {
+        self.max.saturating_sub(self.min) as u64
+    }
+}
+
+/// Iterates the docs set in a bit set.
+pub struct BitSetIterator {
+    bits: FixedBitSet,
+    doc: Option<u32>,
+    cost: u64,
+}
+
+impl BitSetIterator {
+    pub fn new(bits: FixedBitSet) -> Self {
+        let cost = bits.cardinality() as u64;
+        Self {
+            bits,
+            doc: None,
+            cost,
+        }
+    }
+}
+
+impl DocIdSetIterator for BitSetIterator {
+    fn doc_id(&self) -> u32 {
+        self.doc.unwrap_or(0)
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        let target = self.doc.map_or(0, |doc| doc.saturating_add(1));
+        self.advance(target)
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        let doc = self.bits.next_set_bit(target).unwrap_or(NO_MORE_DOCS);
+        self.doc = Some(doc);
+        doc
+    }
+
+    fn cost(&self) -> u64 {
+        self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_id_range() {
+        let mut range = DocIdRange::new(2, 5);
+
+        assert_eq!(range.cost(), 3);
+        assert_eq!(range.next_doc(), 2);
+        assert_eq!(range.advance(4), 4);
+        assert_eq!(range.next_doc(), NO_MORE_DOCS);
+
+        let mut range = DocIdRange::all(3);
+        assert_eq!(range.advance(5), NO_MORE_DOCS);
+        assert_eq!(range.doc_id(), NO_MORE_DOCS);
+    }
+
+    #[test]
+    fn test_bit_set_iterator() {
+        let mut bits = FixedBitSet::new(200);
+        bits.set(3);
+        bits.set(70);
+        bits.set(199);
+        let mut iterator = BitSetIterator::new(bits);
+
+        assert_eq!(iterator.cost(), 3);
+        assert_eq!(iterator.next_doc(), 3);
+        assert_eq!(iterator.advance(4), 70);
+        assert_eq!(iterator.next_doc(), 199);
+        assert_eq!(iterator.next_doc(), NO_MORE_DOCS);
+    }
+}
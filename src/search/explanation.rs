@@ -0,0 +1,335 @@
+use std::fmt::{self, Write};
+
+/// How a doc was scored, as a tree of values computed from the values of their details.
+///
+/// Displays as indented text, one line per value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    matched: bool,
+    value: f32,
+    description: String,
+    details: Vec<Explanation>,
+}
+
+impl Explanation {
+    pub fn matched(value: f32, description: String, details: Vec<Explanation>) -> Self {
+        Self {
+            matched: true,
+            value,
+            description,
+            details,
+        }
+    }
+
+    /// Why a doc doesn't match, with a value of 0.
+    pub fn no_match(description: String, details: Vec<Explanation>) -> Self {
+        Self {
+            matched: false,
+            value: 0.0,
+            description,
+            details,
+        }
+    }
+
+    pub fn is_match(&self) -> bool {
+        self.matched
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn details(&self) -> &[Explanation] {
+        &self.details
+    }
+
+    /// JSON object with `match`, `value`, `description` and `details` keys.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) {
+        json.push_str("{\"match\":");
+        json.push_str(if self.matched { "true" } else { "false" });
+
+        json.push_str(",\"value\":");
+        if self.value.is_finite() {
+            write!(json, "{:?}", self.value).unwrap();
+        } else {
+            json.push_str("null");
+        }
+
+        json.push_str(",\"description\":\"");
+        for c in self.description.chars() {
+            match c {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                '\n' => json.push_str("\\n"),
+                '\r' => json.push_str("\\r"),
+                '\t' => json.push_str("\\t"),
+                c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+                c => json.push(c),
+            }
+        }
+
+        json.push_str("\",\"details\":[");
+        for (i, detail) in self.details.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            detail.write_json(json);
+        }
+        json.push_str("]}");
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{:?} = {}",
+            "",
+            self.value,
+            self.description,
+            indent = depth * 2
+        )?;
+        for detail in &self.details {
+            detail.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        index::{DirectoryReader, IndexError, Term},
+        search::{
+            BooleanQuery, BoostQuery, ClassicSimilarity, ConstantScoreQuery, FuzzyQuery,
+            IndexSearcher, MatchAllDocsQuery, Occur, PhraseQuery, PrefixQuery, Query, TermQuery,
+        },
+        test_util::{index, reader},
+    };
+
+    use super::*;
+
+    fn explanation() -> Explanation {
+        Explanation::matched(
+            3.0,
+            "sum of:".to_string(),
+            vec![
+                Explanation::matched(1.0, "weight(body:\"unit\")".to_string(), vec![]),
+                Explanation::matched(
+                    2.0,
+                    "product of:".to_string(),
+                    vec![Explanation::matched(2.0, "boost".to_string(), vec![])],
+                ),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            explanation().to_string(),
+            "3.0 = sum of:\n  1.0 = weight(body:\"unit\")\n  2.0 = product of:\n    2.0 = boost\n"
+        );
+
+        let no_match = Explanation::no_match("no matching term".to_string(), vec![]);
+        assert!(!no_match.is_match());
+        assert_eq!(no_match.to_string(), "0.0 = no matching term\n");
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            explanation().to_json(),
+            concat!(
+                r#"{"match":true,"value":3.0,"description":"sum of:","details":["#,
+                r#"{"match":true,"value":1.0,"description":"weight(body:\"unit\")","details":[]},"#,
+                r#"{"match":true,"value":2.0,"description":"product of:","details":["#,
+                r#"{"match":true,"value":2.0,"description":"boost","details":[]}]}]}"#
+            )
+        );
+
+        let no_match = Explanation::no_match("tab\there\u{1}".to_string(), vec![]);
+        assert_eq!(
+            no_match.to_json(),
+            r#"{"match":false,"value":0.0,"description":"tab\there\u0001","details":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = index(
+            &temp_dir.path().join("test-index"),
+            &[
+                &["unit tests", "tests and more tests"],
+                &["tests tests tests", "integration", "tests tests"],
+            ],
+        );
+        writer
+            .delete_documents(Term::new("id".to_string(), "5".to_string()))
+            .unwrap();
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+        let term = |text: &str| Term::new("body".to_string(), text.to_string());
+        let term_query = |text: &str| Arc::new(TermQuery::new(term(text))) as Arc<dyn Query>;
+
+        let explanation = searcher.explain(&TermQuery::new(term("tests")), 2).unwrap();
+        assert!(explanation.is_match());
+        let top_docs = searcher.search(&TermQuery::new(term("tests")), 1).unwrap();
+        assert_eq!(top_docs.score_docs[0].doc, 2);
+        assert_eq!(explanation.value(), top_docs.score_docs[0].score);
+        let text = explanation.to_string();
+        assert!(text.starts_with(&format!(
+            "{:?} = weight(body:tests in 0), result of:\n  {:?} = score(freq=3.0), computed as boost * idf * tf from:\n",
+            explanation.value(),
+            explanation.value()
+        )));
+        for description in [
+            "idf, computed as log(1 + (N - n + 0.5) / (n + 0.5)) from:",
+            "n, number of documents containing term",
+            "freq, occurrences of term within document",
+            "dl, length of field (approximate)",
+        ] {
+            assert!(text.contains(description), "{}", description);
+        }
+        assert!(explanation
+            .to_json()
+            .starts_with("{\"match\":true,\"value\":"));
+
+        let no_match = searcher.explain(&TermQuery::new(term("tests")), 3).unwrap();
+        assert!(!no_match.is_match());
+        assert_eq!(
+            no_match.description(),
+            "weight(body:tests in 1), no matching term"
+        );
+
+        let mut query = BooleanQuery::new();
+        query.add(term_query("tests"), Occur::Must);
+        query.add(term_query("unit"), Occur::MustNot);
+        let explanation = searcher.explain(&query, 0).unwrap();
+        assert!(!explanation.is_match());
+        assert_eq!(
+            explanation.details()[1].description(),
+            "match on prohibited clause"
+        );
+
+        assert_eq!(
+            searcher.explain(&MatchAllDocsQuery, 3).unwrap().value(),
+            1.0
+        );
+        assert!(matches!(
+            searcher.explain(&MatchAllDocsQuery, 5),
+            Err(IndexError::IllegalArgument(_))
+        ));
+
+        // The deleted doc matches the query, but not the searcher
+        let explanation = searcher.explain(&TermQuery::new(term("tests")), 4).unwrap();
+        assert!(!explanation.is_match());
+        assert_eq!(explanation.description(), "Document is deleted");
+    }
+
+    #[test]
+    fn test_explain_scores() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(
+            &temp_dir.path().join("test-index"),
+            &[
+                &["unit tests"],
+                &["integration tests for the index"],
+                &["tests of the index writer"],
+                &["the unit of the index"],
+                &["writer tests tests"],
+            ],
+        );
+        let mut searcher = IndexSearcher::new(&reader);
+        let term = |text: &str| Term::new("body".to_string(), text.to_string());
+        let term_query = |text: &str| Arc::new(TermQuery::new(term(text))) as Arc<dyn Query>;
+
+        let mut phrase = PhraseQuery::new();
+        phrase.add(term("the"));
+        phrase.add(term("index"));
+        let mut sloppy = phrase.clone();
+        sloppy.set_slop(2);
+
+        let mut boolean = BooleanQuery::new();
+        boolean.add(term_query("tests"), Occur::Should);
+        boolean.add(term_query("index"), Occur::Should);
+        boolean.add(
+            Arc::new(BoostQuery::new(term_query("unit"), 2.5)),
+            Occur::Should,
+        );
+        boolean.add(term_query("integration"), Occur::MustNot);
+
+        let mut filtered = BooleanQuery::new();
+        filtered.add(term_query("the"), Occur::Must);
+        filtered.add(term_query("writer"), Occur::Filter);
+        filtered.add(Arc::new(sloppy.clone()), Occur::Should);
+
+        let mut minimum = BooleanQuery::new();
+        minimum.add(term_query("tests"), Occur::Should);
+        minimum.add(term_query("writer"), Occur::Should);
+        minimum.add(term_query("unit"), Occur::Should);
+        minimum.set_minimum_should_match(2);
+
+        let queries: Vec<Arc<dyn Query>> = vec![
+            term_query("tests"),
+            Arc::new(phrase),
+            Arc::new(sloppy),
+            Arc::new(boolean),
+            Arc::new(filtered),
+            Arc::new(minimum),
+            Arc::new(PrefixQuery::new(term("writ"))),
+            Arc::new(FuzzyQuery::new(term("tsets"), 2, 0, true)),
+            Arc::new(ConstantScoreQuery::new(term_query("index"))),
+            Arc::new(MatchAllDocsQuery),
+        ];
+
+        for classic in [false, true] {
+            if classic {
+                searcher.set_similarity(ClassicSimilarity);
+            }
+            for query in &queries {
+                let top_docs = searcher.search(query.as_ref(), 10).unwrap();
+                for doc in 0..reader.max_doc() {
+                    let explanation = searcher.explain(query.as_ref(), doc).unwrap();
+                    match top_docs.score_docs.iter().find(|sd| sd.doc == doc) {
+                        Some(hit) => {
+                            assert!(explanation.is_match(), "{} {}\n{}", query, doc, explanation);
+                            assert!(
+                                (explanation.value() - hit.score).abs() < 1e-5,
+                                "{} {} {}\n{}",
+                                query,
+                                doc,
+                                hit.score,
+                                explanation
+                            );
+                        }
+                        None => assert!(
+                            !explanation.is_match(),
+                            "{} {}\n{}",
+                            query,
+                            doc,
+                            explanation
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use crate::{
+    index::{DirectoryReader, IndexError, Term, Terms},
+    util::{levenshtein_automata, Automaton},
+};
+
+use super::{
+    multi_term_query::for_each_term, query::ScoreMode, BooleanQuery, BoostQuery, IndexSearcher,
+    Occur, Query, TermQuery, TermStatistics, Weight,
+};
+
+/// Default maximum number of similar terms a `FuzzyQuery` expands to.
+pub const DEFAULT_MAX_EXPANSIONS: usize = 50;
+
+/// A term of a field similar to the term of a `FuzzyQuery`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarTerm {
+    pub text: String,
+    pub edits: u32,
+    pub doc_freq: u64,
+    pub total_term_freq: u64,
+}
+
+/// Matches docs containing a term within `max_edits` of a term, like `ferocene~1`.
+///
+/// Rewrites to the `max_expansions` closest terms, each boosted by how close it is,
+/// and scored with the blended statistics of all of them so rare misspellings
+/// don't outrank the term itself.
+///
+/// Terms are only read through `Terms`, so any implementation of it can be searched,
+/// like segments written by ferrocene and `MemoryTerms`. Segments written by Lucene
+/// are not, as their postings can't be read.
+#[derive(Debug, Clone)]
+pub struct FuzzyQuery {
+    term: Term,
+    max_edits: u32,
+    prefix_length: usize,
+    transpositions: bool,
+    max_expansions: usize,
+    automaton: Arc<Automaton>,
+    prefix: String,
+}
+
+impl FuzzyQuery {
+    /// Terms must share the first `prefix_length` chars of the term. With `transpositions`,
+    /// swapping two adjacent chars is a single edit.
+    pub fn new(term: Term, max_edits: u32, prefix_length: usize, transpositions: bool) -> Self {
+        let prefix: String = term.text.chars().take(prefix_length).collect();
+        let rest = &term.text[prefix.len()..];
+
+        // Levenshtein automata of at most 2 edits grow linearly with the term
+        let automaton = Automaton::concatenate(vec![
+            Automaton::string(&prefix),
+            levenshtein_automata::automaton(rest, max_edits, transpositions),
+        ])
+        .determinize(usize::MAX)
+        .unwrap();
+
+        Self {
+            prefix: automaton.common_prefix(),
+            automaton: Arc::new(automaton),
+            term,
+            max_edits,
+            prefix_length,
+            transpositions,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn max_edits(&self) -> u32 {
+        self.max_edits
+    }
+
+    pub fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    pub fn transpositions(&self) -> bool {
+        self.transpositions
+    }
+
+    pub fn set_max_expansions(&mut self, max_expansions: usize) {
+        assert!(max_expansions > 0, "max_expansions must be positive");
+        self.max_expansions = max_expansions;
+    }
+
+    pub fn max_expansions(&self) -> usize {
+        self.max_expansions
+    }
+
+    /// Terms within `max_edits` of the term, in term order.
+    pub fn similar_terms(&self, terms: &dyn Terms) -> Vec<SimilarTerm> {
+        let mut similar_terms = Vec::new();
+        for_each_term(terms, &self.automaton, &self.prefix, |terms_enum| {
+            let text = terms_enum.term();
+            similar_terms.push(SimilarTerm {
+                text: text.to_string(),
+                edits: levenshtein_automata::distance(&self.term.text, text, self.transpositions),
+                doc_freq: terms_enum.doc_freq() as u64,
+                total_term_freq: terms_enum.total_term_freq(),
+            });
+        });
+        similar_terms
+    }
+
+    /// `1 - edits / length` of the shorter of both terms.
+    fn boost(&self, similar_term: &SimilarTerm) -> f32 {
+        let length = self
+            .term
+            .text
+            .chars()
+            .count()
+            .min(similar_term.text.chars().count());
+        if similar_term.edits == 0 {
+            return 1.0;
+        }
+        (1.0 - similar_term.edits as f32 / length as f32).max(0.0)
+    }
+}
+
+impl Query for FuzzyQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        if self.max_edits == 0 {
+            return Ok(Some(Arc::new(TermQuery::new(self.term.clone()))));
+        }
+
+        let mut similar_terms: BTreeMap<String, SimilarTerm> = BTreeMap::new();
+        for terms in reader
+            .leaves()
+            .iter()
+            .filter_map(|r| r.terms(&self.term.field))
+        {
+            for similar_term in self.similar_terms(&terms) {
+                similar_terms
+                    .entry(similar_term.text.clone())
+                    .and_modify(|t| {
+                        t.doc_freq += similar_term.doc_freq;
+                        t.total_term_freq += similar_term.total_term_freq;
+                    })
+                    .or_insert(similar_term);
+            }
+        }
+
+        // Closest terms first
+        let mut similar_terms: Vec<(f32, SimilarTerm)> = similar_terms
+            .into_values()
+            .map(|t| (self.boost(&t), t))
+            .collect();
+        similar_terms.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        similar_terms.truncate(self.max_expansions);
+
+        let doc_freq = similar_terms.iter().map(|(_, t)| t.doc_freq).max();
+        let total_term_freq = similar_terms.iter().map(|(_, t)| t.total_term_freq).max();
+
+        let mut query = BooleanQuery::new();
+        for (boost, similar_term) in similar_terms {
+            let term = Term::new(self.term.field.clone(), similar_term.text);
+            let statistics = TermStatistics {
+                term: term.clone(),
+                doc_freq: doc_freq.unwrap(),
+                total_term_freq: total_term_freq.unwrap(),
+            };
+
+            let term_query = Arc::new(TermQuery::with_statistics(term, statistics));
+            query.add(Arc::new(BoostQuery::new(term_query, boost)), Occur::Should);
+        }
+        Ok(Some(Arc::new(query)))
+    }
+
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        let query = self.rewrite(searcher.reader())?.unwrap();
+        query.create_weight(searcher, score_mode, boost)
+    }
+}
+
+impl fmt::Display for FuzzyQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}~{}",
+            self.term.field, self.term.text, self.max_edits
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{index::document_writer::PostingsTable, test_util::reader};
+
+    use super::*;
+
+    const SEGMENTS: &[&[&str]] = &[
+        &["ferrocene", "ferrocene", "ferocene"],
+        &["ferroceen", "iron"],
+    ];
+
+    fn fuzzy(text: &str, max_edits: u32, prefix_length: usize, transpositions: bool) -> FuzzyQuery {
+        let term = Term::new("body".to_string(), text.to_string());
+        FuzzyQuery::new(term, max_edits, prefix_length, transpositions)
+    }
+
+    fn ids(searcher: &IndexSearcher, query: &dyn Query) -> Vec<String> {
+        searcher
+            .search(query, 10)
+            .unwrap()
+            .score_docs
+            .iter()
+            .map(|sd| searcher.doc(sd.doc).get("id").unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        // The rarer misspellings rank after the term itself, the transposition is closer
+        let query = fuzzy("ferrocene", 2, 0, true);
+        assert_eq!(ids(&searcher, &query), vec!["1", "2", "4", "3"]);
+        assert_eq!(query.to_string(), "body:ferrocene~2");
+
+        // Without transpositions, swapped chars take 2 edits
+        assert_eq!(ids(&searcher, &fuzzy("ferrocene", 1, 0, true)).len(), 4);
+        assert_eq!(
+            ids(&searcher, &fuzzy("ferrocene", 1, 0, false)),
+            vec!["1", "2", "3"]
+        );
+
+        // Misspelled queries find the term
+        assert_eq!(
+            ids(&searcher, &fuzzy("ferocene", 1, 0, false)),
+            vec!["3", "1", "2"]
+        );
+        assert_eq!(
+            ids(&searcher, &fuzzy("gerrocene", 1, 0, false)),
+            vec!["1", "2"]
+        );
+        assert!(ids(&searcher, &fuzzy("gerrocene", 1, 1, false)).is_empty());
+        assert_eq!(
+            ids(&searcher, &fuzzy("ferrocene", 0, 0, false)),
+            vec!["1", "2"]
+        );
+
+        let mut query = fuzzy("ferrocene", 2, 0, true);
+        query.set_max_expansions(1);
+        assert_eq!(ids(&searcher, &query), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_similar_terms() {
+        let mut table = PostingsTable::new();
+        for (position, text) in ["ferocene", "iron", "ferrocene", "ferrocene"]
+            .iter()
+            .enumerate()
+        {
+            let term = Arc::new(Term::new("body".to_string(), text.to_string()));
+            table.add(term, position);
+        }
+
+        let query = fuzzy("ferrocene", 1, 2, false);
+        let similar_terms = query.similar_terms(&table.terms("body").unwrap());
+        assert_eq!(
+            similar_terms,
+            vec![
+                SimilarTerm {
+                    text: "ferocene".to_string(),
+                    edits: 1,
+                    doc_freq: 1,
+                    total_term_freq: 1,
+                },
+                SimilarTerm {
+                    text: "ferrocene".to_string(),
+                    edits: 0,
+                    doc_freq: 1,
+                    total_term_freq: 2,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,468 @@
+use std::sync::Arc;
+
+use crate::{
+    document::Document,
+    index::{DirectoryReader, IndexError, Term, Terms},
+};
+
+use super::{
+    similarity::{CollectionStatistics, Similarity, TermStatistics},
+    BM25Similarity, Collector, Explanation, FieldDoc, Query, ScoreDoc, ScoreMode, Sort, TopDocs,
+    TopFieldCollector, TopFieldDocs, TopScoreDocCollector, TotalHitCountCollector, Weight,
+    NO_MORE_DOCS,
+};
+
+/// Runs queries against the segments of a `DirectoryReader`.
+///
+/// Hits are numbered like docs of the reader, across segments.
+/// Deleted and soft-deleted docs never match.
+pub struct IndexSearcher<'a> {
+    reader: &'a DirectoryReader,
+    similarity: Box<dyn Similarity>,
+}
+
+impl<'a> IndexSearcher<'a> {
+    /// Searcher scoring with `BM25Similarity`.
+    pub fn new(reader: &'a DirectoryReader) -> Self {
+        Self {
+            reader,
+            similarity: Box::new(BM25Similarity::default()),
+        }
+    }
+
+    pub fn reader(&self) -> &'a DirectoryReader {
+        self.reader
+    }
+
+    pub fn similarity(&self) -> &dyn Similarity {
+        self.similarity.as_ref()
+    }
+
+    pub fn set_similarity<S>(&mut self, similarity: S)
+    where
+        S: Similarity + 'static,
+    {
+        self.similarity = Box::new(similarity);
+    }
+
+    /// Statistics of a field over all segments, `None` when no doc has terms for it.
+    pub fn collection_statistics(&self, field: &str) -> Option<CollectionStatistics> {
+        let mut statistics = CollectionStatistics {
+            field: field.to_string(),
+            max_doc: self.reader.max_doc() as u64,
+            doc_count: 0,
+            sum_total_term_freq: 0,
+            sum_doc_freq: 0,
+        };
+
+        for terms in self.reader.leaves().iter().filter_map(|r| r.terms(field)) {
+            statistics.doc_count += terms.doc_count() as u64;
+            statistics.sum_total_term_freq += terms.sum_total_term_freq();
+            statistics.sum_doc_freq += terms.sum_doc_freq();
+        }
+
+        (statistics.doc_count > 0).then_some(statistics)
+    }
+
+    /// Statistics of a term over all segments, `None` when no doc has the term.
+    pub fn term_statistics(&self, term: &Term) -> Option<TermStatistics> {
+        let mut statistics = TermStatistics {
+            term: term.clone(),
+            doc_freq: 0,
+            total_term_freq: 0,
+        };
+
+        for term_info in self
+            .reader
+            .leaves()
+            .iter()
+            .filter_map(|r| r.term_info(term))
+        {
+            statistics.doc_freq += term_info.doc_freq as u64;
+            statistics.total_term_freq += term_info.total_term_freq;
+        }
+
+        (statistics.doc_freq > 0).then_some(statistics)
+    }
+
+    /// The `k` best hits of the query, best first.
+    pub fn search(&self, query: &dyn Query, k: usize) -> Result<TopDocs, IndexError> {
+        let mut collector = TopScoreDocCollector::new(k);
+        self.search_with_collector(query, &mut collector)?;
+
+        Ok(collector.top_docs())
+    }
+
+    /// The `k` best hits of the query ranked after a hit of a previous page, best first.
+    ///
+    /// Unlike skipping the hits of previous pages, only `k` hits are kept in memory.
+    pub fn search_after(
+        &self,
+        after: &ScoreDoc,
+        query: &dyn Query,
+        k: usize,
+    ) -> Result<TopDocs, IndexError> {
+        let mut collector = TopScoreDocCollector::with_after(k, *after);
+        self.search_with_collector(query, &mut collector)?;
+
+        Ok(collector.top_docs())
+    }
+
+    /// The `k` first hits of the query in the order of the sort.
+    pub fn search_sorted(
+        &self,
+        query: &dyn Query,
+        k: usize,
+        sort: &Sort,
+    ) -> Result<TopFieldDocs, IndexError> {
+        let mut collector = TopFieldCollector::new(sort.clone(), k);
+        self.search_with_collector(query, &mut collector)?;
+
+        Ok(collector.top_docs())
+    }
+
+    /// The `k` first hits of the query sorted after a hit of a previous page with the same sort.
+    /// Fails if the hit doesn't have the values of the sort, like hits of another sort.
+    pub fn search_after_sorted(
+        &self,
+        after: &FieldDoc,
+        query: &dyn Query,
+        k: usize,
+        sort: &Sort,
+    ) -> Result<TopFieldDocs, IndexError> {
+        let mut collector = TopFieldCollector::with_after(sort.clone(), k, after.clone())?;
+        self.search_with_collector(query, &mut collector)?;
+
+        Ok(collector.top_docs())
+    }
+
+    /// Number of docs matching the query.
+    pub fn count(&self, query: &dyn Query) -> Result<u64, IndexError> {
+        let mut collector = TotalHitCountCollector::new();
+        self.search_with_collector(query, &mut collector)?;
+
+        Ok(collector.total_hits())
+    }
+
+    /// Passes all docs matching the query to the collector, segment by segment.
+    pub fn search_with_collector(
+        &self,
+        query: &dyn Query,
+        collector: &mut dyn Collector,
+    ) -> Result<(), IndexError> {
+        let score_mode = if collector.needs_scores() {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        };
+        let weight = self.create_weight(query, score_mode)?;
+
+        for (reader, &doc_base) in self.reader.leaves().iter().zip(self.reader.doc_bases()) {
+            collector.set_next_reader(reader, doc_base);
+
+            let mut scorer = match weight.scorer(reader)? {
+                Some(scorer) => scorer,
+                None => continue,
+            };
+
+            loop {
+                let doc = scorer.next_doc();
+                if doc == NO_MORE_DOCS {
+                    break;
+                }
+                if !reader.is_live(doc) {
+                    continue;
+                }
+
+                let score = if score_mode.needs_scores() {
+                    scorer.score()
+                } else {
+                    0.0
+                };
+                collector.collect(doc, score);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rewrites the query until it is primitive, `None` when it already is.
+    pub fn rewrite(&self, query: &dyn Query) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        let mut rewritten: Option<Arc<dyn Query>> = None;
+
+        loop {
+            let current = rewritten.as_deref().unwrap_or(query);
+            match current.rewrite(self.reader)? {
+                Some(query) => rewritten = Some(query),
+                None => return Ok(rewritten),
+            }
+        }
+    }
+
+    /// Rewrites the query and prepares it to score segments of this searcher.
+    pub fn create_weight(
+        &self,
+        query: &dyn Query,
+        score_mode: ScoreMode,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        let rewritten = self.rewrite(query)?;
+        rewritten
+            .as_deref()
+            .unwrap_or(query)
+            .create_weight(self, score_mode, 1.0)
+    }
+
+    /// How the query scores a doc, or why it doesn't match it.
+    pub fn explain(&self, query: &dyn Query, doc: u32) -> Result<Explanation, IndexError> {
+        if doc >= self.reader.max_doc() {
+            return Err(IndexError::IllegalArgument(format!(
+                "Doc {} is out of bounds, max doc is {}",
+                doc,
+                self.reader.max_doc()
+            )));
+        }
+
+        let segment = self.reader.doc_bases().partition_point(|&base| base <= doc) - 1;
+        let reader = &self.reader.leaves()[segment];
+        let doc = doc - self.reader.doc_bases()[segment];
+        if !reader.is_live(doc) {
+            return Ok(Explanation::no_match(
+                "Document is deleted".to_string(),
+                vec![],
+            ));
+        }
+
+        let weight = self.create_weight(query, ScoreMode::Complete)?;
+        weight.explain(reader, doc)
+    }
+
+    /// Stored fields of a hit.
+    pub fn doc(&self, doc: u32) -> Document {
+        self.reader.document(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt, sync::Arc};
+
+    use crate::{
+        index::{segment_reader::SegmentReader, PostingsEnum, Term},
+        search::{
+            ConstantScoreQuery, DocIdSetIterator, MatchAllDocsQuery, MatchNoDocsQuery, ScoreDoc,
+            Scorer,
+        },
+        test_util::index,
+    };
+
+    use super::*;
+
+    /// Scores docs containing a term by the term's freq.
+    #[derive(Debug)]
+    struct FreqQuery(Term);
+
+    impl fmt::Display for FreqQuery {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}:{}", self.0.field, self.0.text)
+        }
+    }
+
+    impl Query for FreqQuery {
+        fn create_weight(
+            &self,
+            _searcher: &IndexSearcher,
+            _score_mode: ScoreMode,
+            boost: f32,
+        ) -> Result<Box<dyn Weight>, IndexError> {
+            Ok(Box::new(FreqWeight(self.0.clone(), boost)))
+        }
+    }
+
+    struct FreqWeight(Term, f32);
+
+    impl Weight for FreqWeight {
+        fn scorer<'a>(
+            &'a self,
+            reader: &'a SegmentReader,
+        ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+            Ok(reader
+                .postings_enum(&self.0)
+                .map(|postings| Box::new(FreqScorer(postings, self.1)) as Box<dyn Scorer>))
+        }
+
+        fn explain(&self, reader: &SegmentReader, doc: u32) -> Result<Explanation, IndexError> {
+            if let Some(mut postings) = reader.postings_enum(&self.0) {
+                if postings.advance(doc) == doc {
+                    let score = postings.freq() as f32 * self.1;
+                    return Ok(Explanation::matched(
+                        score,
+                        "freq * boost".to_string(),
+                        vec![],
+                    ));
+                }
+            }
+            Ok(Explanation::no_match(
+                "no matching term".to_string(),
+                vec![],
+            ))
+        }
+    }
+
+    struct FreqScorer<P>(P, f32);
+
+    impl<P: PostingsEnum> DocIdSetIterator for FreqScorer<P> {
+        fn doc_id(&self) -> u32 {
+            self.0.doc_id()
+        }
+
+        fn next_doc(&mut self) -> u32 {
+            self.0.next_doc()
+        }
+
+        fn cost(&self) -> u64 {
+            self.0.cost()
+        }
+    }
+
+    impl<P: PostingsEnum> Scorer for FreqScorer<P> {
+        fn score(&mut self) -> f32 {
+            self.0.freq() as f32 * self.1
+        }
+    }
+
+    /// Rewrites to the query of its term in lower case.
+    #[derive(Debug)]
+    struct LowerCaseQuery(Term);
+
+    impl fmt::Display for LowerCaseQuery {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "lower({}:{})", self.0.field, self.0.text)
+        }
+    }
+
+    impl Query for LowerCaseQuery {
+        fn rewrite(&self, _reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+            let term = Term::new(self.0.field.clone(), self.0.text.to_lowercase());
+            Ok(Some(Arc::new(FreqQuery(term))))
+        }
+
+        fn create_weight(
+            &self,
+            _searcher: &IndexSearcher,
+            _score_mode: ScoreMode,
+            _boost: f32,
+        ) -> Result<Box<dyn Weight>, IndexError> {
+            Err(IndexError::IllegalState(
+                "Query must be rewritten".to_string(),
+            ))
+        }
+    }
+
+    /// Collects hits of each segment, without scores.
+    #[derive(Default)]
+    struct SegmentCollector {
+        hits: Vec<(String, u32)>,
+        segment: String,
+    }
+
+    impl Collector for SegmentCollector {
+        fn needs_scores(&self) -> bool {
+            false
+        }
+
+        fn set_next_reader(&mut self, reader: &Arc<SegmentReader>, _doc_base: u32) {
+            self.segment = reader.name().to_string();
+        }
+
+        fn collect(&mut self, doc: u32, score: f32) {
+            assert_eq!(score, 0.0);
+            self.hits.push((self.segment.clone(), doc));
+        }
+    }
+
+    fn reader(path: &std::path::Path) -> DirectoryReader {
+        let mut writer = index(
+            path,
+            &[
+                &["unit tests", "tests and more tests"],
+                &["tests tests tests", "integration", "tests tests"],
+            ],
+        );
+        writer
+            .delete_documents(Term::new("id".to_string(), "5".to_string()))
+            .unwrap();
+
+        DirectoryReader::open_from_writer(&mut writer).unwrap()
+    }
+
+    #[test]
+    fn test_search() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"));
+        let searcher = IndexSearcher::new(&reader);
+
+        let tests = FreqQuery(Term::new("body".to_string(), "tests".to_string()));
+        let top_docs = searcher.search(&tests, 2).unwrap();
+
+        assert_eq!(top_docs.total_hits, 3);
+        assert_eq!(
+            top_docs.score_docs,
+            vec![ScoreDoc::new(2, 3.0), ScoreDoc::new(1, 2.0)]
+        );
+        assert_eq!(searcher.doc(2).get("id"), Some("3"));
+
+        let top_docs = searcher.search(&MatchAllDocsQuery, 10).unwrap();
+        assert_eq!(top_docs.total_hits, 4);
+        assert_eq!(top_docs.score_docs.len(), 4);
+        assert!(top_docs.score_docs.windows(2).all(|w| w[0].doc < w[1].doc));
+
+        let missing = FreqQuery(Term::new("body".to_string(), "missing".to_string()));
+        assert_eq!(searcher.search(&missing, 10).unwrap().total_hits, 0);
+        assert_eq!(searcher.count(&tests).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_custom_collector() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"));
+        let searcher = IndexSearcher::new(&reader);
+
+        let mut collector = SegmentCollector::default();
+        searcher
+            .search_with_collector(&MatchAllDocsQuery, &mut collector)
+            .unwrap();
+
+        let hits: Vec<(&str, u32)> = collector
+            .hits
+            .iter()
+            .map(|(s, d)| (s.as_str(), *d))
+            .collect();
+        assert_eq!(hits, vec![("_0", 0), ("_0", 1), ("_1", 0), ("_1", 1)]);
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"));
+        let searcher = IndexSearcher::new(&reader);
+
+        let query = LowerCaseQuery(Term::new("body".to_string(), "TESTS".to_string()));
+        let rewritten = searcher.rewrite(&query).unwrap().unwrap();
+        assert_eq!(rewritten.to_string(), "body:tests");
+        assert!(searcher.rewrite(rewritten.as_ref()).unwrap().is_none());
+
+        let top_docs = searcher.search(&query, 1).unwrap();
+        assert_eq!(top_docs.total_hits, 3);
+        assert_eq!(top_docs.score_docs, vec![ScoreDoc::new(2, 3.0)]);
+
+        // Rewritten inside a constant score query, which ignores its scores
+        let query = ConstantScoreQuery::new(Arc::new(query));
+        assert_eq!(query.to_string(), "ConstantScore(lower(body:TESTS))");
+        let top_docs = searcher.search(&query, 10).unwrap();
+        assert_eq!(top_docs.total_hits, 3);
+        assert!(top_docs.score_docs.iter().all(|sd| sd.score == 1.0));
+        assert_eq!(top_docs.score_docs[0].doc, 0);
+
+        assert_eq!(searcher.count(&MatchNoDocsQuery).unwrap(), 0);
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+
+use crate::index::{segment_reader::SegmentReader, IndexError};
+
+use super::{
+    doc_id_set_iterator::DocIdRange, query::ScoreMode, scorer::ConstantScoreScorer, Explanation,
+    IndexSearcher, Query, Scorer, Weight,
+};
+
+/// Matches every doc, with a score of 1.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchAllDocsQuery;
+
+impl Query for MatchAllDocsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        Ok(Box::new(MatchAllDocsWeight { boost }))
+    }
+}
+
+impl fmt::Display for MatchAllDocsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "*:*")
+    }
+}
+
+struct MatchAllDocsWeight {
+    boost: f32,
+}
+
+impl Weight for MatchAllDocsWeight {
+    fn scorer<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+        let docs = Box::new(DocIdRange::all(reader.max_doc()));
+        Ok(Some(Box::new(ConstantScoreScorer::new(docs, self.boost))))
+    }
+
+    fn explain(&self, _reader: &SegmentReader, _doc: u32) -> Result<Explanation, IndexError> {
+        Ok(Explanation::matched(self.boost, "*:*".to_string(), vec![]))
+    }
+}
+
+/// Matches no doc, like a query rewritten from nothing to search.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchNoDocsQuery;
+
+impl Query for MatchNoDocsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        _boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        Ok(Box::new(MatchNoDocsWeight))
+    }
+}
+
+impl fmt::Display for MatchNoDocsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MatchNoDocsQuery")
+    }
+}
+
+struct MatchNoDocsWeight;
+
+impl Weight for MatchNoDocsWeight {
+    fn scorer<'a>(
+        &'a self,
+        _reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+        Ok(None)
+    }
+
+    fn explain(&self, _reader: &SegmentReader, _doc: u32) -> Result<Explanation, IndexError> {
+        Ok(Explanation::no_match(
+            "MatchNoDocsQuery".to_string(),
+            vec![],
+        ))
+    }
+}
@@ -0,0 +1,251 @@
+use std::{collections::BTreeSet, fmt, sync::Arc};
+
+use crate::{
+    index::{
+        segment_reader::SegmentReader, DirectoryReader, IndexError, SeekStatus, Term, Terms,
+        TermsEnum,
+    },
+    util::{Automaton, FixedBitSet},
+};
+
+use super::{
+    boolean_query::DEFAULT_MAX_CLAUSE_COUNT, doc_id_set_iterator::BitSetIterator, query::ScoreMode,
+    scorer::ConstantScoreScorer, BooleanQuery, Explanation, IndexSearcher, Occur, Query, Scorer,
+    TermQuery, Weight, NO_MORE_DOCS,
+};
+
+/// How a query matching many terms is run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RewriteMethod {
+    /// Collects the docs of all matching terms in a bit set per segment,
+    /// all with a score equal to the boost. Works with any number of terms.
+    #[default]
+    ConstantScore,
+    /// Rewrites to a `BooleanQuery` of a `Should` `TermQuery` per matching term, scored like them.
+    /// Fails with `TooManyClauses` past `max_clause_count` terms.
+    ScoringBoolean { max_clause_count: usize },
+}
+
+impl RewriteMethod {
+    /// `ScoringBoolean` with the default max clause count.
+    pub fn scoring_boolean() -> Self {
+        RewriteMethod::ScoringBoolean {
+            max_clause_count: DEFAULT_MAX_CLAUSE_COUNT,
+        }
+    }
+}
+
+/// Matches docs containing a term of a field the automaton accepts.
+///
+/// Only terms starting with the common prefix of the automaton are enumerated.
+#[derive(Debug, Clone)]
+pub struct AutomatonQuery {
+    field: String,
+    automaton: Arc<Automaton>,
+    prefix: String,
+    rewrite_method: RewriteMethod,
+}
+
+impl AutomatonQuery {
+    pub fn new(field: String, automaton: Automaton) -> Self {
+        assert!(
+            automaton.is_deterministic(),
+            "The automaton must be deterministic"
+        );
+
+        Self {
+            field,
+            prefix: automaton.common_prefix(),
+            automaton: Arc::new(automaton),
+            rewrite_method: RewriteMethod::default(),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn automaton(&self) -> &Automaton {
+        &self.automaton
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.rewrite_method = rewrite_method;
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.rewrite_method
+    }
+
+    /// Constant score weight, explained with the description of the query wrapping this one.
+    pub(crate) fn weight(&self, description: String, boost: f32) -> Box<dyn Weight> {
+        Box::new(AutomatonWeight {
+            query: self.clone(),
+            description,
+            boost,
+        })
+    }
+}
+
+impl Query for AutomatonQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        let max_clause_count = match self.rewrite_method {
+            RewriteMethod::ConstantScore => return Ok(None),
+            RewriteMethod::ScoringBoolean { max_clause_count } => max_clause_count,
+        };
+
+        let mut texts = BTreeSet::new();
+        for terms in reader.leaves().iter().filter_map(|r| r.terms(&self.field)) {
+            for_each_term(&terms, &self.automaton, &self.prefix, |terms_enum| {
+                texts.insert(terms_enum.term().to_string());
+            });
+
+            if texts.len() > max_clause_count {
+                return Err(IndexError::TooManyClauses(format!(
+                    "{} matches more than {} terms",
+                    self, max_clause_count
+                )));
+            }
+        }
+
+        let mut query = BooleanQuery::new();
+        for text in texts {
+            let term = Term::new(self.field.clone(), text);
+            query.add(Arc::new(TermQuery::new(term)), Occur::Should);
+        }
+        Ok(Some(Arc::new(query)))
+    }
+
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        Ok(self.weight(self.to_string(), boost))
+    }
+}
+
+impl fmt::Display for AutomatonQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:<automaton>", self.field)
+    }
+}
+
+/// Calls `f` on each term accepted by the automaton, enumerating only the terms with the prefix.
+pub(crate) fn for_each_term(
+    terms: &dyn Terms,
+    automaton: &Automaton,
+    prefix: &str,
+    mut f: impl FnMut(&dyn TermsEnum),
+) {
+    let mut terms_enum = terms.iterator();
+    if terms_enum.seek_ceil(prefix) == SeekStatus::End {
+        return;
+    }
+
+    loop {
+        let term = terms_enum.term();
+        if !term.starts_with(prefix) {
+            return;
+        }
+        if automaton.run(term) {
+            f(terms_enum.as_ref());
+        }
+        if terms_enum.next().is_none() {
+            return;
+        }
+    }
+}
+
+struct AutomatonWeight {
+    query: AutomatonQuery,
+    description: String,
+    boost: f32,
+}
+
+impl Weight for AutomatonWeight {
+    fn scorer<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+        let terms = match reader.terms(&self.query.field) {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+
+        let mut bits = FixedBitSet::new(reader.max_doc());
+        let mut matched = false;
+        for_each_term(
+            &terms,
+            &self.query.automaton,
+            &self.query.prefix,
+            |terms_enum| {
+                let mut postings = terms_enum.postings();
+                while postings.next_doc() != NO_MORE_DOCS {
+                    bits.set(postings.doc_id());
+                }
+                matched = true;
+            },
+        );
+
+        if !matched {
+            return Ok(None);
+        }
+        let docs = Box::new(BitSetIterator::new(bits));
+        Ok(Some(Box::new(ConstantScoreScorer::new(docs, self.boost))))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: u32) -> Result<Explanation, IndexError> {
+        let description = self.description.clone();
+        Ok(match self.matches(reader, doc)? {
+            true => Explanation::matched(self.boost, description, vec![]),
+            false => Explanation::no_match(format!("{}, no matching term", description), vec![]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::reader;
+
+    use super::*;
+
+    const SEGMENTS: &[&[&str]] = &[&["test"], &["tests testing"], &["text"], &["toast"]];
+
+    #[test]
+    fn test_rewrite_methods() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        let mut query = AutomatonQuery::new("body".to_string(), Automaton::prefix("tes"));
+        let top_docs = searcher.search(&query, 10).unwrap();
+        assert_eq!(top_docs.total_hits, 2);
+        assert!(top_docs.score_docs.iter().all(|sd| sd.score == 1.0));
+
+        // Terms of all segments become clauses, scored like term queries
+        query.set_rewrite_method(RewriteMethod::scoring_boolean());
+        let rewritten = searcher.rewrite(&query).unwrap().unwrap();
+        assert_eq!(rewritten.to_string(), "body:test body:testing body:tests");
+        let top_docs = searcher.search(&query, 10).unwrap();
+        assert_eq!(top_docs.total_hits, 2);
+        assert_eq!(
+            searcher.doc(top_docs.score_docs[0].doc).get("id"),
+            Some("2")
+        );
+
+        query.set_rewrite_method(RewriteMethod::ScoringBoolean {
+            max_clause_count: 2,
+        });
+        assert!(matches!(
+            searcher.search(&query, 10),
+            Err(IndexError::TooManyClauses(_))
+        ));
+
+        let query = AutomatonQuery::new("body".to_string(), Automaton::prefix("missing"));
+        assert_eq!(searcher.count(&query).unwrap(), 0);
+        let query = AutomatonQuery::new("missing".to_string(), Automaton::prefix("tes"));
+        assert_eq!(searcher.count(&query).unwrap(), 0);
+    }
+}
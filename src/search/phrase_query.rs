@@ -0,0 +1,492 @@
+use std::{fmt, sync::Arc};
+
+use crate::index::{
+    segment_reader::SegmentReader, segment_terms::SegmentPostingsEnum, DirectoryReader, IndexError,
+    PostingsEnum, Term,
+};
+
+use super::{
+    query::ScoreMode, similarity::SimScorer, DocIdSetIterator, Explanation, IndexSearcher,
+    MatchNoDocsQuery, Query, Scorer, TermQuery, Weight, NO_MORE_DOCS,
+};
+
+/// Matches docs containing terms at given relative positions, like `"integration tests"`.
+///
+/// With a `slop`, terms may be up to that many moves away from their positions,
+/// and closer matches score higher. Reversing two terms takes 2 moves.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhraseQuery {
+    terms: Vec<Term>,
+    positions: Vec<u32>,
+    slop: u32,
+}
+
+impl PhraseQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a term right after the last one.
+    pub fn add(&mut self, term: Term) {
+        let position = self.positions.last().map_or(0, |p| p + 1);
+        self.add_at(term, position);
+    }
+
+    /// Adds a term at a position, leaving a gap for skipped positions.
+    pub fn add_at(&mut self, term: Term, position: u32) {
+        if let Some(first) = self.terms.first() {
+            assert!(
+                first.field == term.field,
+                "All terms of a phrase must be in the same field"
+            );
+        }
+        if let Some(&last) = self.positions.last() {
+            assert!(
+                position >= last,
+                "Positions must be added in order, got {} after {}",
+                position,
+                last
+            );
+        }
+
+        self.terms.push(term);
+        self.positions.push(position);
+    }
+
+    /// Maximum number of moves between terms of a match, 0 by default for exact phrases.
+    pub fn set_slop(&mut self, slop: u32) {
+        self.slop = slop;
+    }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    pub fn positions(&self) -> &[u32] {
+        &self.positions
+    }
+
+    pub fn slop(&self) -> u32 {
+        self.slop
+    }
+}
+
+impl Query for PhraseQuery {
+    fn rewrite(&self, _reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        match self.terms.as_slice() {
+            [] => Ok(Some(Arc::new(MatchNoDocsQuery))),
+            [term] => Ok(Some(Arc::new(TermQuery::new(term.clone())))),
+            _ => Ok(None),
+        }
+    }
+
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        let field = &self.terms[0].field;
+        let term_statistics: Option<Vec<_>> = self
+            .terms
+            .iter()
+            .map(|term| searcher.term_statistics(term))
+            .collect();
+
+        let sim_scorer = match (searcher.collection_statistics(field), term_statistics) {
+            (Some(collection), Some(terms)) => {
+                Some(searcher.similarity().scorer(boost, &collection, &terms))
+            }
+            _ => None,
+        };
+
+        Ok(Box::new(PhraseWeight {
+            query: self.clone(),
+            sim_scorer,
+        }))
+    }
+}
+
+impl fmt::Display for PhraseQuery {
+    /// Skipped positions are displayed as `?`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = self.terms.first().map_or("", |t| t.field.as_str());
+        write!(f, "{}:\"", field)?;
+
+        let mut last = None;
+        for (term, &position) in self.terms.iter().zip(&self.positions) {
+            match last {
+                Some(last) if last == position => write!(f, "|")?,
+                Some(last) => {
+                    for _ in last + 1..position {
+                        write!(f, " ?")?;
+                    }
+                    write!(f, " ")?;
+                }
+                None => {
+                    for _ in 0..position {
+                        write!(f, "? ")?;
+                    }
+                }
+            }
+            write!(f, "{}", term.text)?;
+            last = Some(position);
+        }
+
+        write!(f, "\"")?;
+        if self.slop > 0 {
+            write!(f, "~{}", self.slop)?;
+        }
+        Ok(())
+    }
+}
+
+struct PhraseWeight {
+    query: PhraseQuery,
+    /// `None` when a term isn't in any segment.
+    sim_scorer: Option<Box<dyn SimScorer>>,
+}
+
+impl PhraseWeight {
+    /// Scorer of the segment, `None` when a term isn't in the segment.
+    fn phrase_scorer<'a>(&'a self, reader: &'a SegmentReader) -> Option<PhraseScorer<'a>> {
+        let sim_scorer = self.sim_scorer.as_deref()?;
+
+        let mut postings = Vec::with_capacity(self.query.terms.len());
+        for (term, &position) in self.query.terms.iter().zip(&self.query.positions) {
+            let enum_ = reader.postings_enum(term)?;
+            postings.push(PhrasePositions::new(enum_, term, position));
+        }
+        // The rarest term leads
+        postings.sort_by_key(|p| p.postings.cost());
+
+        let field = &self.query.terms[0].field;
+        Some(PhraseScorer {
+            postings,
+            slop: self.query.slop,
+            sim_scorer,
+            norms: reader.norms(field),
+            doc: 0,
+            freq: 0.0,
+            started: false,
+        })
+    }
+}
+
+impl Weight for PhraseWeight {
+    fn scorer<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+        Ok(self
+            .phrase_scorer(reader)
+            .map(|scorer| Box::new(scorer) as Box<dyn Scorer + 'a>))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: u32) -> Result<Explanation, IndexError> {
+        let description = format!("weight({} in {})", self.query, doc);
+
+        if let Some(mut scorer) = self.phrase_scorer(reader) {
+            if scorer.advance(doc) == doc {
+                let freq = Explanation::matched(
+                    scorer.freq,
+                    "phraseFreq, matches of the phrase, sloppy ones counting less".to_string(),
+                    vec![],
+                );
+                let norm = scorer.norms.map_or(1, |norms| norms[doc as usize]);
+
+                let score = scorer.sim_scorer.explain(freq, norm);
+                return Ok(Explanation::matched(
+                    score.value(),
+                    format!("{}, result of:", description),
+                    vec![score],
+                ));
+            }
+        }
+
+        Ok(Explanation::no_match(
+            format!("{}, no matching phrase", description),
+            vec![],
+        ))
+    }
+}
+
+/// Postings of a term of the phrase, and its positions in the current doc.
+struct PhrasePositions<'a> {
+    postings: SegmentPostingsEnum,
+    term: &'a Term,
+    /// Position of the term within the phrase.
+    offset: u32,
+    positions: Vec<u32>,
+}
+
+impl<'a> PhrasePositions<'a> {
+    fn new(postings: SegmentPostingsEnum, term: &'a Term, offset: u32) -> Self {
+        Self {
+            postings,
+            term,
+            offset,
+            positions: Vec::new(),
+        }
+    }
+
+    fn read_positions(&mut self) {
+        self.positions.clear();
+        for _ in 0..self.postings.freq() {
+            self.positions.push(self.postings.next_position());
+        }
+    }
+
+    /// Start of the phrase if the term matches at the i-th position.
+    fn phrase_start(&self, i: usize) -> i64 {
+        self.positions[i] as i64 - self.offset as i64
+    }
+}
+
+/// Matches docs having all terms of a phrase, scored from the phrase freq.
+///
+/// An exact phrase freq is the number of matches, a sloppy match of
+/// `n` moves only counts for `1 / (1 + n)`.
+struct PhraseScorer<'a> {
+    postings: Vec<PhrasePositions<'a>>,
+    slop: u32,
+    sim_scorer: &'a dyn SimScorer,
+    norms: Option<&'a [u8]>,
+    doc: u32,
+    freq: f32,
+    started: bool,
+}
+
+impl PhraseScorer<'_> {
+    /// Positions the others on the lead's doc, until they agree on a doc with a match.
+    fn do_next(&mut self, mut doc: u32) -> u32 {
+        'lead: while doc != NO_MORE_DOCS {
+            for i in 1..self.postings.len() {
+                let other = &mut self.postings[i].postings;
+                let mut other_doc = other.doc_id();
+                if other_doc < doc {
+                    other_doc = other.advance(doc);
+                }
+
+                if other_doc > doc {
+                    doc = self.postings[0].postings.advance(other_doc);
+                    continue 'lead;
+                }
+            }
+
+            self.freq = self.phrase_freq();
+            if self.freq > 0.0 {
+                break;
+            }
+            doc = self.postings[0].postings.next_doc();
+        }
+
+        self.doc = doc;
+        doc
+    }
+
+    /// Moves the term whose phrase starts first to its next position until one runs out,
+    /// counting the spans of the phrase within the slop.
+    fn phrase_freq(&mut self) -> f32 {
+        for pp in self.postings.iter_mut() {
+            pp.read_positions();
+        }
+
+        let mut indexes = vec![0; self.postings.len()];
+        let mut freq = 0.0;
+        loop {
+            let starts = self
+                .postings
+                .iter()
+                .zip(&indexes)
+                .map(|(pp, &i)| pp.phrase_start(i));
+            let (min, max) = starts.fold((i64::MAX, i64::MIN), |(min, max), start| {
+                (min.min(start), max.max(start))
+            });
+
+            let length = (max - min) as u32;
+            if length <= self.slop && !self.has_repeats(&indexes) {
+                freq += 1.0 / (1.0 + length as f32);
+            }
+
+            let (first, _) = self
+                .postings
+                .iter()
+                .zip(&indexes)
+                .enumerate()
+                .min_by_key(|(_, (pp, &i))| pp.phrase_start(i))
+                .unwrap();
+            indexes[first] += 1;
+            if indexes[first] == self.postings[first].positions.len() {
+                return freq;
+            }
+        }
+    }
+
+    /// Whether a repeated term of the phrase is matched twice at the same position.
+    fn has_repeats(&self, indexes: &[usize]) -> bool {
+        for (a, pa) in self.postings.iter().enumerate() {
+            for (b, pb) in self.postings.iter().enumerate().skip(a + 1) {
+                if pa.term == pb.term && pa.positions[indexes[a]] == pb.positions[indexes[b]] {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+impl DocIdSetIterator for PhraseScorer<'_> {
+    fn doc_id(&self) -> u32 {
+        self.doc
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        if !self.started {
+            return self.advance(0);
+        }
+
+        let doc = self.postings[0].postings.next_doc();
+        self.do_next(doc)
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        if !self.started {
+            self.started = true;
+            for pp in self.postings[1..].iter_mut() {
+                pp.postings.advance(target);
+            }
+        }
+
+        let doc = self.postings[0].postings.advance(target);
+        self.do_next(doc)
+    }
+
+    fn cost(&self) -> u64 {
+        self.postings[0].postings.cost()
+    }
+}
+
+impl Scorer for PhraseScorer<'_> {
+    fn score(&mut self) -> f32 {
+        let norm = self.norms.map_or(1, |norms| norms[self.doc as usize]);
+        self.sim_scorer.score(self.freq, norm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::reader;
+
+    use super::*;
+
+    const SEGMENTS: &[&[&str]] = &[
+        &["integration tests for the writer", "tests of the integration"],
+        &[
+            "integration and unit tests",
+            "integration tests integration tests",
+            "tests tests",
+        ],
+    ];
+
+    fn term(text: &str) -> Term {
+        Term::new("body".to_string(), text.to_string())
+    }
+
+    fn phrase(texts: &[&str], slop: u32) -> PhraseQuery {
+        let mut query = PhraseQuery::new();
+        for text in texts {
+            query.add(term(text));
+        }
+        query.set_slop(slop);
+        query
+    }
+
+    fn ids(searcher: &IndexSearcher, query: &dyn Query) -> Vec<String> {
+        searcher
+            .search(query, 10)
+            .unwrap()
+            .score_docs
+            .iter()
+            .map(|sd| searcher.doc(sd.doc).get("id").unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_exact() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        // More matches score higher
+        let query = phrase(&["integration", "tests"], 0);
+        assert_eq!(ids(&searcher, &query), vec!["4", "1"]);
+        assert_eq!(query.to_string(), "body:\"integration tests\"");
+
+        assert_eq!(
+            ids(&searcher, &phrase(&["tests", "integration"], 0)),
+            vec!["4"]
+        );
+        assert!(ids(&searcher, &phrase(&["unit", "integration"], 0)).is_empty());
+        assert!(ids(&searcher, &phrase(&["integration", "missing"], 0)).is_empty());
+        assert_eq!(ids(&searcher, &phrase(&["tests", "tests"], 0)), vec!["5"]);
+        assert_eq!(
+            ids(&searcher, &phrase(&["integration", "tests", "for"], 0)),
+            vec!["1"]
+        );
+
+        // A gap matches any term
+        let mut query = PhraseQuery::new();
+        query.add(term("integration"));
+        query.add_at(term("unit"), 2);
+        assert_eq!(ids(&searcher, &query), vec!["3"]);
+        assert_eq!(query.to_string(), "body:\"integration ? unit\"");
+
+        // A single term is a term query
+        assert_eq!(ids(&searcher, &phrase(&["unit"], 0)), vec!["3"]);
+        assert!(ids(&searcher, &PhraseQuery::new()).is_empty());
+    }
+
+    #[test]
+    fn test_sloppy() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        let query = phrase(&["integration", "tests"], 2);
+        assert_eq!(query.to_string(), "body:\"integration tests\"~2");
+        assert_eq!(ids(&searcher, &query), vec!["4", "1", "3"]);
+
+        // Reversed terms take 2 moves
+        let query = phrase(&["unit", "and"], 1);
+        assert!(ids(&searcher, &query).is_empty());
+        let query = phrase(&["unit", "and"], 2);
+        assert_eq!(ids(&searcher, &query), vec!["3"]);
+
+        // Shorter fields score higher for the same phrase freq
+        let query = phrase(&["tests", "integration"], 3);
+        assert_eq!(ids(&searcher, &query), vec!["4", "2", "1"]);
+
+        // A repeated term doesn't match twice at the same position
+        let query = phrase(&["integration", "integration"], 2);
+        assert_eq!(ids(&searcher, &query), vec!["4"]);
+
+        // Exact matches score as in an exact phrase, closer matches score higher
+        let top_docs = searcher
+            .search(&phrase(&["integration", "tests"], 2), 10)
+            .unwrap();
+        let exact = searcher
+            .search(&phrase(&["integration", "tests"], 0), 10)
+            .unwrap();
+        assert_eq!(top_docs.score_docs[1].score, exact.score_docs[1].score);
+        assert!(top_docs.score_docs[1].score > top_docs.score_docs[2].score);
+    }
+
+    #[test]
+    #[should_panic(expected = "All terms of a phrase must be in the same field")]
+    fn test_fields() {
+        let mut query = PhraseQuery::new();
+        query.add(term("integration"));
+        query.add(Term::new("title".to_string(), "tests".to_string()));
+    }
+}
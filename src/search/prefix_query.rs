@@ -0,0 +1,95 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    index::{DirectoryReader, IndexError, Term},
+    util::Automaton,
+};
+
+use super::{
+    multi_term_query::{AutomatonQuery, RewriteMethod},
+    query::ScoreMode,
+    IndexSearcher, Query, Weight,
+};
+
+/// Matches docs containing a term starting with a prefix, like `test*`.
+#[derive(Debug, Clone)]
+pub struct PrefixQuery {
+    prefix: Term,
+    query: AutomatonQuery,
+}
+
+impl PrefixQuery {
+    pub fn new(prefix: Term) -> Self {
+        let automaton = Automaton::prefix(&prefix.text);
+        Self {
+            query: AutomatonQuery::new(prefix.field.clone(), automaton),
+            prefix,
+        }
+    }
+
+    pub fn prefix(&self) -> &Term {
+        &self.prefix
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.query.rewrite_method()
+    }
+}
+
+impl Query for PrefixQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        self.query.rewrite(reader)
+    }
+
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        Ok(self.query.weight(self.to_string(), boost))
+    }
+}
+
+impl fmt::Display for PrefixQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}*", self.prefix.field, self.prefix.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::IndexWriter,
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_prefix_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+        for body in ["test", "tests", "testing", "text", "attest"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            writer.add_document(doc).unwrap();
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        let prefix = |text: &str| PrefixQuery::new(Term::new("body".to_string(), text.to_string()));
+        assert_eq!(searcher.count(&prefix("test")).unwrap(), 3);
+        assert_eq!(searcher.count(&prefix("te")).unwrap(), 4);
+        assert_eq!(searcher.count(&prefix("")).unwrap(), 5);
+        assert_eq!(searcher.count(&prefix("testings")).unwrap(), 0);
+        assert_eq!(prefix("test").to_string(), "body:test*");
+    }
+}
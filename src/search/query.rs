@@ -0,0 +1,39 @@
+use std::{fmt, sync::Arc};
+
+use crate::index::{DirectoryReader, IndexError};
+
+use super::{IndexSearcher, Weight};
+
+/// Whether the scores of matching docs are needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreMode {
+    Complete,
+    /// Only matches are needed, like for filters and counts.
+    CompleteNoScores,
+}
+
+impl ScoreMode {
+    pub fn needs_scores(&self) -> bool {
+        *self == ScoreMode::Complete
+    }
+}
+
+/// A query run by `IndexSearcher`.
+///
+/// The searcher first rewrites the query into primitive queries, then creates the
+/// `Weight` which scores each segment. Queries are displayed in the syntax of the query parser.
+pub trait Query: fmt::Display + fmt::Debug + Send + Sync {
+    /// Query to run instead, `None` when the query is primitive.
+    /// Called again on the rewritten query until it returns `None`.
+    fn rewrite(&self, _reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        Ok(None)
+    }
+
+    /// Prepares the rewritten query, with scores multiplied by `boost`.
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError>;
+}
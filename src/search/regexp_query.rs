@@ -0,0 +1,117 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    index::{DirectoryReader, IndexError, Term},
+    util::{automaton::DEFAULT_MAX_DETERMINIZED_STATES, regexp, AutomatonError},
+};
+
+use super::{
+    multi_term_query::{AutomatonQuery, RewriteMethod},
+    query::ScoreMode,
+    IndexSearcher, Query, Weight,
+};
+
+/// Matches docs containing a term matching a regular expression, like `/te[sx]t/`.
+///
+/// The syntax is described in `util::regexp::parse`, the expression must match the whole term.
+#[derive(Debug, Clone)]
+pub struct RegexpQuery {
+    regexp: Term,
+    query: AutomatonQuery,
+}
+
+impl RegexpQuery {
+    /// Fails when the expression is invalid.
+    pub fn new(regexp: Term) -> Result<Self, AutomatonError> {
+        Self::with_max_determinized_states(regexp, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Fails when the expression is invalid, or its automaton needs more than
+    /// `max_determinized_states` states.
+    pub fn with_max_determinized_states(
+        regexp: Term,
+        max_determinized_states: usize,
+    ) -> Result<Self, AutomatonError> {
+        let automaton = regexp::parse(&regexp.text)?.determinize(max_determinized_states)?;
+        Ok(Self {
+            query: AutomatonQuery::new(regexp.field.clone(), automaton),
+            regexp,
+        })
+    }
+
+    pub fn regexp(&self) -> &Term {
+        &self.regexp
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.query.rewrite_method()
+    }
+}
+
+impl Query for RegexpQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        self.query.rewrite(reader)
+    }
+
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        Ok(self.query.weight(self.to_string(), boost))
+    }
+}
+
+impl fmt::Display for RegexpQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:/{}/", self.regexp.field, self.regexp.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::IndexWriter,
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    fn regexp(pattern: &str) -> Result<RegexpQuery, AutomatonError> {
+        RegexpQuery::new(Term::new("body".to_string(), pattern.to_string()))
+    }
+
+    #[test]
+    fn test_regexp_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+        for body in ["test", "text", "tent", "tests", "t3st"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            writer.add_document(doc).unwrap();
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        let count = |pattern: &str| searcher.count(&regexp(pattern).unwrap()).unwrap();
+        assert_eq!(count("te[sx]t"), 2);
+        assert_eq!(count("te[sx]ts?"), 3);
+        assert_eq!(count("t[^e]st"), 1);
+        assert_eq!(count("t.*"), 5);
+        assert_eq!(count("es"), 0);
+        assert_eq!(regexp("te[sx]t").unwrap().to_string(), "body:/te[sx]t/");
+
+        assert_eq!(
+            regexp("te[sx").unwrap_err(),
+            AutomatonError::InvalidSyntax("Unterminated class at position 5".to_string())
+        );
+    }
+}
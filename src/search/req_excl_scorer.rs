@@ -0,0 +1,61 @@
+use super::{DocIdSetIterator, Scorer, NO_MORE_DOCS};
+
+/// Matches docs of a required scorer that an excluded iterator doesn't match.
+pub struct ReqExclScorer<'a> {
+    req: Box<dyn Scorer + 'a>,
+    excl: Box<dyn DocIdSetIterator + 'a>,
+    excl_started: bool,
+}
+
+impl<'a> ReqExclScorer<'a> {
+    pub fn new(req: Box<dyn Scorer + 'a>, excl: Box<dyn DocIdSetIterator + 'a>) -> Self {
+        Self {
+            req,
+            excl,
+            excl_started: false,
+        }
+    }
+
+    fn is_excluded(&mut self, doc: u32) -> bool {
+        let mut excl_doc = self.excl.doc_id();
+        if !self.excl_started || excl_doc < doc {
+            self.excl_started = true;
+            excl_doc = self.excl.advance(doc);
+        }
+
+        excl_doc == doc
+    }
+
+    fn do_next(&mut self, mut doc: u32) -> u32 {
+        while doc != NO_MORE_DOCS && self.is_excluded(doc) {
+            doc = self.req.next_doc();
+        }
+        doc
+    }
+}
+
+impl DocIdSetIterator for ReqExclScorer<'_> {
+    fn doc_id(&self) -> u32 {
+        self.req.doc_id()
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        let doc = self.req.next_doc();
+        self.do_next(doc)
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        let doc = self.req.advance(target);
+        self.do_next(doc)
+    }
+
+    fn cost(&self) -> u64 {
+        self.req.cost()
+    }
+}
+
+impl Scorer for ReqExclScorer<'_> {
+    fn score(&mut self) -> f32 {
+        self.req.score()
+    }
+}
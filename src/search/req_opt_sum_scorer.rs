@@ -0,0 +1,54 @@
+use super::{DocIdSetIterator, Scorer};
+
+/// Matches docs of a required scorer, adding the score of an optional scorer when it matches too.
+pub struct ReqOptSumScorer<'a> {
+    req: Box<dyn Scorer + 'a>,
+    opt: Box<dyn Scorer + 'a>,
+    opt_started: bool,
+}
+
+impl<'a> ReqOptSumScorer<'a> {
+    pub fn new(req: Box<dyn Scorer + 'a>, opt: Box<dyn Scorer + 'a>) -> Self {
+        Self {
+            req,
+            opt,
+            opt_started: false,
+        }
+    }
+}
+
+impl DocIdSetIterator for ReqOptSumScorer<'_> {
+    fn doc_id(&self) -> u32 {
+        self.req.doc_id()
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        self.req.next_doc()
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        self.req.advance(target)
+    }
+
+    fn cost(&self) -> u64 {
+        self.req.cost()
+    }
+}
+
+impl Scorer for ReqOptSumScorer<'_> {
+    fn score(&mut self) -> f32 {
+        let doc = self.req.doc_id();
+        let mut score = self.req.score();
+
+        let mut opt_doc = self.opt.doc_id();
+        if !self.opt_started || opt_doc < doc {
+            self.opt_started = true;
+            opt_doc = self.opt.advance(doc);
+        }
+        if opt_doc == doc {
+            score += self.opt.score();
+        }
+
+        score
+    }
+}
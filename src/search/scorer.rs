@@ -0,0 +1,43 @@
+use super::DocIdSetIterator;
+
+/// Iterates the docs of a segment matching a query, and scores them.
+pub trait Scorer: DocIdSetIterator {
+    /// Score of the current doc.
+    fn score(&mut self) -> f32;
+}
+
+/// Gives every doc of an iterator the same score.
+pub struct ConstantScoreScorer<'a> {
+    iterator: Box<dyn DocIdSetIterator + 'a>,
+    score: f32,
+}
+
+impl<'a> ConstantScoreScorer<'a> {
+    pub fn new(iterator: Box<dyn DocIdSetIterator + 'a>, score: f32) -> Self {
+        Self { iterator, score }
+    }
+}
+
+impl DocIdSetIterator for ConstantScoreScorer<'_> {
+    fn doc_id(&self) -> u32 {
+        self.iterator.doc_id()
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        self.iterator.next_doc()
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        self.iterator.advance(target)
+    }
+
+    fn cost(&self) -> u64 {
+        self.iterator.cost()
+    }
+}
+
+impl Scorer for ConstantScoreScorer<'_> {
+    fn score(&mut self) -> f32 {
+        self.score
+    }
+}
@@ -0,0 +1,46 @@
+use crate::index::Term;
+
+use super::Explanation;
+
+/// Statistics of a field across all segments of the searched reader.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionStatistics {
+    pub field: String,
+    /// Number of docs, including deleted docs.
+    pub max_doc: u64,
+    /// Number of docs having at least one term for the field.
+    pub doc_count: u64,
+    pub sum_total_term_freq: u64,
+    pub sum_doc_freq: u64,
+}
+
+/// Statistics of a term across all segments of the searched reader, including deleted docs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermStatistics {
+    pub term: Term,
+    pub doc_freq: u64,
+    pub total_term_freq: u64,
+}
+
+/// Decides how matching docs are scored.
+///
+/// Statistics are gathered once per query and turned into a `SimScorer`,
+/// which then scores each doc from its term freq and the norm of its field.
+pub trait Similarity {
+    /// Scorer of a term, or of a phrase with the statistics of each of its terms.
+    fn scorer(
+        &self,
+        boost: f32,
+        collection: &CollectionStatistics,
+        terms: &[TermStatistics],
+    ) -> Box<dyn SimScorer>;
+}
+
+/// Scores docs with the statistics of a query.
+pub trait SimScorer {
+    /// Score of a doc from the term freq, and the field length encoded in its norm.
+    fn score(&self, freq: f32, norm: u8) -> f32;
+
+    /// How `score` computed the score, from the explanation of the freq.
+    fn explain(&self, freq: Explanation, norm: u8) -> Explanation;
+}
@@ -0,0 +1,430 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::index::{segment_reader::SegmentReader, IndexError};
+
+use super::{FieldDoc, SortValue};
+
+/// What a `SortField` sorts hits by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortFieldType {
+    /// Score, best first.
+    Score,
+    /// Doc ID, in index order.
+    Doc,
+    /// Numeric doc values of a field, smallest first.
+    Long,
+}
+
+/// Where hits without a value for a field are sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Missing {
+    /// Before hits with a value, even when reversed.
+    First,
+    /// After hits with a value, even when reversed.
+    #[default]
+    Last,
+    /// As if they had this value.
+    Value(i64),
+}
+
+/// A key of a `Sort`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortField {
+    field: Option<String>,
+    sort_type: SortFieldType,
+    reverse: bool,
+    missing: Missing,
+}
+
+impl SortField {
+    /// Sorts by the values of a field.
+    /// Fails for sort types that are not backed by a field, see `score` and `index_order`.
+    pub fn new(field: String, sort_type: SortFieldType) -> Result<Self, IndexError> {
+        if sort_type != SortFieldType::Long {
+            return Err(IndexError::IllegalArgument(format!(
+                "{:?} sort fields don't have a field",
+                sort_type
+            )));
+        }
+
+        Ok(Self {
+            field: Some(field),
+            sort_type,
+            reverse: false,
+            missing: Missing::default(),
+        })
+    }
+
+    /// Sorts by score, best first.
+    pub fn score() -> Self {
+        Self {
+            field: None,
+            sort_type: SortFieldType::Score,
+            reverse: false,
+            missing: Missing::default(),
+        }
+    }
+
+    /// Sorts by doc ID.
+    pub fn index_order() -> Self {
+        Self {
+            field: None,
+            sort_type: SortFieldType::Doc,
+            reverse: false,
+            missing: Missing::default(),
+        }
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+
+    pub fn sort_type(&self) -> SortFieldType {
+        self.sort_type
+    }
+
+    /// Whether the natural order of the field is reversed.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    pub fn set_missing(&mut self, missing: Missing) {
+        self.missing = missing;
+    }
+
+    pub fn missing(&self) -> Missing {
+        self.missing
+    }
+
+    /// Value of a doc of the segment to sort by.
+    pub(crate) fn value(
+        &self,
+        reader: &SegmentReader,
+        doc: u32,
+        doc_base: u32,
+        score: f32,
+    ) -> SortValue {
+        match self.sort_type {
+            SortFieldType::Score => SortValue::Score(score),
+            SortFieldType::Doc => SortValue::Doc(doc_base + doc),
+            SortFieldType::Long => {
+                SortValue::Long(reader.numeric_doc_value(self.field.as_ref().unwrap(), doc))
+            }
+        }
+    }
+
+    fn accepts(&self, value: &SortValue) -> bool {
+        matches!(
+            (self.sort_type, value),
+            (SortFieldType::Score, SortValue::Score(_))
+                | (SortFieldType::Doc, SortValue::Doc(_))
+                | (SortFieldType::Long, SortValue::Long(_))
+        )
+    }
+
+    /// Order of two values of this field, the first sorted first being `Less`.
+    /// Values of other fields, which `accepts` rejects, are equal.
+    pub(crate) fn compare(&self, a: &SortValue, b: &SortValue) -> Ordering {
+        let ordering = match (a, b) {
+            (SortValue::Score(a), SortValue::Score(b)) => b.total_cmp(a),
+            (SortValue::Doc(a), SortValue::Doc(b)) => a.cmp(b),
+            (SortValue::Long(a), SortValue::Long(b)) => {
+                let (a, b) = match (self.missing, a, b) {
+                    (_, Some(a), Some(b)) => (*a, *b),
+                    (Missing::Value(v), a, b) => (a.unwrap_or(v), b.unwrap_or(v)),
+                    (_, None, None) => return Ordering::Equal,
+                    (Missing::First, None, _) | (Missing::Last, _, None) => return Ordering::Less,
+                    (Missing::First, _, None) | (Missing::Last, None, _) => {
+                        return Ordering::Greater
+                    }
+                };
+                a.cmp(&b)
+            }
+            _ => Ordering::Equal,
+        };
+
+        match self.reverse {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    }
+}
+
+impl fmt::Display for SortField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sort_type {
+            SortFieldType::Score => write!(f, "<score>")?,
+            SortFieldType::Doc => write!(f, "<doc>")?,
+            SortFieldType::Long => write!(f, "<long: \"{}\">", self.field.as_ref().unwrap())?,
+        }
+        if self.reverse {
+            write!(f, "!")?;
+        }
+        Ok(())
+    }
+}
+
+/// Order of the hits of a search, by each sort field in turn, then by doc ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort {
+    fields: Vec<SortField>,
+}
+
+impl Sort {
+    /// Fails when there are no fields to sort by.
+    pub fn new(fields: Vec<SortField>) -> Result<Self, IndexError> {
+        if fields.is_empty() {
+            return Err(IndexError::IllegalArgument(
+                "A sort needs at least one field".to_string(),
+            ));
+        }
+
+        Ok(Self { fields })
+    }
+
+    /// Sorts by score, like `IndexSearcher::search`.
+    pub fn relevance() -> Self {
+        Self {
+            fields: vec![SortField::score()],
+        }
+    }
+
+    pub fn index_order() -> Self {
+        Self {
+            fields: vec![SortField::index_order()],
+        }
+    }
+
+    pub fn fields(&self) -> &[SortField] {
+        &self.fields
+    }
+
+    pub fn needs_scores(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|f| f.sort_type == SortFieldType::Score)
+    }
+
+    /// Whether the values are of the fields of this sort, like the ones of its hits.
+    pub fn accepts(&self, values: &[SortValue]) -> bool {
+        self.fields.len() == values.len()
+            && self.fields.iter().zip(values).all(|(f, v)| f.accepts(v))
+    }
+
+    /// Order of two hits, the first sorted first being `Less`.
+    /// Values of other fields than the ones of the sort are equal, see `accepts`.
+    pub fn compare(&self, a: &FieldDoc, b: &FieldDoc) -> Ordering {
+        self.fields
+            .iter()
+            .zip(a.fields.iter().zip(&b.fields))
+            .map(|(field, (a, b))| field.compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.doc.cmp(&b.doc))
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        document::Field,
+        index::{DirectoryReader, Term},
+        search::{IndexSearcher, TermQuery},
+        test_util::{doc, writer},
+    };
+
+    use super::*;
+
+    fn field_doc(doc: u32, price: Option<i64>, score: f32) -> FieldDoc {
+        FieldDoc::new(
+            doc,
+            score,
+            vec![SortValue::Long(price), SortValue::Score(score)],
+        )
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut price = SortField::new("price".to_string(), SortFieldType::Long).unwrap();
+        let sort = Sort::new(vec![price.clone(), SortField::score()]).unwrap();
+        assert_eq!(sort.to_string(), "<long: \"price\">,<score>");
+
+        let cheap = field_doc(3, Some(5), 1.0);
+        let expensive = field_doc(1, Some(10), 2.0);
+        let missing = field_doc(0, None, 3.0);
+        assert_eq!(sort.compare(&cheap, &expensive), Ordering::Less);
+        assert_eq!(sort.compare(&expensive, &missing), Ordering::Less);
+        assert_eq!(
+            sort.compare(&field_doc(2, Some(5), 2.0), &cheap),
+            Ordering::Less
+        );
+        assert_eq!(
+            sort.compare(&field_doc(2, Some(5), 1.0), &cheap),
+            Ordering::Less
+        );
+
+        price.set_reverse(true);
+        let sort = Sort::new(vec![price.clone()]).unwrap();
+        assert_eq!(sort.to_string(), "<long: \"price\">!");
+        assert_eq!(sort.compare(&expensive, &cheap), Ordering::Less);
+        assert_eq!(sort.compare(&cheap, &missing), Ordering::Less);
+
+        price.set_missing(Missing::First);
+        let sort = Sort::new(vec![price.clone()]).unwrap();
+        assert_eq!(sort.compare(&missing, &expensive), Ordering::Less);
+
+        price.set_missing(Missing::Value(7));
+        let sort = Sort::new(vec![price]).unwrap();
+        assert_eq!(sort.compare(&expensive, &missing), Ordering::Less);
+        assert_eq!(sort.compare(&missing, &cheap), Ordering::Less);
+    }
+
+    #[test]
+    fn test_field_of_score() {
+        assert!(matches!(
+            SortField::new("price".to_string(), SortFieldType::Score),
+            Err(IndexError::IllegalArgument(_))
+        ));
+        assert!(matches!(
+            SortField::new("price".to_string(), SortFieldType::Doc),
+            Err(IndexError::IllegalArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_sort_without_fields() {
+        assert!(matches!(
+            Sort::new(vec![]),
+            Err(IndexError::IllegalArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_compare_other_fields() {
+        let sort = Sort::new(vec![SortField::score()]).unwrap();
+        let score = FieldDoc::new(2, 1.0, vec![SortValue::Score(1.0)]);
+        let long = FieldDoc::new(1, 1.0, vec![SortValue::Long(Some(1))]);
+        assert!(!sort.accepts(&long.fields));
+
+        // Values the sort doesn't accept are equal, so only docs decide
+        assert_eq!(sort.compare(&long, &score), Ordering::Less);
+        assert_eq!(sort.compare(&score, &long), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_search_sorted() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = writer(&temp_dir.path().join("test-index"));
+        for (i, (body, price)) in [
+            ("tests", Some(30)),
+            ("tests tests", Some(10)),
+            ("tests", None),
+            ("tests", Some(10)),
+            ("integration", Some(5)),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut doc = doc(&i.to_string(), body);
+            if let Some(price) = price {
+                doc.add(Field::numeric_doc_values("price".to_string(), price));
+            }
+            writer.add_document(doc).unwrap();
+            if i == 1 {
+                writer.flush().unwrap();
+            }
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        let tests = TermQuery::new(Term::new("body".to_string(), "tests".to_string()));
+        let docs = |sort: &Sort, k: usize| -> Vec<u32> {
+            let top_docs = searcher.search_sorted(&tests, k, sort).unwrap();
+            assert_eq!(top_docs.total_hits, 4);
+            top_docs.field_docs.iter().map(|fd| fd.doc).collect()
+        };
+
+        // Ties go to the smaller doc, docs without a price last
+        let mut price = SortField::new("price".to_string(), SortFieldType::Long).unwrap();
+        let sort = Sort::new(vec![price.clone()]).unwrap();
+        assert_eq!(docs(&sort, 10), [1, 3, 0, 2]);
+        assert_eq!(docs(&sort, 2), [1, 3]);
+
+        let top_docs = searcher.search_sorted(&tests, 1, &sort).unwrap();
+        assert_eq!(top_docs.field_docs[0].fields, [SortValue::Long(Some(10))]);
+        assert_eq!(top_docs.field_docs[0].score, 0.0);
+
+        price.set_reverse(true);
+        assert_eq!(
+            docs(&Sort::new(vec![price.clone()]).unwrap(), 10),
+            [0, 1, 3, 2]
+        );
+        price.set_missing(Missing::First);
+        assert_eq!(
+            docs(&Sort::new(vec![price.clone()]).unwrap(), 10),
+            [2, 0, 1, 3]
+        );
+        price.set_missing(Missing::Value(20));
+        assert_eq!(
+            docs(&Sort::new(vec![price.clone()]).unwrap(), 10),
+            [0, 2, 1, 3]
+        );
+
+        // Scores break ties of prices
+        price.set_reverse(false);
+        let sort = Sort::new(vec![price, SortField::score()]).unwrap();
+        let top_docs = searcher.search_sorted(&tests, 10, &sort).unwrap();
+        assert_eq!(
+            top_docs
+                .field_docs
+                .iter()
+                .map(|fd| fd.doc)
+                .collect::<Vec<_>>(),
+            [1, 3, 2, 0]
+        );
+        assert!(top_docs.field_docs[0].score > top_docs.field_docs[1].score);
+
+        let relevance = searcher
+            .search_sorted(&tests, 10, &Sort::relevance())
+            .unwrap();
+        let top_docs = searcher.search(&tests, 10).unwrap();
+        assert_eq!(
+            relevance
+                .field_docs
+                .iter()
+                .map(|fd| fd.doc)
+                .collect::<Vec<_>>(),
+            top_docs
+                .score_docs
+                .iter()
+                .map(|sd| sd.doc)
+                .collect::<Vec<_>>()
+        );
+
+        let mut index_order = SortField::index_order();
+        assert_eq!(
+            docs(&Sort::new(vec![index_order.clone()]).unwrap(), 10),
+            [0, 1, 2, 3]
+        );
+        index_order.set_reverse(true);
+        assert_eq!(
+            docs(&Sort::new(vec![index_order]).unwrap(), 10),
+            [3, 2, 1, 0]
+        );
+    }
+}
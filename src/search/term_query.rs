@@ -0,0 +1,246 @@
+use std::fmt;
+
+use crate::index::{
+    segment_reader::SegmentReader, segment_terms::SegmentPostingsEnum, IndexError, PostingsEnum,
+    Term,
+};
+
+use super::{
+    query::ScoreMode, similarity::SimScorer, DocIdSetIterator, Explanation, IndexSearcher, Query,
+    Scorer, TermStatistics, Weight,
+};
+
+/// Matches docs containing a term, scored by the similarity of the searcher.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermQuery {
+    term: Term,
+    /// Statistics to score with instead of the ones of the searcher.
+    statistics: Option<TermStatistics>,
+}
+
+impl TermQuery {
+    pub fn new(term: Term) -> Self {
+        Self {
+            term,
+            statistics: None,
+        }
+    }
+
+    /// Scores with the statistics, like when blending the statistics of similar terms.
+    pub fn with_statistics(term: Term, statistics: TermStatistics) -> Self {
+        Self {
+            term,
+            statistics: Some(statistics),
+        }
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+}
+
+impl Query for TermQuery {
+    fn create_weight(
+        &self,
+        searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        let sim_scorer = match (
+            searcher.collection_statistics(&self.term.field),
+            self.statistics
+                .clone()
+                .or_else(|| searcher.term_statistics(&self.term)),
+        ) {
+            (Some(collection), Some(term)) => {
+                Some(searcher.similarity().scorer(boost, &collection, &[term]))
+            }
+            _ => None,
+        };
+
+        Ok(Box::new(TermWeight {
+            term: self.term.clone(),
+            sim_scorer,
+        }))
+    }
+}
+
+impl fmt::Display for TermQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.term.field, self.term.text)
+    }
+}
+
+struct TermWeight {
+    term: Term,
+    /// `None` when no segment has the term.
+    sim_scorer: Option<Box<dyn SimScorer>>,
+}
+
+impl Weight for TermWeight {
+    fn scorer<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError> {
+        let sim_scorer = match &self.sim_scorer {
+            Some(sim_scorer) => sim_scorer.as_ref(),
+            None => return Ok(None),
+        };
+
+        Ok(reader.postings_enum(&self.term).map(|postings| {
+            Box::new(TermScorer {
+                postings,
+                sim_scorer,
+                norms: reader.norms(&self.term.field),
+            }) as Box<dyn Scorer + 'a>
+        }))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: u32) -> Result<Explanation, IndexError> {
+        let description = format!("weight({}:{} in {})", self.term.field, self.term.text, doc);
+
+        if let (Some(sim_scorer), Some(mut postings)) =
+            (&self.sim_scorer, reader.postings_enum(&self.term))
+        {
+            if postings.advance(doc) == doc {
+                let freq = Explanation::matched(
+                    postings.freq() as f32,
+                    "freq, occurrences of term within document".to_string(),
+                    vec![],
+                );
+                let norm = reader
+                    .norms(&self.term.field)
+                    .map_or(1, |norms| norms[doc as usize]);
+
+                let score = sim_scorer.explain(freq, norm);
+                return Ok(Explanation::matched(
+                    score.value(),
+                    format!("{}, result of:", description),
+                    vec![score],
+                ));
+            }
+        }
+
+        Ok(Explanation::no_match(
+            format!("{}, no matching term", description),
+            vec![],
+        ))
+    }
+}
+
+/// Scores the docs of a term from their freq and field length.
+pub struct TermScorer<'a> {
+    postings: SegmentPostingsEnum,
+    sim_scorer: &'a dyn SimScorer,
+    norms: Option<&'a [u8]>,
+}
+
+impl TermScorer<'_> {
+    pub fn freq(&self) -> u32 {
+        self.postings.freq()
+    }
+}
+
+impl DocIdSetIterator for TermScorer<'_> {
+    fn doc_id(&self) -> u32 {
+        self.postings.doc_id()
+    }
+
+    fn next_doc(&mut self) -> u32 {
+        self.postings.next_doc()
+    }
+
+    fn advance(&mut self, target: u32) -> u32 {
+        self.postings.advance(target)
+    }
+
+    fn cost(&self) -> u64 {
+        self.postings.cost()
+    }
+}
+
+impl Scorer for TermScorer<'_> {
+    fn score(&mut self) -> f32 {
+        let doc = self.postings.doc_id() as usize;
+        let norm = self.norms.map_or(1, |norms| norms[doc]);
+
+        self.sim_scorer.score(self.postings.freq() as f32, norm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        search::{BM25Similarity, ClassicSimilarity},
+        test_util::reader,
+    };
+
+    use super::*;
+
+    const SEGMENTS: &[&[&str]] = &[
+        &[
+            "unit tests",
+            "integration tests for the index writer and the reader",
+        ],
+        &["tests tests tests", "integration"],
+    ];
+
+    fn body(text: &str) -> TermQuery {
+        TermQuery::new(Term::new("body".to_string(), text.to_string()))
+    }
+
+    fn ids(searcher: &IndexSearcher, query: &dyn Query) -> Vec<String> {
+        let top_docs = searcher.search(query, 10).unwrap();
+        top_docs
+            .score_docs
+            .iter()
+            .map(|sd| searcher.doc(sd.doc).get("id").unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_term_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let searcher = IndexSearcher::new(&reader);
+
+        // Higher freq and shorter fields rank first
+        assert_eq!(ids(&searcher, &body("tests")), vec!["3", "1", "2"]);
+        assert_eq!(ids(&searcher, &body("integration")), vec!["4", "2"]);
+        assert!(ids(&searcher, &body("missing")).is_empty());
+        assert_eq!(body("tests").to_string(), "body:tests");
+
+        // Scores across segments use statistics of the whole index
+        let top_docs = searcher.search(&body("tests"), 10).unwrap();
+        let collection = searcher.collection_statistics("body").unwrap();
+        assert_eq!(collection.doc_count, 4);
+        assert_eq!(collection.sum_total_term_freq, 15);
+        let term = searcher.term_statistics(body("tests").term()).unwrap();
+        assert_eq!(term.doc_freq, 3);
+        assert_eq!(term.total_term_freq, 5);
+
+        let idf = BM25Similarity::idf(3, 4);
+        let avgdl = 15.0 / 4.0;
+        let expected = idf * 3.0 / (3.0 + 1.2 * (0.25 + 0.75 * 3.0 / avgdl));
+        assert!((top_docs.score_docs[0].score - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_similarities() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let reader = reader(&temp_dir.path().join("test-index"), SEGMENTS);
+        let mut searcher = IndexSearcher::new(&reader);
+
+        searcher.set_similarity(ClassicSimilarity);
+        let top_docs = searcher.search(&body("tests"), 10).unwrap();
+        let idf = ClassicSimilarity::idf(3, 4);
+        let expected = 3.0f32.sqrt() * idf * idf / 3.0f32.sqrt();
+        assert!((top_docs.score_docs[0].score - expected).abs() < 1e-6);
+
+        // Without length normalization, the longer field ties with the shorter one
+        searcher.set_similarity(BM25Similarity::new(1.2, 0.0));
+        let top_docs = searcher.search(&body("tests"), 10).unwrap();
+        assert_eq!(top_docs.score_docs[1].score, top_docs.score_docs[2].score);
+        assert_eq!(ids(&searcher, &body("tests")), vec!["3", "1", "2"]);
+    }
+}
@@ -0,0 +1,159 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    index::{DirectoryReader, IndexError},
+    util::Automaton,
+};
+
+use super::{
+    multi_term_query::{AutomatonQuery, RewriteMethod},
+    query::ScoreMode,
+    IndexSearcher, Query, Weight,
+};
+
+/// Matches docs containing a term between two bounds, like `[a TO m]`.
+///
+/// Terms are compared by code point, a `None` bound is open.
+#[derive(Debug, Clone)]
+pub struct TermRangeQuery {
+    field: String,
+    lower: Option<String>,
+    upper: Option<String>,
+    include_lower: bool,
+    include_upper: bool,
+    query: AutomatonQuery,
+}
+
+impl TermRangeQuery {
+    pub fn new(
+        field: String,
+        lower: Option<String>,
+        upper: Option<String>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> Self {
+        let automaton = Automaton::range(
+            lower.as_deref(),
+            upper.as_deref(),
+            include_lower,
+            include_upper,
+        );
+
+        Self {
+            query: AutomatonQuery::new(field.clone(), automaton),
+            field,
+            lower,
+            upper,
+            include_lower,
+            include_upper,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn lower(&self) -> Option<&str> {
+        self.lower.as_deref()
+    }
+
+    pub fn upper(&self) -> Option<&str> {
+        self.upper.as_deref()
+    }
+
+    pub fn include_lower(&self) -> bool {
+        self.include_lower
+    }
+
+    pub fn include_upper(&self) -> bool {
+        self.include_upper
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.query.rewrite_method()
+    }
+}
+
+impl Query for TermRangeQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        self.query.rewrite(reader)
+    }
+
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        Ok(self.query.weight(self.to_string(), boost))
+    }
+}
+
+impl fmt::Display for TermRangeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}{} TO {}{}",
+            self.field,
+            if self.include_lower { '[' } else { '{' },
+            self.lower.as_deref().unwrap_or("*"),
+            self.upper.as_deref().unwrap_or("*"),
+            if self.include_upper { ']' } else { '}' },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::IndexWriter,
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    fn range(lower: Option<&str>, upper: Option<&str>, inclusive: bool) -> TermRangeQuery {
+        TermRangeQuery::new(
+            "body".to_string(),
+            lower.map(String::from),
+            upper.map(String::from),
+            inclusive,
+            inclusive,
+        )
+    }
+
+    #[test]
+    fn test_term_range_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+        for body in ["apple", "banana", "cherry", "mango", "melon", "pear"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            writer.add_document(doc).unwrap();
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        let count = |query: &TermRangeQuery| searcher.count(query).unwrap();
+        assert_eq!(count(&range(Some("a"), Some("m"), true)), 3);
+        assert_eq!(count(&range(Some("banana"), Some("mango"), true)), 3);
+        assert_eq!(count(&range(Some("banana"), Some("mango"), false)), 1);
+        assert_eq!(count(&range(Some("mango"), None, true)), 3);
+        assert_eq!(count(&range(None, Some("cherry"), false)), 2);
+        assert_eq!(count(&range(None, None, true)), 6);
+        assert_eq!(count(&range(Some("x"), Some("z"), true)), 0);
+
+        assert_eq!(
+            range(Some("a"), Some("m"), true).to_string(),
+            "body:[a TO m]"
+        );
+        assert_eq!(range(Some("a"), None, false).to_string(), "body:{a TO *}");
+    }
+}
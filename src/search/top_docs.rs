@@ -0,0 +1,262 @@
+use super::cursor::{CursorError, CursorReader, CursorWriter};
+
+const SCORE_DOC_CURSOR: u8 = 1;
+const FIELD_DOC_CURSOR: u8 = 2;
+
+/// A hit of a search, with its doc ID across all segments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreDoc {
+    pub doc: u32,
+    pub score: f32,
+}
+
+impl ScoreDoc {
+    pub fn new(doc: u32, score: f32) -> Self {
+        Self { doc, score }
+    }
+
+    /// Opaque string to resume a search after this hit, with `IndexSearcher::search_after`.
+    pub fn to_cursor(&self) -> String {
+        let mut writer = CursorWriter::new(SCORE_DOC_CURSOR);
+        writer.write_u32(self.doc);
+        writer.write_u32(self.score.to_bits());
+        writer.finish()
+    }
+
+    pub fn from_cursor(cursor: &str) -> Result<Self, CursorError> {
+        let mut reader = CursorReader::new(cursor, SCORE_DOC_CURSOR)?;
+        let doc = reader.read_u32()?;
+        let score = f32::from_bits(reader.read_u32()?);
+        reader.finish()?;
+        Ok(Self { doc, score })
+    }
+}
+
+/// Best hits of a search, best first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopDocs {
+    /// Number of live docs matching the query, not only the ones returned.
+    pub total_hits: u64,
+    pub score_docs: Vec<ScoreDoc>,
+}
+
+impl TopDocs {
+    pub fn new(total_hits: u64, score_docs: Vec<ScoreDoc>) -> Self {
+        Self {
+            total_hits,
+            score_docs,
+        }
+    }
+
+    /// Score of the best hit, `None` without hits.
+    pub fn max_score(&self) -> Option<f32> {
+        self.score_docs.first().map(|sd| sd.score)
+    }
+}
+
+/// Value of a hit for a `SortField`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortValue {
+    Score(f32),
+    Doc(u32),
+    /// `None` when the doc has no value.
+    Long(Option<i64>),
+}
+
+/// A hit of a sorted search, with its values for each field of the sort.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDoc {
+    pub doc: u32,
+    /// 0 when the sort doesn't need scores.
+    pub score: f32,
+    pub fields: Vec<SortValue>,
+}
+
+impl FieldDoc {
+    pub fn new(doc: u32, score: f32, fields: Vec<SortValue>) -> Self {
+        Self { doc, score, fields }
+    }
+
+    /// Opaque string to resume a sorted search after this hit,
+    /// with `IndexSearcher::search_after_sorted`.
+    pub fn to_cursor(&self) -> String {
+        let mut writer = CursorWriter::new(FIELD_DOC_CURSOR);
+        writer.write_u32(self.doc);
+        writer.write_u32(self.score.to_bits());
+        writer.write_u32(self.fields.len() as u32);
+        for value in &self.fields {
+            match value {
+                SortValue::Score(score) => {
+                    writer.write_u8(0);
+                    writer.write_u32(score.to_bits());
+                }
+                SortValue::Doc(doc) => {
+                    writer.write_u8(1);
+                    writer.write_u32(*doc);
+                }
+                SortValue::Long(None) => writer.write_u8(2),
+                SortValue::Long(Some(value)) => {
+                    writer.write_u8(3);
+                    writer.write_u64(*value as u64);
+                }
+            }
+        }
+        writer.finish()
+    }
+
+    pub fn from_cursor(cursor: &str) -> Result<Self, CursorError> {
+        let mut reader = CursorReader::new(cursor, FIELD_DOC_CURSOR)?;
+        let doc = reader.read_u32()?;
+        let score = f32::from_bits(reader.read_u32()?);
+
+        let len = reader.read_u32()?;
+        let mut fields = Vec::new();
+        for _ in 0..len {
+            fields.push(match reader.read_u8()? {
+                0 => SortValue::Score(f32::from_bits(reader.read_u32()?)),
+                1 => SortValue::Doc(reader.read_u32()?),
+                2 => SortValue::Long(None),
+                3 => SortValue::Long(Some(reader.read_u64()? as i64)),
+                tag => return Err(CursorError(format!("Unknown sort value {}", tag))),
+            });
+        }
+        reader.finish()?;
+
+        Ok(Self { doc, score, fields })
+    }
+}
+
+/// First hits of a sorted search, in the order of the sort.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopFieldDocs {
+    /// Number of live docs matching the query, not only the ones returned.
+    pub total_hits: u64,
+    pub field_docs: Vec<FieldDoc>,
+}
+
+impl TopFieldDocs {
+    pub fn new(total_hits: u64, field_docs: Vec<FieldDoc>) -> Self {
+        Self {
+            total_hits,
+            field_docs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        document::Field,
+        index::{DirectoryReader, IndexError, Term},
+        search::{IndexSearcher, Sort, SortField, SortFieldType, TermQuery},
+        test_util::{doc, writer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_cursors() {
+        let score_doc = ScoreDoc::new(42, 1.25);
+        assert_eq!(ScoreDoc::from_cursor(&score_doc.to_cursor()), Ok(score_doc));
+
+        let field_doc = FieldDoc::new(
+            7,
+            0.5,
+            vec![
+                SortValue::Long(Some(-3)),
+                SortValue::Long(None),
+                SortValue::Score(0.5),
+                SortValue::Doc(7),
+            ],
+        );
+        let cursor = field_doc.to_cursor();
+        assert_eq!(FieldDoc::from_cursor(&cursor), Ok(field_doc));
+
+        assert!(ScoreDoc::from_cursor(&cursor).is_err());
+        assert!(FieldDoc::from_cursor(&cursor[..cursor.len() - 2]).is_err());
+        assert!(FieldDoc::from_cursor("not a cursor").is_err());
+    }
+
+    #[test]
+    fn test_search_after() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let mut writer = writer(&temp_dir.path().join("test-index"));
+        for i in 0..10 {
+            let mut doc = doc(&i.to_string(), &"tests ".repeat(i % 3 + 1));
+            doc.add(Field::numeric_doc_values(
+                "price".to_string(),
+                (i % 4) as i64,
+            ));
+            writer.add_document(doc).unwrap();
+            if i % 4 == 3 {
+                writer.flush().unwrap();
+            }
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+        let tests = TermQuery::new(Term::new("body".to_string(), "tests".to_string()));
+
+        // Pages of scored hits, resumed from cursors
+        let all = searcher.search(&tests, 10).unwrap().score_docs;
+        let mut pages = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let top_docs = match &after {
+                Some(cursor) => {
+                    let after = ScoreDoc::from_cursor(cursor).unwrap();
+                    searcher.search_after(&after, &tests, 3).unwrap()
+                }
+                None => searcher.search(&tests, 3).unwrap(),
+            };
+            assert_eq!(top_docs.total_hits, 10);
+            match top_docs.score_docs.last() {
+                Some(last) => after = Some(last.to_cursor()),
+                None => break,
+            }
+            pages.extend(top_docs.score_docs);
+        }
+        assert_eq!(pages, all);
+
+        // Pages of sorted hits
+        let mut price = SortField::new("price".to_string(), SortFieldType::Long).unwrap();
+        price.set_reverse(true);
+        let sort = Sort::new(vec![price, SortField::score()]).unwrap();
+        let all = searcher
+            .search_sorted(&tests, 10, &sort)
+            .unwrap()
+            .field_docs;
+        let mut pages = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let top_docs = match &after {
+                Some(cursor) => {
+                    let after = FieldDoc::from_cursor(cursor).unwrap();
+                    searcher
+                        .search_after_sorted(&after, &tests, 4, &sort)
+                        .unwrap()
+                }
+                None => searcher.search_sorted(&tests, 4, &sort).unwrap(),
+            };
+            assert_eq!(top_docs.total_hits, 10);
+            match top_docs.field_docs.last() {
+                Some(last) => after = Some(last.to_cursor()),
+                None => break,
+            }
+            pages.extend(top_docs.field_docs);
+        }
+        assert_eq!(pages, all);
+
+        // Cursors of another sort, or of scored hits, can't resume sorted hits
+        let index_order = searcher
+            .search_sorted(&tests, 1, &Sort::index_order())
+            .unwrap();
+        let after = FieldDoc::from_cursor(&index_order.field_docs[0].to_cursor()).unwrap();
+        assert!(matches!(
+            searcher.search_after_sorted(&after, &tests, 4, &sort),
+            Err(IndexError::IllegalArgument(_))
+        ));
+
+        let cursor = searcher.search(&tests, 1).unwrap().score_docs[0].to_cursor();
+        assert!(FieldDoc::from_cursor(&cursor).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use crate::index::{segment_reader::SegmentReader, IndexError};
+
+use super::{Explanation, Scorer};
+
+/// A query prepared for a searcher, holding the statistics of the whole index it needs
+/// to score each segment.
+pub trait Weight {
+    /// Scorer of the segment, `None` when no doc of the segment can match.
+    fn scorer<'a>(
+        &'a self,
+        reader: &'a SegmentReader,
+    ) -> Result<Option<Box<dyn Scorer + 'a>>, IndexError>;
+
+    /// How a doc of the segment is scored, or why it doesn't match.
+    /// Its value is the score of the doc, up to rounding.
+    fn explain(&self, reader: &SegmentReader, doc: u32) -> Result<Explanation, IndexError>;
+
+    /// Whether a doc of the segment matches, deleted or not.
+    fn matches(&self, reader: &SegmentReader, doc: u32) -> Result<bool, IndexError> {
+        Ok(match self.scorer(reader)? {
+            Some(mut scorer) => scorer.advance(doc) == doc,
+            None => false,
+        })
+    }
+}
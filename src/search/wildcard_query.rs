@@ -0,0 +1,137 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    index::{DirectoryReader, IndexError, Term},
+    util::{automaton::DEFAULT_MAX_DETERMINIZED_STATES, Automaton, AutomatonError},
+};
+
+use super::{
+    multi_term_query::{AutomatonQuery, RewriteMethod},
+    query::ScoreMode,
+    IndexSearcher, Query, Weight,
+};
+
+/// Matches docs containing a term matching a pattern, like `te?t*`.
+///
+/// `*` matches any string, `?` any char, and `\` escapes the next char.
+#[derive(Debug, Clone)]
+pub struct WildcardQuery {
+    pattern: Term,
+    query: AutomatonQuery,
+}
+
+impl WildcardQuery {
+    pub fn new(pattern: Term) -> Result<Self, AutomatonError> {
+        Self::with_max_determinized_states(pattern, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    /// Fails when the automaton of the pattern needs more than `max_determinized_states` states.
+    pub fn with_max_determinized_states(
+        pattern: Term,
+        max_determinized_states: usize,
+    ) -> Result<Self, AutomatonError> {
+        let automaton = Self::to_automaton(&pattern.text).determinize(max_determinized_states)?;
+        Ok(Self {
+            query: AutomatonQuery::new(pattern.field.clone(), automaton),
+            pattern,
+        })
+    }
+
+    fn to_automaton(pattern: &str) -> Automaton {
+        let mut automata = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            automata.push(match c {
+                '*' => Automaton::any_string(),
+                '?' => Automaton::any_char(),
+                '\\' => {
+                    // A trailing backslash is kept as is
+                    let c = chars.next().unwrap_or('\\');
+                    Automaton::char_range(c, c)
+                }
+                c => Automaton::char_range(c, c),
+            });
+        }
+        Automaton::concatenate(automata)
+    }
+
+    pub fn pattern(&self) -> &Term {
+        &self.pattern
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.query.rewrite_method()
+    }
+}
+
+impl Query for WildcardQuery {
+    fn rewrite(&self, reader: &DirectoryReader) -> Result<Option<Arc<dyn Query>>, IndexError> {
+        self.query.rewrite(reader)
+    }
+
+    fn create_weight(
+        &self,
+        _searcher: &IndexSearcher,
+        _score_mode: ScoreMode,
+        boost: f32,
+    ) -> Result<Box<dyn Weight>, IndexError> {
+        Ok(self.query.weight(self.to_string(), boost))
+    }
+}
+
+impl fmt::Display for WildcardQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.pattern.field, self.pattern.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        analysis::StandardAnalyzer,
+        document::{Document, Field},
+        index::IndexWriter,
+        store::FSDirectory,
+    };
+
+    use super::*;
+
+    fn wildcard(pattern: &str) -> WildcardQuery {
+        WildcardQuery::new(Term::new("body".to_string(), pattern.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_wildcard_query() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+        for body in ["test", "tests", "text", "tent*", "toast"] {
+            let mut doc = Document::new();
+            doc.add(Field::text("body".to_string(), body.to_string()));
+            writer.add_document(doc).unwrap();
+        }
+        let reader = DirectoryReader::open_from_writer(&mut writer).unwrap();
+        let searcher = IndexSearcher::new(&reader);
+
+        assert_eq!(searcher.count(&wildcard("te?t")).unwrap(), 2);
+        assert_eq!(searcher.count(&wildcard("te?t*")).unwrap(), 4);
+        assert_eq!(searcher.count(&wildcard("t*t")).unwrap(), 3);
+        assert_eq!(searcher.count(&wildcard("*")).unwrap(), 5);
+        assert_eq!(searcher.count(&wildcard("ten\\*")).unwrap(), 0);
+        assert_eq!(searcher.count(&wildcard("tent\\*")).unwrap(), 1);
+        assert_eq!(wildcard("te?t*").to_string(), "body:te?t*");
+    }
+
+    #[test]
+    fn test_too_complex() {
+        let pattern = Term::new("body".to_string(), "*a?????????????".to_string());
+        assert!(matches!(
+            WildcardQuery::with_max_determinized_states(pattern, 1000),
+            Err(AutomatonError::TooComplexToDeterminize(_))
+        ));
+    }
+}
@@ -2,11 +2,14 @@ pub mod directory;
 pub mod fs_directory;
 pub mod io_stream;
 pub mod ram_directory;
+pub mod rate_limiter;
 pub mod util;
 
 #[cfg(test)]
 pub mod mock_directory;
 
-pub use directory::{Directory, DirectoryError};
+pub use directory::{copy_file, copy_files, Directory, DirectoryError};
 pub use fs_directory::FSDirectory;
 pub use io_stream::{InputStream, OutputStream};
+pub use ram_directory::RAMDirectory;
+pub use rate_limiter::{RateLimitedDirectory, RateLimiter};
@@ -1,4 +1,4 @@
-use std::{io, time::SystemTime};
+use std::{io, sync::Arc, time::SystemTime};
 
 use thiserror::Error;
 
@@ -35,10 +35,105 @@ pub trait Directory {
     /// Open a file for reading.
     fn open_file(&self, name: &str) -> Result<Self::Input, DirectoryError>;
 
+    /// Ensures the given files are persisted to stable storage.
+    fn sync(&self, names: &[String]) -> Result<(), DirectoryError>;
+
+    /// Ensures renames and deletes of files are persisted to stable storage.
+    fn sync_meta_data(&self) -> Result<(), DirectoryError>;
+
     // Close the store
     fn close(&self) -> Result<(), DirectoryError>;
 }
 
+/// A shared directory, so that writers and readers can hold the same store.
+impl<D: Directory> Directory for Arc<D> {
+    type Output = D::Output;
+    type Input = D::Input;
+
+    fn list(&self) -> Result<Vec<String>, DirectoryError> {
+        self.as_ref().list()
+    }
+
+    fn file_exists(&self, name: &str) -> bool {
+        self.as_ref().file_exists(name)
+    }
+
+    fn file_modified_at(&self, name: &str) -> Result<SystemTime, DirectoryError> {
+        self.as_ref().file_modified_at(name)
+    }
+
+    fn file_length(&self, name: &str) -> Result<u64, DirectoryError> {
+        self.as_ref().file_length(name)
+    }
+
+    fn delete_file(&self, name: &str) -> Result<(), DirectoryError> {
+        self.as_ref().delete_file(name)
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), DirectoryError> {
+        self.as_ref().rename_file(from, to)
+    }
+
+    fn create_file(&self, name: &str) -> Result<Self::Output, DirectoryError> {
+        self.as_ref().create_file(name)
+    }
+
+    fn open_file(&self, name: &str) -> Result<Self::Input, DirectoryError> {
+        self.as_ref().open_file(name)
+    }
+
+    fn sync(&self, names: &[String]) -> Result<(), DirectoryError> {
+        self.as_ref().sync(names)
+    }
+
+    fn sync_meta_data(&self) -> Result<(), DirectoryError> {
+        self.as_ref().sync_meta_data()
+    }
+
+    fn close(&self) -> Result<(), DirectoryError> {
+        self.as_ref().close()
+    }
+}
+
+/// Bytes copied at once by `copy_file`.
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Copies a file to another directory, which can be of another kind.
+pub fn copy_file<S, T>(source: &S, target: &T, name: &str) -> Result<(), DirectoryError>
+where
+    S: Directory,
+    T: Directory,
+{
+    let mut remaining = source.file_length(name)? as usize;
+    let mut input = source.open_file(name)?;
+    let mut output = target.create_file(name)?;
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+
+    while remaining > 0 {
+        let length = remaining.min(COPY_BUFFER_SIZE);
+        input.read_exact(&mut buffer[..length]);
+        output.write_bytes(&buffer[..length]);
+        remaining -= length;
+    }
+
+    output.flush();
+
+    Ok(())
+}
+
+/// Copies files to another directory in the given order, and syncs them there.
+pub fn copy_files<S, T>(source: &S, target: &T, names: &[String]) -> Result<(), DirectoryError>
+where
+    S: Directory,
+    T: Directory,
+{
+    for name in names {
+        copy_file(source, target, name)?;
+    }
+
+    target.sync(names)
+}
+
 /// Error type for Directory operations.
 #[derive(Error, Debug)]
 pub enum DirectoryError {
@@ -48,4 +143,6 @@ pub enum DirectoryError {
     PathError(String),
     #[error("File Deleted Error")]
     FileDeletedError(String),
+    #[error("Corrupt Index Error")]
+    CorruptIndexError(String),
 }
@@ -1,3 +1,4 @@
+use crc32fast::Hasher;
 use itertools::Itertools;
 use std::{
     fs::{self, DirEntry, File},
@@ -81,8 +82,7 @@ impl Directory for FSDirectory {
     fn create_file(&self, name: &str) -> Result<Self::Output, DirectoryError> {
         let path = self.path.join(name);
         let mut open_options = fs::File::options();
-        let open_options = open_options
-            .read(true).write(true).create_new(true);
+        let open_options = open_options.read(true).write(true).create_new(true);
 
         Ok(open_options.open(path)?.into())
     }
@@ -92,6 +92,19 @@ impl Directory for FSDirectory {
         Ok(fs::File::open(path)?.into())
     }
 
+    fn sync(&self, names: &[String]) -> Result<(), DirectoryError> {
+        for name in names {
+            let path = self.path.join(name);
+            fs::File::open(path)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+
+    fn sync_meta_data(&self) -> Result<(), DirectoryError> {
+        Ok(fs::File::open(&self.path)?.sync_all()?)
+    }
+
     fn close(&self) -> Result<(), DirectoryError> {
         Ok(())
     }
@@ -109,12 +122,19 @@ impl InputStream for FSInputStream {
 
 pub struct FSOutputStream {
     writer: BufWriter<File>,
+    crc: Hasher,
 }
 
 // TODO: Improve error handling
 impl OutputStream for FSOutputStream {
     fn write_byte(&mut self, value: u8) {
         self.writer.write_all(&[value]).unwrap();
+        self.crc.update(&[value]);
+    }
+
+    fn write_bytes(&mut self, values: &[u8]) {
+        self.writer.write_all(values).unwrap();
+        self.crc.update(values);
     }
 
     fn seek(&mut self, position: u64) {
@@ -130,20 +150,25 @@ impl OutputStream for FSOutputStream {
     fn flush(&mut self) {
         self.writer.flush().unwrap();
     }
+
+    fn checksum(&self) -> u64 {
+        self.crc.clone().finalize() as u64
+    }
 }
 
-impl Into<FSInputStream> for File {
-    fn into(self) -> FSInputStream {
+impl From<File> for FSInputStream {
+    fn from(file: File) -> Self {
         FSInputStream {
-            reader: BufReader::new(self),
+            reader: BufReader::new(file),
         }
     }
 }
 
-impl Into<FSOutputStream> for File {
-    fn into(self) -> FSOutputStream {
+impl From<File> for FSOutputStream {
+    fn from(file: File) -> Self {
         FSOutputStream {
-            writer: BufWriter::new(self),
+            writer: BufWriter::new(file),
+            crc: Hasher::new(),
         }
     }
 }
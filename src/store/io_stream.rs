@@ -134,6 +134,9 @@ pub trait OutputStream {
     fn stream_position(&mut self) -> u64;
     fn flush(&mut self);
 
+    /// CRC32 of all the bytes written so far, as stored in codec footers.
+    fn checksum(&self) -> u64;
+
     fn write_bytes(&mut self, values: &[u8]) {
         for value in values {
             self.write_byte(*value);
@@ -190,4 +193,21 @@ pub trait OutputStream {
         self.write_vint(value.len() as u32);
         self.write_bytes(value.as_bytes());
     }
+
+    fn write_map(&mut self, map: &HashMap<String, String>) {
+        self.write_vint(map.len() as u32);
+
+        for (key, value) in map {
+            self.write_string(key);
+            self.write_string(value);
+        }
+    }
+
+    fn write_set(&mut self, set: &HashSet<String>) {
+        self.write_vint(set.len() as u32);
+
+        for value in set {
+            self.write_string(value);
+        }
+    }
 }
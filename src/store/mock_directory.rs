@@ -18,6 +18,7 @@ mock! {
         fn seek(&mut self, position: u64);
         fn stream_position(&mut self) -> u64;
         fn flush(&mut self);
+        fn checksum(&self) -> u64;
     }
 }
 
@@ -36,6 +37,8 @@ mock! {
         fn rename_file(&self, from: &str, to: &str) -> Result<(), DirectoryError>;
         fn create_file(&self, name: &str) -> Result<MockOutputStream, DirectoryError>;
         fn open_file(&self, name: &str) -> Result<MockInputStream, DirectoryError>;
+        fn sync(&self, names: &[String]) -> Result<(), DirectoryError>;
+        fn sync_meta_data(&self) -> Result<(), DirectoryError>;
         fn close(&self) -> Result<(), DirectoryError>;
     }
 }
@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use crc32fast::Hasher;
+use itertools::Itertools;
+
+use super::{Directory, DirectoryError, InputStream, OutputStream};
+
+struct RAMFile {
+    data: Bytes,
+    modified_at: SystemTime,
+}
+
+type RAMFiles = Arc<RwLock<HashMap<String, RAMFile>>>;
+
+/// Directory keeping all files in memory, for tests and small temporary indexes.
+///
+/// Clones share the same files. Written bytes become visible to readers
+/// when the output is flushed or dropped.
+#[derive(Clone, Default)]
+pub struct RAMDirectory {
+    files: RAMFiles,
+}
+
+impl RAMDirectory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total bytes of all files.
+    pub fn size_in_bytes(&self) -> u64 {
+        let files = self.files.read().unwrap();
+        files.values().map(|f| f.data.len() as u64).sum()
+    }
+
+    fn not_found(name: &str) -> DirectoryError {
+        DirectoryError::IOError(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("File not found - {}", name),
+        ))
+    }
+}
+
+impl Directory for RAMDirectory {
+    type Input = RAMInputStream;
+    type Output = RAMOutputStream;
+
+    fn list(&self) -> Result<Vec<String>, DirectoryError> {
+        let files = self.files.read().unwrap();
+        Ok(files.keys().cloned().sorted().collect())
+    }
+
+    fn file_exists(&self, name: &str) -> bool {
+        self.files.read().unwrap().contains_key(name)
+    }
+
+    fn file_modified_at(&self, name: &str) -> Result<SystemTime, DirectoryError> {
+        let files = self.files.read().unwrap();
+        files
+            .get(name)
+            .map(|f| f.modified_at)
+            .ok_or_else(|| Self::not_found(name))
+    }
+
+    fn file_length(&self, name: &str) -> Result<u64, DirectoryError> {
+        let files = self.files.read().unwrap();
+        files
+            .get(name)
+            .map(|f| f.data.len() as u64)
+            .ok_or_else(|| Self::not_found(name))
+    }
+
+    fn delete_file(&self, name: &str) -> Result<(), DirectoryError> {
+        let mut files = self.files.write().unwrap();
+        files
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| Self::not_found(name))
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), DirectoryError> {
+        let mut files = self.files.write().unwrap();
+        let file = files.remove(from).ok_or_else(|| Self::not_found(from))?;
+        files.insert(to.to_owned(), file);
+
+        Ok(())
+    }
+
+    fn create_file(&self, name: &str) -> Result<Self::Output, DirectoryError> {
+        let mut files = self.files.write().unwrap();
+
+        if files.contains_key(name) {
+            return Err(DirectoryError::IOError(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("File exists - {}", name),
+            )));
+        }
+
+        files.insert(
+            name.to_owned(),
+            RAMFile {
+                data: Bytes::new(),
+                modified_at: SystemTime::now(),
+            },
+        );
+
+        Ok(RAMOutputStream {
+            name: name.to_owned(),
+            files: self.files.clone(),
+            buffer: Vec::new(),
+            position: 0,
+            crc: Hasher::new(),
+        })
+    }
+
+    fn open_file(&self, name: &str) -> Result<Self::Input, DirectoryError> {
+        let files = self.files.read().unwrap();
+        let file = files.get(name).ok_or_else(|| Self::not_found(name))?;
+
+        Ok(RAMInputStream {
+            data: file.data.clone(),
+            position: 0,
+        })
+    }
+
+    /// Nothing to persist, files only live in memory.
+    fn sync(&self, names: &[String]) -> Result<(), DirectoryError> {
+        for name in names {
+            if !self.file_exists(name) {
+                return Err(Self::not_found(name));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sync_meta_data(&self) -> Result<(), DirectoryError> {
+        Ok(())
+    }
+
+    fn close(&self) -> Result<(), DirectoryError> {
+        Ok(())
+    }
+}
+
+pub struct RAMInputStream {
+    data: Bytes,
+    position: usize,
+}
+
+impl InputStream for RAMInputStream {
+    fn read_exact(&mut self, buf: &mut [u8]) {
+        let end = self.position + buf.len();
+        buf.copy_from_slice(&self.data[self.position..end]);
+        self.position = end;
+    }
+}
+
+pub struct RAMOutputStream {
+    name: String,
+    files: RAMFiles,
+    buffer: Vec<u8>,
+    position: usize,
+    crc: Hasher,
+}
+
+impl RAMOutputStream {
+    /// Makes the written bytes visible, unless the file was deleted meanwhile.
+    fn publish(&mut self) {
+        let mut files = self.files.write().unwrap();
+
+        if let Some(file) = files.get_mut(&self.name) {
+            file.data = Bytes::from(self.buffer.clone());
+            file.modified_at = SystemTime::now();
+        }
+    }
+}
+
+impl OutputStream for RAMOutputStream {
+    fn write_byte(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    fn write_bytes(&mut self, values: &[u8]) {
+        let end = self.position + values.len();
+        if end > self.buffer.len() {
+            self.buffer.resize(end, 0);
+        }
+
+        self.buffer[self.position..end].copy_from_slice(values);
+        self.position = end;
+        self.crc.update(values);
+    }
+
+    fn seek(&mut self, position: u64) {
+        self.position = position as usize;
+    }
+
+    fn stream_position(&mut self) -> u64 {
+        self.position as u64
+    }
+
+    fn flush(&mut self) {
+        self.publish();
+    }
+
+    fn checksum(&self) -> u64 {
+        self.crc.clone().finalize() as u64
+    }
+}
+
+impl Drop for RAMOutputStream {
+    fn drop(&mut self) {
+        self.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{copy_files, FSDirectory};
+
+    use super::*;
+
+    #[test]
+    fn test_write_and_read() {
+        let directory = RAMDirectory::new();
+
+        let mut output = directory.create_file("test.bin").unwrap();
+        output.write_vint(300);
+        output.write_string("ferrocene");
+
+        // Not visible before a flush
+        assert_eq!(directory.file_length("test.bin").unwrap(), 0);
+        output.flush();
+        drop(output);
+
+        assert_eq!(directory.file_length("test.bin").unwrap(), 12);
+        assert!(directory.create_file("test.bin").is_err());
+
+        let mut input = directory.open_file("test.bin").unwrap();
+        assert_eq!(input.read_vint(), 300);
+        assert_eq!(input.read_string(), "ferrocene");
+    }
+
+    #[test]
+    fn test_file_operations() {
+        let directory = RAMDirectory::new();
+        let shared = directory.clone();
+
+        drop(directory.create_file("b").unwrap());
+        drop(directory.create_file("a").unwrap());
+        assert_eq!(shared.list().unwrap(), vec!["a", "b"]);
+
+        directory.rename_file("a", "c").unwrap();
+        assert!(!shared.file_exists("a"));
+        assert!(shared.file_exists("c"));
+
+        directory.delete_file("c").unwrap();
+        assert!(directory.delete_file("c").is_err());
+        assert!(directory.open_file("c").is_err());
+        assert!(directory.sync(&["c".to_string()]).is_err());
+        assert_eq!(directory.list().unwrap(), vec!["b"]);
+    }
+
+    #[test]
+    fn test_copy_files() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let fs_directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let directory = RAMDirectory::new();
+
+        // Larger than the copy buffer
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let mut output = fs_directory.create_file("large.bin").unwrap();
+        output.write_bytes(&data);
+        output.flush();
+        drop(output);
+
+        let names = vec!["large.bin".to_string()];
+        copy_files(&fs_directory, &directory, &names).unwrap();
+        assert_eq!(directory.file_length("large.bin").unwrap(), 200_000);
+
+        // And back into another kind of directory
+        let other = FSDirectory::new(temp_dir.path().join("other-index")).unwrap();
+        copy_files(&directory, &other, &names).unwrap();
+        assert_eq!(other.open_file("large.bin").unwrap().read_bytes(200_000), data);
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::{Directory, DirectoryError, OutputStream};
+
+/// Bytes written between checks of the rate limit.
+const MIN_PAUSE_CHECK_BYTES: u64 = 64 * 1024;
+
+/// Limits the rate of bytes written, shared by all outputs writing through it.
+///
+/// Writers report bytes they wrote with `pause`, which sleeps long enough
+/// to keep the rate at or below `mb_per_sec`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    mb_per_sec: f64,
+    /// Time the bytes written so far are due at.
+    next_time: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(mb_per_sec: f64) -> Self {
+        assert!(mb_per_sec > 0.0, "mb_per_sec must be positive");
+
+        Self {
+            mb_per_sec,
+            next_time: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn mb_per_sec(&self) -> f64 {
+        self.mb_per_sec
+    }
+
+    /// Accounts for `bytes` written, and sleeps if they were written too fast.
+    /// Returns the time slept.
+    pub fn pause(&self, bytes: u64) -> Duration {
+        let duration = Duration::from_secs_f64(bytes as f64 / (self.mb_per_sec * 1024.0 * 1024.0));
+
+        let target = {
+            let mut next_time = self.next_time.lock().unwrap();
+            // Idle time is not saved up for later bursts
+            let now = Instant::now();
+            if *next_time < now {
+                *next_time = now;
+            }
+            *next_time += duration;
+            *next_time
+        };
+
+        let pause = target.saturating_duration_since(Instant::now());
+        if !pause.is_zero() {
+            thread::sleep(pause);
+        }
+
+        pause
+    }
+}
+
+/// Directory whose created files are written at most at the rate of the limiter.
+/// Reads and other operations are not limited.
+pub struct RateLimitedDirectory<'a, D> {
+    directory: &'a D,
+    rate_limiter: &'a RateLimiter,
+}
+
+impl<'a, D: Directory> RateLimitedDirectory<'a, D> {
+    pub fn new(directory: &'a D, rate_limiter: &'a RateLimiter) -> Self {
+        Self {
+            directory,
+            rate_limiter,
+        }
+    }
+}
+
+impl<'a, D: Directory> Directory for RateLimitedDirectory<'a, D> {
+    type Output = RateLimitedOutputStream<'a, D::Output>;
+    type Input = D::Input;
+
+    fn list(&self) -> Result<Vec<String>, DirectoryError> {
+        self.directory.list()
+    }
+
+    fn file_exists(&self, name: &str) -> bool {
+        self.directory.file_exists(name)
+    }
+
+    fn file_modified_at(&self, name: &str) -> Result<SystemTime, DirectoryError> {
+        self.directory.file_modified_at(name)
+    }
+
+    fn file_length(&self, name: &str) -> Result<u64, DirectoryError> {
+        self.directory.file_length(name)
+    }
+
+    fn delete_file(&self, name: &str) -> Result<(), DirectoryError> {
+        self.directory.delete_file(name)
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> Result<(), DirectoryError> {
+        self.directory.rename_file(from, to)
+    }
+
+    fn create_file(&self, name: &str) -> Result<Self::Output, DirectoryError> {
+        Ok(RateLimitedOutputStream {
+            output: self.directory.create_file(name)?,
+            rate_limiter: self.rate_limiter,
+            bytes_since_pause: 0,
+        })
+    }
+
+    fn open_file(&self, name: &str) -> Result<Self::Input, DirectoryError> {
+        self.directory.open_file(name)
+    }
+
+    fn sync(&self, names: &[String]) -> Result<(), DirectoryError> {
+        self.directory.sync(names)
+    }
+
+    fn sync_meta_data(&self) -> Result<(), DirectoryError> {
+        self.directory.sync_meta_data()
+    }
+
+    fn close(&self) -> Result<(), DirectoryError> {
+        self.directory.close()
+    }
+}
+
+pub struct RateLimitedOutputStream<'a, O> {
+    output: O,
+    rate_limiter: &'a RateLimiter,
+    bytes_since_pause: u64,
+}
+
+impl<O: OutputStream> RateLimitedOutputStream<'_, O> {
+    fn written(&mut self, bytes: u64) {
+        self.bytes_since_pause += bytes;
+
+        if self.bytes_since_pause >= MIN_PAUSE_CHECK_BYTES {
+            self.rate_limiter.pause(self.bytes_since_pause);
+            self.bytes_since_pause = 0;
+        }
+    }
+}
+
+impl<O: OutputStream> OutputStream for RateLimitedOutputStream<'_, O> {
+    fn write_byte(&mut self, value: u8) {
+        self.output.write_byte(value);
+        self.written(1);
+    }
+
+    fn write_bytes(&mut self, values: &[u8]) {
+        self.output.write_bytes(values);
+        self.written(values.len() as u64);
+    }
+
+    fn seek(&mut self, position: u64) {
+        self.output.seek(position);
+    }
+
+    fn stream_position(&mut self) -> u64 {
+        self.output.stream_position()
+    }
+
+    fn flush(&mut self) {
+        self.output.flush();
+    }
+
+    fn checksum(&self) -> u64 {
+        self.output.checksum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::{FSDirectory, InputStream};
+
+    use super::*;
+
+    #[test]
+    fn test_pause() {
+        // 1 MB per second, so 100 KB take about 100ms
+        let rate_limiter = RateLimiter::new(1.0);
+        let start = Instant::now();
+
+        for _ in 0..10 {
+            rate_limiter.pause(10 * 1024);
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn test_rate_limited_directory() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let directory = FSDirectory::new(temp_dir.path().join("test-index")).unwrap();
+        let rate_limiter = RateLimiter::new(2.0);
+        let limited = RateLimitedDirectory::new(&directory, &rate_limiter);
+
+        let start = Instant::now();
+        let mut output = limited.create_file("test.bin").unwrap();
+        output.write_bytes(&[7; 256 * 1024]);
+        output.flush();
+
+        // 256 KB at 2 MB per second
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let mut input = limited.open_file("test.bin").unwrap();
+        assert_eq!(input.read_bytes(3), vec![7, 7, 7]);
+        assert_eq!(limited.file_length("test.bin").unwrap(), 256 * 1024);
+    }
+}
@@ -2,8 +2,18 @@ use std::{collections::{HashMap, HashSet}, string::FromUtf8Error};
 
 use bytes::{Buf, Bytes};
 
+use super::{Directory, DirectoryError, InputStream};
+
 pub const ID_LENGTH: usize = 16;
 
+/// Reads a whole file from the directory into a Bytes buffer.
+pub fn read_file<D: Directory>(directory: &D, name: &str) -> Result<Bytes, DirectoryError> {
+    let length = directory.file_length(name)?;
+    let mut input = directory.open_file(name)?;
+
+    Ok(Bytes::from(input.read_bytes(length as usize)))
+}
+
 /// Reads a fixed-length string from a Bytes buffer.
 pub fn read_string_fixed(bytes: &mut Bytes, length: usize) -> Result<String, FromUtf8Error> {
     match length {
@@ -45,6 +55,24 @@ pub fn read_vint(bytes: &mut Bytes) -> u32 {
     value
 }
 
+/// Reads a vlong from a Bytes buffer.
+pub fn read_vlong(bytes: &mut Bytes) -> u64 {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+
+    loop {
+        let b = bytes.get_u8();
+        value |= ((b & 0x7F) as u64) << shift;
+        shift += 7;
+
+        if b & 0x80 == 0 {
+            break;
+        }
+    }
+
+    value
+}
+
 pub fn read_id(bytes: &mut Bytes) -> Vec<u8> {
     read_bytes(bytes, ID_LENGTH)
 }
@@ -0,0 +1,48 @@
+//! Fixtures shared by tests.
+
+use std::path::Path;
+
+use crate::{
+    analysis::StandardAnalyzer,
+    document::{Document, Field},
+    index::{DirectoryReader, IndexWriter, NoMergePolicy},
+    store::FSDirectory,
+};
+
+/// Doc with a keyword `id` and a text `body`.
+pub fn doc(id: &str, body: &str) -> Document {
+    let mut doc = Document::new();
+    doc.add(Field::keyword("id".to_string(), id.to_string()));
+    doc.add(Field::text("body".to_string(), body.to_string()));
+    doc
+}
+
+/// Writer of a new index that never merges, so tests control the segments.
+pub fn writer(path: &Path) -> IndexWriter<StandardAnalyzer, FSDirectory> {
+    let directory = FSDirectory::new(path).unwrap();
+    let mut writer = IndexWriter::create(directory, StandardAnalyzer::new()).unwrap();
+    writer.set_merge_policy(NoMergePolicy);
+    writer
+}
+
+/// Writer of a new index with a doc per body, and a segment per slice of bodies.
+/// Docs have IDs from 1 in the order of their bodies.
+pub fn index(path: &Path, segments: &[&[&str]]) -> IndexWriter<StandardAnalyzer, FSDirectory> {
+    let mut writer = writer(path);
+
+    let mut id = 0;
+    for bodies in segments {
+        for body in bodies.iter() {
+            id += 1;
+            writer.add_document(doc(&id.to_string(), body)).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    writer
+}
+
+/// Near-real-time reader of an index built like `index`.
+pub fn reader(path: &Path, segments: &[&[&str]]) -> DirectoryReader {
+    DirectoryReader::open_from_writer(&mut index(path, segments)).unwrap()
+}
@@ -0,0 +1,9 @@
+pub mod automaton;
+pub mod fixed_bit_set;
+pub mod levenshtein_automata;
+pub mod priority_queue;
+pub mod regexp;
+
+pub use automaton::{Automaton, AutomatonError};
+pub use fixed_bit_set::FixedBitSet;
+pub use priority_queue::PriorityQueue;
@@ -0,0 +1,539 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use thiserror::Error;
+
+/// Largest Unicode code point.
+pub const MAX_CODE_POINT: u32 = 0x10FFFF;
+
+/// Default maximum number of states of a determinized automaton.
+pub const DEFAULT_MAX_DETERMINIZED_STATES: usize = 10_000;
+
+/// Error type for building automata.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AutomatonError {
+    #[error("Too Complex To Determinize")]
+    TooComplexToDeterminize(String),
+    #[error("Invalid Syntax")]
+    InvalidSyntax(String),
+}
+
+/// Transition on the code points from `min` to `max`, included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub min: u32,
+    pub max: u32,
+    pub to: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct State {
+    accept: bool,
+    transitions: Vec<Transition>,
+    epsilons: Vec<usize>,
+}
+
+/// A finite automaton over code points, starting at state 0.
+///
+/// Operations like `concatenate` and `union` may add epsilon transitions and
+/// overlapping transitions. `determinize` removes them, only deterministic
+/// automata can `run` on terms.
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    states: Vec<State>,
+}
+
+impl Automaton {
+    /// Adds a state without transitions, returns its index.
+    pub fn add_state(&mut self, accept: bool) -> usize {
+        self.states.push(State {
+            accept,
+            ..Default::default()
+        });
+        self.states.len() - 1
+    }
+
+    pub fn set_accept(&mut self, state: usize, accept: bool) {
+        self.states[state].accept = accept;
+    }
+
+    /// Adds a transition on the code points from `min` to `max`, included.
+    pub fn add_transition(&mut self, from: usize, to: usize, min: u32, max: u32) {
+        self.states[from]
+            .transitions
+            .push(Transition { min, max, to });
+    }
+
+    /// Adds a transition reading nothing.
+    pub fn add_epsilon(&mut self, from: usize, to: usize) {
+        self.states[from].epsilons.push(to);
+    }
+
+    /// Copies the states of another automaton, returns the index of its start state.
+    fn append(&mut self, other: &Automaton) -> usize {
+        let offset = self.states.len();
+        for state in &other.states {
+            self.states.push(State {
+                accept: state.accept,
+                transitions: state
+                    .transitions
+                    .iter()
+                    .map(|t| Transition {
+                        to: t.to + offset,
+                        ..*t
+                    })
+                    .collect(),
+                epsilons: state.epsilons.iter().map(|&s| s + offset).collect(),
+            });
+        }
+        offset
+    }
+
+    /// Accepts no string.
+    pub fn empty() -> Self {
+        Self {
+            states: vec![State::default()],
+        }
+    }
+
+    /// Accepts only the empty string.
+    pub fn empty_string() -> Self {
+        let mut automaton = Self { states: vec![] };
+        automaton.add_state(true);
+        automaton
+    }
+
+    /// Accepts a single code point within one of the ranges.
+    pub fn char_ranges(ranges: &[(u32, u32)]) -> Self {
+        let mut automaton = Self::empty();
+        let accept = automaton.add_state(true);
+        for &(min, max) in ranges {
+            automaton.add_transition(0, accept, min, max);
+        }
+        automaton
+    }
+
+    pub fn char_range(min: char, max: char) -> Self {
+        Self::char_ranges(&[(min as u32, max as u32)])
+    }
+
+    pub fn any_char() -> Self {
+        Self::char_ranges(&[(0, MAX_CODE_POINT)])
+    }
+
+    pub fn any_string() -> Self {
+        let mut automaton = Self::empty_string();
+        automaton.add_transition(0, 0, 0, MAX_CODE_POINT);
+        automaton
+    }
+
+    /// Accepts only the string.
+    pub fn string(text: &str) -> Self {
+        let mut automaton = Self::empty();
+        let mut state = 0;
+        for c in text.chars() {
+            let next = automaton.add_state(false);
+            automaton.add_transition(state, next, c as u32, c as u32);
+            state = next;
+        }
+        automaton.states[state].accept = true;
+        automaton
+    }
+
+    /// Accepts the strings starting with the prefix, deterministic.
+    pub fn prefix(prefix: &str) -> Self {
+        let mut automaton = Self::string(prefix);
+        let last = automaton.states.len() - 1;
+        automaton.add_transition(last, last, 0, MAX_CODE_POINT);
+        automaton
+    }
+
+    /// Accepts the concatenation of strings accepted by each automaton, in order.
+    pub fn concatenate(automata: Vec<Automaton>) -> Self {
+        let mut result = Self::empty_string();
+        let mut accepts = vec![0];
+
+        for automaton in &automata {
+            let start = result.append(automaton);
+            for &accept in &accepts {
+                result.states[accept].accept = false;
+                result.add_epsilon(accept, start);
+            }
+            accepts = (start..result.states.len())
+                .filter(|&s| result.states[s].accept)
+                .collect();
+        }
+
+        result
+    }
+
+    /// Accepts the strings any automaton accepts.
+    pub fn union(automata: Vec<Automaton>) -> Self {
+        let mut result = Self::empty();
+        for automaton in &automata {
+            let start = result.append(automaton);
+            result.add_epsilon(0, start);
+        }
+        result
+    }
+
+    /// Accepts the empty string and the strings the automaton accepts.
+    pub fn optional(automaton: Automaton) -> Self {
+        Self::union(vec![automaton, Self::empty_string()])
+    }
+
+    /// Accepts any number of strings the automaton accepts, one after the other.
+    pub fn repeat(automaton: Automaton) -> Self {
+        let mut result = Self::empty_string();
+        let start = result.append(&automaton);
+        result.add_epsilon(0, start);
+        for state in start..result.states.len() {
+            if result.states[state].accept {
+                result.add_epsilon(state, 0);
+            }
+        }
+        result
+    }
+
+    /// Accepts at least `min` strings the automaton accepts, one after the other.
+    pub fn repeat_min(automaton: Automaton, min: usize) -> Self {
+        let mut automata = vec![automaton.clone(); min];
+        automata.push(Self::repeat(automaton));
+        Self::concatenate(automata)
+    }
+
+    /// Accepts from `min` to `max` strings the automaton accepts, one after the other.
+    pub fn repeat_range(automaton: Automaton, min: usize, max: usize) -> Self {
+        assert!(min <= max, "min must be at most max");
+
+        let mut automata = vec![automaton.clone(); min];
+        automata.extend((min..max).map(|_| Self::optional(automaton.clone())));
+        Self::concatenate(automata)
+    }
+
+    /// Accepts the strings between the bounds in code point order, `None` for an open bound.
+    pub fn range(
+        lower: Option<&str>,
+        upper: Option<&str>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> Self {
+        let at_least = match lower {
+            Some(lower) => Self::at_least(lower, include_lower),
+            None => Self::any_string(),
+        };
+        let at_most = match upper {
+            Some(upper) => Self::at_most(upper, include_upper),
+            None => Self::any_string(),
+        };
+        at_least.intersection(&at_most)
+    }
+
+    /// Accepts the strings after `lower`, deterministic.
+    fn at_least(lower: &str, inclusive: bool) -> Self {
+        let mut automaton = Self::string(lower);
+        let last = automaton.states.len() - 1;
+        automaton.states[last].accept = inclusive;
+
+        // Any string after a smaller code point, or longer than `lower`
+        let all = automaton.add_state(true);
+        automaton.add_transition(all, all, 0, MAX_CODE_POINT);
+        for (state, c) in lower.chars().enumerate() {
+            if (c as u32) < MAX_CODE_POINT {
+                automaton.add_transition(state, all, c as u32 + 1, MAX_CODE_POINT);
+            }
+        }
+        automaton.add_transition(last, all, 0, MAX_CODE_POINT);
+
+        automaton
+    }
+
+    /// Accepts the strings before `upper`, deterministic.
+    fn at_most(upper: &str, inclusive: bool) -> Self {
+        let mut automaton = Self::string(upper);
+        let last = automaton.states.len() - 1;
+        automaton.states[last].accept = inclusive;
+
+        // Prefixes of `upper`, and any string after a bigger code point
+        let all = automaton.add_state(true);
+        automaton.add_transition(all, all, 0, MAX_CODE_POINT);
+        for (state, c) in upper.chars().enumerate() {
+            automaton.states[state].accept = true;
+            if c as u32 > 0 {
+                automaton.add_transition(state, all, 0, c as u32 - 1);
+            }
+        }
+
+        automaton
+    }
+
+    /// Accepts the strings both deterministic automata accept.
+    pub fn intersection(&self, other: &Automaton) -> Self {
+        assert!(
+            self.is_deterministic() && other.is_deterministic(),
+            "Only deterministic automata can be intersected"
+        );
+
+        let mut result = Self { states: vec![] };
+        let mut ids = HashMap::new();
+        let mut pending = vec![(0, 0)];
+        ids.insert(
+            (0, 0),
+            result.add_state(self.is_accept(0) && other.is_accept(0)),
+        );
+
+        while let Some((a, b)) = pending.pop() {
+            let from = ids[&(a, b)];
+            for t1 in &self.states[a].transitions {
+                for t2 in &other.states[b].transitions {
+                    let (min, max) = (t1.min.max(t2.min), t1.max.min(t2.max));
+                    if min > max {
+                        continue;
+                    }
+
+                    let pair = (t1.to, t2.to);
+                    let to = match ids.get(&pair) {
+                        Some(&to) => to,
+                        None => {
+                            let to =
+                                result.add_state(self.is_accept(t1.to) && other.is_accept(t2.to));
+                            ids.insert(pair, to);
+                            pending.push(pair);
+                            to
+                        }
+                    };
+                    result.add_transition(from, to, min, max);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Equivalent deterministic automaton, built with the subset construction.
+    ///
+    /// Fails when it would have more than `max_states` states, which patterns
+    /// like `(a|b)*a(a|b)(a|b)...` quickly reach.
+    pub fn determinize(&self, max_states: usize) -> Result<Self, AutomatonError> {
+        let mut result = Self { states: vec![] };
+        let mut ids: HashMap<Vec<usize>, usize> = HashMap::new();
+
+        let start = self.closure([0].into_iter().collect());
+        result.add_state(start.iter().any(|&s| self.states[s].accept));
+        ids.insert(start.clone(), 0);
+        let mut pending = vec![start];
+
+        while let Some(set) = pending.pop() {
+            let from = ids[&set];
+
+            // Code points where the reachable states may change
+            let mut points: Vec<u32> = set
+                .iter()
+                .flat_map(|&s| &self.states[s].transitions)
+                .flat_map(|t| [t.min, t.max + 1])
+                .collect();
+            points.sort_unstable();
+            points.dedup();
+
+            for window in points.windows(2) {
+                let (min, max) = (window[0], window[1] - 1);
+                let targets: BTreeSet<usize> = set
+                    .iter()
+                    .flat_map(|&s| &self.states[s].transitions)
+                    .filter(|t| t.min <= min && min <= t.max)
+                    .map(|t| t.to)
+                    .collect();
+                if targets.is_empty() {
+                    continue;
+                }
+
+                let target = self.closure(targets);
+                let to = match ids.get(&target) {
+                    Some(&to) => to,
+                    None => {
+                        if result.states.len() == max_states {
+                            return Err(AutomatonError::TooComplexToDeterminize(format!(
+                                "Determinizing needs more than {} states",
+                                max_states
+                            )));
+                        }
+
+                        let accept = target.iter().any(|&s| self.states[s].accept);
+                        let to = result.add_state(accept);
+                        ids.insert(target.clone(), to);
+                        pending.push(target);
+                        to
+                    }
+                };
+
+                // Merge with the previous range when contiguous
+                match result.states[from].transitions.last_mut() {
+                    Some(last) if last.to == to && last.max + 1 == min => last.max = max,
+                    _ => result.add_transition(from, to, min, max),
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// States reachable from the set with epsilon transitions, sorted.
+    fn closure(&self, mut set: BTreeSet<usize>) -> Vec<usize> {
+        let mut pending: Vec<usize> = set.iter().copied().collect();
+        while let Some(state) = pending.pop() {
+            for &next in &self.states[state].epsilons {
+                if set.insert(next) {
+                    pending.push(next);
+                }
+            }
+        }
+        set.into_iter().collect()
+    }
+
+    /// Whether there are no epsilon transitions and no overlapping transitions.
+    pub fn is_deterministic(&self) -> bool {
+        self.states.iter().all(|state| {
+            let mut ranges: Vec<_> = state.transitions.iter().map(|t| (t.min, t.max)).collect();
+            ranges.sort_unstable();
+            state.epsilons.is_empty() && ranges.windows(2).all(|w| w[0].1 < w[1].0)
+        })
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.states[state].accept
+    }
+
+    pub fn transitions(&self, state: usize) -> &[Transition] {
+        &self.states[state].transitions
+    }
+
+    /// State after reading the code point, `None` when no string is accepted from there.
+    pub fn step(&self, state: usize, c: char) -> Option<usize> {
+        let c = c as u32;
+        self.states[state]
+            .transitions
+            .iter()
+            .find(|t| t.min <= c && c <= t.max)
+            .map(|t| t.to)
+    }
+
+    /// Whether the deterministic automaton accepts the text.
+    pub fn run(&self, text: &str) -> bool {
+        debug_assert!(self.is_deterministic());
+
+        let mut state = 0;
+        for c in text.chars() {
+            match self.step(state, c) {
+                Some(next) => state = next,
+                None => return false,
+            }
+        }
+        self.states[state].accept
+    }
+
+    /// Longest prefix of all accepted strings of the deterministic automaton.
+    pub fn common_prefix(&self) -> String {
+        let mut prefix = String::new();
+        let mut visited = HashSet::new();
+        let mut state = 0;
+
+        while visited.insert(state) && !self.states[state].accept {
+            match self.states[state].transitions.as_slice() {
+                [t] if t.min == t.max => {
+                    prefix.extend(char::from_u32(t.min));
+                    state = t.to;
+                }
+                _ => break,
+            }
+        }
+
+        prefix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dfa(automaton: Automaton) -> Automaton {
+        automaton
+            .determinize(DEFAULT_MAX_DETERMINIZED_STATES)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_operations() {
+        let automaton = dfa(Automaton::concatenate(vec![
+            Automaton::string("te"),
+            Automaton::union(vec![Automaton::string("s"), Automaton::string("x")]),
+            Automaton::repeat(Automaton::string("t")),
+        ]));
+        assert!(automaton.is_deterministic());
+        assert!(automaton.run("tes"));
+        assert!(automaton.run("text"));
+        assert!(automaton.run("testtt"));
+        assert!(!automaton.run("te"));
+        assert!(!automaton.run("tesx"));
+        assert_eq!(automaton.common_prefix(), "te");
+
+        let automaton = dfa(Automaton::repeat_range(Automaton::any_char(), 2, 3));
+        assert!(!automaton.run("a"));
+        assert!(automaton.run("ab"));
+        assert!(automaton.run("abc"));
+        assert!(!automaton.run("abcd"));
+
+        let automaton = dfa(Automaton::repeat_min(Automaton::char_range('a', 'c'), 1));
+        assert!(automaton.run("abcba"));
+        assert!(!automaton.run(""));
+        assert!(!automaton.run("abd"));
+
+        assert!(!dfa(Automaton::empty()).run(""));
+        assert!(dfa(Automaton::empty_string()).run(""));
+        assert!(dfa(Automaton::optional(Automaton::string("a"))).run(""));
+        assert!(Automaton::prefix("tes").run("tests"));
+        assert_eq!(Automaton::prefix("tes").common_prefix(), "tes");
+    }
+
+    #[test]
+    fn test_range() {
+        let automaton = Automaton::range(Some("b"), Some("d"), true, false);
+        assert!(automaton.is_deterministic());
+        for text in ["b", "ba", "c", "czz"] {
+            assert!(automaton.run(text), "{} in range", text);
+        }
+        for text in ["", "a", "azz", "d", "da", "e"] {
+            assert!(!automaton.run(text), "{} not in range", text);
+        }
+
+        let automaton = Automaton::range(Some("b"), Some("d"), false, true);
+        assert!(!automaton.run("b"));
+        assert!(automaton.run("d"));
+
+        let automaton = Automaton::range(None, Some("bc"), true, true);
+        assert!(automaton.run(""));
+        assert!(automaton.run("b"));
+        assert!(automaton.run("bbz"));
+        assert!(!automaton.run("bca"));
+
+        let automaton = Automaton::range(Some("test"), Some("text"), true, true);
+        assert_eq!(automaton.common_prefix(), "te");
+    }
+
+    #[test]
+    fn test_determinize_limit() {
+        // The n-th last char being an `a` needs 2^n states
+        let ab = || Automaton::char_range('a', 'b');
+        let mut automata = vec![Automaton::repeat(ab()), Automaton::string("a")];
+        automata.extend((0..12).map(|_| ab()));
+        let automaton = Automaton::concatenate(automata);
+
+        assert!(matches!(
+            automaton.determinize(1000),
+            Err(AutomatonError::TooComplexToDeterminize(_))
+        ));
+        assert!(automaton.determinize(10_000).is_ok());
+    }
+}
@@ -0,0 +1,151 @@
+/// A bit set of fixed length, backed by 64 bit words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedBitSet {
+    words: Vec<u64>,
+    len: u32,
+}
+
+impl FixedBitSet {
+    /// Creates a bit set of `len` bits, all cleared.
+    pub fn new(len: u32) -> Self {
+        Self {
+            words: vec![0; Self::num_words(len)],
+            len,
+        }
+    }
+
+    /// Creates a bit set of `len` bits, all set.
+    pub fn all(len: u32) -> Self {
+        let mut bits = Self {
+            words: vec![u64::MAX; Self::num_words(len)],
+            len,
+        };
+        bits.clear_ghost_bits();
+        bits
+    }
+
+    /// Creates a bit set from words, as returned by `words`.
+    pub fn from_words(words: Vec<u64>, len: u32) -> Self {
+        assert_eq!(words.len(), Self::num_words(len), "Invalid number of words");
+        Self { words, len }
+    }
+
+    /// Number of words needed to hold `len` bits.
+    pub fn num_words(len: u32) -> usize {
+        (len as usize).div_ceil(64)
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: u32) -> bool {
+        debug_assert!(index < self.len);
+        self.words[(index / 64) as usize] & (1 << (index % 64)) != 0
+    }
+
+    pub fn set(&mut self, index: u32) {
+        debug_assert!(index < self.len);
+        self.words[(index / 64) as usize] |= 1 << (index % 64);
+    }
+
+    pub fn clear(&mut self, index: u32) {
+        debug_assert!(index < self.len);
+        self.words[(index / 64) as usize] &= !(1 << (index % 64));
+    }
+
+    /// Number of set bits.
+    pub fn cardinality(&self) -> u32 {
+        self.words.iter().map(|w| w.count_ones()).sum()
+    }
+
+    /// Index of the first set bit at or after `index`, if any.
+    pub fn next_set_bit(&self, index: u32) -> Option<u32> {
+        if index >= self.len {
+            return None;
+        }
+
+        let mut word_index = (index / 64) as usize;
+        let mut word = self.words[word_index] >> (index % 64);
+
+        if word != 0 {
+            return Some(index + word.trailing_zeros());
+        }
+
+        loop {
+            word_index += 1;
+            if word_index >= self.words.len() {
+                return None;
+            }
+
+            word = self.words[word_index];
+            if word != 0 {
+                return Some(word_index as u32 * 64 + word.trailing_zeros());
+            }
+        }
+    }
+
+    fn clear_ghost_bits(&mut self) {
+        let extra = self.len % 64;
+
+        if extra != 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << extra) - 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_clear() {
+        let mut bits = FixedBitSet::new(130);
+
+        bits.set(0);
+        bits.set(64);
+        bits.set(129);
+
+        assert!(bits.get(0));
+        assert!(!bits.get(1));
+        assert!(bits.get(64));
+        assert!(bits.get(129));
+        assert_eq!(bits.cardinality(), 3);
+
+        bits.clear(64);
+        assert!(!bits.get(64));
+        assert_eq!(bits.cardinality(), 2);
+    }
+
+    #[test]
+    fn test_all() {
+        let bits = FixedBitSet::all(70);
+
+        assert_eq!(bits.cardinality(), 70);
+        assert_eq!(bits.words().len(), 2);
+        assert_eq!(FixedBitSet::all(0).cardinality(), 0);
+    }
+
+    #[test]
+    fn test_next_set_bit() {
+        let mut bits = FixedBitSet::new(200);
+        bits.set(3);
+        bits.set(150);
+
+        assert_eq!(bits.next_set_bit(0), Some(3));
+        assert_eq!(bits.next_set_bit(3), Some(3));
+        assert_eq!(bits.next_set_bit(4), Some(150));
+        assert_eq!(bits.next_set_bit(151), None);
+        assert_eq!(bits.next_set_bit(200), None);
+    }
+}
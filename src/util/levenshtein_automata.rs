@@ -0,0 +1,150 @@
+use super::automaton::{Automaton, MAX_CODE_POINT};
+
+/// Maximum number of edits supported, larger distances match too many terms to be useful.
+pub const MAX_EDITS: u32 = 2;
+
+/// Non-deterministic automaton accepting the strings within `max_edits` of the text.
+///
+/// An edit inserts, deletes or substitutes a char, or with `transpositions`
+/// swaps two adjacent chars.
+pub fn automaton(text: &str, max_edits: u32, transpositions: bool) -> Automaton {
+    assert!(
+        max_edits <= MAX_EDITS,
+        "max_edits must be at most {}",
+        MAX_EDITS
+    );
+
+    let chars: Vec<u32> = text.chars().map(|c| c as u32).collect();
+    let n = chars.len();
+    let k = max_edits as usize;
+
+    // State of `i` chars of the text read with `e` edits
+    let state = |i: usize, e: usize| e * (n + 1) + i;
+
+    let mut automaton = Automaton::empty();
+    automaton.set_accept(0, n == 0);
+    for e in 0..=k {
+        for i in 0..=n {
+            if state(i, e) > 0 {
+                automaton.add_state(i == n);
+            }
+        }
+    }
+
+    for e in 0..=k {
+        for i in 0..=n {
+            let from = state(i, e);
+            if i < n {
+                automaton.add_transition(from, state(i + 1, e), chars[i], chars[i]);
+            }
+            if e == k {
+                continue;
+            }
+
+            // Insertion
+            automaton.add_transition(from, state(i, e + 1), 0, MAX_CODE_POINT);
+            if i < n {
+                // Substitution and deletion
+                automaton.add_transition(from, state(i + 1, e + 1), 0, MAX_CODE_POINT);
+                automaton.add_epsilon(from, state(i + 1, e + 1));
+            }
+            if transpositions && i + 1 < n && chars[i] != chars[i + 1] {
+                let swapped = automaton.add_state(false);
+                automaton.add_transition(from, swapped, chars[i + 1], chars[i + 1]);
+                automaton.add_transition(swapped, state(i + 2, e + 1), chars[i], chars[i]);
+            }
+        }
+    }
+
+    automaton
+}
+
+/// Edit distance between two strings, counting adjacent swaps as one edit with `transpositions`.
+pub fn distance(a: &str, b: &str, transpositions: bool) -> u32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Rows of distances between prefixes of `a` and `b`
+    let mut before: Vec<u32> = vec![0; b.len() + 1];
+    let mut previous: Vec<u32> = (0..=b.len() as u32).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i as u32;
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as u32;
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+
+            if transpositions && i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::DEFAULT_MAX_DETERMINIZED_STATES;
+
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("ferrocene", "ferrocene", false), 0);
+        assert_eq!(distance("ferrocene", "ferocene", false), 1);
+        assert_eq!(distance("ferrocene", "ferroceen", false), 2);
+        assert_eq!(distance("ferrocene", "ferroceen", true), 1);
+        assert_eq!(distance("", "abc", true), 3);
+        assert_eq!(distance("abc", "", true), 3);
+    }
+
+    #[test]
+    fn test_automaton() {
+        let words = [
+            "",
+            "f",
+            "fe",
+            "ef",
+            "fer",
+            "ferr",
+            "frer",
+            "ferro",
+            "ferocene",
+            "ferrocene",
+            "ferroceen",
+            "ferrocenes",
+            "fetrocene",
+            "errocene",
+            "erocene",
+            "ferrocne",
+        ];
+
+        for text in ["", "f", "fer", "ferrocene"] {
+            for max_edits in 0..=MAX_EDITS {
+                for transpositions in [false, true] {
+                    let automaton = automaton(text, max_edits, transpositions)
+                        .determinize(DEFAULT_MAX_DETERMINIZED_STATES)
+                        .unwrap();
+
+                    for word in words {
+                        assert_eq!(
+                            automaton.run(word),
+                            distance(text, word, transpositions) <= max_edits,
+                            "{} within {} of {}",
+                            word,
+                            max_edits,
+                            text
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
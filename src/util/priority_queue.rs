@@ -0,0 +1,144 @@
+/// A bounded min-heap, ordered by a `less_than` comparator.
+///
+/// The least element is on top, so the queue keeps the `max_size` greatest
+/// elements added, like the best hits of a search.
+pub struct PriorityQueue<T, F> {
+    heap: Vec<T>,
+    max_size: usize,
+    less_than: F,
+}
+
+impl<T, F> PriorityQueue<T, F>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    pub fn new(max_size: usize, less_than: F) -> Self {
+        Self {
+            heap: Vec::with_capacity(max_size.min(1024)),
+            max_size,
+            less_than,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.heap.len() >= self.max_size
+    }
+
+    /// Least element of the queue.
+    pub fn top(&self) -> Option<&T> {
+        self.heap.first()
+    }
+
+    /// Adds an element, dropping the least one once the queue is full.
+    /// Returns the element dropped, which is `element` itself if it is the least.
+    pub fn insert_with_overflow(&mut self, element: T) -> Option<T> {
+        if self.heap.len() < self.max_size {
+            self.heap.push(element);
+            self.up_heap(self.heap.len() - 1);
+            return None;
+        }
+
+        match self.heap.first() {
+            Some(top) if (self.less_than)(top, &element) => {
+                let dropped = std::mem::replace(&mut self.heap[0], element);
+                self.down_heap(0);
+                Some(dropped)
+            }
+            _ => Some(element),
+        }
+    }
+
+    /// Removes and returns the least element.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.heap.is_empty() {
+            return None;
+        }
+
+        let last = self.heap.len() - 1;
+        self.heap.swap(0, last);
+        let top = self.heap.pop();
+        self.down_heap(0);
+        top
+    }
+
+    /// All elements, greatest first.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut sorted = Vec::with_capacity(self.heap.len());
+        while let Some(element) = self.pop() {
+            sorted.push(element);
+        }
+        sorted.reverse();
+        sorted
+    }
+
+    fn up_heap(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !(self.less_than)(&self.heap[i], &self.heap[parent]) {
+                break;
+            }
+            self.heap.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn down_heap(&mut self, mut i: usize) {
+        let len = self.heap.len();
+
+        loop {
+            let left = 2 * i + 1;
+            let right = left + 1;
+            let mut least = i;
+
+            if left < len && (self.less_than)(&self.heap[left], &self.heap[least]) {
+                least = left;
+            }
+            if right < len && (self.less_than)(&self.heap[right], &self.heap[least]) {
+                least = right;
+            }
+            if least == i {
+                break;
+            }
+
+            self.heap.swap(i, least);
+            i = least;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_greatest() {
+        let mut queue = PriorityQueue::new(3, |a: &u32, b: &u32| a < b);
+
+        for value in [5, 1, 8, 3, 9, 2] {
+            queue.insert_with_overflow(value);
+        }
+
+        assert_eq!(queue.len(), 3);
+        assert!(queue.is_full());
+        assert_eq!(queue.top(), Some(&5));
+        assert_eq!(queue.insert_with_overflow(4), Some(4));
+        assert_eq!(queue.into_sorted_vec(), vec![9, 8, 5]);
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut queue = PriorityQueue::new(0, |a: &u32, b: &u32| a < b);
+
+        assert_eq!(queue.insert_with_overflow(1), Some(1));
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,256 @@
+use super::automaton::{Automaton, AutomatonError, MAX_CODE_POINT};
+
+/// Parses a regular expression into a non-deterministic automaton matching whole strings.
+///
+/// Supports literals, `.`, `|`, `(...)`, `*`, `+`, `?`, `{n}`, `{n,}`, `{n,m}`,
+/// classes like `[a-z]` and `[^0-9]`, and `\` to escape a special char.
+pub fn parse(pattern: &str) -> Result<Automaton, AutomatonError> {
+    let mut parser = Parser {
+        chars: pattern.chars().collect(),
+        pos: 0,
+    };
+
+    let automaton = parser.parse_union()?;
+    match parser.peek() {
+        None => Ok(automaton),
+        Some(c) => Err(parser.unexpected(c)),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error(&self, message: &str) -> AutomatonError {
+        AutomatonError::InvalidSyntax(format!("{} at position {}", message, self.pos))
+    }
+
+    /// Error for the char just peeked.
+    fn unexpected(&self, c: char) -> AutomatonError {
+        self.error(&format!("Unexpected '{}'", c))
+    }
+
+    fn parse_union(&mut self) -> Result<Automaton, AutomatonError> {
+        let mut alternatives = vec![self.parse_concatenation()?];
+        while self.eat('|') {
+            alternatives.push(self.parse_concatenation()?);
+        }
+
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Automaton::union(alternatives),
+        })
+    }
+
+    fn parse_concatenation(&mut self) -> Result<Automaton, AutomatonError> {
+        let mut automata = Vec::new();
+        while !matches!(self.peek(), None | Some('|') | Some(')')) {
+            automata.push(self.parse_repeat()?);
+        }
+        Ok(Automaton::concatenate(automata))
+    }
+
+    fn parse_repeat(&mut self) -> Result<Automaton, AutomatonError> {
+        let mut automaton = self.parse_atom()?;
+
+        loop {
+            automaton = match self.peek() {
+                Some('*') => Automaton::repeat(automaton),
+                Some('+') => Automaton::repeat_min(automaton, 1),
+                Some('?') => Automaton::optional(automaton),
+                Some('{') => {
+                    self.pos += 1;
+                    let min = self.parse_number()?;
+                    let max = if self.eat(',') {
+                        match self.peek() {
+                            Some('}') => None,
+                            _ => Some(self.parse_number()?),
+                        }
+                    } else {
+                        Some(min)
+                    };
+                    if self.peek() != Some('}') {
+                        return Err(self.error("Expected '}'"));
+                    }
+
+                    match max {
+                        None => Automaton::repeat_min(automaton, min),
+                        Some(max) if max >= min => Automaton::repeat_range(automaton, min, max),
+                        Some(_) => return Err(self.error("Invalid repeat range")),
+                    }
+                }
+                _ => return Ok(automaton),
+            };
+            self.pos += 1;
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<usize, AutomatonError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().map_err(|_| self.error("Expected a number"))
+    }
+
+    fn parse_atom(&mut self) -> Result<Automaton, AutomatonError> {
+        match self.next() {
+            Some('.') => Ok(Automaton::any_char()),
+            Some('(') => {
+                let automaton = self.parse_union()?;
+                if !self.eat(')') {
+                    return Err(self.error("Expected ')'"));
+                }
+                Ok(automaton)
+            }
+            Some('[') => self.parse_class(),
+            Some('\\') => match self.next() {
+                Some(c) => Ok(Automaton::char_range(c, c)),
+                None => Err(self.error("Nothing to escape")),
+            },
+            Some(c @ ('*' | '+' | '?' | '{' | '}' | ']')) => {
+                self.pos -= 1;
+                Err(self.unexpected(c))
+            }
+            Some(c) => Ok(Automaton::char_range(c, c)),
+            None => Err(self.error("Unexpected end")),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Automaton, AutomatonError> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+
+        while !self.eat(']') {
+            let min = self.parse_class_char()?;
+            let max = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                self.parse_class_char()?
+            } else {
+                min
+            };
+            if max < min {
+                return Err(self.error("Invalid class range"));
+            }
+            ranges.push((min as u32, max as u32));
+        }
+
+        if ranges.is_empty() {
+            return Err(self.error("Empty class"));
+        }
+        if negated {
+            ranges = complement(ranges);
+        }
+        Ok(Automaton::char_ranges(&ranges))
+    }
+
+    fn parse_class_char(&mut self) -> Result<char, AutomatonError> {
+        match self.next() {
+            Some('\\') => self.next().ok_or_else(|| self.error("Nothing to escape")),
+            Some(c) => Ok(c),
+            None => Err(self.error("Unterminated class")),
+        }
+    }
+}
+
+/// Code points outside of the ranges.
+fn complement(mut ranges: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    ranges.sort_unstable();
+
+    let mut result = Vec::new();
+    let mut next = 0;
+    for (min, max) in ranges {
+        if min > next {
+            result.push((next, min - 1));
+        }
+        next = next.max(max + 1);
+    }
+    if next <= MAX_CODE_POINT {
+        result.push((next, MAX_CODE_POINT));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::automaton::DEFAULT_MAX_DETERMINIZED_STATES;
+
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        parse(pattern)
+            .unwrap()
+            .determinize(DEFAULT_MAX_DETERMINIZED_STATES)
+            .unwrap()
+            .run(text)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(matches("te[sx]t", "test"));
+        assert!(matches("te[sx]t", "text"));
+        assert!(!matches("te[sx]t", "tent"));
+        assert!(matches("te[^a-r]t", "test"));
+        assert!(!matches("te[^a-r]t", "tent"));
+        assert!(matches("t.st", "tost"));
+        assert!(matches("(unit|integration) tests?", "unit test"));
+        assert!(matches("(unit|integration) tests?", "integration tests"));
+        assert!(!matches("(unit|integration) tests?", "tests"));
+        assert!(matches("a+b*", "aaa"));
+        assert!(!matches("a+b*", "b"));
+        assert!(matches("a{2,3}", "aaa"));
+        assert!(!matches("a{2,3}", "aaaa"));
+        assert!(matches("a{2,}", "aaaa"));
+        assert!(matches("a{2}", "aa"));
+        assert!(matches("\\*\\.", "*."));
+        assert!(matches("[a\\]-]+", "a]-"));
+        assert!(matches("", ""));
+        assert!(matches("a|", ""));
+    }
+
+    #[test]
+    fn test_invalid() {
+        for (pattern, error) in [
+            ("te(st", "Expected ')' at position 5"),
+            ("test)", "Unexpected ')' at position 4"),
+            ("*a", "Unexpected '*' at position 0"),
+            ("a{2", "Expected '}' at position 3"),
+            ("a{3,2}", "Invalid repeat range at position 5"),
+            ("[z-a]", "Invalid class range at position 4"),
+            ("[ab", "Unterminated class at position 3"),
+            ("[]", "Empty class at position 2"),
+            ("a\\", "Nothing to escape at position 2"),
+        ] {
+            assert_eq!(
+                parse(pattern).unwrap_err(),
+                AutomatonError::InvalidSyntax(error.to_string()),
+                "{}",
+                pattern
+            );
+        }
+    }
+}
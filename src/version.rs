@@ -1,6 +1,13 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version {
     pub major: u32,
     pub minor: u32,
     pub bugfix: u32,
 }
+
+/// Lucene version written into new segments and commits.
+pub const LATEST: Version = Version {
+    major: 7,
+    minor: 7,
+    bugfix: 3,
+};